@@ -15,6 +15,7 @@ pub(crate) async fn handle(command: Command, client: &ClusterClient) -> Result<(
                     poll_messages.stream_id,
                     poll_messages.offset,
                     poll_messages.count,
+                    poll_messages.consistency,
                 )
                 .await?;
             info!("Polled {} messages", messages.len());
@@ -29,11 +30,11 @@ pub(crate) async fn handle(command: Command, client: &ClusterClient) -> Result<(
                 .await?;
             info!("Appended {count} messages");
         }
-        Command::GetMetadata(_) => {
-            client.update_metadata().await?;
+        Command::GetMetadata(get_metadata) => {
+            client.get_metadata(get_metadata.consistency).await?;
         }
-        Command::GetStreams(_) => {
-            let streams = client.get_streams().await?;
+        Command::GetStreams(get_streams) => {
+            let streams = client.get_streams(get_streams.consistency).await?;
             info!("Got {} streams", streams.len());
             for stream in streams {
                 info!("{stream}");
@@ -41,7 +42,12 @@ pub(crate) async fn handle(command: Command, client: &ClusterClient) -> Result<(
         }
         Command::CreateStream(create_stream) => {
             client
-                .create_stream(create_stream.id, create_stream.replication_factor)
+                .create_stream(
+                    create_stream.id,
+                    create_stream.replication_factor,
+                    create_stream.min_in_sync_replicas,
+                    create_stream.replicas.clone(),
+                )
                 .await?;
             info!("Created stream with ID: {}", create_stream.id);
         }
@@ -49,6 +55,38 @@ pub(crate) async fn handle(command: Command, client: &ClusterClient) -> Result<(
             client.delete_stream(delete_stream.id).await?;
             info!("Deleted stream with ID: {}", delete_stream.id);
         }
+        Command::TransferLeadership(transfer_leadership) => {
+            client
+                .transfer_leadership(transfer_leadership.node_id)
+                .await?;
+            info!(
+                "Transferred leadership to node with ID: {}",
+                transfer_leadership.node_id
+            );
+        }
+        Command::RebalanceLeaders(_) => {
+            client.rebalance_leaders().await?;
+            info!("Rebalanced stream leaders");
+        }
+        Command::AddNode(add_node) => {
+            client
+                .add_node(
+                    add_node.id,
+                    &add_node.name,
+                    &add_node.public_address,
+                    &add_node.internal_address,
+                    add_node.learner,
+                )
+                .await?;
+            info!(
+                "Added node with ID: {}, learner: {}",
+                add_node.id, add_node.learner
+            );
+        }
+        Command::RemoveNode(remove_node) => {
+            client.remove_node(remove_node.id).await?;
+            info!("Removed node with ID: {}", remove_node.id);
+        }
         _ => {
             return Err(SystemError::InvalidCommand);
         }
@@ -1,4 +1,5 @@
 use bytes::Bytes;
+use sdk::commands::add_node::AddNode;
 use sdk::commands::append_messages::{AppendMessages, AppendableMessage};
 use sdk::commands::command::Command;
 use sdk::commands::create_stream::CreateStream;
@@ -7,18 +8,30 @@ use sdk::commands::get_metadata::GetMetadata;
 use sdk::commands::get_streams::GetStreams;
 use sdk::commands::ping::Ping;
 use sdk::commands::poll_messages::PollMessages;
+use sdk::commands::rebalance_leaders::RebalanceLeaders;
+use sdk::commands::remove_node::RemoveNode;
+use sdk::commands::transfer_leadership::TransferLeadership;
+use sdk::models::read_consistency::ReadConsistency;
 
 pub(crate) fn parse(input: &str) -> Option<Command> {
-    let parts = input.split('.').collect::<Vec<&str>>();
+    let parts = input.splitn(2, '.').collect::<Vec<&str>>();
     let command = parts[0];
     match command {
-        "metadata" => Some(GetMetadata::new_command()),
+        "metadata" => Some(GetMetadata::new_command(parse_consistency(
+            parts.get(1).unwrap_or(&""),
+        )?)),
         "ping" => Some(Ping::new_command()),
-        "get_streams" => Some(GetStreams::new_command()),
+        "get_streams" => Some(GetStreams::new_command(parse_consistency(
+            parts.get(1).unwrap_or(&""),
+        )?)),
         "create_stream" => parse_create_stream(parts.get(1).unwrap_or(&"")),
         "delete_stream" => parse_delete_stream(parts.get(1).unwrap_or(&"")),
         "append" => parse_append_messages(parts.get(1).unwrap_or(&"")),
         "poll" => parse_poll_messages(parts.get(1).unwrap_or(&"")),
+        "transfer_leadership" => parse_transfer_leadership(parts.get(1).unwrap_or(&"")),
+        "rebalance_leaders" => Some(RebalanceLeaders::new_command()),
+        "add_node" => parse_add_node(parts.get(1).unwrap_or(&"")),
+        "remove_node" => parse_remove_node(parts.get(1).unwrap_or(&"")),
         _ => None,
     }
 }
@@ -27,7 +40,19 @@ fn parse_create_stream(input: &str) -> Option<Command> {
     let parts = input.split('|').collect::<Vec<&str>>();
     let id = parts[0].parse::<u64>().unwrap();
     let replication_factor = parts.get(1).unwrap_or(&"").parse::<u8>().ok();
-    Some(CreateStream::new_command(id, replication_factor))
+    let min_in_sync_replicas = parts.get(2).unwrap_or(&"").parse::<u8>().ok();
+    let replicas = parts
+        .get(3)
+        .unwrap_or(&"")
+        .split(',')
+        .filter_map(|node_id| node_id.parse::<u64>().ok())
+        .collect();
+    Some(CreateStream::new_command(
+        id,
+        replication_factor,
+        min_in_sync_replicas,
+        replicas,
+    ))
 }
 
 fn parse_delete_stream(input: &str) -> Option<Command> {
@@ -55,12 +80,53 @@ fn parse_append_messages(input: &str) -> Option<Command> {
 
 fn parse_poll_messages(input: &str) -> Option<Command> {
     let parts = input.split('|').collect::<Vec<&str>>();
-    if parts.len() != 3 {
+    if parts.len() != 3 && parts.len() != 4 {
         return None;
     }
 
     let stream_id = parts[0].parse::<u64>().unwrap();
     let offset = parts[1].parse::<u64>().unwrap();
     let count = parts[2].parse::<u64>().unwrap();
-    Some(PollMessages::new_command(stream_id, offset, count))
+    let consistency = parse_consistency(parts.get(3).unwrap_or(&""))?;
+    Some(PollMessages::new_command(
+        stream_id,
+        offset,
+        count,
+        consistency,
+    ))
+}
+
+fn parse_consistency(input: &str) -> Option<ReadConsistency> {
+    if input.is_empty() {
+        return Some(ReadConsistency::default());
+    }
+
+    input.parse::<ReadConsistency>().ok()
+}
+
+fn parse_transfer_leadership(input: &str) -> Option<Command> {
+    let node_id = input.parse::<u64>().ok()?;
+    Some(TransferLeadership::new_command(node_id))
+}
+
+fn parse_add_node(input: &str) -> Option<Command> {
+    let parts = input.split('|').collect::<Vec<&str>>();
+    if parts.len() != 4 && parts.len() != 5 {
+        return None;
+    }
+
+    let id = parts[0].parse::<u64>().ok()?;
+    let learner = parts.get(4) == Some(&"learner");
+    Some(AddNode::new_command(
+        id,
+        parts[1].to_string(),
+        parts[2].to_string(),
+        parts[3].to_string(),
+        learner,
+    ))
+}
+
+fn parse_remove_node(input: &str) -> Option<Command> {
+    let node_id = input.parse::<u64>().ok()?;
+    Some(RemoveNode::new_command(node_id))
 }
@@ -18,7 +18,14 @@ async fn main() -> Result<(), SystemError> {
     let addresses = env::var("IGGY_CLUSTER_ADDRESS")
         .unwrap_or("127.0.0.1:8101,127.0.0.1:8102,127.0.0.1:8103".to_string());
     let addresses: Vec<&str> = addresses.split(',').collect();
-    let mut client = ClusterClient::new(addresses, reconnection_interval, reconnection_retries);
+    let prefer_leader_reads =
+        env::var("IGGY_PREFER_LEADER_READS").is_ok_and(|value| value == "true");
+    let mut client = ClusterClient::new(
+        addresses,
+        reconnection_interval,
+        reconnection_retries,
+        prefer_leader_reads,
+    );
     client.init().await?;
     let stdin = io::stdin();
     let mut user_input = String::new();
@@ -0,0 +1,29 @@
+//! Runs the clusters of `iggy-node` processes on the free local ports, so that the tests can
+//! drive them with the SDK clients and kill or restart any of the nodes. The nodes can also be connected
+//! to each other through `iggy-proxy`, which partitions them.
+
+pub mod recorder;
+pub mod test_cluster;
+pub mod test_node;
+pub mod test_proxy;
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Checks the condition until it holds, or panics with the message once the timeout has passed.
+pub async fn eventually<F, Fut>(timeout: Duration, message: &str, mut condition: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if condition().await {
+            return;
+        }
+        monoio::time::sleep(POLL_INTERVAL).await;
+    }
+    panic!("{message} Timeout: {} ms has passed.", timeout.as_millis());
+}
@@ -0,0 +1,127 @@
+use bytes::Bytes;
+use linearizability::history::{ClientId, History, Operation, Outcome};
+use sdk::clients::cluster_client::ClusterClient;
+use sdk::commands::append_messages::AppendableMessage;
+use sdk::error::SystemError;
+use sdk::models::message::Message;
+use sdk::models::read_consistency::ReadConsistency;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Instant;
+
+/// Sends the operations of the client to the cluster and records them in the shared history.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    client_id: ClientId,
+    history: Rc<RefCell<History>>,
+}
+
+impl Recorder {
+    pub fn new(client_id: ClientId, history: Rc<RefCell<History>>) -> Self {
+        Self { client_id, history }
+    }
+
+    pub async fn create_stream(
+        &self,
+        client: &ClusterClient,
+        stream_id: u64,
+        replication_factor: u8,
+        min_in_sync_replicas: u8,
+    ) -> Result<(), SystemError> {
+        let index = self.invoke(Operation::CreateStream { stream_id });
+        let result = client
+            .create_stream(
+                stream_id,
+                Some(replication_factor),
+                Some(min_in_sync_replicas),
+                vec![],
+            )
+            .await;
+        self.complete(index, result.as_ref().map(|_| Outcome::Ok));
+        result
+    }
+
+    pub async fn delete_stream(
+        &self,
+        client: &ClusterClient,
+        stream_id: u64,
+    ) -> Result<(), SystemError> {
+        let index = self.invoke(Operation::DeleteStream { stream_id });
+        let result = client.delete_stream(stream_id).await;
+        self.complete(index, result.as_ref().map(|_| Outcome::Ok));
+        result
+    }
+
+    /// Appends the messages with the given IDs, which must be unique across all the clients.
+    pub async fn append(
+        &self,
+        client: &ClusterClient,
+        stream_id: u64,
+        ids: &[u64],
+    ) -> Result<(), SystemError> {
+        let index = self.invoke(Operation::AppendMessages {
+            stream_id,
+            ids: ids.to_vec(),
+        });
+        let messages = ids
+            .iter()
+            .map(|id| AppendableMessage {
+                id: *id,
+                payload: Bytes::from(format!("message-{id}")),
+            })
+            .collect();
+        let result = client.append_messages(stream_id, messages).await;
+        self.complete(index, result.as_ref().map(|_| Outcome::Ok));
+        result
+    }
+
+    pub async fn poll(
+        &self,
+        client: &ClusterClient,
+        stream_id: u64,
+        offset: u64,
+        count: u64,
+    ) -> Result<Vec<Message>, SystemError> {
+        let index = self.invoke(Operation::PollMessages {
+            stream_id,
+            offset,
+            count,
+        });
+        let result = client
+            .poll_messages(stream_id, offset, count, ReadConsistency::Linearizable)
+            .await;
+        self.complete(
+            index,
+            result.as_ref().map(|messages| {
+                Outcome::Messages(
+                    messages
+                        .iter()
+                        .map(|message| (message.offset, message.id))
+                        .collect(),
+                )
+            }),
+        );
+        result
+    }
+
+    /// Panics with the violation, unless the history of all the clients is linearizable.
+    pub fn assert_linearizable(&self) {
+        let result = linearizability::checker::check(&self.history.borrow());
+        if let Err(violation) = result {
+            panic!("{violation}");
+        }
+    }
+
+    fn invoke(&self, operation: Operation) -> usize {
+        self.history
+            .borrow_mut()
+            .invoke(self.client_id, operation, Instant::now())
+    }
+
+    fn complete<T>(&self, index: usize, outcome: Result<Outcome, T>) {
+        let outcome = outcome.unwrap_or(Outcome::Failed);
+        self.history
+            .borrow_mut()
+            .complete(index, outcome, Instant::now());
+    }
+}
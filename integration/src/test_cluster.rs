@@ -0,0 +1,284 @@
+use crate::eventually;
+use crate::test_node::TestNode;
+use crate::test_proxy::TestProxy;
+use sdk::clients::cluster_client::ClusterClient;
+use sdk::models::read_consistency::ReadConsistency;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::Command as Process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const IGGY_NODE_BINARY: &str = "IGGY_NODE_BINARY";
+const IGGY_PROXY_BINARY: &str = "IGGY_PROXY_BINARY";
+const LEADER_TIMEOUT: Duration = Duration::from_secs(30);
+const RECONNECTION_INTERVAL: u64 = 500;
+const RECONNECTION_RETRIES: u32 = 20;
+
+static CLUSTERS: AtomicU64 = AtomicU64::new(0);
+static NODE_BINARY: OnceLock<PathBuf> = OnceLock::new();
+static PROXY_BINARY: OnceLock<PathBuf> = OnceLock::new();
+
+/// The cluster of the nodes running as the separate processes, which are killed once it's dropped.
+#[derive(Debug)]
+pub struct TestCluster {
+    directory: PathBuf,
+    binary: PathBuf,
+    nodes: Vec<TestNode>,
+    proxy: Option<TestProxy>,
+}
+
+impl TestCluster {
+    /// Starts the nodes, each of them on its own free public and internal ports.
+    pub fn start(nodes_count: u64) -> Self {
+        Self::start_nodes(nodes_count, false)
+    }
+
+    /// Starts the nodes, which connect to each other through the proxy, so that the faults can be injected.
+    pub fn start_with_proxy(nodes_count: u64) -> Self {
+        Self::start_nodes(nodes_count, true)
+    }
+
+    fn start_nodes(nodes_count: u64, with_proxy: bool) -> Self {
+        let directory = std::env::temp_dir().join("iggy-integration").join(format!(
+            "{}-{}",
+            std::process::id(),
+            CLUSTERS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).expect("Failed to create the cluster directory.");
+        let nodes = (1..=nodes_count)
+            .map(|id| TestNode::new(id, free_address(), free_address(), &directory))
+            .collect::<Vec<_>>();
+        let proxy = with_proxy.then(|| {
+            let links = nodes
+                .iter()
+                .flat_map(|from| nodes.iter().map(move |to| (from.id, to.id)))
+                .filter(|(from, to)| from != to)
+                .map(|link| (link, free_address()))
+                .collect::<BTreeMap<_, _>>();
+            let mut proxy = TestProxy::new(free_address(), links, &directory);
+            proxy.start(
+                binary(&PROXY_BINARY, IGGY_PROXY_BINARY, "iggy-proxy"),
+                nodes
+                    .iter()
+                    .map(|node| (node.id, node.internal_address.as_str())),
+            );
+            proxy
+        });
+        let mut cluster = Self {
+            directory,
+            binary: binary(&NODE_BINARY, IGGY_NODE_BINARY, "iggy-node").clone(),
+            nodes,
+            proxy,
+        };
+        for node in &cluster.nodes {
+            std::fs::write(node.config_path(), cluster.config(node))
+                .expect("Failed to write the node config.");
+        }
+        for node in &mut cluster.nodes {
+            node.start(&cluster.binary);
+        }
+        cluster
+    }
+
+    pub fn node(&self, node_id: u64) -> &TestNode {
+        &self.nodes[node_id as usize - 1]
+    }
+
+    pub fn nodes(&self) -> &[TestNode] {
+        &self.nodes
+    }
+
+    pub fn running_nodes(&self) -> impl Iterator<Item = &TestNode> {
+        self.nodes.iter().filter(|node| node.is_running())
+    }
+
+    pub fn kill(&mut self, node_id: u64) {
+        self.nodes[node_id as usize - 1].kill();
+    }
+
+    /// Starts the killed node again, with its data kept on disk.
+    pub fn restart(&mut self, node_id: u64) {
+        self.nodes[node_id as usize - 1].start(&self.binary);
+    }
+
+    /// Blackholes the traffic between the nodes both ways, until it's healed.
+    pub async fn partition(&self, first: u64, second: u64) {
+        self.proxy()
+            .execute(&format!("partition.{first}|{second}"))
+            .await;
+    }
+
+    /// Removes all the faults, the held traffic is delivered once the links are healed.
+    pub async fn heal(&self) {
+        self.proxy().execute("heal").await;
+    }
+
+    fn proxy(&self) -> &TestProxy {
+        self.proxy
+            .as_ref()
+            .expect("The cluster has been started without the proxy.")
+    }
+
+    /// Waits until one of the running nodes confirms its leadership, and returns its ID.
+    pub async fn wait_for_leader(&self) -> u64 {
+        let leader_id = Cell::new(None);
+        eventually(LEADER_TIMEOUT, "The leader has not been elected.", || {
+            let leader_id = &leader_id;
+            async move {
+                for node in self.running_nodes() {
+                    if let Ok(metadata) = node.get_metadata(ReadConsistency::Linearizable).await {
+                        if metadata.leader_id == Some(node.id) {
+                            leader_id.set(Some(node.id));
+                            return true;
+                        }
+                    }
+                }
+                false
+            }
+        })
+        .await;
+        leader_id.get().unwrap()
+    }
+
+    /// Returns the client connected to all the running nodes.
+    pub async fn client(&self) -> ClusterClient {
+        let addresses = self
+            .running_nodes()
+            .map(|node| node.public_address.as_str())
+            .collect::<Vec<_>>();
+        let mut client =
+            ClusterClient::new(addresses, RECONNECTION_INTERVAL, RECONNECTION_RETRIES, true);
+        client
+            .init()
+            .await
+            .expect("Failed to connect the client to the cluster.");
+        client
+    }
+
+    fn config(&self, node: &TestNode) -> String {
+        let data = self.directory.join(format!("node{}", node.id));
+        let data = data.to_string_lossy();
+        let mut config = format!(
+            r#"[stream]
+path = "{data}/streams"
+
+[metadata]
+backend = "Files"
+path = "{data}/metadata"
+
+[server]
+address = "{public_address}"
+
+[node]
+id = {id}
+name = "node{id}"
+address = "{internal_address}"
+
+[cluster]
+max_timeout = 1000
+heartbeat_interval = 1000
+info_interval = 5000
+reconnection_interval = 500
+reconnection_retries = 1000
+secret = "secret123!"
+election_timeout_range_from = 150
+election_timeout_range_to = 300
+required_acknowledgements = "Majority"
+state_path = "{data}"
+leadership_transfer_timeout = 5000
+join_existing = false
+learner = false
+replication_retry_interval = 500
+commit_timeout = 1000
+max_append_entries = 100
+max_append_entries_in_flight = 4
+fetch_interval = 100
+max_fetch_messages = 1000
+max_replica_lag = 2000
+leader_rebalance_interval = 30000
+bootstrap_chunk_size = 1048576
+disk_check_interval = 5000
+disk_soft_limit = 80
+disk_hard_limit = 95
+snapshot_threshold = 1000
+"#,
+            id = node.id,
+            public_address = node.public_address,
+            internal_address = node.internal_address,
+        );
+        for other in self.nodes.iter().filter(|other| other.id != node.id) {
+            let internal_address = match &self.proxy {
+                Some(proxy) => proxy.link_address(node.id, other.id),
+                None => other.internal_address.as_str(),
+            };
+            config.push_str(&format!(
+                r#"
+[[cluster.nodes]]
+id = {id}
+name = "node{id}"
+public_address = "{public_address}"
+internal_address = "{internal_address}"
+"#,
+                id = other.id,
+                public_address = other.public_address,
+            ));
+        }
+        config
+    }
+}
+
+impl Drop for TestCluster {
+    fn drop(&mut self) {
+        for node in &mut self.nodes {
+            node.kill();
+        }
+        if let Some(proxy) = &mut self.proxy {
+            proxy.kill();
+        }
+
+        // The logs of the nodes are kept for the failed test.
+        if std::thread::panicking() {
+            eprintln!(
+                "The data and logs of the cluster are kept in: {}",
+                self.directory.display()
+            );
+            return;
+        }
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
+
+/// Returns the port which is free at the moment, as reserved by the OS for the listener bound to port 0.
+fn free_address() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to find a free port.");
+    listener
+        .local_addr()
+        .expect("Failed to get the free port.")
+        .to_string()
+}
+
+/// Returns the path of the binary, e.g. `iggy-node`, which is built first, unless it's set explicitly.
+fn binary<'a>(binary: &'a OnceLock<PathBuf>, variable: &str, name: &str) -> &'a PathBuf {
+    binary.get_or_init(|| {
+        if let Ok(binary) = std::env::var(variable) {
+            return PathBuf::from(binary);
+        }
+
+        let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let status = Process::new(env!("CARGO"))
+            .args(["build", "--bin", name])
+            .current_dir(workspace)
+            .status()
+            .unwrap_or_else(|error| panic!("Failed to build {name}, {error}"));
+        assert!(status.success(), "Failed to build {name}.");
+        let target = std::env::var("CARGO_TARGET_DIR")
+            .map(PathBuf::from)
+            .unwrap_or(workspace.join("target"));
+        target.join("debug").join(name)
+    })
+}
@@ -0,0 +1,101 @@
+use sdk::bytes_serializable::BytesSerializable;
+use sdk::clients::node_client::NodeClient;
+use sdk::commands::command::Command;
+use sdk::commands::get_metadata::GetMetadata;
+use sdk::commands::get_streams::GetStreams;
+use sdk::error::SystemError;
+use sdk::models::metadata::Metadata;
+use sdk::models::read_consistency::ReadConsistency;
+use sdk::models::stream::Stream;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command as Process, Stdio};
+
+const IGGY_NODE_CONFIG_PATH: &str = "IGGY_NODE_CONFIG_PATH";
+const STREAM_SIZE: usize = 25;
+
+/// The `iggy-node` process, which keeps its config, data and logs in the directory of the cluster.
+#[derive(Debug)]
+pub struct TestNode {
+    pub id: u64,
+    pub public_address: String,
+    pub internal_address: String,
+    config_path: PathBuf,
+    log_path: PathBuf,
+    process: Option<Child>,
+}
+
+impl TestNode {
+    pub(crate) fn new(
+        id: u64,
+        public_address: String,
+        internal_address: String,
+        directory: &Path,
+    ) -> Self {
+        Self {
+            id,
+            public_address,
+            internal_address,
+            config_path: directory.join(format!("node{id}.toml")),
+            log_path: directory.join(format!("node{id}.log")),
+            process: None,
+        }
+    }
+
+    pub(crate) fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    pub(crate) fn start(&mut self, binary: &Path) {
+        if self.is_running() {
+            return;
+        }
+
+        let log = File::options()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)
+            .expect("Failed to open the node log.");
+        let process = Process::new(binary)
+            .env(IGGY_NODE_CONFIG_PATH, &self.config_path)
+            .stdout(log.try_clone().expect("Failed to clone the node log."))
+            .stderr(log)
+            .stdin(Stdio::null())
+            .spawn()
+            .unwrap_or_else(|error| panic!("Failed to start node ID: {}, {error}", self.id));
+        self.process = Some(process);
+    }
+
+    /// Kills the process without letting it shut down gracefully, as if the node has crashed.
+    pub(crate) fn kill(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.process.is_some()
+    }
+
+    /// Returns the streams of the node itself, which might not be the leader.
+    pub async fn get_streams(&self) -> Result<Vec<Stream>, SystemError> {
+        let bytes = self
+            .send(&GetStreams::new_command(ReadConsistency::Relaxed))
+            .await?;
+        bytes.chunks(STREAM_SIZE).map(Stream::from_bytes).collect()
+    }
+
+    pub async fn get_metadata(
+        &self,
+        consistency: ReadConsistency,
+    ) -> Result<Metadata, SystemError> {
+        let bytes = self.send(&GetMetadata::new_command(consistency)).await?;
+        Metadata::from_bytes(&bytes)
+    }
+
+    async fn send(&self, command: &Command) -> Result<Vec<u8>, SystemError> {
+        let mut client = NodeClient::init(&self.public_address).await?;
+        client.send(command).await
+    }
+}
@@ -0,0 +1,103 @@
+use monoio::io::{AsyncReadRent, AsyncWriteRentExt};
+use monoio::net::TcpStream;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command as Process, Stdio};
+
+const IGGY_PROXY_CONFIG_PATH: &str = "IGGY_PROXY_CONFIG_PATH";
+const BUFFER_SIZE: usize = 1024;
+
+/// The `iggy-proxy` process, which carries the traffic between the nodes, so that the faults can be injected
+/// into the links. Each ordered pair of the nodes has its own link, which is the internal address
+/// of the node `to`, as configured for the node `from`.
+#[derive(Debug)]
+pub struct TestProxy {
+    pub control_address: String,
+    links: BTreeMap<(u64, u64), String>,
+    config_path: PathBuf,
+    log_path: PathBuf,
+    process: Option<Child>,
+}
+
+impl TestProxy {
+    pub(crate) fn new(
+        control_address: String,
+        links: BTreeMap<(u64, u64), String>,
+        directory: &Path,
+    ) -> Self {
+        Self {
+            control_address,
+            links,
+            config_path: directory.join("proxy.toml"),
+            log_path: directory.join("proxy.log"),
+            process: None,
+        }
+    }
+
+    pub(crate) fn link_address(&self, from: u64, to: u64) -> &str {
+        &self.links[&(from, to)]
+    }
+
+    /// Writes the config, which points the links to the actual internal addresses of the nodes, and starts the proxy.
+    pub(crate) fn start<'a>(&mut self, binary: &Path, nodes: impl Iterator<Item = (u64, &'a str)>) {
+        let mut config = format!("control_address = \"{}\"\n", self.control_address);
+        for (id, address) in nodes {
+            config.push_str(&format!(
+                "\n[[nodes]]\nid = {id}\naddress = \"{address}\"\n"
+            ));
+        }
+        for ((from, to), address) in &self.links {
+            config.push_str(&format!(
+                "\n[[links]]\nfrom = {from}\nto = {to}\naddress = \"{address}\"\n"
+            ));
+        }
+        std::fs::write(&self.config_path, config).expect("Failed to write the proxy config.");
+
+        let log = File::options()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)
+            .expect("Failed to open the proxy log.");
+        let process = Process::new(binary)
+            .env(IGGY_PROXY_CONFIG_PATH, &self.config_path)
+            .stdout(log.try_clone().expect("Failed to clone the proxy log."))
+            .stderr(log)
+            .stdin(Stdio::null())
+            .spawn()
+            .unwrap_or_else(|error| panic!("Failed to start the proxy, {error}"));
+        self.process = Some(process);
+    }
+
+    pub(crate) fn kill(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+
+    /// Sends the command to the control address, e.g. `partition.1|2`, and returns the response.
+    pub async fn execute(&self, command: &str) -> String {
+        let mut stream = TcpStream::connect(&self.control_address)
+            .await
+            .expect("Failed to connect to the proxy.");
+        let (result, _) = stream.write_all(format!("{command}\n").into_bytes()).await;
+        result.expect("Failed to send the proxy command.");
+        let mut response = Vec::new();
+        while !response.ends_with(b"\n") {
+            let (result, buffer) = stream.read(vec![0u8; BUFFER_SIZE]).await;
+            let read_bytes = result.expect("Failed to read the proxy response.");
+            assert!(
+                read_bytes > 0,
+                "The proxy has closed the control connection."
+            );
+            response.extend_from_slice(&buffer[..read_bytes]);
+        }
+        let response = String::from_utf8_lossy(&response).trim().to_string();
+        assert!(
+            !response.starts_with("error"),
+            "The proxy has rejected command: {command}, {response}"
+        );
+        response
+    }
+}
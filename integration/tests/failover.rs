@@ -0,0 +1,74 @@
+use integration::eventually;
+use integration::recorder::Recorder;
+use integration::test_cluster::TestCluster;
+use linearizability::history::History;
+use sdk::models::read_consistency::ReadConsistency;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+const STREAM_ID: u64 = 1;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[monoio::test(timer_enabled = true)]
+async fn killed_leader_should_be_replaced_without_losing_acknowledged_messages() {
+    // Both the cluster leader and the stream leader are killed, so the quorum must survive losing two nodes.
+    let mut cluster = TestCluster::start(5);
+    let leader_id = cluster.wait_for_leader().await;
+    let recorder = Recorder::new(1, Rc::new(RefCell::new(History::new())));
+    let client = cluster.client().await;
+    recorder
+        .create_stream(&client, STREAM_ID, 5, 3)
+        .await
+        .expect("Failed to create the stream.");
+    for id in 1..=10 {
+        recorder
+            .append(&client, STREAM_ID, &[id])
+            .await
+            .expect("Failed to append the message.");
+    }
+
+    let stream_leader_id = cluster
+        .node(leader_id)
+        .get_metadata(ReadConsistency::Linearizable)
+        .await
+        .expect("Failed to get the metadata.")
+        .streams[&STREAM_ID]
+        .leader_id;
+    cluster.kill(leader_id);
+    cluster.kill(stream_leader_id);
+    let new_leader_id = cluster.wait_for_leader().await;
+    assert_ne!(new_leader_id, leader_id);
+
+    // Each attempt gets its own ID, as the failed one might still have been appended.
+    let client = cluster.client().await;
+    let next_id = Cell::new(11);
+    let mut acknowledged_ids = (1..=10).collect::<Vec<_>>();
+    while acknowledged_ids.len() < 20 {
+        let acknowledged_id = Cell::new(None);
+        eventually(TIMEOUT, "The message has not been appended.", || async {
+            let id = next_id.get();
+            next_id.set(id + 1);
+            let appended = recorder.append(&client, STREAM_ID, &[id]).await.is_ok();
+            if appended {
+                acknowledged_id.set(Some(id));
+            }
+            appended
+        })
+        .await;
+        acknowledged_ids.extend(acknowledged_id.get());
+    }
+
+    let messages = recorder
+        .poll(&client, STREAM_ID, 0, 1000)
+        .await
+        .expect("Failed to poll the messages.");
+    let ids = messages
+        .iter()
+        .map(|message| message.id)
+        .collect::<Vec<_>>();
+    for id in acknowledged_ids {
+        assert!(ids.contains(&id), "Acknowledged message ID: {id} is lost.");
+    }
+    recorder.assert_linearizable();
+}
@@ -0,0 +1,130 @@
+use integration::eventually;
+use integration::test_cluster::TestCluster;
+use sdk::clients::cluster_client::ClusterClient;
+use sdk::commands::append_messages::AppendableMessage;
+use sdk::models::read_consistency::ReadConsistency;
+use std::time::Duration;
+
+const STREAM_ID: u64 = 1;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[monoio::test(timer_enabled = true)]
+async fn acknowledged_messages_should_survive_restart_of_all_nodes() {
+    let mut cluster = TestCluster::start(3);
+    cluster.wait_for_leader().await;
+    let client = cluster.client().await;
+    client
+        .create_stream(STREAM_ID, Some(3), Some(2), vec![])
+        .await
+        .expect("Failed to create the stream.");
+    append(&client, 1..=10).await;
+
+    for node_id in 1..=3 {
+        cluster.kill(node_id);
+    }
+    for node_id in 1..=3 {
+        cluster.restart(node_id);
+    }
+    cluster.wait_for_leader().await;
+
+    let client = cluster.client().await;
+    eventually(TIMEOUT, "The messages have not been recovered.", || async {
+        client
+            .poll_messages(STREAM_ID, 0, 100, ReadConsistency::Linearizable)
+            .await
+            .is_ok_and(|messages| {
+                messages
+                    .iter()
+                    .map(|message| message.id)
+                    .collect::<Vec<_>>()
+                    == (1..=10).collect::<Vec<_>>()
+            })
+    })
+    .await;
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn restarted_follower_should_rejoin_and_catch_up_with_stream_leader() {
+    let mut cluster = TestCluster::start(3);
+    let leader_id = cluster.wait_for_leader().await;
+    let client = cluster.client().await;
+    client
+        .create_stream(STREAM_ID, Some(3), Some(2), vec![])
+        .await
+        .expect("Failed to create the stream.");
+    append(&client, 1..=10).await;
+
+    let stream_leader_id = cluster
+        .node(leader_id)
+        .get_metadata(ReadConsistency::Linearizable)
+        .await
+        .expect("Failed to get the metadata.")
+        .streams[&STREAM_ID]
+        .leader_id;
+    let follower_id = cluster
+        .nodes()
+        .iter()
+        .map(|node| node.id)
+        .find(|node_id| *node_id != stream_leader_id)
+        .unwrap();
+    cluster.kill(follower_id);
+
+    // The appends are retried until the killed follower is removed from the in-sync replicas.
+    for id in 11..=20 {
+        eventually(TIMEOUT, "The message has not been appended.", || async {
+            client
+                .append_messages(STREAM_ID, vec![message(id)])
+                .await
+                .is_ok()
+        })
+        .await;
+    }
+    cluster.restart(follower_id);
+
+    let stream_leader = cluster.node(stream_leader_id);
+    let follower = cluster.node(follower_id);
+    eventually(TIMEOUT, "The follower has not caught up.", || async {
+        let (Ok(leader_streams), Ok(follower_streams)) = (
+            stream_leader.get_streams().await,
+            follower.get_streams().await,
+        ) else {
+            return false;
+        };
+        let leader_stream = leader_streams.iter().find(|stream| stream.id == STREAM_ID);
+        let follower_stream = follower_streams
+            .iter()
+            .find(|stream| stream.id == STREAM_ID);
+        matches!((leader_stream, follower_stream), (Some(leader_stream), Some(follower_stream))
+            if leader_stream.high_watermark >= 20
+                && follower_stream.offset == leader_stream.offset
+                && follower_stream.high_watermark == leader_stream.high_watermark)
+    })
+    .await;
+    eventually(
+        TIMEOUT,
+        "The follower has not rejoined the in-sync replicas.",
+        || async {
+            stream_leader
+                .get_metadata(ReadConsistency::Relaxed)
+                .await
+                .is_ok_and(|metadata| metadata.streams[&STREAM_ID].isr.contains(&follower_id))
+        },
+    )
+    .await;
+}
+
+async fn append(client: &ClusterClient, ids: impl Iterator<Item = u64>) {
+    for id in ids {
+        client
+            .append_messages(STREAM_ID, vec![message(id)])
+            .await
+            .expect("Failed to append the message.");
+    }
+}
+
+fn message(id: u64) -> AppendableMessage {
+    AppendableMessage {
+        id,
+        payload: format!("message-{id}").into(),
+    }
+}
@@ -0,0 +1,154 @@
+use integration::eventually;
+use integration::recorder::Recorder;
+use integration::test_cluster::TestCluster;
+use linearizability::history::History;
+use sdk::clients::cluster_client::ClusterClient;
+use sdk::models::read_consistency::ReadConsistency;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const FAILOVER_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[monoio::test(timer_enabled = true)]
+async fn created_and_deleted_stream_should_be_replicated_to_all_nodes() {
+    let cluster = TestCluster::start(3);
+    cluster.wait_for_leader().await;
+    let recorder = Recorder::new(1, Rc::new(RefCell::new(History::new())));
+    let client = cluster.client().await;
+    let stream_id = 1;
+
+    recorder
+        .create_stream(&client, stream_id, 3, 2)
+        .await
+        .expect("Failed to create the stream.");
+    wait_for_stream(&cluster, stream_id).await;
+
+    for id in 1..=5 {
+        recorder
+            .append(&client, stream_id, &[id])
+            .await
+            .expect("Failed to append the message.");
+    }
+    recorder
+        .poll(&client, stream_id, 0, 10)
+        .await
+        .expect("Failed to poll the messages.");
+
+    recorder
+        .delete_stream(&client, stream_id)
+        .await
+        .expect("Failed to delete the stream.");
+    for node in cluster.nodes() {
+        eventually(
+            TIMEOUT,
+            "The stream has not been deleted on all the nodes.",
+            || async {
+                node.get_streams()
+                    .await
+                    .is_ok_and(|streams| streams.iter().all(|stream| stream.id != stream_id))
+            },
+        )
+        .await;
+    }
+    recorder.assert_linearizable();
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn partitioned_leader_should_be_replaced_and_rejoin_once_healed() {
+    let cluster = TestCluster::start_with_proxy(3);
+    let leader_id = cluster.wait_for_leader().await;
+    let recorder = Recorder::new(1, Rc::new(RefCell::new(History::new())));
+    let client = cluster.client().await;
+    let stream_id = 1;
+    recorder
+        .create_stream(&client, stream_id, 3, 2)
+        .await
+        .expect("Failed to create the stream.");
+    wait_for_stream(&cluster, stream_id).await;
+    let next_id = Cell::new(1);
+    let mut acknowledged_ids = append_eventually(&recorder, &client, stream_id, &next_id).await;
+
+    for node in cluster.nodes().iter().filter(|node| node.id != leader_id) {
+        cluster.partition(leader_id, node.id).await;
+    }
+    let new_leader_id = cluster.wait_for_leader().await;
+    assert_ne!(new_leader_id, leader_id);
+    acknowledged_ids.extend(append_eventually(&recorder, &client, stream_id, &next_id).await);
+
+    cluster.heal().await;
+    // The old leader has stepped down and follows the same leader as the rest of the nodes.
+    let leader_id = cluster.wait_for_leader().await;
+    for node in cluster.nodes() {
+        eventually(
+            FAILOVER_TIMEOUT,
+            "The node has not rejoined the cluster.",
+            || async {
+                node.get_metadata(ReadConsistency::Relaxed)
+                    .await
+                    .is_ok_and(|metadata| metadata.leader_id == Some(leader_id))
+            },
+        )
+        .await;
+    }
+    acknowledged_ids.extend(append_eventually(&recorder, &client, stream_id, &next_id).await);
+
+    let messages = recorder
+        .poll(&client, stream_id, 0, 1000)
+        .await
+        .expect("Failed to poll the messages.");
+    let ids = messages
+        .iter()
+        .map(|message| message.id)
+        .collect::<Vec<_>>();
+    for id in acknowledged_ids {
+        assert!(ids.contains(&id), "Acknowledged message ID: {id} is lost.");
+    }
+    recorder.assert_linearizable();
+}
+
+async fn wait_for_stream(cluster: &TestCluster, stream_id: u64) {
+    for node in cluster.nodes() {
+        eventually(
+            TIMEOUT,
+            "The stream has not been created on all the nodes.",
+            || async {
+                node.get_streams()
+                    .await
+                    .is_ok_and(|streams| streams.iter().any(|stream| stream.id == stream_id))
+            },
+        )
+        .await;
+    }
+}
+
+/// Appends 5 messages, retrying each until it's acknowledged, and returns the IDs of the acknowledged ones.
+/// Each attempt gets its own ID, as the failed one might still have been appended.
+async fn append_eventually(
+    recorder: &Recorder,
+    client: &ClusterClient,
+    stream_id: u64,
+    next_id: &Cell<u64>,
+) -> Vec<u64> {
+    let mut acknowledged_ids = Vec::new();
+    for _ in 0..5 {
+        let acknowledged_id = Cell::new(None);
+        eventually(
+            FAILOVER_TIMEOUT,
+            "The message has not been appended.",
+            || async {
+                let id = next_id.get();
+                next_id.set(id + 1);
+                let appended = recorder.append(client, stream_id, &[id]).await.is_ok();
+                if appended {
+                    acknowledged_id.set(Some(id));
+                }
+                appended
+            },
+        )
+        .await;
+        acknowledged_ids.extend(acknowledged_id.get());
+    }
+    acknowledged_ids
+}
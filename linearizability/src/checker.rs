@@ -0,0 +1,370 @@
+use crate::history::{Entry, History, Operation, Outcome};
+use crate::model::StreamModel;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::time::Instant;
+
+/// The number of the last linearized operations, which are included in the violation.
+const VIOLATION_CONTEXT: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub stream_id: u64,
+    /// The longest order of the operations, which could have been linearized.
+    pub linearized: Vec<String>,
+    /// The operations, none of which could have been linearized next.
+    pub blocked: Vec<String>,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "The history of stream with ID: {} is not linearizable, linearized {} operations, the last ones:",
+            self.stream_id,
+            self.linearized.len()
+        )?;
+        let skip = self.linearized.len().saturating_sub(VIOLATION_CONTEXT);
+        for operation in self.linearized.iter().skip(skip) {
+            writeln!(f, "  {operation}")?;
+        }
+        writeln!(f, "none of which could be followed by:")?;
+        for operation in &self.blocked {
+            writeln!(f, "  {operation}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the history, the streams are independent of each other, so each of them is checked on its own.
+pub fn check(history: &History) -> Result<(), Violation> {
+    let mut streams = BTreeMap::<u64, Vec<&Entry>>::new();
+    // The offsets of the polled messages, each of their IDs is appended only once.
+    let mut polled_offsets = HashMap::new();
+    for entry in history.entries() {
+        if let Some(Outcome::Messages(messages)) = entry.definite_outcome() {
+            for (offset, id) in messages {
+                polled_offsets
+                    .entry((entry.operation.stream_id(), *id))
+                    .or_insert(*offset);
+            }
+        }
+    }
+
+    for entry in history.entries() {
+        let stream_id = entry.operation.stream_id();
+        if entry.definite_outcome().is_none() {
+            match &entry.operation {
+                // The failed poll has no effect, so there is nothing to check.
+                Operation::PollMessages { .. } => continue,
+                // The failed append, which has never been polled, can't be told apart from the one
+                // which hasn't taken effect, as the message IDs are unique.
+                Operation::AppendMessages { ids, .. }
+                    if !ids
+                        .iter()
+                        .any(|id| polled_offsets.contains_key(&(stream_id, *id))) =>
+                {
+                    continue
+                }
+                _ => {}
+            }
+        }
+
+        streams.entry(stream_id).or_default().push(entry);
+    }
+
+    for (stream_id, entries) in streams {
+        StreamChecker::new(entries, &polled_offsets).check(stream_id)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Call<'a> {
+    entry: &'a Entry,
+    /// The failed operation might take effect at any time after its invocation.
+    completed_at: Option<Instant>,
+    outcome: Option<&'a Outcome>,
+    /// The failed append, which has been polled, must have taken effect.
+    required: bool,
+    /// The offset, at which the polled append has taken effect, it can't be linearized anywhere else.
+    polled_offset: Option<u64>,
+}
+
+/// Searches for the linearization in the Wing & Gong manner, with the states already visited being
+/// skipped, as in Lowe's and Porcupine's improvements.
+#[derive(Debug)]
+struct StreamChecker<'a> {
+    calls: Vec<Call<'a>>,
+    linearized: Vec<bool>,
+    order: Vec<usize>,
+    visited: HashSet<(Vec<bool>, StreamModel)>,
+    longest: Vec<usize>,
+    blocked: Vec<usize>,
+}
+
+impl<'a> StreamChecker<'a> {
+    fn new(mut entries: Vec<&'a Entry>, polled_offsets: &HashMap<(u64, u64), u64>) -> Self {
+        entries.sort_by_key(|entry| entry.invoked_at);
+        let calls = entries
+            .into_iter()
+            .map(|entry| {
+                let outcome = entry.definite_outcome();
+                let (polled, polled_offset) = match &entry.operation {
+                    Operation::AppendMessages { stream_id, ids } => (
+                        ids.iter()
+                            .any(|id| polled_offsets.contains_key(&(*stream_id, *id))),
+                        ids.iter().enumerate().find_map(|(index, id)| {
+                            polled_offsets
+                                .get(&(*stream_id, *id))
+                                .and_then(|offset| offset.checked_sub(index as u64))
+                        }),
+                    ),
+                    _ => (false, None),
+                };
+                Call {
+                    entry,
+                    completed_at: outcome.and(entry.completed_at),
+                    outcome,
+                    required: outcome.is_some() || polled,
+                    polled_offset,
+                }
+            })
+            .collect::<Vec<_>>();
+        Self {
+            linearized: vec![false; calls.len()],
+            calls,
+            order: Vec::new(),
+            visited: HashSet::new(),
+            longest: Vec::new(),
+            blocked: Vec::new(),
+        }
+    }
+
+    fn check(mut self, stream_id: u64) -> Result<(), Violation> {
+        let remaining = self.calls.iter().filter(|call| call.required).count();
+        if self.search(&StreamModel::default(), remaining) {
+            return Ok(());
+        }
+
+        Err(Violation {
+            stream_id,
+            linearized: self
+                .longest
+                .iter()
+                .map(|index| self.calls[*index].entry.to_string())
+                .collect(),
+            blocked: self
+                .blocked
+                .iter()
+                .map(|index| self.calls[*index].entry.to_string())
+                .collect(),
+        })
+    }
+
+    /// Tries every operation, which could have been linearized next: it has been invoked before
+    /// any of the remaining completed operations has completed.
+    fn search(&mut self, model: &StreamModel, remaining: usize) -> bool {
+        if remaining == 0 {
+            return true;
+        }
+
+        if !self
+            .visited
+            .insert((self.linearized.clone(), model.clone()))
+        {
+            return false;
+        }
+
+        let deadline = self
+            .calls
+            .iter()
+            .zip(&self.linearized)
+            .filter(|(_, linearized)| !**linearized)
+            .filter_map(|(call, _)| call.completed_at)
+            .min();
+        let candidates = (0..self.calls.len())
+            .filter(|index| !self.linearized[*index])
+            .take_while(|index| deadline.is_none_or(|at| self.calls[*index].entry.invoked_at <= at))
+            .collect::<Vec<_>>();
+        for index in candidates.iter().copied() {
+            let call = &self.calls[index];
+            // Any other order fails once the poll is linearized, so it isn't searched at all.
+            if call
+                .polled_offset
+                .is_some_and(|offset| model.log_end_offset() != Some(offset))
+            {
+                continue;
+            }
+
+            let Some(next) = model.apply(&call.entry.operation, call.outcome) else {
+                continue;
+            };
+
+            let remaining = remaining - call.required as usize;
+            self.linearized[index] = true;
+            self.order.push(index);
+            if self.search(&next, remaining) {
+                return true;
+            }
+            self.order.pop();
+            self.linearized[index] = false;
+        }
+
+        if self.order.len() >= self.longest.len() {
+            self.longest = self.order.clone();
+            self.blocked = candidates
+                .into_iter()
+                .filter(|index| self.calls[*index].required)
+                .collect();
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    struct Recorder {
+        history: History,
+        start: Instant,
+    }
+
+    impl Recorder {
+        fn new() -> Self {
+            Self {
+                history: History::new(),
+                start: Instant::now(),
+            }
+        }
+
+        fn record(
+            &mut self,
+            client_id: u64,
+            operation: Operation,
+            from: u64,
+            to: u64,
+            outcome: Outcome,
+        ) {
+            let index = self.history.invoke(client_id, operation, self.at(from));
+            self.history.complete(index, outcome, self.at(to));
+        }
+
+        fn at(&self, millis: u64) -> Instant {
+            self.start + Duration::from_millis(millis)
+        }
+    }
+
+    fn append(ids: &[u64]) -> Operation {
+        Operation::AppendMessages {
+            stream_id: 1,
+            ids: ids.to_vec(),
+        }
+    }
+
+    fn poll() -> Operation {
+        Operation::PollMessages {
+            stream_id: 1,
+            offset: 0,
+            count: 10,
+        }
+    }
+
+    fn poll_all() -> Operation {
+        Operation::PollMessages {
+            stream_id: 1,
+            offset: 0,
+            count: 100,
+        }
+    }
+
+    fn messages(ids: &[u64]) -> Outcome {
+        Outcome::Messages(
+            ids.iter()
+                .copied()
+                .enumerate()
+                .map(|(offset, id)| (offset as u64, id))
+                .collect(),
+        )
+    }
+
+    fn created() -> Recorder {
+        let mut recorder = Recorder::new();
+        recorder.record(
+            1,
+            Operation::CreateStream { stream_id: 1 },
+            0,
+            1,
+            Outcome::Ok,
+        );
+        recorder
+    }
+
+    #[test]
+    fn concurrent_operations_should_be_linearizable_in_any_valid_order() {
+        let mut recorder = created();
+        recorder.record(1, append(&[1]), 10, 20, Outcome::Ok);
+        recorder.record(2, append(&[2]), 11, 21, Outcome::Ok);
+        recorder.record(3, poll(), 12, 22, messages(&[2]));
+        recorder.record(3, poll(), 30, 40, messages(&[2, 1]));
+        assert!(check(&recorder.history).is_ok());
+    }
+
+    #[test]
+    fn failed_append_may_take_effect_later() {
+        let mut recorder = created();
+        recorder.record(1, append(&[1]), 10, 20, Outcome::Failed);
+        recorder.record(2, poll(), 30, 40, messages(&[]));
+        recorder.record(2, poll(), 50, 60, messages(&[1]));
+        assert!(check(&recorder.history).is_ok());
+    }
+
+    #[test]
+    fn many_failed_appends_polled_in_reverse_order_should_be_linearizable() {
+        let mut recorder = created();
+        let ids = (1..=30).collect::<Vec<u64>>();
+        for id in &ids {
+            recorder.record(*id, append(&[*id]), 10 + *id, 100, Outcome::Failed);
+        }
+        let polled = ids.iter().rev().copied().collect::<Vec<u64>>();
+        recorder.record(31, poll_all(), 200, 210, messages(&polled));
+        assert!(check(&recorder.history).is_ok());
+    }
+
+    #[test]
+    fn lost_acknowledged_append_should_be_detected() {
+        let mut recorder = created();
+        recorder.record(1, append(&[1]), 10, 20, Outcome::Ok);
+        recorder.record(1, append(&[2]), 30, 40, Outcome::Ok);
+        recorder.record(2, poll(), 50, 60, messages(&[2]));
+        let violation = check(&recorder.history).unwrap_err();
+        assert_eq!(violation.stream_id, 1);
+        assert_eq!(violation.blocked.len(), 1);
+    }
+
+    #[test]
+    fn stale_read_should_be_detected() {
+        let mut recorder = created();
+        recorder.record(1, append(&[1]), 10, 20, Outcome::Ok);
+        recorder.record(2, poll(), 30, 40, messages(&[1]));
+        recorder.record(3, poll(), 50, 60, messages(&[]));
+        assert!(check(&recorder.history).is_err());
+    }
+
+    #[test]
+    fn poll_of_deleted_stream_should_be_detected() {
+        let mut recorder = created();
+        recorder.record(1, append(&[1]), 10, 20, Outcome::Ok);
+        recorder.record(
+            1,
+            Operation::DeleteStream { stream_id: 1 },
+            30,
+            40,
+            Outcome::Ok,
+        );
+        recorder.record(2, poll(), 50, 60, messages(&[1]));
+        assert!(check(&recorder.history).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt::{Display, Formatter};
+use std::time::Instant;
+
+pub type ClientId = u64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    CreateStream {
+        stream_id: u64,
+    },
+    DeleteStream {
+        stream_id: u64,
+    },
+    AppendMessages {
+        stream_id: u64,
+        ids: Vec<u64>,
+    },
+    PollMessages {
+        stream_id: u64,
+        offset: u64,
+        count: u64,
+    },
+}
+
+impl Operation {
+    pub fn stream_id(&self) -> u64 {
+        match self {
+            Operation::CreateStream { stream_id }
+            | Operation::DeleteStream { stream_id }
+            | Operation::AppendMessages { stream_id, .. }
+            | Operation::PollMessages { stream_id, .. } => *stream_id,
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::CreateStream { stream_id } => write!(f, "create stream {stream_id}"),
+            Operation::DeleteStream { stream_id } => write!(f, "delete stream {stream_id}"),
+            Operation::AppendMessages { stream_id, ids } => {
+                write!(f, "append {ids:?} to stream {stream_id}")
+            }
+            Operation::PollMessages {
+                stream_id,
+                offset,
+                count,
+            } => write!(f, "poll {count} from offset {offset} of stream {stream_id}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    /// The offsets and IDs of the polled messages.
+    Messages(Vec<(u64, u64)>),
+    /// The operation has failed or timed out, so it might have taken effect or not.
+    Failed,
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Ok => write!(f, "ok"),
+            Outcome::Messages(messages) => write!(f, "messages {messages:?}"),
+            Outcome::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub client_id: ClientId,
+    pub operation: Operation,
+    pub invoked_at: Instant,
+    /// The entry which has never been completed is treated as the failed one.
+    pub completed_at: Option<Instant>,
+    pub outcome: Option<Outcome>,
+}
+
+impl Entry {
+    /// Returns the outcome, if the operation has definitely taken effect.
+    pub fn definite_outcome(&self) -> Option<&Outcome> {
+        self.outcome
+            .as_ref()
+            .filter(|outcome| **outcome != Outcome::Failed)
+    }
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let outcome = self
+            .outcome
+            .as_ref()
+            .map_or("incomplete".to_string(), |outcome| outcome.to_string());
+        write!(
+            f,
+            "client {}: {} -> {outcome}",
+            self.client_id, self.operation
+        )
+    }
+}
+
+/// The operations of all the clients, in the order of their invocation.
+#[derive(Debug, Default, Clone)]
+pub struct History {
+    entries: Vec<Entry>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the invoked operation and returns its index, which completes it later on.
+    pub fn invoke(&mut self, client_id: ClientId, operation: Operation, at: Instant) -> usize {
+        self.entries.push(Entry {
+            client_id,
+            operation,
+            invoked_at: at,
+            completed_at: None,
+            outcome: None,
+        });
+        self.entries.len() - 1
+    }
+
+    pub fn complete(&mut self, index: usize, outcome: Outcome, at: Instant) {
+        let entry = &mut self.entries[index];
+        entry.completed_at = Some(at);
+        entry.outcome = Some(outcome);
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
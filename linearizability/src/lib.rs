@@ -0,0 +1,7 @@
+//! Records the operations of the clients against the cluster and checks whether the history
+//! is linearizable, by searching for the order in which the operations could have been applied
+//! one at a time to the sequential model of the streams, within their invocation and completion.
+
+pub mod checker;
+pub mod history;
+pub mod model;
@@ -0,0 +1,59 @@
+use crate::history::{Operation, Outcome};
+
+/// The sequential model of a single stream, which holds the IDs of its messages in the order of their offsets.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct StreamModel {
+    messages: Option<Vec<u64>>,
+}
+
+impl StreamModel {
+    /// Returns the offset of the next appended message, or none, if the stream doesn't exist.
+    pub fn log_end_offset(&self) -> Option<u64> {
+        self.messages.as_ref().map(|messages| messages.len() as u64)
+    }
+
+    /// Applies the operation and returns the next state, if the operation could have resulted in the outcome.
+    /// The outcome is missing for the operation which might have taken effect, so it only has to be valid.
+    pub fn apply(&self, operation: &Operation, outcome: Option<&Outcome>) -> Option<StreamModel> {
+        match operation {
+            Operation::CreateStream { .. } => {
+                if self.messages.is_some() {
+                    return None;
+                }
+
+                Some(StreamModel {
+                    messages: Some(Vec::new()),
+                })
+            }
+            Operation::DeleteStream { .. } => {
+                self.messages.as_ref()?;
+                Some(StreamModel { messages: None })
+            }
+            Operation::AppendMessages { ids, .. } => {
+                let mut messages = self.messages.clone()?;
+                messages.extend(ids);
+                Some(StreamModel {
+                    messages: Some(messages),
+                })
+            }
+            Operation::PollMessages { offset, count, .. } => {
+                let messages = self.messages.as_ref()?;
+                let Some(Outcome::Messages(polled)) = outcome else {
+                    return Some(self.clone());
+                };
+
+                let start = (*offset as usize).min(messages.len());
+                let end = offset.saturating_add(*count).min(messages.len() as u64) as usize;
+                let expected = messages[start..end]
+                    .iter()
+                    .enumerate()
+                    .map(|(index, id)| ((start + index) as u64, *id));
+                if !expected.eq(polled.iter().copied()) {
+                    return None;
+                }
+
+                Some(self.clone())
+            }
+        }
+    }
+}
@@ -1,21 +1,26 @@
 use crate::clusters::elections::election::{ElectionManager, ElectionTimeout};
 use crate::clusters::nodes::node::{Node, Resiliency};
 use crate::clusters::state::State;
+use crate::clusters::state_machine::StateMachines;
+use crate::clusters::streams::metadata_machine::StreamMetadataMachine;
 use crate::configs::config::{ClusterConfig, RequiredAcknowledgements};
+use crate::environment;
+use crate::metadata::MetadataStore;
 use crate::streaming::streamer::Streamer;
-use crate::types::{Index, NodeId};
+use crate::types::{Index, NodeId, Term};
 use bytes::Bytes;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use futures::lock::Mutex;
-use monoio::time::sleep;
 use sdk::error::SystemError;
 use sdk::models::appended_state::AppendedState;
 use sdk::models::log_entry::LogEntry;
 use sdk::models::message::Message;
-use sdk::models::metadata::{Metadata, NodeInfo, StreamInfo};
+use sdk::models::metadata::{Metadata, NodeInfo, NodeRole, StreamInfo};
 use sdk::models::node_state::NodeState;
 use sdk::models::stream::Stream;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
 use std::rc::Rc;
 use std::time::Duration;
 use tracing::{error, info};
@@ -45,14 +50,55 @@ impl Display for ClusterHealth {
 
 #[derive(Debug)]
 pub struct Cluster {
-    pub nodes: HashMap<u64, Rc<ClusterNode>>,
-    pub streamer: Mutex<Streamer>,
+    pub nodes: Mutex<BTreeMap<u64, Rc<ClusterNode>>>,
+    pub membership_change: Mutex<Option<NodeId>>,
+    /// The node caught up by the leader, which joins the cluster once its addition is applied.
+    pub(crate) added_node: Mutex<Option<Rc<ClusterNode>>>,
+    /// The streams are created one at a time, so that each one is checked against the ones created before.
+    pub(crate) stream_creation: Mutex<()>,
+    pub is_member: Mutex<bool>,
+    /// The address of the latest connection opened by each node, which has sent the hello.
+    /// The older connections might still be closed once the node has reconnected, e.g. after the network partition.
+    pub(crate) node_connections: Mutex<HashMap<NodeId, SocketAddr>>,
+    self_node: Rc<ClusterNode>,
+    self_node_info: SelfNode,
+    resiliency: Resiliency,
+    pub streamer: Rc<Mutex<Streamer>>,
+    pub state_machines: StateMachines,
     pub secret: String,
     pub state: Mutex<State>,
+    pub metadata: Rc<dyn MetadataStore>,
+    /// The index of the last committed entry applied to the state machines in the log order, without any gaps.
+    pub applied_index: Mutex<Index>,
+    /// The index of the last saved state snapshot, which is saved again once the threshold of entries is applied.
+    pub snapshot_index: Mutex<Index>,
+    pub snapshot_threshold: u64,
     pub election_manager: ElectionManager,
     pub heartbeat_interval: Duration,
     pub info_interval: Duration,
     pub required_acknowledgements: RequiredAcknowledgements,
+    pub leadership_transfer: Mutex<Option<NodeId>>,
+    pub leadership_transfer_timeout: Duration,
+    pub replication_retry_interval: Duration,
+    pub commit_timeout: Duration,
+    pub max_append_entries: usize,
+    pub max_append_entries_in_flight: usize,
+    pub fetch_interval: Duration,
+    pub max_fetch_messages: u64,
+    pub max_replica_lag: Duration,
+    pub leader_rebalance_interval: Duration,
+    pub bootstrap_chunk_size: u32,
+    /// The stream and state directories, which are monitored for the disk usage.
+    pub disk_paths: Vec<String>,
+    pub disk_check_interval: Duration,
+    pub disk_soft_limit: u8,
+    pub disk_hard_limit: u8,
+    pub disk_usage: Mutex<u8>,
+    pub replication_trigger: UnboundedSender<()>,
+    pub replication_trigger_receiver: Mutex<Option<UnboundedReceiver<()>>>,
+    /// The streams assigned to this node by the applied entries, which are bootstrapped by the fetcher.
+    pub bootstrap_trigger: UnboundedSender<u64>,
+    pub bootstrap_trigger_receiver: Mutex<Option<UnboundedReceiver<u64>>>,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -60,12 +106,24 @@ pub enum ClusterNodeState {
     Leader,
     Candidate,
     Follower,
+    Learner,
 }
 
 #[derive(Debug)]
 pub(crate) struct ClusterNode {
     pub state: Mutex<ClusterNodeState>,
     pub node: Node,
+    pub progress: ReplicationProgress,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ReplicationProgress {
+    pub next_index: Mutex<Index>,
+    pub match_index: Mutex<Index>,
+    /// The commit index acknowledged by the node.
+    pub commit_index: Mutex<Index>,
+    /// Held by the single task, which replicates the entries to the node.
+    pub replicating: Mutex<()>,
 }
 
 impl ClusterNode {
@@ -76,6 +134,10 @@ impl ClusterNode {
     pub async fn is_leader(&self) -> bool {
         *self.state.lock().await == ClusterNodeState::Leader
     }
+
+    pub async fn is_learner(&self) -> bool {
+        *self.state.lock().await == ClusterNodeState::Learner
+    }
 }
 
 impl Display for ClusterNodeState {
@@ -84,6 +146,7 @@ impl Display for ClusterNodeState {
             ClusterNodeState::Leader => write!(f, "leader"),
             ClusterNodeState::Follower => write!(f, "follower"),
             ClusterNodeState::Candidate => write!(f, "candidate"),
+            ClusterNodeState::Learner => write!(f, "learner"),
         }
     }
 }
@@ -94,90 +157,183 @@ impl Cluster {
         config: &ClusterConfig,
         streamer: Streamer,
         state: State,
+        metadata: Rc<dyn MetadataStore>,
     ) -> Result<Self, SystemError> {
-        let mut nodes = HashMap::new();
+        let mut nodes = BTreeMap::new();
         let self_node_id = self_node.id;
-        nodes.insert(
-            self_node.id,
-            Rc::new(ClusterNode {
-                state: Mutex::new(ClusterNodeState::Leader),
-                node: Self::create_node(
-                    self_node.id,
-                    &config.secret,
-                    &self_node.name,
-                    &self_node.internal_address,
-                    &self_node.public_address,
-                    self_node.clone(),
-                    config,
-                )?,
-            }),
-        );
+        let resiliency = Resiliency {
+            heartbeat_interval: config.heartbeat_interval,
+            reconnection_retries: config.reconnection_retries,
+            reconnection_interval: config.reconnection_interval,
+            request_timeout: config.max_timeout as u64,
+        };
+        // The node becomes the leader only once it has won the election.
+        let self_node_state = if config.learner {
+            ClusterNodeState::Learner
+        } else {
+            ClusterNodeState::Candidate
+        };
+        let self_cluster_node = Rc::new(ClusterNode {
+            state: Mutex::new(self_node_state),
+            node: Node::new(
+                self_node.id,
+                &config.secret,
+                &self_node.name,
+                &self_node.internal_address,
+                &self_node.public_address,
+                self_node.clone(),
+                resiliency,
+            )?,
+            progress: ReplicationProgress::default(),
+        });
+        nodes.insert(self_node.id, self_cluster_node.clone());
 
+        let mut voters_count = if config.learner { 0 } else { 1 };
         for node in &config.nodes {
+            let node_state = if node.learner {
+                ClusterNodeState::Learner
+            } else {
+                voters_count += 1;
+                ClusterNodeState::Candidate
+            };
             let cluster_node = ClusterNode {
-                state: Mutex::new(ClusterNodeState::Candidate),
-                node: Self::create_node(
+                state: Mutex::new(node_state),
+                node: Node::new(
                     node.id,
                     &config.secret,
                     &node.name,
                     &node.internal_address,
                     &node.public_address,
                     self_node.clone(),
-                    config,
+                    resiliency,
                 )?,
+                progress: ReplicationProgress::default(),
             };
             nodes.insert(node.id, Rc::new(cluster_node));
         }
 
+        let disk_paths = vec![
+            streamer.get_path().to_string(),
+            state.get_path().to_string(),
+        ];
+        let streamer = Rc::new(Mutex::new(streamer));
+        let mut state_machines = StateMachines::default();
+        state_machines.register(Box::new(StreamMetadataMachine::new(streamer.clone())));
+        let (replication_trigger, replication_trigger_receiver) = unbounded();
+        let (bootstrap_trigger, bootstrap_trigger_receiver) = unbounded();
         Ok(Self {
             heartbeat_interval: Duration::from_millis(config.heartbeat_interval),
             info_interval: Duration::from_millis(config.info_interval),
             election_manager: ElectionManager::new(
                 self_node_id,
-                nodes.len() as u64,
+                voters_count,
                 ElectionTimeout::new(
                     config.election_timeout_range_from,
                     config.election_timeout_range_to,
                 ),
             ),
-            nodes,
+            nodes: Mutex::new(nodes),
+            membership_change: Mutex::new(None),
+            added_node: Mutex::new(None),
+            stream_creation: Mutex::new(()),
+            is_member: Mutex::new(!config.join_existing),
+            node_connections: Mutex::new(HashMap::new()),
+            self_node: self_cluster_node,
+            self_node_info: self_node,
+            resiliency,
             state: Mutex::new(state),
-            streamer: Mutex::new(streamer),
+            metadata,
+            applied_index: Mutex::new(0),
+            snapshot_index: Mutex::new(0),
+            snapshot_threshold: config.snapshot_threshold.max(1),
+            streamer,
+            state_machines,
             secret: config.secret.to_string(),
             required_acknowledgements: config.required_acknowledgements,
+            leadership_transfer: Mutex::new(None),
+            leadership_transfer_timeout: Duration::from_millis(config.leadership_transfer_timeout),
+            replication_retry_interval: Duration::from_millis(config.replication_retry_interval),
+            commit_timeout: Duration::from_millis(config.commit_timeout),
+            max_append_entries: config.max_append_entries as usize,
+            max_append_entries_in_flight: config.max_append_entries_in_flight.max(1) as usize,
+            fetch_interval: Duration::from_millis(config.fetch_interval),
+            max_fetch_messages: config.max_fetch_messages,
+            max_replica_lag: Duration::from_millis(config.max_replica_lag),
+            leader_rebalance_interval: Duration::from_millis(config.leader_rebalance_interval),
+            bootstrap_chunk_size: config.bootstrap_chunk_size,
+            disk_paths,
+            disk_check_interval: Duration::from_millis(config.disk_check_interval),
+            disk_soft_limit: config.disk_soft_limit,
+            disk_hard_limit: config.disk_hard_limit,
+            disk_usage: Mutex::new(0),
+            replication_trigger,
+            replication_trigger_receiver: Mutex::new(Some(replication_trigger_receiver)),
+            bootstrap_trigger,
+            bootstrap_trigger_receiver: Mutex::new(Some(bootstrap_trigger_receiver)),
         })
     }
 
-    fn create_node(
-        id: u64,
-        secret: &str,
-        node_name: &str,
-        node_address: &str,
+    pub(crate) fn create_node(
+        &self,
+        id: NodeId,
+        name: &str,
+        internal_address: &str,
         public_address: &str,
-        self_node: SelfNode,
-        config: &ClusterConfig,
     ) -> Result<Node, SystemError> {
         Node::new(
             id,
-            secret,
-            node_name,
-            node_address,
+            &self.secret,
+            name,
+            internal_address,
             public_address,
-            self_node,
-            Resiliency {
-                heartbeat_interval: config.heartbeat_interval,
-                reconnection_retries: config.reconnection_retries,
-                reconnection_interval: config.reconnection_interval,
-            },
+            self.self_node_info.clone(),
+            self.resiliency,
         )
     }
 
+    pub async fn get_nodes(&self) -> Vec<Rc<ClusterNode>> {
+        self.nodes.lock().await.values().cloned().collect()
+    }
+
+    pub async fn get_node(&self, node_id: NodeId) -> Option<Rc<ClusterNode>> {
+        self.nodes.lock().await.get(&node_id).cloned()
+    }
+
+    pub async fn get_nodes_count(&self) -> u64 {
+        self.nodes.lock().await.len() as u64
+    }
+
+    pub async fn get_voter_ids(&self) -> Vec<NodeId> {
+        let mut voter_ids = Vec::new();
+        for node in self.get_nodes().await {
+            if !node.is_learner().await {
+                voter_ids.push(node.node.id);
+            }
+        }
+        voter_ids.sort_unstable();
+        voter_ids
+    }
+
+    pub async fn get_voters_count(&self) -> u64 {
+        let mut voters_count = 0;
+        for node in self.get_nodes().await {
+            if !node.is_learner().await {
+                voters_count += 1;
+            }
+        }
+        voters_count
+    }
+
     pub async fn init(&self) -> Result<(), SystemError> {
         info!("Initializing cluster...");
-        let entries = self.state.lock().await.load_entries(None).await;
-        self.replay_state(None, &entries).await?;
-        let term = self.state.lock().await.term;
-        self.election_manager.set_term(term).await;
+        self.restore_state_snapshot().await?;
+        self.restore_membership().await?;
+        self.apply_committed_state(None).await?;
+        let (term, voted_for) = {
+            let state = self.state.lock().await;
+            (state.term, state.voted_for)
+        };
+        self.election_manager.restore(term, voted_for).await;
         self.connect_to_all_nodes().await?;
         let available_leaders = self.sync_nodes_state().await?;
         if available_leaders.is_empty() {
@@ -192,8 +348,17 @@ impl Cluster {
                 .collect::<Vec<String>>()
                 .join(", ")
         );
-        self.sync_state_and_streams_from_other_nodes(&available_leaders)
-            .await?;
+        if !self.is_member().await {
+            info!("This node is joining the existing cluster and will be synced by the leader once added.");
+            self.get_self_node()
+                .unwrap()
+                .node
+                .complete_initial_sync()
+                .await;
+            return Ok(());
+        }
+
+        self.sync_streams_from_other_nodes().await;
         self.get_self_node()
             .unwrap()
             .node
@@ -206,9 +371,10 @@ impl Cluster {
     async fn connect_to_all_nodes(&self) -> Result<(), SystemError> {
         info!("Connecting all cluster nodes...");
         let mut connections = 0;
-        let expected_connections = (self.nodes.len() / 2) + 1;
-        for node in self.nodes.values() {
-            if Self::init_node_connection(node.clone()).await.is_ok() {
+        let expected_connections = self.get_quorum_count().await;
+        for node in self.get_nodes().await {
+            let is_learner = node.is_learner().await;
+            if Self::init_node_connection(node).await.is_ok() && !is_learner {
                 connections += 1;
             }
         }
@@ -228,7 +394,7 @@ impl Cluster {
     async fn wait_for_all_nodes_to_complete_initial_sync(&self) -> Result<(), SystemError> {
         info!("Waiting for all nodes to complete initial sync...");
         let mut synced_nodes = 0;
-        for node in self.nodes.values() {
+        for node in self.get_nodes().await {
             if node.node.is_self_node() {
                 continue;
             }
@@ -244,15 +410,14 @@ impl Cluster {
                     synced_nodes += 1;
                     break;
                 }
-                sleep(Duration::from_millis(200)).await;
+                environment::sleep(Duration::from_millis(200)).await;
             }
         }
 
-        if synced_nodes < self.get_quorum_count() {
+        let quorum = self.get_quorum_count().await;
+        if synced_nodes < quorum {
             error!(
-                "Not enough cluster nodes completed initial sync. Expected: {}, actual: {}",
-                self.get_quorum_count(),
-                synced_nodes
+                "Not enough cluster nodes completed initial sync. Expected: {quorum}, actual: {synced_nodes}"
             );
             return Err(SystemError::UnhealthyCluster);
         }
@@ -272,28 +437,30 @@ impl Cluster {
     }
 
     pub async fn connect_to(&self, node_id: u64) -> Result<(), SystemError> {
-        let cluster_node = self.nodes.get(&node_id);
+        let cluster_node = self.get_node(node_id).await;
         if cluster_node.is_none() {
             return Err(SystemError::InvalidNode(node_id));
         }
 
-        Self::connect_to_node(cluster_node.unwrap().clone()).await
+        Self::connect_to_node(cluster_node.unwrap()).await
     }
 
     pub fn get_self_node(&self) -> Option<Rc<ClusterNode>> {
-        self.nodes
-            .values()
-            .find(|cluster_node| cluster_node.node.is_self_node())
-            .cloned()
+        Some(self.self_node.clone())
     }
 
-    pub async fn handle_disconnected_node(&self, node_id: NodeId) {
+    pub async fn handle_disconnected_node(&self, node_id: NodeId, address: SocketAddr) {
         if node_id == 0 {
             return;
         }
 
+        if self.node_connections.lock().await.get(&node_id) != Some(&address) {
+            info!("Closed the previous connection: {address} of node ID: {node_id}.");
+            return;
+        }
+
         info!("Handling disconnected node ID: {}...", node_id);
-        let cluster_node = self.nodes.get(&node_id);
+        let cluster_node = self.get_node(node_id).await;
         if cluster_node.is_none() {
             error!("Invalid node ID: {node_id}");
             return;
@@ -316,7 +483,7 @@ impl Cluster {
         info!("Handled disconnected node ID: {node_id}.");
     }
 
-    async fn connect_to_node(cluster_node: Rc<ClusterNode>) -> Result<(), SystemError> {
+    pub(crate) async fn connect_to_node(cluster_node: Rc<ClusterNode>) -> Result<(), SystemError> {
         info!(
             "Connecting to cluster node: {}, ID: {}...",
             cluster_node.node.name, cluster_node.node.id
@@ -344,21 +511,32 @@ impl Cluster {
         let node_id = cluster_node.node.id;
         let node_name = cluster_node.node.name.clone();
         info!("Starting heartbeat for node: {node_name}, ID: {node_id}...");
-        monoio::spawn(async move {
-            if cluster_node.node.start_heartbeat().await.is_err() {
-                cluster_node
-                    .node
-                    .disconnect()
-                    .await
-                    .unwrap_or_else(|error| {
-                        error!(
-                            "Failed to disconnect from cluster node ID: {node_id}, error: {error}"
-                        );
-                    });
-                error!(
-                    "Failed to start heartbeat for cluster node: {}, ID: {node_id}.",
+        environment::spawn(async move {
+            if cluster_node.node.start_heartbeat().await.is_ok() {
+                return;
+            }
+
+            cluster_node
+                .node
+                .disconnect()
+                .await
+                .unwrap_or_else(|error| {
+                    error!("Failed to disconnect from cluster node ID: {node_id}, error: {error}");
+                });
+            error!(
+                "Failed to start heartbeat for cluster node: {}, ID: {node_id}.",
+                cluster_node.node.name
+            );
+            // The connection might have broken, e.g. due to the network partition, in which case the node
+            // might never send the hello, which would make this node connect to it again.
+            if !cluster_node.node.is_closed().await {
+                info!(
+                    "Reconnecting to cluster node: {}, ID: {node_id}...",
                     cluster_node.node.name
                 );
+                if let Err(error) = Self::connect_to_node(cluster_node).await {
+                    error!("Failed to reconnect to cluster node ID: {node_id}, error: {error}");
+                }
             }
         });
 
@@ -368,15 +546,15 @@ impl Cluster {
 
     pub async fn disconnect(&self) -> Result<(), SystemError> {
         info!("Disconnecting all cluster nodes...");
-        for cluster_node in self.nodes.values() {
-            cluster_node.node.disconnect().await?;
+        for cluster_node in self.get_nodes().await {
+            cluster_node.node.close().await?;
         }
         info!("All cluster nodes disconnected.");
         Ok(())
     }
 
     pub async fn is_connected_to(&self, node_id: u64) -> bool {
-        let node = self.nodes.get(&node_id);
+        let node = self.get_node(node_id).await;
         if node.is_none() {
             return false;
         }
@@ -411,29 +589,50 @@ impl Cluster {
         Err(SystemError::NotLeader)
     }
 
+    pub async fn verify_accepts_writes(&self) -> Result<(), SystemError> {
+        self.verify_is_leader().await?;
+        self.verify_is_not_fenced().await?;
+        if let Some(node_id) = *self.leadership_transfer.lock().await {
+            error!("Leadership is being transferred to node ID: {node_id}, rejecting writes.");
+            return Err(SystemError::LeadershipTransferInProgress);
+        }
+
+        Ok(())
+    }
+
+    /// Only the committed messages are served, by the stream leader and the other replicas alike.
     pub async fn poll_messages(
         &self,
         stream_id: u64,
         offset: u64,
         count: u64,
     ) -> Result<Vec<Message>, SystemError> {
-        if !self.is_leader().await {
-            return Err(SystemError::NotLeader);
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        let streamer = self.streamer.lock().await;
+        let Some(stream) = streamer.get_stream(stream_id) else {
+            return Err(SystemError::InvalidStreamId);
+        };
+
+        if stream.leader_id != self_node_id && !stream.is_replica(self_node_id) {
+            error!(
+                "This node is not a replica of stream with ID: {stream_id}, leader ID: {}.",
+                stream.leader_id
+            );
+            return Err(SystemError::NotStreamReplica(stream.leader_id));
         }
 
-        let streamer = self.streamer.lock().await;
-        Ok(streamer.poll_messages(stream_id, offset, count)?.to_vec())
+        Ok(stream.poll_committed_messages(offset, count)?.to_vec())
     }
 
+    /// The leader serves all the streams, while the other nodes serve only the ones they replicate.
     pub async fn get_streams(&self) -> Result<Vec<Stream>, SystemError> {
-        if !self.is_leader().await {
-            return Err(SystemError::NotLeader);
-        }
-
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        let is_leader = self.is_leader().await;
         let streamer = self.streamer.lock().await;
         let mut streams: Vec<Stream> = streamer
             .get_streams()
             .iter()
+            .filter(|stream| is_leader || stream.is_replica(self_node_id))
             .map(|stream| Stream {
                 id: stream.stream_id,
                 offset: stream.current_offset,
@@ -441,7 +640,7 @@ impl Cluster {
                 replication_factor: stream.replication_factor,
             })
             .collect();
-        streams.sort_by(|a, b| a.id.cmp(&b.id));
+        streams.sort_by_key(|stream| stream.id);
         Ok(streams)
     }
 
@@ -451,20 +650,26 @@ impl Cluster {
             nodes: HashMap::new(),
             streams: HashMap::new(),
         };
-        metadata.nodes = self
-            .nodes
-            .values()
-            .map(|node| {
-                (
-                    node.node.id,
-                    NodeInfo {
-                        id: node.node.id,
-                        name: node.node.name.clone(),
-                        address: node.node.public_address.clone(),
-                    },
-                )
-            })
-            .collect();
+        for node in self.get_nodes().await {
+            let role = if node.node.is_fenced().await {
+                NodeRole::Fenced
+            } else if node.is_learner().await {
+                NodeRole::Learner
+            } else if metadata.leader_id == Some(node.node.id) {
+                NodeRole::Leader
+            } else {
+                NodeRole::Follower
+            };
+            metadata.nodes.insert(
+                node.node.id,
+                NodeInfo {
+                    id: node.node.id,
+                    name: node.node.name.clone(),
+                    address: node.node.public_address.clone(),
+                    role,
+                },
+            );
+        }
 
         metadata.streams = self
             .streamer
@@ -478,6 +683,8 @@ impl Cluster {
                     StreamInfo {
                         stream_id: stream.stream_id,
                         leader_id: stream.leader_id,
+                        replicas: stream.replicas.clone(),
+                        isr: stream.isr.clone(),
                     },
                 )
             })
@@ -486,10 +693,10 @@ impl Cluster {
     }
 
     pub async fn get_health(&self) -> ClusterHealth {
-        let mut available_nodes = 1;
-        let required_nodes = (self.nodes.len() / 2) + 1;
-        for node in self.nodes.values() {
-            if node.node.is_self_node() {
+        let mut available_nodes = 0;
+        let required_nodes = self.get_quorum_count().await;
+        for node in self.get_nodes().await {
+            if node.is_learner().await {
                 continue;
             }
 
@@ -512,11 +719,13 @@ impl Cluster {
         }
 
         let self_node = self_node.unwrap();
+        let disk_usage = *self.disk_usage.lock().await;
+        let disk_status = self.get_disk_status().await;
         let state = self.state.lock().await;
         let node_state = NodeState {
             id: self_node.node.id,
             address: self_node.node.address.to_owned(),
-            last_applied: state.last_applied,
+            last_index: state.last_index,
             commit_index: state.commit_index,
             term: state.term,
             initial_sync_completed: self
@@ -525,6 +734,8 @@ impl Cluster {
                 .node
                 .initial_sync_completed()
                 .await,
+            disk_usage,
+            disk_status,
         };
         Ok(node_state)
     }
@@ -534,75 +745,39 @@ impl Cluster {
         start_index: u64,
     ) -> Result<AppendedState, SystemError> {
         let state = self.state.lock().await;
+        let start_index = start_index.max(1);
+        let count = (state.last_index + 1).saturating_sub(start_index) as usize;
         let appended_state = AppendedState {
             term: state.term,
-            last_applied: state.last_applied,
-            entries: state
-                .load_entries(None)
-                .await
-                .iter()
-                .filter(|entry| entry.index >= start_index)
-                .map(|entry| LogEntry {
-                    index: entry.index,
-                    size: entry.data.len() as u32,
-                    data: entry.data.clone(),
-                })
-                .collect(),
+            last_index: state.last_index,
+            entries: state.load_entries_from(start_index, count).await?,
         };
         Ok(appended_state)
     }
 
-    pub async fn append_state(
-        &self,
-        payload: Bytes,
-    ) -> Result<(Index, Index, LogEntry), SystemError> {
-        let mut state = self.state.lock().await;
-        let log_entry = state.append(payload).await?;
-        let prev_log_index = state.last_applied;
-        state.update_last_applied_to_commit_index();
-        Ok((state.last_applied, prev_log_index, log_entry))
+    pub async fn append_state(&self, term: Term, payload: Bytes) -> Result<LogEntry, SystemError> {
+        let result = self.state.lock().await.append(term, payload).await;
+        if matches!(result, Err(SystemError::NotLeader)) {
+            return result;
+        }
+
+        let entry = self.fence_on_error(result).await?;
+        // The single voter commits the entry on its own.
+        self.advance_commit_index().await;
+        Ok(entry)
     }
 
-    pub async fn can_sync_state(
-        &self,
-        leader_commit: u64,
-        prev_log_index: u64,
-    ) -> Result<(), SystemError> {
-        let state = self.state.lock().await;
-        if state.last_applied == 0 {
+    /// Commits the entries up to the leader's commit index, which the leader has capped
+    /// by the last entry of this node, that matches its own log.
+    pub async fn commit_state(&self, term: Term, commit_index: Index) -> Result<(), SystemError> {
+        if !self.state.lock().await.set_commit_index(commit_index) {
             return Ok(());
         }
 
-        if state.last_applied >= leader_commit {
-            error!(
-                "Invalid leader commit: {leader_commit}, last applied: {}",
-                state.last_applied
-            );
-            return Err(SystemError::InvalidLeaderCommit);
-        }
-        if state.last_applied != prev_log_index {
-            error!(
-                "Invalid previous log index: {prev_log_index}, last applied: {}",
-                state.last_applied
-            );
-            return Err(SystemError::InvalidPreviousLogIndex);
-        }
-        Ok(())
-    }
-
-    pub async fn append_entry(&self, entry: &LogEntry) -> Result<(), SystemError> {
-        let mut state = self.state.lock().await;
-        state
-            .sync(LogEntry {
-                index: entry.index,
-                size: entry.size,
-                data: entry.data.clone(),
-            })
-            .await?;
-        Ok(())
+        self.apply_committed_state(Some(term)).await
     }
 
-    pub fn get_quorum_count(&self) -> u64 {
-        (self.nodes.len() / 2 + 1) as u64
+    pub async fn get_quorum_count(&self) -> u64 {
+        self.get_voters_count().await / 2 + 1
     }
 }
@@ -1,12 +1,12 @@
 use crate::clusters::cluster::Cluster;
-use monoio::time::sleep;
+use crate::environment;
 use std::rc::Rc;
 use tracing::info;
 
 const SEPARATOR: &str = "------------------------------------------";
 
 pub fn subscribe(cluster: Rc<Cluster>) {
-    monoio::spawn(async move {
+    environment::spawn(async move {
         listen(cluster).await;
     });
 }
@@ -14,16 +14,29 @@ pub fn subscribe(cluster: Rc<Cluster>) {
 async fn listen(cluster: Rc<Cluster>) {
     let interval = cluster.info_interval;
     loop {
-        sleep(interval).await;
+        environment::sleep(interval).await;
         let mut info = String::new();
         let health = cluster.get_health().await;
         info.push_str(&format!("\n\n{SEPARATOR}\n"));
         info.push_str(&format!("+++ Cluster is {health}\n"));
+        let nodes_count = cluster.get_nodes_count().await;
+        let voters_count = cluster.get_voters_count().await;
+        let role = *cluster.get_self_node().unwrap().state.lock().await;
+        let fenced = cluster.is_fenced().await;
+        let disk_usage = *cluster.disk_usage.lock().await;
+        let disk_status = cluster.get_disk_status().await;
+        let leader_imbalance = cluster.get_leader_imbalance().await;
         let state = cluster.state.lock().await;
-        info.push_str(&format!("+++ Nodes: {}\n", cluster.nodes.len()));
+        info.push_str(&format!("+++ Role: {role}\n"));
+        info.push_str(&format!("+++ Fenced: {fenced}\n"));
+        info.push_str(&format!("+++ Disk usage: {disk_usage}%, {disk_status}\n"));
+        info.push_str(&format!(
+            "+++ Nodes: {nodes_count}, voters: {voters_count}\n"
+        ));
         info.push_str(&format!("+++ Term: {}\n", state.term));
         info.push_str(&format!("+++ Commit index: {}\n", state.commit_index));
-        info.push_str(&format!("+++ Last applied: {}\n", state.last_applied));
+        info.push_str(&format!("+++ Last index: {}\n", state.last_index));
+        info.push_str(&format!("+++ Stream leaders: {leader_imbalance}\n"));
         info.push_str(&format!("{SEPARATOR}\n"));
         info!("{info}");
     }
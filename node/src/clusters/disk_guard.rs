@@ -0,0 +1,177 @@
+use crate::clusters::cluster::Cluster;
+use crate::environment;
+use sdk::error::SystemError;
+use sdk::models::node_state::DiskStatus;
+use std::rc::Rc;
+use tracing::{error, info, warn};
+
+pub fn subscribe(cluster: Rc<Cluster>) {
+    environment::spawn(async move {
+        monitor(cluster).await;
+    });
+}
+
+async fn monitor(cluster: Rc<Cluster>) {
+    let interval = cluster.disk_check_interval;
+    loop {
+        cluster.check_disk_usage().await;
+        environment::sleep(interval).await;
+    }
+}
+
+impl Cluster {
+    /// Updates the disk usage as the highest one of the monitored directories, the leadership is handed over
+    /// once the hard limit is exceeded, as the leader couldn't accept the writes anymore.
+    pub async fn check_disk_usage(&self) {
+        let storage = environment::storage();
+        let mut usage = 0;
+        for path in &self.disk_paths {
+            match storage.disk_usage(path) {
+                Ok(disk_usage) => usage = usage.max(disk_usage.percentage()),
+                Err(error) => error!("Failed to check the disk usage of: {path}, {error}"),
+            }
+        }
+
+        let previous_status = self.get_disk_status().await;
+        *self.disk_usage.lock().await = usage;
+        let status = self.get_disk_status().await;
+        // The remote nodes learn the status from the rejected heartbeats.
+        self.get_self_node()
+            .unwrap()
+            .node
+            .set_disk_full(status == DiskStatus::HardLimitExceeded)
+            .await;
+        if status == previous_status {
+            return;
+        }
+
+        match status {
+            DiskStatus::Normal => info!("Disk usage: {usage}% is back below the soft limit."),
+            DiskStatus::SoftLimitExceeded => warn!(
+                "Disk usage: {usage}% has exceeded the soft limit: {}%.",
+                self.disk_soft_limit
+            ),
+            DiskStatus::HardLimitExceeded => {
+                error!(
+                    "Disk usage: {usage}% has exceeded the hard limit: {}%, rejecting writes.",
+                    self.disk_hard_limit
+                );
+                if self.is_leader().await {
+                    self.step_down().await;
+                }
+            }
+        }
+    }
+
+    pub async fn get_disk_status(&self) -> DiskStatus {
+        let usage = *self.disk_usage.lock().await;
+        if usage >= self.disk_hard_limit {
+            DiskStatus::HardLimitExceeded
+        } else if usage >= self.disk_soft_limit {
+            DiskStatus::SoftLimitExceeded
+        } else {
+            DiskStatus::Normal
+        }
+    }
+
+    pub async fn verify_has_disk_space(&self) -> Result<(), SystemError> {
+        if self.get_disk_status().await == DiskStatus::HardLimitExceeded {
+            error!("Disk usage has exceeded the hard limit, rejecting writes.");
+            return Err(SystemError::InsufficientDiskSpace);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{as_command, run_scenario, send, send_to, NODES_COUNT};
+    use linearizability::history::Operation;
+    use sdk::error::SystemError;
+    use sdk::models::metadata::NodeRole;
+    use sdk::models::node_state::DiskStatus;
+    use std::time::Duration;
+
+    #[test]
+    fn leader_should_reject_writes_and_step_down_once_its_disk_is_full() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                simulation.storage(leader_id).set_capacity(Some(0));
+                simulation.run_for(Duration::from_secs(10)).await?;
+
+                let full_cluster = simulation.cluster(leader_id).unwrap();
+                let node_state = full_cluster.get_node_state().await.unwrap();
+                assert_eq!(node_state.disk_usage, 100);
+                assert_eq!(node_state.disk_status, DiskStatus::HardLimitExceeded);
+                assert!(!full_cluster.is_leader().await);
+                let metadata = simulation
+                    .cluster(leader_id % NODES_COUNT + 1)
+                    .unwrap()
+                    .get_metadata()
+                    .await;
+                assert_eq!(metadata.nodes[&leader_id].role, NodeRole::Follower);
+                assert!(metadata
+                    .leader_id
+                    .is_some_and(|node_id| node_id != leader_id));
+
+                let rejection = simulation.spawn_client(async move {
+                    let command = as_command(&Operation::AppendMessages {
+                        stream_id: 1,
+                        ids: vec![1],
+                    });
+                    send_to(leader_id, &command).await
+                });
+                simulation.run_for(Duration::from_secs(5)).await?;
+                assert!(matches!(
+                    rejection.take(),
+                    Some(Err(SystemError::InvalidResponse(status, _)))
+                        if status == SystemError::InsufficientDiskSpace.as_code()
+                ));
+                Ok(())
+            })
+        });
+    }
+
+    #[test]
+    fn stream_leadership_should_move_away_from_node_past_hard_disk_limit() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                simulation.wait_for_leader().await?;
+                let created = simulation.spawn_client(async move {
+                    send(&as_command(&Operation::CreateStream { stream_id: 1 })).await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(created.take().is_some_and(|result| result.is_some()));
+
+                let leader_id = simulation.wait_for_leader().await?;
+                let metadata = simulation.cluster(leader_id).unwrap().get_metadata().await;
+                let full_node_id = metadata.streams[&1].leader_id;
+                simulation.storage(full_node_id).set_capacity(Some(0));
+                simulation.run_for(Duration::from_secs(10)).await?;
+
+                let leader_id = simulation.wait_for_leader().await?;
+                let leader = simulation.cluster(leader_id).unwrap();
+                let full_node = leader.get_node(full_node_id).await.unwrap();
+                assert!(full_node.node.is_disk_full().await);
+                let stream_leader_id = leader.get_metadata().await.streams[&1].leader_id;
+                assert_ne!(stream_leader_id, full_node_id);
+
+                let appended = simulation.spawn_client(async move {
+                    send(&as_command(&Operation::AppendMessages {
+                        stream_id: 1,
+                        ids: vec![1],
+                    }))
+                    .await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert_eq!(
+                    appended.take().flatten().map(|(node_id, _)| node_id),
+                    Some(stream_leader_id)
+                );
+                Ok(())
+            })
+        });
+    }
+}
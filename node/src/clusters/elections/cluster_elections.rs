@@ -1,29 +1,51 @@
 use crate::clusters::cluster::{Cluster, ClusterNodeState};
 use crate::clusters::elections::election::ElectionState;
+use crate::environment;
 use crate::types::{CandidateId, NodeId, Term};
-use monoio::time::sleep;
 use sdk::error::SystemError;
 use std::time::Duration;
 use tracing::{error, info, warn};
 
 impl Cluster {
     pub async fn start_election(&self) -> Result<(), SystemError> {
+        self.run_election(false).await
+    }
+
+    /// Starts an election without waiting for the randomized election timeout,
+    /// used when the current leader hands over its leadership to this node.
+    pub async fn start_election_now(&self) -> Result<(), SystemError> {
+        self.election_manager.remove_leader().await;
+        self.run_election(true).await
+    }
+
+    async fn run_election(&self, mut immediate: bool) -> Result<(), SystemError> {
         let self_node = self.get_self_node();
         if self_node.is_none() {
             return Err(SystemError::UnhealthyCluster);
         }
 
+        if !self.is_member().await {
+            info!("This node is not a cluster member yet, skipping election.");
+            return Ok(());
+        }
+
         let self_node = self_node.unwrap();
+        if self_node.is_learner().await {
+            info!("This node is a learner, skipping election.");
+            return Ok(());
+        }
+
         let unhealthy_interval = Duration::from_millis(1000);
         loop {
             if !self_node.node.can_be_leader().await {
                 error!("This node cannot be a leader.");
+                environment::sleep(unhealthy_interval).await;
                 continue;
             }
 
             if self.verify_is_healthy().await.is_err() {
                 error!("Cluster is unhealthy.");
-                sleep(unhealthy_interval).await;
+                environment::sleep(unhealthy_interval).await;
                 continue;
             }
 
@@ -35,10 +57,21 @@ impl Cluster {
                 break;
             }
 
+            if self.verify_has_disk_space().await.is_err() {
+                error!("This node is running out of disk space, skipping election.");
+                environment::sleep(unhealthy_interval).await;
+                continue;
+            }
+
             self_node.set_state(ClusterNodeState::Candidate).await;
             let term = self.election_manager.next_term().await;
             info!("Set term: {term}.");
-            let election_state = self.election_manager.start_election(term).await;
+            let election_state = if immediate {
+                immediate = false;
+                self.election_manager.start_election_now(term).await
+            } else {
+                self.election_manager.start_election(term).await
+            };
             match election_state {
                 ElectionState::TermChanged(new_term) => {
                     if let Some(leader) = self.election_manager.get_leader_id().await {
@@ -55,7 +88,9 @@ impl Cluster {
                     self.state.lock().await.set_term(term);
                     info!("Election in term: {term} has completed, leader ID: {leader_id}.");
                     if leader_id == self_node.node.id {
+                        self.reset_replication_progress().await;
                         self_node.set_state(ClusterNodeState::Leader).await;
+                        self.append_term_start_entry(term).await;
                         info!("Your role is leader, term: {term}.");
                     } else {
                         self_node.set_state(ClusterNodeState::Follower).await;
@@ -87,7 +122,9 @@ impl Cluster {
 
                     info!("Election in term: {term} has completed, this node is a leader with ID: {}.", self_node.node.id);
                     self.state.lock().await.set_term(term);
+                    self.reset_replication_progress().await;
                     self_node.set_state(ClusterNodeState::Leader).await;
+                    self.append_term_start_entry(term).await;
                     break;
                 }
             }
@@ -97,6 +134,14 @@ impl Cluster {
     }
 
     pub async fn set_leader(&self, term: Term, leader_id: NodeId) {
+        // The node restarted in the term of its leadership isn't the leader anymore, it has to be elected again.
+        if let Some(self_node) = self.get_self_node() {
+            if self_node.node.id == leader_id && !self_node.is_leader().await {
+                warn!("Ignoring this node as the leader in term: {term}, it hasn't been elected.");
+                return;
+            }
+        }
+
         if self
             .election_manager
             .set_leader(term, leader_id)
@@ -110,7 +155,7 @@ impl Cluster {
             return;
         }
 
-        for node in self.nodes.values() {
+        for node in self.get_nodes().await {
             if node.node.is_self_node() {
                 continue;
             }
@@ -118,8 +163,15 @@ impl Cluster {
             node.node.set_leader(term, leader_id).await;
         }
 
+        if let Some(self_node) = self.get_self_node() {
+            if self_node.node.id != leader_id && self_node.is_leader().await {
+                info!("Leader ID: {leader_id} has been elected in term: {term}, stepping down.");
+                self_node.set_state(ClusterNodeState::Follower).await;
+            }
+        }
+
         let mut streamer = self.streamer.lock().await;
-        streamer.set_leader(leader_id);
+        streamer.set_leader(leader_id, term).await;
         self.state.lock().await.set_term(term);
     }
 
@@ -131,7 +183,7 @@ impl Cluster {
 
         let leader_id = self_node.unwrap().node.id;
         let mut updated_nodes_count = 1;
-        for node in self.nodes.values() {
+        for node in self.get_nodes().await {
             if node.node.is_self_node() {
                 continue;
             }
@@ -147,12 +199,14 @@ impl Cluster {
                 );
                 continue;
             }
-            updated_nodes_count += 1;
+            if !node.is_learner().await {
+                updated_nodes_count += 1;
+            }
             node.node.set_leader(term, leader_id).await;
             info!("Update leader request sent to node ID: {}.", node.node.id);
         }
 
-        if updated_nodes_count < self.election_manager.get_quorum_count() {
+        if updated_nodes_count < self.election_manager.get_quorum_count().await {
             return Err(SystemError::LeaderRejected);
         }
 
@@ -169,15 +223,23 @@ impl Cluster {
         let self_node_id = self_node.unwrap().node.id;
         self.vote(term, self_node_id, self_node_id).await?;
         let mut votes_count = 1;
-        for node in self.nodes.values() {
-            if node.node.is_self_node() {
+        let (last_log_index, last_log_term) = {
+            let state = self.state.lock().await;
+            (state.last_index, state.last_log_term())
+        };
+        for node in self.get_nodes().await {
+            if node.node.is_self_node() || node.is_learner().await {
                 continue;
             }
             info!(
                 "Requesting vote from node: {} in term: {term}...",
                 node.node.id
             );
-            if let Err(err) = node.node.request_vote(term).await {
+            if let Err(err) = node
+                .node
+                .request_vote(term, last_log_index, last_log_term)
+                .await
+            {
                 match err {
                     SystemError::InvalidResponse(status, payload) => {
                         error!(
@@ -185,20 +247,28 @@ impl Cluster {
                             node.node.id
                         );
                         if status == 26 {
-                            let payload = payload.unwrap();
-                            let new_term = u64::from_le_bytes(payload.try_into().unwrap());
+                            let Some(new_term) = payload
+                                .as_deref()
+                                .and_then(|payload| payload.try_into().ok())
+                                .map(u64::from_le_bytes)
+                            else {
+                                error!(
+                                    "Invalid term payload from node: {node_id}.",
+                                    node_id = node.node.id
+                                );
+                                continue;
+                            };
                             if new_term > term {
                                 error!("Invalid current term: {term}, new term: {new_term}");
                                 self.election_manager.set_term(new_term).await;
-                                continue;
                             }
                         } else {
                             error!(
                                 "Invalid response from node: {node_id}.",
                                 node_id = node.node.id
                             );
-                            continue;
                         }
+                        continue;
                     }
                     _ => {
                         error!(
@@ -216,6 +286,10 @@ impl Cluster {
                 node.node.id
             );
             self.vote(term, self_node_id, node.node.id).await?;
+            // Once counted, the majority completes the election, so the remaining votes would be rejected.
+            if self.has_majority_votes(term).await {
+                break;
+            }
         }
 
         info!("Managed to request votes from {votes_count} nodes in term: {term}.");
@@ -242,7 +316,19 @@ impl Cluster {
     ) -> Result<(), SystemError> {
         self.election_manager
             .vote(term, candidate_id, node_id)
-            .await
+            .await?;
+        if self
+            .get_self_node()
+            .is_none_or(|self_node| self_node.node.id != node_id)
+        {
+            return Ok(());
+        }
+
+        // The vote of this node is saved before it's sent, so that the node never votes twice in the term after the restart.
+        let mut state = self.state.lock().await;
+        state.set_term(term);
+        state.voted_for = Some(candidate_id);
+        state.save_hard_state().await
     }
 
     pub async fn is_leader(&self) -> bool {
@@ -254,3 +340,50 @@ impl Cluster {
         self_node.unwrap().is_leader().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{run_scenario, NODES_COUNT};
+    use std::time::Duration;
+
+    #[test]
+    fn vote_should_be_kept_after_restart() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                let term = simulation
+                    .cluster(leader_id)
+                    .unwrap()
+                    .election_manager
+                    .get_current_term()
+                    .await;
+                let mut voter_id = None;
+                for node_id in (1..=NODES_COUNT).filter(|node_id| *node_id != leader_id) {
+                    let cluster = simulation.cluster(node_id).unwrap();
+                    let state = cluster.state.lock().await;
+                    if (state.term, state.voted_for) == (term, Some(leader_id)) {
+                        voter_id = Some(node_id);
+                    }
+                }
+                let voter_id = voter_id.ok_or("No follower has voted for the leader.")?;
+
+                simulation.crash_node(voter_id);
+                simulation.start_node(voter_id);
+                let voter = loop {
+                    if let Some(voter) = simulation.cluster(voter_id) {
+                        break voter;
+                    }
+                    simulation.run_for(Duration::from_millis(10)).await?;
+                };
+                let election = voter.election_manager.try_snapshot().unwrap();
+                assert_eq!((election.term, election.voted_for), (term, Some(leader_id)));
+                assert!(voter
+                    .election_manager
+                    .vote(term, voter_id, voter_id)
+                    .await
+                    .is_err());
+                Ok(())
+            })
+        });
+    }
+}
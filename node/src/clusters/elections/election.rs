@@ -1,11 +1,9 @@
+use crate::environment;
 use crate::types::{CandidateId, NodeId, Term};
 use futures::lock::Mutex;
-use monoio::time::sleep;
-use rand::rngs::ThreadRng;
-use rand::Rng;
 use sdk::error::SystemError;
-use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::time::Duration;
@@ -16,11 +14,23 @@ pub(crate) struct Election {
     pub is_completed: Mutex<bool>,
     pub term: Mutex<Term>,
     pub leader_id: Mutex<Option<NodeId>>,
-    pub votes: Mutex<HashMap<CandidateId, HashSet<NodeId>>>,
+    pub votes: Mutex<BTreeMap<CandidateId, BTreeSet<NodeId>>>,
     pub voted_for: Mutex<Option<CandidateId>>,
     pub last_heartbeat_at: Mutex<u64>,
 }
 
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ElectionSnapshot {
+    pub term: Term,
+    pub leader_id: Option<CandidateId>,
+    pub is_completed: bool,
+    pub election_term: Term,
+    pub election_leader_id: Option<NodeId>,
+    pub votes: BTreeMap<CandidateId, BTreeSet<NodeId>>,
+    pub voted_for: Option<CandidateId>,
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum ElectionState {
     LeaderElected(u64),
@@ -34,7 +44,7 @@ impl Default for Election {
             is_completed: Mutex::new(true),
             term: Mutex::new(0),
             leader_id: Mutex::new(None),
-            votes: Mutex::new(HashMap::new()),
+            votes: Mutex::new(BTreeMap::new()),
             voted_for: Mutex::new(None),
             last_heartbeat_at: Mutex::new(0),
         }
@@ -47,8 +57,7 @@ pub struct ElectionManager {
     current_term: Mutex<Term>,
     current_leader_id: Mutex<Option<CandidateId>>,
     election: Election,
-    randomizer: Mutex<ThreadRng>,
-    nodes_count: u64,
+    nodes_count: Mutex<u64>,
     timeout_range: ElectionTimeout,
 }
 
@@ -82,8 +91,7 @@ impl ElectionManager {
             current_term: Mutex::new(0),
             current_leader_id: Mutex::new(None),
             election: Election::default(),
-            randomizer: Mutex::new(rand::thread_rng()),
-            nodes_count,
+            nodes_count: Mutex::new(nodes_count),
             timeout_range,
         }
     }
@@ -100,16 +108,45 @@ impl ElectionManager {
         *self.current_leader_id.lock().await
     }
 
+    /// Moves to the newer term, in which neither the leader nor the vote of this node is known yet.
     pub async fn set_term(&self, term: Term) {
         let current_term = *self.current_term.lock().await;
         if term > current_term {
             info!("Setting term: {term}...");
+            self.remove_leader().await;
+            self.set_election_completed_state(false).await;
             *self.current_term.lock().await = term;
+            *self.election.term.lock().await = term;
+            self.election.voted_for.lock().await.take();
+            self.election.votes.lock().await.clear();
         }
     }
 
-    pub fn get_quorum_count(&self) -> u64 {
-        self.nodes_count / 2 + 1
+    /// Continues in the term and with the vote saved before the restart, so that the node doesn't vote again in the term.
+    pub async fn restore(&self, term: Term, voted_for: Option<CandidateId>) {
+        self.set_term(term).await;
+        let Some(candidate_id) = voted_for else {
+            return;
+        };
+
+        info!("Restoring the vote for candidate ID: {candidate_id} in term: {term}.");
+        self.election.voted_for.lock().await.replace(candidate_id);
+        self.election
+            .votes
+            .lock()
+            .await
+            .entry(candidate_id)
+            .or_default()
+            .insert(self.self_id);
+    }
+
+    pub async fn get_quorum_count(&self) -> u64 {
+        *self.nodes_count.lock().await / 2 + 1
+    }
+
+    pub async fn set_nodes_count(&self, nodes_count: u64) {
+        info!("Setting nodes count: {nodes_count}...");
+        *self.nodes_count.lock().await = nodes_count;
     }
 
     pub async fn set_leader(&self, term: Term, leader_id: CandidateId) -> Result<(), SystemError> {
@@ -137,6 +174,8 @@ impl ElectionManager {
         self.set_election_completed_state(true).await;
         *self.current_term.lock().await = term;
         self.current_leader_id.lock().await.replace(leader_id);
+        // The new leader sends its first heartbeat only after the interval, so it's awaited from now on.
+        self.set_last_heartbeat(environment::timestamp()).await;
         info!("Leader ID: {leader_id} has been set in term: {term}.");
         Ok(())
     }
@@ -151,26 +190,36 @@ impl ElectionManager {
     }
 
     pub async fn start_election(&self, term: Term) -> ElectionState {
+        let timeout = environment::gen_range(self.timeout_range.from, self.timeout_range.to);
+        self.begin_election(term, timeout).await
+    }
+
+    pub async fn start_election_now(&self, term: Term) -> ElectionState {
+        self.begin_election(term, 0).await
+    }
+
+    async fn begin_election(&self, term: Term, timeout: u64) -> ElectionState {
+        let previous_term = self.open_election(term).await;
+        info!("Starting election for new term {term}, previous term: {previous_term}, required votes: {} timeout: {timeout} ms...", self.get_quorum_count().await);
+        // Wait for random timeout and check if there is no leader in the meantime
+        environment::sleep(Duration::from_millis(timeout)).await;
+        self.close_election(term).await
+    }
+
+    /// Moves to the term of the new election, in which no votes have been cast yet, and returns the previous term.
+    pub async fn open_election(&self, term: Term) -> Term {
         self.remove_leader().await;
         self.set_election_completed_state(false).await;
         *self.election.term.lock().await = term;
         self.election.voted_for.lock().await.take();
         self.election.votes.lock().await.clear();
-        let previous_term;
-
-        {
-            previous_term = *self.current_term.lock().await;
-            *self.current_term.lock().await = term;
-        }
+        let previous_term = *self.current_term.lock().await;
+        *self.current_term.lock().await = term;
+        previous_term
+    }
 
-        let timeout = self
-            .randomizer
-            .lock()
-            .await
-            .gen_range(self.timeout_range.from..=self.timeout_range.to);
-        info!("Starting election for new term {term}, previous term: {previous_term}, required votes: {} timeout: {timeout} ms...", self.get_quorum_count());
-        // Wait for random timeout and check if there is no leader in the meantime
-        sleep(Duration::from_millis(timeout)).await;
+    /// Counts the votes once the election timeout has passed, unless the term has changed in the meantime.
+    pub async fn close_election(&self, term: Term) -> ElectionState {
         let current_term = *self.current_term.lock().await;
         if current_term > term {
             self.set_election_completed_state(true).await;
@@ -238,7 +287,7 @@ impl ElectionManager {
             }
 
             if let Entry::Vacant(entry) = votes_count.entry(candidate_id) {
-                let mut votes = HashSet::new();
+                let mut votes = BTreeSet::new();
                 votes.insert(node_id);
                 entry.insert(votes);
                 info!("Initial vote for candidate ID: {candidate_id} from node ID: {node_id} in term: {term}.");
@@ -280,7 +329,7 @@ impl ElectionManager {
             "Most votes: {} in term: {term}, for node ID: {leader}",
             votes.len()
         );
-        if votes.len() as u64 > self.get_quorum_count() {
+        if votes.len() as u64 >= self.get_quorum_count().await {
             self.set_election_completed_state(true).await;
             let leader = *leader;
             self.current_leader_id.lock().await.replace(leader);
@@ -310,7 +359,47 @@ impl ElectionManager {
         }
 
         let candidate_votes = candidate_votes.unwrap();
-        candidate_votes.len() as u64 >= self.get_quorum_count()
+        candidate_votes.len() as u64 >= self.get_quorum_count().await
+    }
+
+    /// Returns the current term and leader without waiting, if none of them is locked.
+    #[cfg(test)]
+    pub fn try_get_leader(&self) -> Option<(Term, Option<CandidateId>)> {
+        let term = *self.current_term.try_lock()?;
+        let leader_id = *self.current_leader_id.try_lock()?;
+        Some((term, leader_id))
+    }
+
+    /// Creates the manager of the node, which continues the election from the given state.
+    #[cfg(test)]
+    pub fn from_snapshot(
+        self_id: CandidateId,
+        nodes_count: u64,
+        snapshot: &ElectionSnapshot,
+    ) -> Self {
+        let manager = Self::new(self_id, nodes_count, ElectionTimeout::new(0, 0));
+        *manager.current_term.try_lock().unwrap() = snapshot.term;
+        *manager.current_leader_id.try_lock().unwrap() = snapshot.leader_id;
+        *manager.election.is_completed.try_lock().unwrap() = snapshot.is_completed;
+        *manager.election.term.try_lock().unwrap() = snapshot.election_term;
+        *manager.election.leader_id.try_lock().unwrap() = snapshot.election_leader_id;
+        *manager.election.votes.try_lock().unwrap() = snapshot.votes.clone();
+        *manager.election.voted_for.try_lock().unwrap() = snapshot.voted_for;
+        manager
+    }
+
+    /// Returns the whole state of the election without waiting, if none of it is locked.
+    #[cfg(test)]
+    pub fn try_snapshot(&self) -> Option<ElectionSnapshot> {
+        Some(ElectionSnapshot {
+            term: *self.current_term.try_lock()?,
+            leader_id: *self.current_leader_id.try_lock()?,
+            is_completed: *self.election.is_completed.try_lock()?,
+            election_term: *self.election.term.try_lock()?,
+            election_leader_id: *self.election.leader_id.try_lock()?,
+            votes: self.election.votes.try_lock()?.clone(),
+            voted_for: *self.election.voted_for.try_lock()?,
+        })
     }
 
     pub async fn is_election_completed(&self) -> bool {
@@ -0,0 +1,182 @@
+use crate::clusters::cluster::{Cluster, ClusterNode};
+use crate::environment;
+use crate::types::NodeId;
+use sdk::error::SystemError;
+use std::rc::Rc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+const LEADER_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+impl Cluster {
+    pub async fn transfer_leadership(&self, node_id: NodeId) -> Result<(), SystemError> {
+        self.verify_is_leader().await?;
+        let self_node = self.get_self_node().unwrap();
+        if self_node.node.id == node_id {
+            info!("This node with ID: {node_id} is already a leader.");
+            return Ok(());
+        }
+
+        let target = self.get_node(node_id).await;
+        if target.is_none() {
+            error!("Cannot transfer leadership to invalid node ID: {node_id}.");
+            return Err(SystemError::InvalidNode(node_id));
+        }
+
+        let target = target.unwrap();
+        if target.is_learner().await {
+            error!("Cannot transfer leadership to learner node ID: {node_id}.");
+            return Err(SystemError::NodeIsLearner(node_id));
+        }
+
+        if !target.node.is_connected().await {
+            error!("Cannot transfer leadership to disconnected node ID: {node_id}.");
+            return Err(SystemError::CannotTransferLeadership(node_id));
+        }
+
+        {
+            let mut leadership_transfer = self.leadership_transfer.lock().await;
+            if let Some(transfer_node_id) = *leadership_transfer {
+                warn!("Leadership is already being transferred to node ID: {transfer_node_id}.");
+                return Err(SystemError::LeadershipTransferInProgress);
+            }
+            leadership_transfer.replace(node_id);
+        }
+
+        info!("Transferring leadership to node ID: {node_id}...");
+        let result = self.hand_over_leadership(target).await;
+        self.leadership_transfer.lock().await.take();
+        match &result {
+            Ok(()) => info!("Leadership has been transferred to node ID: {node_id}."),
+            Err(error) => {
+                error!("Failed to transfer leadership to node ID: {node_id}, {error}")
+            }
+        }
+        result
+    }
+
+    async fn hand_over_leadership(&self, target: Rc<ClusterNode>) -> Result<(), SystemError> {
+        let node_id = target.node.id;
+        let term = self.election_manager.get_current_term().await;
+        self.catch_up_node(term, &target).await?;
+        target.node.timeout_now(term).await?;
+        info!(
+            "Sent timeout now to node ID: {node_id} in term: {term}, waiting for the new leader..."
+        );
+        let now = environment::now();
+        while environment::elapsed(now) < self.leadership_transfer_timeout {
+            if self.election_manager.get_leader_id().await == Some(node_id) {
+                return Ok(());
+            }
+            environment::sleep(LEADER_CHECK_INTERVAL).await;
+        }
+
+        error!(
+            "Node ID: {node_id} did not become a leader in: {} ms.",
+            self.leadership_transfer_timeout.as_millis()
+        );
+        Err(SystemError::CannotTransferLeadership(node_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment;
+    use crate::simulation::{as_command, run_scenario, send_to, NODES_COUNT};
+    use linearizability::history::Operation;
+    use sdk::commands::transfer_leadership::TransferLeadership;
+    use sdk::error::SystemError;
+    use std::time::Duration;
+
+    #[test]
+    fn timeout_now_from_non_leader_should_not_start_election() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                let follower_id = leader_id % NODES_COUNT + 1;
+                let target_id = follower_id % NODES_COUNT + 1;
+                let follower = simulation.cluster(follower_id).unwrap();
+                let term = follower.election_manager.get_current_term().await;
+                let timeout_now = simulation.spawn_client(async move {
+                    let target = follower.get_node(target_id).await.unwrap();
+                    target.node.timeout_now(term).await
+                });
+                simulation.run_for(Duration::from_secs(1)).await?;
+                assert!(matches!(
+                    timeout_now.take().unwrap(),
+                    Err(SystemError::InvalidResponse(status, _))
+                        if status == SystemError::InvalidNode(0).as_code()
+                ));
+                for node_id in 1..=NODES_COUNT {
+                    let cluster = simulation.cluster(node_id).unwrap();
+                    assert_eq!(cluster.election_manager.get_current_term().await, term);
+                    assert_eq!(
+                        cluster.election_manager.get_leader_id().await,
+                        Some(leader_id)
+                    );
+                }
+                Ok(())
+            })
+        });
+    }
+
+    #[test]
+    fn lagging_node_should_catch_up_and_take_over_while_writes_are_rejected() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                let target_id = leader_id % NODES_COUNT + 1;
+                simulation.crash_node(target_id);
+                let created = simulation.spawn_client(async move {
+                    send_to(
+                        leader_id,
+                        &as_command(&Operation::CreateStream { stream_id: 1 }),
+                    )
+                    .await
+                });
+                simulation.run_for(Duration::from_secs(1)).await?;
+                assert!(created.take().is_some_and(|result| result.is_ok()));
+                simulation.start_node(target_id);
+                let leader = simulation.cluster(leader_id).unwrap();
+                let target_node = leader.get_node(target_id).await.unwrap();
+                let target = loop {
+                    if let Some(target) = simulation.cluster(target_id) {
+                        if target_node.node.is_connected().await {
+                            break target;
+                        }
+                    }
+                    simulation.run_for(Duration::from_millis(10)).await?;
+                };
+                let last_index = leader.state.lock().await.last_index;
+                assert!(target.state.lock().await.last_index < last_index);
+
+                let transferred = simulation.spawn_client(async move {
+                    send_to(leader_id, &TransferLeadership::new_command(target_id)).await
+                });
+                let rejected = simulation.spawn_client(async move {
+                    while leader.leadership_transfer.lock().await.is_none() {
+                        environment::sleep(Duration::from_millis(1)).await;
+                    }
+                    send_to(
+                        leader_id,
+                        &as_command(&Operation::CreateStream { stream_id: 2 }),
+                    )
+                    .await
+                });
+                simulation.run_for(Duration::from_secs(5)).await?;
+
+                let rejected = rejected.take();
+                assert!(matches!(
+                    rejected,
+                    Some(Err(SystemError::InvalidResponse(status, _)))
+                        if status == SystemError::LeadershipTransferInProgress.as_code()
+                ));
+                assert!(transferred.take().is_some_and(|result| result.is_ok()));
+                assert_eq!(simulation.leader_id().await, Some(target_id));
+                assert!(target.state.lock().await.last_index >= last_index);
+                assert!(target.streamer.lock().await.get_stream(1).is_some());
+                Ok(())
+            })
+        });
+    }
+}
@@ -1,2 +1,3 @@
 pub mod cluster_elections;
 pub mod election;
+mod leadership_transfer;
@@ -0,0 +1,118 @@
+use crate::clusters::cluster::Cluster;
+use sdk::error::SystemError;
+use tracing::{error, info, warn};
+
+impl Cluster {
+    /// Fences this node once it fails to write to its storage, as its log or metadata might not reflect
+    /// what has been acknowledged anymore. The fenced node keeps serving the committed data, but rejects
+    /// the writes, won't become a leader again, and hands over its leadership, until it's restarted.
+    pub(crate) async fn fence(&self, error: &SystemError) {
+        let self_node = self.get_self_node().unwrap();
+        if self_node.node.is_fenced().await {
+            return;
+        }
+
+        error!("Fencing this node after a storage failure, {error}");
+        self_node.node.set_fenced(true).await;
+        self_node.node.set_can_be_leader(false).await;
+        if self_node.is_leader().await {
+            self.step_down().await;
+        }
+    }
+
+    /// Fences this node if the write has failed, the result must come from the storage only, not the network.
+    /// No lock should be held, as the leadership is handed over first.
+    pub(crate) async fn fence_on_error<T>(
+        &self,
+        result: Result<T, SystemError>,
+    ) -> Result<T, SystemError> {
+        if let Err(error) = &result {
+            self.fence(error).await;
+        }
+        result
+    }
+
+    pub async fn is_fenced(&self) -> bool {
+        self.get_self_node().unwrap().node.is_fenced().await
+    }
+
+    pub async fn verify_is_not_fenced(&self) -> Result<(), SystemError> {
+        if self.is_fenced().await {
+            error!("This node is fenced, rejecting writes.");
+            return Err(SystemError::NodeFenced);
+        }
+
+        Ok(())
+    }
+
+    /// Transfers the leadership to the first available voter, which accepts it.
+    pub(crate) async fn step_down(&self) {
+        for node in self.get_nodes().await {
+            if node.node.is_self_node()
+                || node.is_learner().await
+                || node.node.is_fenced().await
+                || node.node.is_disk_full().await
+                || !node.node.is_connected().await
+            {
+                continue;
+            }
+
+            info!(
+                "Stepping down, transferring leadership to node ID: {}...",
+                node.node.id
+            );
+            if self.transfer_leadership(node.node.id).await.is_ok() {
+                return;
+            }
+        }
+
+        warn!("No available node has taken over the leadership of this fenced node.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::memory::WriteFault;
+    use crate::simulation::{as_command, run_scenario, send, NODES_COUNT};
+    use linearizability::history::Operation;
+    use sdk::models::metadata::NodeRole;
+    use std::time::Duration;
+
+    #[test]
+    fn leader_should_be_fenced_and_step_down_once_its_disk_fails() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                simulation.fail_disk(leader_id, WriteFault::Torn);
+
+                let mut created_streams = Vec::new();
+                for stream_id in [1, 2] {
+                    let created = simulation.spawn_client(async move {
+                        send(&as_command(&Operation::CreateStream { stream_id })).await
+                    });
+                    simulation.run_for(Duration::from_secs(10)).await?;
+                    if let Some(Some((node_id, _))) = created.take() {
+                        created_streams.push((stream_id, node_id));
+                    }
+                }
+
+                let fenced_cluster = simulation.cluster(leader_id).unwrap();
+                assert!(fenced_cluster.is_fenced().await);
+                assert!(!fenced_cluster.is_leader().await);
+                let metadata = simulation
+                    .cluster(leader_id % NODES_COUNT + 1)
+                    .unwrap()
+                    .get_metadata()
+                    .await;
+                assert_eq!(metadata.nodes[&leader_id].role, NodeRole::Fenced);
+                assert!(metadata
+                    .leader_id
+                    .is_some_and(|node_id| node_id != leader_id));
+                assert!(created_streams
+                    .iter()
+                    .any(|(stream_id, node_id)| *stream_id == 2 && *node_id != leader_id));
+                Ok(())
+            })
+        });
+    }
+}
@@ -0,0 +1,75 @@
+use crate::clusters::cluster::Cluster;
+use crate::environment;
+use futures::channel::mpsc::UnboundedReceiver;
+use std::rc::Rc;
+use tracing::{error, warn};
+
+pub fn subscribe(cluster: Rc<Cluster>) {
+    environment::spawn(async move {
+        fetch(cluster).await;
+    });
+}
+
+async fn fetch(cluster: Rc<Cluster>) {
+    let Some(mut bootstrap_trigger) = cluster.bootstrap_trigger_receiver.lock().await.take() else {
+        error!("Fetching messages has already been started.");
+        return;
+    };
+
+    let interval = cluster.fetch_interval;
+    loop {
+        // Keep fetching while the leader returns the messages, so that the lagging replicas catch up quickly
+        // and the leader learns the new log end offsets without waiting for the next interval.
+        if !fetch_streams(&cluster, &mut bootstrap_trigger).await {
+            environment::sleep(interval).await;
+        }
+    }
+}
+
+async fn fetch_streams(cluster: &Cluster, bootstrap_trigger: &mut UnboundedReceiver<u64>) -> bool {
+    if !cluster.is_member().await {
+        return false;
+    }
+
+    let term = cluster.election_manager.get_current_term().await;
+    let self_node_id = cluster.get_self_node().unwrap().node.id;
+    let streams = cluster
+        .streamer
+        .lock()
+        .await
+        .get_streams()
+        .iter()
+        .filter(|stream| stream.leader_id != self_node_id && stream.is_replica(self_node_id))
+        .map(|stream| (stream.stream_id, stream.leader_id))
+        .collect::<Vec<(u64, u64)>>();
+    // The streams assigned to this node since the last fetch, e.g. re-created after its storage has been wiped,
+    // are copied from their leaders in chunks first, instead of being fetched from the start.
+    let mut assigned_stream_ids = Vec::new();
+    while let Ok(Some(stream_id)) = bootstrap_trigger.try_next() {
+        assigned_stream_ids.push(stream_id);
+    }
+    if !assigned_stream_ids.is_empty() {
+        cluster
+            .bootstrap_assigned_streams(&assigned_stream_ids)
+            .await;
+    }
+
+    let mut fetched = false;
+    for (stream_id, leader_id) in streams {
+        let Some(leader) = cluster.get_node(leader_id).await else {
+            continue;
+        };
+
+        if leader.node.is_self_node() || !leader.node.is_connected().await {
+            continue;
+        }
+
+        match cluster.fetch_from_leader(term, &leader, stream_id).await {
+            Ok(count) => fetched |= count > 0,
+            Err(error) => warn!(
+                "Failed to fetch messages for stream with ID: {stream_id} from leader ID: {leader_id}, {error}"
+            ),
+        }
+    }
+    fetched
+}
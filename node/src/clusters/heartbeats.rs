@@ -1,11 +1,10 @@
 use crate::clusters::cluster::Cluster;
-use monoio::time::sleep;
-use sdk::timestamp::TimeStamp;
+use crate::environment;
 use std::rc::Rc;
 use tracing::{error, info, warn};
 
 pub fn subscribe(cluster: Rc<Cluster>) {
-    monoio::spawn(async move {
+    environment::spawn(async move {
         listen(cluster).await;
     });
 }
@@ -15,7 +14,7 @@ async fn listen(cluster: Rc<Cluster>) {
     let interval_ms = interval.as_millis() as u64;
     let interval_micros = interval.as_micros() as u64;
     loop {
-        sleep(interval).await;
+        environment::sleep(interval).await;
         let last_heartbeat = cluster.election_manager.get_last_heartbeat().await;
         if last_heartbeat == 0 {
             info!("No heartbeat received yet.");
@@ -33,7 +32,7 @@ async fn listen(cluster: Rc<Cluster>) {
             continue;
         }
 
-        let now = TimeStamp::now().to_micros();
+        let now = environment::timestamp();
         if now - last_heartbeat <= interval_micros {
             info!(
                 "Received a heartbeat from cluster node ID: {leader_id} in desired interval: {interval_ms} ms."
@@ -0,0 +1,17 @@
+use crate::clusters::cluster::Cluster;
+use crate::environment;
+use std::rc::Rc;
+
+pub fn subscribe(cluster: Rc<Cluster>) {
+    environment::spawn(async move {
+        track(cluster).await;
+    });
+}
+
+async fn track(cluster: Rc<Cluster>) {
+    let interval = cluster.max_replica_lag / 2;
+    loop {
+        environment::sleep(interval).await;
+        cluster.update_in_sync_replicas().await;
+    }
+}
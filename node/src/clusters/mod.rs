@@ -1,7 +1,15 @@
 pub mod cluster;
 pub mod cluster_info;
-mod elections;
+pub mod disk_guard;
+pub mod elections;
+mod fencing;
+pub mod fetcher;
 pub mod heartbeats;
+pub mod isr;
 mod nodes;
+mod read_index;
+pub mod replication;
 pub mod state;
+pub mod state_machine;
+pub mod stream_leaders;
 mod streams;
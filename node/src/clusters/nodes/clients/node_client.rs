@@ -1,30 +1,30 @@
 use crate::clusters::cluster::SelfNode;
-use crate::clusters::nodes::node::Resiliency;
+use crate::clusters::nodes::node::{AppendEntriesBatch, Resiliency};
 use crate::connection::handler::ConnectionHandler;
+use crate::environment;
 use crate::types::{Index, NodeId, Term};
 use futures::lock::Mutex;
-use monoio::net::TcpStream;
-use monoio::time::sleep;
 use sdk::bytes_serializable::BytesSerializable;
 use sdk::commands::append_entries::AppendEntries;
-use sdk::commands::append_messages::AppendableMessage;
+use sdk::commands::fetch_messages::FetchMessages;
+use sdk::commands::get_epoch_end_offset::GetEpochEndOffset;
+use sdk::commands::get_metadata::GetMetadata;
 use sdk::commands::get_node_state::GetNodeState;
-use sdk::commands::get_streams::GetStreams;
+use sdk::commands::get_stream_chunk::GetStreamChunk;
 use sdk::commands::heartbeat::Heartbeat;
 use sdk::commands::hello::Hello;
-use sdk::commands::load_state::LoadState;
-use sdk::commands::poll_messages::PollMessages;
 use sdk::commands::request_vote::RequestVote;
-use sdk::commands::sync_messages::SyncMessages;
+use sdk::commands::timeout_now::TimeoutNow;
+use sdk::commands::update_isr::UpdateIsr;
 use sdk::commands::update_leader::UpdateLeader;
 use sdk::error::SystemError;
-use sdk::models::appended_state::AppendedState;
-use sdk::models::log_entry::LogEntry;
-use sdk::models::message::{messages_from_bytes, Message};
+use sdk::models::fetched_messages::FetchedMessages;
+use sdk::models::leader_epoch::EpochEndOffset;
+use sdk::models::metadata::Metadata;
 use sdk::models::node_state::NodeState;
-use sdk::models::stream::Stream;
+use sdk::models::read_consistency::ReadConsistency;
 use std::net::SocketAddr;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tracing::{debug, error, info, warn};
 
 impl SelfNode {
@@ -80,6 +80,10 @@ impl NodeClient {
         })
     }
 
+    pub(crate) fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.resiliency.request_timeout)
+    }
+
     pub fn is_self_node(&self) -> bool {
         self.id == self.self_node.id
     }
@@ -99,55 +103,71 @@ impl NodeClient {
         }
 
         let mut retry_count = 0;
-        let remote_address;
-        let elapsed;
         loop {
             if self.is_connected().await {
                 return Ok(());
             }
 
-            info!(
-                "Connecting to cluster node ID: {}, address: {}...",
-                self.id, self.address
-            );
-            let now = Instant::now();
-            let connection = TcpStream::connect(self.address).await;
-            if connection.is_err() {
-                error!(
-                    "Failed to connect to cluster node ID: {}, address: {}.",
-                    self.id, self.address
-                );
-                if retry_count < self.resiliency.reconnection_retries {
+            match self.try_connect().await {
+                Ok(()) => return Ok(()),
+                // The hello rejected by the node, e.g. due to the invalid secret, won't pass on retry.
+                Err(SystemError::InvalidResponse(status, payload)) if status != 0 => {
+                    return Err(SystemError::InvalidResponse(status, payload));
+                }
+                Err(error) if retry_count < self.resiliency.reconnection_retries => {
                     retry_count += 1;
                     info!(
-                        "Retrying ({}/{}) to connect to cluster node ID: {}, address: {}, in: {} ms...",
+                        "Retrying ({}/{}) to connect to cluster node ID: {}, address: {}, in: {} ms, error: {error}...",
                         retry_count,
                         self.resiliency.reconnection_retries,
                         self.id,
                         self.address,
                         self.resiliency.reconnection_interval
                     );
-                    sleep(Duration::from_millis(self.resiliency.reconnection_interval)).await;
-                    continue;
+                    environment::sleep(Duration::from_millis(
+                        self.resiliency.reconnection_interval,
+                    ))
+                    .await;
                 }
-
-                return Err(SystemError::CannotConnectToClusterNode(
-                    self.address.to_string(),
-                ));
+                Err(error) => return Err(error),
             }
+        }
+    }
 
-            elapsed = now.elapsed();
-            let stream = connection.unwrap();
-            remote_address = stream.peer_addr()?;
-            self.handler.lock().await.replace(ConnectionHandler::new(
-                stream,
-                remote_address,
-                self.id,
+    /// Connects to the node and sends the hello message, which might not be answered, even though the connection
+    /// has been established, e.g. when the network is partitioned, so the connection is closed then.
+    async fn try_connect(&self) -> Result<(), SystemError> {
+        info!(
+            "Connecting to cluster node ID: {}, address: {}...",
+            self.id, self.address
+        );
+        let now = environment::now();
+        let Ok(stream) = environment::connect(self.address).await else {
+            error!(
+                "Failed to connect to cluster node ID: {}, address: {}.",
+                self.id, self.address
+            );
+            return Err(SystemError::CannotConnectToClusterNode(
+                self.address.to_string(),
             ));
-            self.set_connected().await;
-            break;
+        };
+
+        let elapsed = environment::elapsed(now);
+        // Replacing the connection established by another task in the meantime would make the node disconnect.
+        if self.is_connected().await {
+            info!(
+                "Already connected to cluster node ID: {}, address: {}, closing the new connection.",
+                self.id, self.address
+            );
+            return Ok(());
         }
 
+        let remote_address = stream.peer_address()?;
+        self.handler
+            .lock()
+            .await
+            .replace(ConnectionHandler::new(stream, remote_address, self.id));
+        self.set_connected().await;
         info!(
             "Connected to cluster node ID: {}, address: {remote_address} in {} ms. Sending hello message...",
             self.id,
@@ -156,14 +176,25 @@ impl NodeClient {
 
         let term = *self.term.lock().await;
         let leader_id = *self.leader_id.lock().await;
-        self.send_request(&Hello::new_command(
-            self.secret.clone(),
-            self.self_node.name.clone(),
-            self.self_node.id,
-            term,
-            leader_id,
-        ))
-        .await?;
+        if let Err(error) = self
+            .send_request_with_timeout(&Hello::new_command(
+                self.secret.clone(),
+                self.self_node.name.clone(),
+                self.self_node.id,
+                term,
+                leader_id,
+            ))
+            .await
+        {
+            error!(
+                "Failed to send hello message to cluster node ID: {}, address: {}, error: {error}",
+                self.id, self.address
+            );
+            self.set_disconnected().await;
+            self.handler.lock().await.take();
+            return Err(error);
+        }
+
         info!(
             "Sent hello message to cluster node ID: {}, address: {}",
             self.id, self.address
@@ -189,24 +220,32 @@ impl NodeClient {
         Ok(())
     }
 
-    pub async fn heartbeat(&self, term: u64, leader_id: Option<u64>) -> Result<(), SystemError> {
+    pub async fn heartbeat(
+        &self,
+        term: u64,
+        leader_id: Option<u64>,
+        commit_index: u64,
+    ) -> Result<(), SystemError> {
         debug!(
             "Sending a heartbeat to cluster node ID: {}, address: {}...",
             self.id, self.address
         );
-        let now = Instant::now();
+        let now = environment::now();
         if let Err(error) = self
-            .send_request(&Heartbeat::new_command(term, leader_id))
+            .send_request_with_timeout(&Heartbeat::new_command(term, leader_id, commit_index))
             .await
         {
             error!(
                 "Failed to send a heartbeat to cluster node ID: {}, address: {}",
                 self.id, self.address
             );
-            self.set_disconnected().await;
+            // The node which has rejected the heartbeat is still connected.
+            if !matches!(error, SystemError::InvalidResponse(status, _) if status != 0) {
+                self.set_disconnected().await;
+            }
             return Err(error);
         }
-        let elapsed = now.elapsed();
+        let elapsed = environment::elapsed(now);
         debug!(
             "Received a heartbeat from cluster node ID: {}, address: {} in {} ms.",
             self.id,
@@ -216,13 +255,18 @@ impl NodeClient {
         Ok(())
     }
 
-    pub async fn request_vote(&self, term: u64) -> Result<(), SystemError> {
+    pub async fn request_vote(
+        &self,
+        term: u64,
+        last_log_index: u64,
+        last_log_term: u64,
+    ) -> Result<(), SystemError> {
         info!(
             "Sending a request vote to cluster node ID: {}, address: {} in term: {}...",
             self.id, self.address, term
         );
-        let command = RequestVote::new_command(term);
-        if let Err(error) = self.send_request(&command).await {
+        let command = RequestVote::new_command(term, last_log_index, last_log_term);
+        if let Err(error) = self.send_request_with_timeout(&command).await {
             error!(
                 "Failed to send a request vote to cluster node ID: {}, address: {} in term: {}.",
                 self.id, self.address, term
@@ -236,57 +280,45 @@ impl NodeClient {
         Ok(())
     }
 
-    pub async fn update_leader(&self, term: u64, leader_id: u64) -> Result<(), SystemError> {
+    pub async fn timeout_now(&self, term: u64) -> Result<(), SystemError> {
         info!(
-            "Sending an update leader ID: {leader_id} to cluster node ID: {}, address: {} in term: {term}...",
+            "Sending a timeout now to cluster node ID: {}, address: {} in term: {term}...",
             self.id, self.address
         );
-        let command = UpdateLeader::new_command(term, leader_id);
-        self.send_request(&command).await?;
-        if let Err(error) = self.send_request(&command).await {
+        let command = TimeoutNow::new_command(term);
+        if let Err(error) = self.send_request_with_timeout(&command).await {
             error!(
-                "Failed to send an update leader ID: {leader_id} to cluster node ID: {}, address: {} in term: {term}.",
+                "Failed to send a timeout now to cluster node ID: {}, address: {} in term: {term}.",
                 self.id, self.address
             );
             return Err(error);
         }
         info!(
-            "Received an update leader ID: {leader_id} response from cluster node ID: {}, address: {} in term: {term}.",
-            self.id, self.address,
+            "Received a timeout now response from cluster node ID: {}, address: {} in term: {term}.",
+            self.id, self.address
         );
         Ok(())
     }
 
-    pub async fn get_streams(&self) -> Result<Vec<Stream>, SystemError> {
+    pub async fn update_leader(&self, term: u64, leader_id: u64) -> Result<(), SystemError> {
         info!(
-            "Sending a get streams to cluster node ID: {}, address: {}...",
+            "Sending an update leader ID: {leader_id} to cluster node ID: {}, address: {} in term: {term}...",
             self.id, self.address
         );
-        let command = GetStreams::new_command();
-        let result = self.send_request(&command).await;
-        if result.is_err() {
+        let command = UpdateLeader::new_command(term, leader_id);
+        self.send_request_with_timeout(&command).await?;
+        if let Err(error) = self.send_request_with_timeout(&command).await {
             error!(
-                "Failed to send a get streams to cluster node ID: {}, address: {}.",
+                "Failed to send an update leader ID: {leader_id} to cluster node ID: {}, address: {} in term: {term}.",
                 self.id, self.address
             );
-            return Err(result.unwrap_err());
+            return Err(error);
         }
-
         info!(
-            "Received a get streams response from cluster node ID: {}, address: {}.",
-            self.id, self.address
+            "Received an update leader ID: {leader_id} response from cluster node ID: {}, address: {} in term: {term}.",
+            self.id, self.address,
         );
-
-        let bytes = result.unwrap();
-        let mut streams = Vec::new();
-        let mut position = 0;
-        while position < bytes.len() {
-            let stream = Stream::from_bytes(&bytes[position..position + 25])?;
-            position += 25;
-            streams.push(stream);
-        }
-
-        Ok(streams)
+        Ok(())
     }
 
     pub async fn get_node_state(&self) -> Result<NodeState, SystemError> {
@@ -296,13 +328,13 @@ impl NodeClient {
         );
 
         let command = GetNodeState::new_command();
-        let result = self.send_request(&command).await;
-        if result.is_err() {
+        let result = self.send_request_with_timeout(&command).await;
+        if let Err(error) = result {
             error!(
                 "Failed to send a get node state to cluster node ID: {}, address: {}.",
                 self.id, self.address
             );
-            return Err(result.unwrap_err());
+            return Err(error);
         }
 
         info!(
@@ -314,103 +346,108 @@ impl NodeClient {
         Ok(node_state)
     }
 
-    pub async fn load_state(&self, start_index: Index) -> Result<AppendedState, SystemError> {
-        info!(
-            "Sending a load state to cluster node ID: {}, address: {}...",
-            self.id, self.address
-        );
-
-        let command = LoadState::new_command(start_index);
-        let result = self.send_request(&command).await;
-        if result.is_err() {
-            error!(
-                "Failed to send a load state to cluster node ID: {}, address: {}.",
-                self.id, self.address
-            );
-            return Err(result.unwrap_err());
-        }
-
-        info!(
-            "Received a load state response from cluster node ID: {}, address: {}.",
-            self.id, self.address
-        );
-
-        let appended_state = AppendedState::from_bytes(&result.unwrap())?;
-        Ok(appended_state)
-    }
-
     pub async fn append_entries(
         &self,
         term: Term,
         leader_commit: Index,
-        prev_log_index: Index,
-        entries: Vec<LogEntry>,
-    ) -> Result<(), SystemError> {
+        batches: Vec<AppendEntriesBatch>,
+    ) -> Vec<Result<(), SystemError>> {
         info!(
-            "Sending an append entry to cluster node ID: {}, address: {} in term: {}...",
-            self.id, self.address, term
+            "Sending {} append entries to cluster node ID: {}, address: {} in term: {}...",
+            batches.len(),
+            self.id,
+            self.address,
+            term
         );
-        let leader_id = self.leader_id.lock().await.unwrap();
-        let command =
-            AppendEntries::new_command(term, leader_id, leader_commit, prev_log_index, entries);
-        if let Err(error) = self.send_request(&command).await {
+        let Some(leader_id) = *self.leader_id.lock().await else {
+            error!("Cannot send append entries without the leader in term: {term}.");
+            return batches
+                .iter()
+                .map(|_| Err(SystemError::NotLeader))
+                .collect();
+        };
+
+        let commands = batches
+            .into_iter()
+            .map(|batch| {
+                AppendEntries::new_command(
+                    term,
+                    leader_id,
+                    leader_commit,
+                    batch.prev_log_index,
+                    batch.prev_log_term,
+                    batch.entries,
+                )
+            })
+            .collect::<Vec<_>>();
+        let results = self.send_requests(&commands).await;
+        if let Some(Err(error)) = results.iter().find(|result| result.is_err()) {
             error!(
-                "Failed to send an append entry to cluster node ID: {}, address: {} in term: {}.",
-                self.id, self.address, term
+                "Failed to send append entries to cluster node ID: {}, address: {} in term: {term}, {error}",
+                self.id, self.address
+            );
+        } else {
+            info!(
+                "Received the append entries responses from cluster node ID: {}, address: {} in term: {term}.",
+                self.id, self.address
             );
-            return Err(error);
         }
-        info!(
-            "Received an append entry response from cluster node ID: {}, address: {} in term: {}.",
-            self.id, self.address, term
-        );
-        Ok(())
+        results
+            .into_iter()
+            .map(|result| result.map(|_| ()))
+            .collect()
     }
 
-    pub async fn sync_messages(
+    pub async fn fetch_messages(
         &self,
-        term: u64,
+        term: Term,
         stream_id: u64,
-        current_offset: u64,
-        messages: &[AppendableMessage],
-    ) -> Result<(), SystemError> {
-        info!(
-            "Sending a sync messages to cluster node ID: {}, address: {} in term: {}...",
-            self.id, self.address, term
-        );
-        let mut synced_messages = Vec::with_capacity(messages.len());
-        for message in messages {
-            synced_messages.push(AppendableMessage {
-                id: message.id,
-                payload: message.payload.clone(),
-            });
-        }
+        leader_epoch: u64,
+        offset: u64,
+        count: u64,
+    ) -> Result<FetchedMessages, SystemError> {
+        let command = FetchMessages::new_command(term, stream_id, leader_epoch, offset, count);
+        let bytes = self.send_request_with_timeout(&command).await?;
+        FetchedMessages::from_bytes(&bytes)
+    }
 
-        let command = SyncMessages::new_command(term, stream_id, current_offset, synced_messages);
-        if let Err(error) = self.send_request(&command).await {
-            error!(
-                "Failed to send a sync messages to cluster node ID: {}, address: {} in term: {}.",
-                self.id, self.address, term
-            );
-            return Err(error);
-        }
-        info!(
-            "Received a sync messages response from cluster node ID: {}, address: {} in term: {}.",
-            self.id, self.address, term
-        );
-        Ok(())
+    pub async fn get_epoch_end_offset(
+        &self,
+        stream_id: u64,
+        current_leader_epoch: u64,
+        leader_epoch: u64,
+    ) -> Result<EpochEndOffset, SystemError> {
+        let command = GetEpochEndOffset::new_command(stream_id, current_leader_epoch, leader_epoch);
+        let bytes = self.send_request_with_timeout(&command).await?;
+        EpochEndOffset::from_bytes(&bytes)
     }
 
-    pub async fn poll_messages(
+    pub async fn get_stream_chunk(
         &self,
         stream_id: u64,
         offset: u64,
-        count: u64,
-    ) -> Result<Vec<Message>, SystemError> {
-        let command = PollMessages::new_command(stream_id, offset, count);
+        max_bytes: u32,
+    ) -> Result<FetchedMessages, SystemError> {
+        let command = GetStreamChunk::new_command(stream_id, offset, max_bytes);
         let bytes = self.send_request(&command).await?;
-        let messages = messages_from_bytes(&bytes);
-        Ok(messages)
+        FetchedMessages::from_bytes(&bytes)
+    }
+
+    pub async fn update_isr(
+        &self,
+        stream_id: u64,
+        leader_epoch: u64,
+        isr: Vec<u64>,
+    ) -> Result<(), SystemError> {
+        let command = UpdateIsr::new_command(stream_id, leader_epoch, isr);
+        self.send_request(&command).await?;
+        Ok(())
+    }
+
+    pub async fn get_metadata(&self) -> Result<Metadata, SystemError> {
+        let command = GetMetadata::new_command(ReadConsistency::Linearizable);
+        let bytes = self.send_request_with_timeout(&command).await?;
+        Metadata::from_bytes(&bytes)
     }
 
     pub async fn is_connected(&self) -> bool {
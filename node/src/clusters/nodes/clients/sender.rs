@@ -1,16 +1,48 @@
 use crate::clusters::nodes::clients::node_client::NodeClient;
+use crate::environment;
 use sdk::commands::command::Command;
 use sdk::error::SystemError;
+use std::time::Duration;
 use tracing::{debug, warn};
 
 impl NodeClient {
     pub async fn send_request(&self, command: &Command) -> Result<Vec<u8>, SystemError> {
+        self.send(command, None).await
+    }
+
+    /// Sends the request, which fails once the response hasn't been received within the request timeout,
+    /// as the node might never respond while the connection stays open, e.g. when the network is partitioned.
+    pub async fn send_request_with_timeout(
+        &self,
+        command: &Command,
+    ) -> Result<Vec<u8>, SystemError> {
+        self.send(command, Some(self.request_timeout())).await
+    }
+
+    async fn send(
+        &self,
+        command: &Command,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<u8>, SystemError> {
         if !self.is_connected().await {
             warn!("Cannot send a request, client is disconnected.");
             return Err(SystemError::ClientDisconnected);
         }
 
-        let mut stream = self.handler.lock().await;
+        let mut stream = match timeout {
+            Some(timeout) => {
+                // The connection is kept, as it's used by another request, which is still waiting for its response.
+                let Some(stream) = environment::timeout(timeout, self.handler.lock()).await else {
+                    warn!(
+                        "Cannot send a request to cluster node with address: {}, the connection is busy.",
+                        self.address
+                    );
+                    return Err(SystemError::SendRequestFailed);
+                };
+                stream
+            }
+            None => self.handler.lock().await,
+        };
         if stream.is_none() {
             warn!("Cannot send a request, client is disconnected.");
             return Err(SystemError::ClientDisconnected);
@@ -21,9 +53,35 @@ impl NodeClient {
             self.address
         );
         let handler = stream.as_mut().unwrap();
-        let result = handler.send_request(command).await;
-        if result.is_err() {
-            debug!("Failed to send a request: {:?}", result.err());
+        let result = match timeout {
+            Some(timeout) => {
+                let Some(result) =
+                    environment::timeout(timeout, handler.send_request(command)).await
+                else {
+                    warn!(
+                        "Request to cluster node with address: {} has timed out.",
+                        self.address
+                    );
+                    // The response might still arrive later and be read as the response to the next request.
+                    stream.take();
+                    self.set_disconnected().await;
+                    return Err(SystemError::SendRequestFailed);
+                };
+                result
+            }
+            None => handler.send_request(command).await,
+        };
+        if let Err(error) = result {
+            debug!("Failed to send a request: {:?}", error);
+            // Keep the error status returned by the node, so that the caller can handle it.
+            if let SystemError::InvalidResponse(status, payload) = error {
+                if status != 0 {
+                    return Err(SystemError::InvalidResponse(status, payload));
+                }
+            }
+            // The response might still arrive later and be read as the response to the next request.
+            stream.take();
+            self.set_disconnected().await;
             return Err(SystemError::SendRequestFailed);
         }
 
@@ -33,4 +91,53 @@ impl NodeClient {
         );
         Ok(result.unwrap().1)
     }
+
+    /// Sends the requests without waiting for the responses in between, the results are in the order of the requests.
+    pub async fn send_requests(&self, commands: &[Command]) -> Vec<Result<Vec<u8>, SystemError>> {
+        if !self.is_connected().await {
+            warn!("Cannot send the requests, client is disconnected.");
+            return commands
+                .iter()
+                .map(|_| Err(SystemError::ClientDisconnected))
+                .collect();
+        }
+
+        let mut stream = self.handler.lock().await;
+        let Some(handler) = stream.as_mut() else {
+            warn!("Cannot send the requests, client is disconnected.");
+            return commands
+                .iter()
+                .map(|_| Err(SystemError::ClientDisconnected))
+                .collect();
+        };
+
+        debug!(
+            "Sending {} requests to cluster node with address: {}...",
+            commands.len(),
+            self.address
+        );
+        let results = handler.send_requests(commands).await;
+        if results
+            .iter()
+            .any(|result| matches!(result, Err(SystemError::InvalidResponse(0, _))))
+        {
+            // The unread responses might still arrive later and be read as the responses to the next requests.
+            stream.take();
+            self.set_disconnected().await;
+        }
+
+        results
+            .into_iter()
+            .map(|result| match result {
+                Ok((_, payload)) => Ok(payload),
+                Err(SystemError::InvalidResponse(status, payload)) if status != 0 => {
+                    Err(SystemError::InvalidResponse(status, payload))
+                }
+                Err(error) => {
+                    debug!("Failed to send a request: {:?}", error);
+                    Err(SystemError::SendRequestFailed)
+                }
+            })
+            .collect()
+    }
 }
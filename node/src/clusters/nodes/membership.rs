@@ -0,0 +1,510 @@
+use crate::clusters::cluster::{Cluster, ClusterNode, ClusterNodeState, ReplicationProgress};
+use crate::connection::handler::ConnectionHandler;
+use crate::environment;
+use crate::types::{NodeId, Term};
+use futures::lock::Mutex;
+use sdk::commands::add_node::AddNode;
+use sdk::commands::command::Command;
+use sdk::commands::remove_node::RemoveNode;
+use sdk::error::SystemError;
+use std::rc::Rc;
+use tracing::{error, info, warn};
+
+impl Cluster {
+    pub async fn is_member(&self) -> bool {
+        *self.is_member.lock().await
+    }
+
+    pub async fn add_node(
+        &self,
+        handler: &mut ConnectionHandler,
+        term: Term,
+        command: AddNode,
+    ) -> Result<(), SystemError> {
+        self.begin_membership_change(term, command.id).await?;
+        let result = self.add_node_to_cluster(handler, term, command).await;
+        self.membership_change.lock().await.take();
+        result
+    }
+
+    pub async fn remove_node(
+        &self,
+        handler: &mut ConnectionHandler,
+        term: Term,
+        command: RemoveNode,
+    ) -> Result<(), SystemError> {
+        self.begin_membership_change(term, command.id).await?;
+        let result = self.remove_node_from_cluster(handler, term, command).await;
+        self.membership_change.lock().await.take();
+        result
+    }
+
+    async fn begin_membership_change(
+        &self,
+        term: Term,
+        node_id: NodeId,
+    ) -> Result<(), SystemError> {
+        // The change of the previous term could still be uncommitted, and the single-server changes
+        // are safe only one at a time, so the change waits for the entry at the start of the term.
+        self.verify_term_start_committed(term).await?;
+        let mut membership_change = self.membership_change.lock().await;
+        if let Some(changed_node_id) = *membership_change {
+            warn!(
+                "Cannot change membership of node ID: {node_id}, membership change of node ID: {changed_node_id} is in progress."
+            );
+            return Err(SystemError::MembershipChangeInProgress);
+        }
+
+        *membership_change = Some(node_id);
+        Ok(())
+    }
+
+    async fn add_node_to_cluster(
+        &self,
+        handler: &mut ConnectionHandler,
+        term: Term,
+        command: AddNode,
+    ) -> Result<(), SystemError> {
+        let node_id = command.id;
+        if let Some(cluster_node) = self.get_node(node_id).await {
+            if cluster_node.is_learner().await && !command.learner {
+                return self
+                    .promote_learner(handler, term, cluster_node, command)
+                    .await;
+            }
+
+            error!("Node ID: {node_id} is already a cluster member.");
+            return Err(SystemError::NodeAlreadyExists(node_id));
+        }
+
+        info!(
+            "Adding node: {}, ID: {node_id}, address: {}, learner: {} to the cluster...",
+            command.name, command.internal_address, command.learner
+        );
+        let cluster_node = Rc::new(ClusterNode {
+            state: Mutex::new(Self::get_added_node_state(&command)),
+            node: self.create_node(
+                node_id,
+                &command.name,
+                &command.internal_address,
+                &command.public_address,
+            )?,
+            progress: ReplicationProgress::default(),
+        });
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        cluster_node.node.set_leader(term, self_node_id).await;
+        // The node is caught up before joining the cluster, it becomes a member only once its addition is applied.
+        Self::connect_to_node(cluster_node.clone()).await?;
+        self.added_node.lock().await.replace(cluster_node.clone());
+        let result = match self.catch_up_node(term, &cluster_node).await {
+            Ok(()) => {
+                self.sync_state(handler, term, Command::AddNode(command))
+                    .await
+            }
+            Err(error) => {
+                error!("Failed to catch up node ID: {node_id}, error: {error}");
+                Err(error)
+            }
+        };
+        if let Some(cluster_node) = self.added_node.lock().await.take() {
+            if let Err(error) = cluster_node.node.close().await {
+                warn!("Failed to disconnect from node ID: {node_id}, error: {error}");
+            }
+        }
+        if let Err(error) = result {
+            error!("Failed to replicate addition of node ID: {node_id}, error: {error}");
+            return Err(error);
+        }
+
+        info!("Added node ID: {node_id} to the cluster.");
+        Ok(())
+    }
+
+    async fn promote_learner(
+        &self,
+        handler: &mut ConnectionHandler,
+        term: Term,
+        cluster_node: Rc<ClusterNode>,
+        command: AddNode,
+    ) -> Result<(), SystemError> {
+        let node_id = command.id;
+        info!("Promoting learner node ID: {node_id} to a voter...");
+        self.catch_up_node(term, &cluster_node).await?;
+        // The learner becomes a voter only once the promotion is committed and applied.
+        if let Err(error) = self
+            .sync_state(handler, term, Command::AddNode(command))
+            .await
+        {
+            error!("Failed to replicate promotion of learner node ID: {node_id}, error: {error}");
+            return Err(error);
+        }
+
+        info!("Promoted learner node ID: {node_id} to a voter.");
+        Ok(())
+    }
+
+    async fn remove_node_from_cluster(
+        &self,
+        handler: &mut ConnectionHandler,
+        term: Term,
+        command: RemoveNode,
+    ) -> Result<(), SystemError> {
+        let node_id = command.id;
+        let self_node = self.get_self_node().unwrap();
+        if self_node.node.id == node_id {
+            error!("Cannot remove the leader node ID: {node_id}, transfer the leadership first.");
+            return Err(SystemError::CannotRemoveNode(node_id));
+        }
+
+        info!("Removing node ID: {node_id} from the cluster...");
+        if self.get_node(node_id).await.is_none() {
+            error!("Node ID: {node_id} is not a cluster member.");
+            return Err(SystemError::InvalidNode(node_id));
+        }
+
+        // The node is removed only once the removal is committed and applied.
+        if let Err(error) = self
+            .sync_state(handler, term, Command::RemoveNode(command))
+            .await
+        {
+            error!("Failed to replicate removal of node ID: {node_id}, error: {error}");
+            return Err(error);
+        }
+
+        info!("Removed node ID: {node_id} from the cluster.");
+        Ok(())
+    }
+
+    pub(crate) async fn apply_added_node(
+        &self,
+        term: Option<Term>,
+        command: &AddNode,
+    ) -> Result<(), SystemError> {
+        let node_id = command.id;
+        let self_node = self.get_self_node().unwrap();
+        if self_node.node.id == node_id {
+            *self.is_member.lock().await = true;
+            if command.learner {
+                self_node.set_state(ClusterNodeState::Learner).await;
+                info!("This node with ID: {node_id} is now a learner.");
+            } else {
+                if self_node.is_learner().await {
+                    self_node.set_state(ClusterNodeState::Follower).await;
+                }
+                self_node.node.set_can_be_leader(true).await;
+                info!("This node with ID: {node_id} is now a voting cluster member.");
+            }
+            self.update_voters_count().await;
+            return Ok(());
+        }
+
+        if let Some(cluster_node) = self.get_node(node_id).await {
+            if cluster_node.is_learner().await && !command.learner {
+                cluster_node.set_state(ClusterNodeState::Follower).await;
+                self.update_voters_count().await;
+                info!("Promoted learner node ID: {node_id} to a voter.");
+            }
+            return Ok(());
+        }
+
+        // The leader has already connected to the node it has caught up.
+        let added_node = self
+            .added_node
+            .lock()
+            .await
+            .take_if(|cluster_node| cluster_node.node.id == node_id);
+        if let Some(cluster_node) = added_node {
+            self.insert_node(cluster_node).await;
+            info!(
+                "Added node: {}, ID: {node_id} to the cluster.",
+                command.name
+            );
+            return Ok(());
+        }
+
+        let cluster_node = Rc::new(ClusterNode {
+            state: Mutex::new(Self::get_added_node_state(command)),
+            node: self.create_node(
+                node_id,
+                &command.name,
+                &command.internal_address,
+                &command.public_address,
+            )?,
+            progress: ReplicationProgress::default(),
+        });
+        if let Some(leader_id) = self.election_manager.get_leader_id().await {
+            cluster_node
+                .node
+                .set_leader(self.election_manager.get_current_term().await, leader_id)
+                .await;
+        }
+        self.insert_node(cluster_node.clone()).await;
+        info!(
+            "Added node: {}, ID: {node_id} to the cluster.",
+            command.name
+        );
+
+        // During the initial replay the connections are established afterwards for all the nodes.
+        if term.is_some() {
+            environment::spawn(async move {
+                if let Err(error) = Self::connect_to_node(cluster_node).await {
+                    error!("Failed to connect to the added node ID: {node_id}, error: {error}");
+                }
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn apply_removed_node(
+        &self,
+        term: Option<Term>,
+        command: &RemoveNode,
+    ) -> Result<(), SystemError> {
+        let node_id = command.id;
+        let self_node = self.get_self_node().unwrap();
+        if self_node.node.id == node_id {
+            *self.is_member.lock().await = false;
+            self_node.node.set_can_be_leader(false).await;
+            self_node.set_state(ClusterNodeState::Follower).await;
+            warn!("This node with ID: {node_id} has been removed from the cluster.");
+            return Ok(());
+        }
+
+        let Some(cluster_node) = self.take_node(node_id).await else {
+            return Ok(());
+        };
+
+        // The removed node isn't replicated to anymore, so it learns the commit index of its removal first.
+        if let Some(term) = term {
+            if self.is_leader().await {
+                self.replicate_to_removed_node(term, &cluster_node).await;
+            }
+        }
+
+        // The removal has already been committed, so it's applied, even if the connection can't be closed.
+        if let Err(error) = cluster_node.node.close().await {
+            warn!("Failed to disconnect from the removed node ID: {node_id}, error: {error}");
+        }
+        info!("Removed node ID: {node_id} from the cluster.");
+        Ok(())
+    }
+
+    fn get_added_node_state(command: &AddNode) -> ClusterNodeState {
+        if command.learner {
+            ClusterNodeState::Learner
+        } else {
+            ClusterNodeState::Follower
+        }
+    }
+
+    async fn insert_node(&self, cluster_node: Rc<ClusterNode>) {
+        self.nodes
+            .lock()
+            .await
+            .insert(cluster_node.node.id, cluster_node);
+        self.update_voters_count().await;
+    }
+
+    async fn take_node(&self, node_id: NodeId) -> Option<Rc<ClusterNode>> {
+        let cluster_node = self.nodes.lock().await.remove(&node_id);
+        self.update_voters_count().await;
+        cluster_node
+    }
+
+    async fn update_voters_count(&self) {
+        self.election_manager
+            .set_nodes_count(self.get_voters_count().await)
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{add_node_command, as_command, run_scenario, send_to, NODES_COUNT};
+    use linearizability::history::Operation;
+    use sdk::commands::remove_node::RemoveNode;
+    use sdk::error::SystemError;
+    use std::time::Duration;
+
+    #[test]
+    fn node_should_be_removed_and_added_again_once_changes_are_applied() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                let node_id = leader_id % NODES_COUNT + 1;
+                let other_node_id = node_id % NODES_COUNT + 1;
+                let removed = simulation.spawn_client(async move {
+                    send_to(leader_id, &RemoveNode::new_command(node_id)).await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(removed.take().is_some_and(|result| result.is_ok()));
+                let leader = simulation.cluster(leader_id).unwrap();
+                let other_node = simulation.cluster(other_node_id).unwrap();
+                assert!(leader.get_node(node_id).await.is_none());
+                assert!(other_node.get_node(node_id).await.is_none());
+                assert!(!simulation.cluster(node_id).unwrap().is_member().await);
+
+                let added = simulation.spawn_client(async move {
+                    send_to(leader_id, &add_node_command(node_id, false)).await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(added.take().is_some_and(|result| result.is_ok()));
+                assert!(leader.get_node(node_id).await.is_some());
+                assert!(other_node.get_node(node_id).await.is_some());
+                assert!(simulation.cluster(node_id).unwrap().is_member().await);
+                Ok(())
+            })
+        });
+    }
+
+    #[test]
+    fn node_should_not_be_added_until_it_has_caught_up() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                // The node isn't running, so it cannot be caught up by the leader.
+                let node_id = NODES_COUNT + 1;
+                let added = simulation.spawn_client(async move {
+                    send_to(leader_id, &add_node_command(node_id, false)).await
+                });
+                simulation.run_for(Duration::from_secs(5)).await?;
+                assert!(added.take().is_some_and(|result| result.is_err()));
+                for cluster_node_id in 1..=NODES_COUNT {
+                    let cluster = simulation.cluster(cluster_node_id).unwrap();
+                    assert!(cluster.get_node(node_id).await.is_none());
+                }
+                Ok(())
+            })
+        });
+    }
+
+    #[test]
+    fn learner_should_neither_vote_nor_count_to_quorum_until_promoted() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                let learner_id = leader_id % NODES_COUNT + 1;
+                let follower_id = learner_id % NODES_COUNT + 1;
+                let demoted = simulation.spawn_client(async move {
+                    send_to(leader_id, &RemoveNode::new_command(learner_id)).await?;
+                    send_to(leader_id, &add_node_command(learner_id, true)).await
+                });
+                simulation.run_for(Duration::from_secs(5)).await?;
+                assert!(demoted.take().is_some_and(|result| result.is_ok()));
+                let leader = simulation.cluster(leader_id).unwrap();
+                let learner = simulation.cluster(learner_id).unwrap();
+                assert!(
+                    leader
+                        .get_node(learner_id)
+                        .await
+                        .unwrap()
+                        .is_learner()
+                        .await
+                );
+                assert!(learner.get_self_node().unwrap().is_learner().await);
+                assert_eq!(leader.get_voters_count().await, 2);
+
+                let term = leader.election_manager.get_current_term().await + 1;
+                let (last_log_index, last_log_term) = {
+                    let state = leader.state.lock().await;
+                    (state.last_index, state.last_log_term())
+                };
+                let votes = simulation.spawn_client({
+                    let leader = leader.clone();
+                    let learner = learner.clone();
+                    async move {
+                        let follower = learner.get_node(follower_id).await.unwrap();
+                        let vote_from_learner = follower
+                            .node
+                            .request_vote(term, last_log_index, last_log_term)
+                            .await;
+                        let learner_node = leader.get_node(learner_id).await.unwrap();
+                        let vote_to_learner = learner_node
+                            .node
+                            .request_vote(term, last_log_index, last_log_term)
+                            .await;
+                        (vote_from_learner, vote_to_learner)
+                    }
+                });
+                simulation.run_for(Duration::from_secs(1)).await?;
+                let (vote_from_learner, vote_to_learner) = votes.take().unwrap();
+                let is_learner_rejection = |vote: Result<_, SystemError>| {
+                    matches!(vote, Err(SystemError::InvalidResponse(status, _))
+                        if status == SystemError::NodeIsLearner(0).as_code())
+                };
+                assert!(is_learner_rejection(vote_from_learner));
+                assert!(is_learner_rejection(vote_to_learner));
+
+                // The leader and the learner alone are not the quorum.
+                simulation.crash_node(follower_id);
+                let created = simulation.spawn_client(async move {
+                    send_to(
+                        leader_id,
+                        &as_command(&Operation::CreateStream { stream_id: 1 }),
+                    )
+                    .await
+                });
+                simulation.run_for(Duration::from_secs(5)).await?;
+                assert!(created.take().is_some_and(|result| result.is_err()));
+                assert!(learner.streamer.lock().await.get_stream(1).is_none());
+
+                simulation.start_node(follower_id);
+                simulation.run_for(Duration::from_secs(5)).await?;
+                let leader_id = simulation.wait_for_leader().await?;
+                let promoted = simulation.spawn_client(async move {
+                    send_to(leader_id, &add_node_command(learner_id, false)).await
+                });
+                simulation.run_for(Duration::from_secs(5)).await?;
+                assert!(promoted.take().is_some_and(|result| result.is_ok()));
+                let leader = simulation.cluster(leader_id).unwrap();
+                assert!(
+                    !leader
+                        .get_node(learner_id)
+                        .await
+                        .unwrap()
+                        .is_learner()
+                        .await
+                );
+                assert!(!learner.get_self_node().unwrap().is_learner().await);
+                assert_eq!(leader.get_voters_count().await, 3);
+                Ok(())
+            })
+        });
+    }
+
+    #[test]
+    fn membership_change_should_fail_once_leader_is_deposed() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                let node_id = leader_id % NODES_COUNT + 1;
+                let removed = simulation.spawn_client(async move {
+                    send_to(leader_id, &RemoveNode::new_command(node_id)).await
+                });
+                // The removal is appended by the leader, which cannot replicate it before the followers elect the new one.
+                for follower_id in (1..=NODES_COUNT).filter(|id| *id != leader_id) {
+                    simulation.partition(leader_id, follower_id);
+                }
+                simulation.run_for(Duration::from_secs(5)).await?;
+                assert!(removed.take().is_some_and(|result| result.is_err()));
+                let mut deposed = false;
+                for follower_id in (1..=NODES_COUNT).filter(|id| *id != leader_id) {
+                    deposed |= simulation.cluster(follower_id).unwrap().is_leader().await;
+                }
+                assert!(deposed);
+
+                // The restarted node rejoins the cluster, its uncommitted removal is replaced by the new leader's log.
+                simulation.heal_partitions();
+                simulation.crash_node(leader_id);
+                simulation.start_node(leader_id);
+                simulation.run_for(Duration::from_secs(5)).await?;
+                simulation.wait_for_leader().await?;
+                for cluster_node_id in 1..=NODES_COUNT {
+                    let cluster = simulation.cluster(cluster_node_id).unwrap();
+                    assert!(cluster.get_node(node_id).await.is_some());
+                }
+                Ok(())
+            })
+        });
+    }
+}
@@ -1,3 +1,4 @@
 mod clients;
 mod cluster_nodes;
+mod membership;
 pub mod node;
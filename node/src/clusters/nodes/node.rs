@@ -1,17 +1,16 @@
 use crate::clusters::cluster::SelfNode;
 use crate::clusters::nodes::clients::node_client::NodeClient;
+use crate::environment;
 use crate::types::{Index, NodeId, Term};
 use futures::lock::Mutex;
-use monoio::time::sleep;
-use sdk::commands::append_messages::AppendableMessage;
 use sdk::error::SystemError;
-use sdk::models::appended_state::AppendedState;
+use sdk::models::fetched_messages::FetchedMessages;
+use sdk::models::leader_epoch::EpochEndOffset;
 use sdk::models::log_entry::LogEntry;
-use sdk::models::message::Message;
+use sdk::models::metadata::Metadata;
 use sdk::models::node_state::NodeState;
-use sdk::models::stream::Stream;
 use std::time::Duration;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[derive(Debug)]
 pub struct Node {
@@ -20,23 +19,43 @@ pub struct Node {
     pub address: String,
     pub public_address: String,
     pub can_be_leader: Mutex<bool>,
+    /// The node has failed to write to its storage, the remote nodes report it by rejecting the heartbeats.
+    fenced: Mutex<bool>,
+    /// The disk usage of the node has exceeded the hard limit, which is reported the same way as the fencing.
+    disk_full: Mutex<bool>,
+    /// The connection has been closed on purpose, e.g. once the node has been removed, so it's not reconnected.
+    closed: Mutex<bool>,
     initial_sync_completed: Mutex<bool>,
     term: Mutex<Term>,
     leader_id: Mutex<Option<NodeId>>,
+    commit_index: Mutex<Index>,
     heartbeat: NodeHeartbeat,
     client: NodeClient,
 }
 
+/// The entries which directly follow the previous entry, sent in a single append entries request.
+#[derive(Debug)]
+pub struct AppendEntriesBatch {
+    pub prev_log_index: Index,
+    pub prev_log_term: Term,
+    pub entries: Vec<LogEntry>,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Resiliency {
     pub heartbeat_interval: u64,
     pub reconnection_retries: u32,
     pub reconnection_interval: u64,
+    /// The time in milliseconds to wait for the response to the requests, which are answered right away,
+    /// e.g. the votes, the heartbeats or the fetches.
+    pub request_timeout: u64,
 }
 
 #[derive(Debug)]
 pub struct NodeHeartbeat {
     pub interval: Duration,
+    /// Only a single heartbeat is sent at a time, even though the node is connected to again, e.g. on its hello.
+    running: Mutex<bool>,
 }
 
 impl Node {
@@ -57,10 +76,15 @@ impl Node {
             public_address: public_address.to_string(),
             heartbeat: NodeHeartbeat {
                 interval: Duration::from_millis(resiliency.heartbeat_interval),
+                running: Mutex::new(false),
             },
             term: Mutex::new(0),
             leader_id: Mutex::new(None),
+            commit_index: Mutex::new(0),
             can_be_leader: Mutex::new(true),
+            fenced: Mutex::new(false),
+            disk_full: Mutex::new(false),
+            closed: Mutex::new(false),
             initial_sync_completed: Mutex::new(false),
             client,
         })
@@ -72,6 +96,10 @@ impl Node {
         self.client.set_leader(term, leader_id).await;
     }
 
+    pub async fn set_commit_index(&self, commit_index: Index) {
+        *self.commit_index.lock().await = commit_index;
+    }
+
     pub fn is_self_node(&self) -> bool {
         self.client.is_self_node()
     }
@@ -93,53 +121,106 @@ impl Node {
             return Ok(());
         }
 
+        if std::mem::replace(&mut *self.heartbeat.running.lock().await, true) {
+            info!(
+                "Heartbeat for cluster node: {} is already running.",
+                self.name
+            );
+            return Ok(());
+        }
+
         info!("Starting heartbeat for cluster node: {}...", self.name);
+        let result = self.send_heartbeats().await;
+        *self.heartbeat.running.lock().await = false;
+        result
+    }
+
+    async fn send_heartbeats(&self) -> Result<(), SystemError> {
         loop {
-            sleep(self.heartbeat.interval).await;
+            environment::sleep(self.heartbeat.interval).await;
             let term = *self.term.lock().await;
             let leader_id = *self.leader_id.lock().await;
-            let heartbeat = self.client.heartbeat(term, leader_id).await;
+            let commit_index = *self.commit_index.lock().await;
+            let heartbeat = self.client.heartbeat(term, leader_id, commit_index).await;
             if heartbeat.is_ok() {
                 info!("Heartbeat passed for cluster node: {}", self.name);
+                self.set_fenced(false).await;
+                self.set_disk_full(false).await;
                 continue;
             }
 
             let error = heartbeat.unwrap_err();
+            if let SystemError::InvalidResponse(status, _) = error {
+                if status == SystemError::NodeFenced.as_code() {
+                    if !self.is_fenced().await {
+                        warn!("Cluster node: {} is fenced.", self.name);
+                        self.set_fenced(true).await;
+                    }
+                    continue;
+                }
+
+                if status == SystemError::InsufficientDiskSpace.as_code() {
+                    if !self.is_disk_full().await {
+                        warn!("Cluster node: {} has run out of disk space.", self.name);
+                        self.set_disk_full(true).await;
+                    }
+                    continue;
+                }
+
+                if status != 0 {
+                    warn!(
+                        "Heartbeat rejected by cluster node: {}, status: {status}",
+                        self.name
+                    );
+                    continue;
+                }
+            }
+
             error!("Heartbeat failed for cluster node: {}, {error}", self.name);
             return Err(error);
         }
     }
 
-    pub async fn request_vote(&self, term: u64) -> Result<(), SystemError> {
+    /// Sends the heartbeat of the leader in the given term, which the node acknowledges only in the same term.
+    pub async fn send_heartbeat(&self, term: Term, leader_id: NodeId) -> Result<(), SystemError> {
         if self.is_self_node() {
             return Ok(());
         }
 
-        self.client.request_vote(term).await
+        let leader_id = Some(leader_id);
+        let commit_index = *self.commit_index.lock().await;
+        self.client.heartbeat(term, leader_id, commit_index).await
     }
 
-    pub async fn update_leader(&self, term: u64, leader_id: u64) -> Result<(), SystemError> {
+    pub async fn request_vote(
+        &self,
+        term: u64,
+        last_log_index: u64,
+        last_log_term: u64,
+    ) -> Result<(), SystemError> {
         if self.is_self_node() {
             return Ok(());
         }
 
-        self.client.update_leader(term, leader_id).await
+        self.client
+            .request_vote(term, last_log_index, last_log_term)
+            .await
     }
 
-    pub async fn get_streams(&self) -> Result<Vec<Stream>, SystemError> {
+    pub async fn update_leader(&self, term: u64, leader_id: u64) -> Result<(), SystemError> {
         if self.is_self_node() {
-            return Ok(Vec::new());
+            return Ok(());
         }
 
-        self.client.get_streams().await
+        self.client.update_leader(term, leader_id).await
     }
 
-    pub async fn load_state(&self, start_index: Index) -> Result<AppendedState, SystemError> {
+    pub async fn timeout_now(&self, term: u64) -> Result<(), SystemError> {
         if self.is_self_node() {
-            return Ok(AppendedState::default());
+            return Ok(());
         }
 
-        self.client.load_state(start_index).await
+        self.client.timeout_now(term).await
     }
 
     pub async fn get_node_state(&self) -> Result<NodeState, SystemError> {
@@ -150,49 +231,87 @@ impl Node {
         self.client.get_node_state().await
     }
 
-    pub async fn append_entry(
+    pub async fn append_entries(
         &self,
         term: Term,
         leader_commit: Index,
-        prev_log_index: Index,
-        entries: Vec<LogEntry>,
-    ) -> Result<(), SystemError> {
+        batches: Vec<AppendEntriesBatch>,
+    ) -> Vec<Result<(), SystemError>> {
         if self.is_self_node() {
-            return Ok(());
+            return batches.iter().map(|_| Ok(())).collect();
         }
 
         self.client
-            .append_entries(term, leader_commit, prev_log_index, entries)
+            .append_entries(term, leader_commit, batches)
             .await
     }
 
-    pub async fn sync_messages(
+    pub async fn fetch_messages(
         &self,
-        term: u64,
+        term: Term,
         stream_id: u64,
-        current_offset: u64,
-        messages: &[AppendableMessage],
-    ) -> Result<(), SystemError> {
+        leader_epoch: u64,
+        offset: u64,
+        count: u64,
+    ) -> Result<FetchedMessages, SystemError> {
         if self.is_self_node() {
-            return Ok(());
+            return Ok(FetchedMessages::default());
         }
 
         self.client
-            .sync_messages(term, stream_id, current_offset, messages)
+            .fetch_messages(term, stream_id, leader_epoch, offset, count)
             .await
     }
 
-    pub async fn poll_messages(
+    pub async fn get_epoch_end_offset(
+        &self,
+        stream_id: u64,
+        current_leader_epoch: u64,
+        leader_epoch: u64,
+    ) -> Result<EpochEndOffset, SystemError> {
+        if self.is_self_node() {
+            return Err(SystemError::InvalidNode(self.id));
+        }
+
+        self.client
+            .get_epoch_end_offset(stream_id, current_leader_epoch, leader_epoch)
+            .await
+    }
+
+    pub async fn get_stream_chunk(
         &self,
         stream_id: u64,
         offset: u64,
-        count: u64,
-    ) -> Result<Vec<Message>, SystemError> {
+        max_bytes: u32,
+    ) -> Result<FetchedMessages, SystemError> {
+        if self.is_self_node() {
+            return Err(SystemError::InvalidNode(self.id));
+        }
+
+        self.client
+            .get_stream_chunk(stream_id, offset, max_bytes)
+            .await
+    }
+
+    pub async fn update_isr(
+        &self,
+        stream_id: u64,
+        leader_epoch: u64,
+        isr: Vec<NodeId>,
+    ) -> Result<(), SystemError> {
+        if self.is_self_node() {
+            return Ok(());
+        }
+
+        self.client.update_isr(stream_id, leader_epoch, isr).await
+    }
+
+    pub async fn get_metadata(&self) -> Result<Metadata, SystemError> {
         if self.is_self_node() {
-            return Ok(Vec::new());
+            return Err(SystemError::InvalidNode(self.id));
         }
 
-        self.client.poll_messages(stream_id, offset, count).await
+        self.client.get_metadata().await
     }
 
     pub async fn disconnect(&self) -> Result<(), SystemError> {
@@ -203,6 +322,16 @@ impl Node {
         self.client.disconnect().await
     }
 
+    /// Disconnects from the node, which isn't reconnected once its heartbeat has failed.
+    pub async fn close(&self) -> Result<(), SystemError> {
+        *self.closed.lock().await = true;
+        self.disconnect().await
+    }
+
+    pub async fn is_closed(&self) -> bool {
+        *self.closed.lock().await
+    }
+
     pub async fn is_connected(&self) -> bool {
         if self.is_self_node() {
             return true;
@@ -219,6 +348,22 @@ impl Node {
         *self.can_be_leader.lock().await = can_be_leader;
     }
 
+    pub async fn is_fenced(&self) -> bool {
+        *self.fenced.lock().await
+    }
+
+    pub async fn set_fenced(&self, fenced: bool) {
+        *self.fenced.lock().await = fenced;
+    }
+
+    pub async fn is_disk_full(&self) -> bool {
+        *self.disk_full.lock().await
+    }
+
+    pub async fn set_disk_full(&self, disk_full: bool) {
+        *self.disk_full.lock().await = disk_full;
+    }
+
     pub async fn initial_sync_completed(&self) -> bool {
         *self.initial_sync_completed.lock().await
     }
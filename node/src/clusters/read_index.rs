@@ -0,0 +1,147 @@
+use crate::clusters::cluster::Cluster;
+use crate::types::Term;
+use futures::future::join_all;
+use sdk::error::SystemError;
+use tracing::{info, warn};
+
+impl Cluster {
+    /// Confirms the leadership with a heartbeat round before serving a linearizable read,
+    /// so that the deposed leader cannot return the stale data from its local memory.
+    pub async fn confirm_leadership(&self) -> Result<(), SystemError> {
+        if !self.is_leader().await {
+            return Err(SystemError::NotLeader);
+        }
+
+        let term = self.election_manager.get_current_term().await;
+        self.verify_term_start_committed(term).await?;
+        let read_index = self.state.lock().await.last_index;
+
+        let mut voters = Vec::new();
+        for node in self.get_nodes().await {
+            if node.node.is_self_node() || node.is_learner().await {
+                continue;
+            }
+            voters.push(node);
+        }
+
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        let results = join_all(
+            voters
+                .iter()
+                .map(|node| node.node.send_heartbeat(term, self_node_id)),
+        )
+        .await;
+        let confirmations = 1 + results.iter().filter(|result| result.is_ok()).count() as u64;
+        let quorum = self.get_quorum_count().await;
+        if confirmations < quorum {
+            warn!(
+                "Leadership in term: {term} has not been confirmed, confirmations: {confirmations}/{quorum}."
+            );
+            return Err(SystemError::LeadershipNotConfirmed);
+        }
+
+        if !self.is_leader().await || self.election_manager.get_current_term().await != term {
+            return Err(SystemError::NotLeader);
+        }
+
+        // The state is applied once committed, so the read must wait until the last entry is applied.
+        if !self.wait_for_commit(read_index, term).await {
+            warn!("Read index: {read_index} has not been committed in term: {term}.");
+            return Err(SystemError::LeadershipNotConfirmed);
+        }
+
+        info!("Confirmed leadership in term: {term}, read index: {read_index}.");
+        Ok(())
+    }
+
+    /// The entries of the previous terms are known to be committed only along with the empty entry,
+    /// which the leader appends at the start of its term, so until then its state may still be stale.
+    pub(crate) async fn verify_term_start_committed(&self, term: Term) -> Result<(), SystemError> {
+        if self.state.lock().await.last_log_term() != term {
+            warn!("Leader has not appended the entry at the start of term: {term} yet.");
+            return Err(SystemError::LeadershipNotConfirmed);
+        }
+
+        if !self.wait_for_term_start(term).await {
+            warn!("Leader has not committed the entry at the start of term: {term}.");
+            return Err(SystemError::LeadershipNotConfirmed);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{as_command, run_scenario, send_to, NODES_COUNT};
+    use linearizability::history::Operation;
+    use sdk::bytes_serializable::BytesSerializable;
+    use sdk::commands::get_metadata::GetMetadata;
+    use sdk::error::SystemError;
+    use sdk::models::metadata::Metadata;
+    use sdk::models::read_consistency::ReadConsistency;
+    use std::time::Duration;
+
+    #[test]
+    fn deposed_leader_should_not_serve_linearizable_reads() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let old_leader_id = simulation.wait_for_leader().await?;
+                let created = simulation.spawn_client(async move {
+                    send_to(
+                        old_leader_id,
+                        &as_command(&Operation::CreateStream { stream_id: 1 }),
+                    )
+                    .await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(created.take().is_some_and(|result| result.is_ok()));
+
+                for node_id in 1..=NODES_COUNT {
+                    if node_id != old_leader_id {
+                        simulation.partition(old_leader_id, node_id);
+                    }
+                }
+                simulation.run_for(Duration::from_secs(10)).await?;
+                // The partitioned leader doesn't know about the new one, so it's looked up among the others.
+                let mut leader_id = None;
+                for node_id in (1..=NODES_COUNT).filter(|node_id| *node_id != old_leader_id) {
+                    if simulation.cluster(node_id).unwrap().is_leader().await {
+                        leader_id = Some(node_id);
+                    }
+                }
+                let leader_id = leader_id.ok_or("No new leader has been elected.")?;
+                let follower_id = (1..=NODES_COUNT)
+                    .find(|node_id| *node_id != old_leader_id && *node_id != leader_id)
+                    .unwrap();
+
+                let reads = simulation.spawn_client(async move {
+                    send_to(
+                        leader_id,
+                        &as_command(&Operation::CreateStream { stream_id: 2 }),
+                    )
+                    .await?;
+                    let read = GetMetadata::new_command(ReadConsistency::Linearizable);
+                    Ok::<_, SystemError>((
+                        send_to(old_leader_id, &read).await,
+                        send_to(follower_id, &read).await,
+                        send_to(leader_id, &read).await,
+                    ))
+                });
+                simulation.run_for(Duration::from_secs(5)).await?;
+                let (old_leader_read, follower_read, leader_read) =
+                    reads.take().unwrap().map_err(|error| error.to_string())?;
+                assert!(old_leader_read.is_err());
+                assert!(matches!(
+                    follower_read,
+                    Err(SystemError::InvalidResponse(status, _))
+                        if status == SystemError::NotLeader.as_code()
+                ));
+                let metadata = Metadata::from_bytes(&leader_read.unwrap()).unwrap();
+                assert!(metadata.streams.contains_key(&1));
+                assert!(metadata.streams.contains_key(&2));
+                Ok(())
+            })
+        });
+    }
+}
@@ -0,0 +1,45 @@
+use crate::clusters::cluster::Cluster;
+use crate::environment;
+use futures::StreamExt;
+use std::rc::Rc;
+use tracing::error;
+
+pub fn subscribe(cluster: Rc<Cluster>) {
+    environment::spawn(async move {
+        listen(cluster).await;
+    });
+}
+
+async fn listen(cluster: Rc<Cluster>) {
+    let Some(mut trigger) = cluster.replication_trigger_receiver.lock().await.take() else {
+        error!("State replication has already been started.");
+        return;
+    };
+
+    let interval = cluster.replication_retry_interval;
+    loop {
+        // Replicate the appended entries immediately, otherwise retry the lagging followers periodically.
+        let _ = environment::timeout(interval, trigger.next()).await;
+        while let Ok(Some(_)) = trigger.try_next() {}
+        let term = cluster.election_manager.get_current_term().await;
+        // The leader applies the entries here, once they've been committed by the replication.
+        if let Err(error) = cluster.apply_committed_state(Some(term)).await {
+            error!("Failed to apply the committed state, {error}");
+        }
+
+        if !cluster.is_leader().await {
+            continue;
+        }
+
+        for node in cluster.get_nodes().await {
+            if node.node.is_self_node() || !node.node.is_connected().await {
+                continue;
+            }
+
+            let cluster = cluster.clone();
+            environment::spawn(async move {
+                cluster.replicate_to_follower(term, &node).await;
+            });
+        }
+    }
+}
@@ -1,29 +1,40 @@
-use crate::streaming::file;
-use crate::types::{Index, Term};
+use crate::environment;
+use crate::types::{CandidateId, Index, Term};
 use bytes::{BufMut, Bytes};
 use sdk::error::SystemError;
-use sdk::models::log_entry::LogEntry;
+use sdk::models::log_entry::{LogEntry, LOG_ENTRY_HEADER_SIZE};
 use std::fmt::Display;
-use std::fs::create_dir_all;
-use std::path::Path;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+const HARD_STATE_SIZE: usize = 24;
 
 #[derive(Debug)]
 pub struct State {
     pub term: Term,
+    pub voted_for: Option<CandidateId>,
     pub commit_index: Index,
-    pub last_applied: Index,
+    /// The index of the last entry in the log, which is not necessarily committed or applied yet.
+    pub last_index: Index,
+    /// The term and the position in the log file of each entry, the log starts at index 1 without any gaps.
+    entries: Vec<EntryLocation>,
     current_position: u64,
     directory_path: String,
     log_path: String,
+    hard_state_path: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EntryLocation {
+    term: Term,
+    position: u64,
 }
 
 impl Display for State {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "term: {}, commit_index: {}, last_applied: {}",
-            self.term, self.commit_index, self.last_applied
+            "term: {}, commit_index: {}, last_index: {}",
+            self.term, self.commit_index, self.last_index
         )
     }
 }
@@ -32,129 +43,213 @@ impl State {
     pub fn new(term: Term, path: &str) -> State {
         State {
             term,
+            voted_for: None,
             commit_index: 0,
-            last_applied: 0,
+            last_index: 0,
+            entries: Vec::new(),
             current_position: 0,
             directory_path: path.to_string(),
             log_path: format!("{}/state.log", path),
+            hard_state_path: format!("{}/hard_state", path),
         }
     }
 
-    pub async fn truncate(&mut self, to_index: Index) -> Result<(), SystemError> {
-        self.load_and_set_state(Some(to_index))
-            .await
-            .unwrap_or_else(|_| panic!("Failed to load state from disk: {}", self.log_path));
-
-        let file = std::fs::File::open(&self.log_path)?;
-        let file_size = file.metadata()?.len();
-        if file_size > self.current_position {
-            info!(
-                "Truncating state file from: {file_size} bytes to {} bytes...",
-                self.current_position
+    pub fn get_path(&self) -> &str {
+        &self.directory_path
+    }
+
+    /// Removes the entries starting at the index, which conflict with the log of the leader.
+    /// The committed entries are never removed, as they might have been applied already.
+    pub async fn truncate_from(&mut self, index: Index) -> Result<(), SystemError> {
+        if index <= self.commit_index {
+            error!(
+                "Cannot truncate the state from index: {index}, commit index: {}.",
+                self.commit_index
             );
-            file.set_len(self.current_position)?;
+            return Err(SystemError::CannotSyncState);
         }
 
+        let Some(location) = self.entries.get(index as usize - 1).copied() else {
+            return Ok(());
+        };
+
+        warn!(
+            "Truncating state from index: {index}, last index: {}...",
+            self.last_index
+        );
+        environment::storage().truncate(&self.log_path, location.position)?;
+        self.entries.truncate(index as usize - 1);
+        self.current_position = location.position;
+        self.last_index = index - 1;
         Ok(())
     }
 
-    pub async fn init(&mut self) {
+    pub async fn init(&mut self) -> Result<(), SystemError> {
         info!("Initializing state...");
-        if !Path::new(&self.directory_path).exists() {
-            create_dir_all(&self.directory_path).unwrap_or_else(|_| {
-                panic!("Failed to create state directory: {}", self.directory_path)
-            });
+        let storage = environment::storage();
+        if !storage.exists(&self.directory_path) {
+            if let Err(error) = storage.create_dir_all(&self.directory_path) {
+                error!(
+                    "Failed to create state directory: {}, {error}",
+                    self.directory_path
+                );
+                return Err(error.into());
+            }
             info!("Created state directory: {}", self.directory_path);
         }
-        if !Path::new(&self.log_path).exists() {
-            file::write(&self.log_path)
-                .await
-                .unwrap_or_else(|_| panic!("Failed to create state file: {}", self.log_path));
+        if !storage.exists(&self.log_path) {
+            if let Err(error) = storage.write_at(&self.log_path, 0, Vec::new()).await {
+                error!("Failed to create state file: {}, {error}", self.log_path);
+                return Err(error.into());
+            }
             info!("Created empty state file: {}", self.log_path);
         }
 
         info!("Initializing state...");
-        self.load_and_set_state(None)
+        if let Err(error) = self.load_state().await {
+            error!("Failed to load state from disk: {}, {error}", self.log_path);
+            return Err(error);
+        }
+
+        info!("Initialized state: {self}");
+        Ok(())
+    }
+
+    /// Loads the log and the hard state, the partially written entry at the end of the log is removed.
+    async fn load_state(&mut self) -> Result<(), SystemError> {
+        let storage = environment::storage();
+        let mut entries = Vec::new();
+        let (position, last_index) = self
+            .load_state_from_disk(None, &mut |entry, position| {
+                entries.push(EntryLocation {
+                    term: entry.term,
+                    position,
+                })
+            })
+            .await?;
+        let term = entries.last().map_or(0, |location| location.term);
+        self.entries = entries;
+        self.current_position = position;
+        self.last_index = last_index;
+        let file_size = storage.size(&self.log_path)?;
+        if file_size > position {
+            info!("Truncating state file from: {file_size} bytes to {position} bytes...");
+            storage.truncate(&self.log_path, position)?;
+        }
+
+        // The term of the last entry is the lowest possible term, if the hard state hasn't been saved yet.
+        self.term = term;
+        if !storage.exists(&self.hard_state_path) {
+            return Ok(());
+        }
+
+        let bytes = storage.read(&self.hard_state_path).await?;
+        if bytes.len() != HARD_STATE_SIZE {
+            error!("Invalid hard state file: {}.", self.hard_state_path);
+            return Err(SystemError::CannotSyncState);
+        }
+
+        let saved_term = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let voted_for = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let commit_index = u64::from_le_bytes(bytes[16..24].try_into()?);
+        if saved_term >= term {
+            self.term = saved_term;
+            self.voted_for = (voted_for > 0).then_some(voted_for);
+        }
+        self.commit_index = commit_index.min(self.last_index);
+        Ok(())
+    }
+
+    /// Saves the term, the vote and the commit index, which must survive the restart of the node,
+    /// the file is replaced atomically, so that the previous hard state is kept if the write fails.
+    pub async fn save_hard_state(&self) -> Result<(), SystemError> {
+        let mut bytes = Vec::with_capacity(HARD_STATE_SIZE);
+        bytes.put_u64_le(self.term);
+        bytes.put_u64_le(self.voted_for.unwrap_or_default());
+        bytes.put_u64_le(self.commit_index);
+        if let Err(error) = environment::storage()
+            .replace(&self.hard_state_path, bytes)
             .await
-            .unwrap_or_else(|_| panic!("Failed to load state from disk: {}", self.log_path));
+        {
+            error!(
+                "Failed to save hard state: {}, {error}",
+                self.hard_state_path
+            );
+            return Err(SystemError::CannotAppendToState);
+        }
 
-        info!("Initialized state");
+        Ok(())
     }
 
-    pub async fn load_entries(&self, to_index: Option<Index>) -> Vec<LogEntry> {
+    pub async fn load_entries(
+        &self,
+        to_index: Option<Index>,
+    ) -> Result<Vec<LogEntry>, SystemError> {
         info!("Loading entries from disk, to index: {:?}", to_index);
         let mut entries = vec![];
-        self.load_state_from_disk(to_index, &mut |entry| entries.push(entry))
-            .await;
-        entries
+        self.load_state_from_disk(to_index, &mut |entry, _| entries.push(entry))
+            .await?;
+        Ok(entries)
     }
 
-    async fn load_and_set_state(&mut self, to_index: Option<Index>) -> Result<(), SystemError> {
-        let mut entries = vec![];
-        let (position, term, index) = self
-            .load_state_from_disk(to_index, &mut |entry| entries.push(entry))
-            .await;
-        self.commit_index = index;
-        self.term = term;
-        self.current_position = position;
-        self.last_applied = self.commit_index;
-        Ok(())
+    /// Loads at most the given number of entries, starting at the index.
+    pub async fn load_entries_from(
+        &self,
+        start_index: Index,
+        count: usize,
+    ) -> Result<Vec<LogEntry>, SystemError> {
+        if start_index == 0 || start_index > self.last_index || count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let start = self.entries[start_index as usize - 1].position as usize;
+        let end_index = (start_index as usize - 1 + count).min(self.entries.len());
+        let end = self
+            .entries
+            .get(end_index)
+            .map_or(self.current_position, |location| location.position) as usize;
+        let Ok(bytes) = environment::storage()
+            .read_at(&self.log_path, start as u64, end - start)
+            .await
+        else {
+            error!("Failed to read entries from index: {start_index}.");
+            return Err(SystemError::CannotSyncState);
+        };
+
+        let mut entries = Vec::with_capacity(end_index + 1 - start_index as usize);
+        let mut position = 0;
+        while position < bytes.len() {
+            let entry = read_entry(&bytes[position..]).ok_or(SystemError::CannotSyncState)?;
+            position += LOG_ENTRY_HEADER_SIZE + entry.data.len();
+            entries.push(entry);
+        }
+        Ok(entries)
     }
 
     async fn load_state_from_disk<F>(
         &self,
         to_index: Option<u64>,
         on_entry: &mut F,
-    ) -> (u64, Term, Index)
+    ) -> Result<(u64, Index), SystemError>
     where
-        F: FnMut(LogEntry),
+        F: FnMut(LogEntry, u64),
     {
-        let file = file::open(&self.log_path).await.unwrap();
-        let mut position = 0u64;
-        let mut term = 0;
+        let file = environment::storage().read(&self.log_path).await?;
+        // The position and index of the last complete entry, the torn one at the end is skipped.
+        let mut position = 0usize;
         let mut index = 0;
         loop {
-            let buffer = vec![0u8; 8];
-            let (result, buffer) = file.read_exact_at(buffer, position).await;
-            if result.is_err() {
-                error!("Failed to read index");
-                break;
-            }
-
-            index = u64::from_le_bytes(buffer.try_into().unwrap());
-            position += 8;
-
-            let buffer = vec![0u8; 8];
-            let (result, buffer) = file.read_exact_at(buffer, position).await;
-            if result.is_err() {
-                error!("Failed to read term");
-                break;
-            }
-
-            term = u64::from_le_bytes(buffer.try_into().unwrap());
-            position += 8;
-
-            let buffer = vec![0u8; 4];
-            let (result, buffer) = file.read_exact_at(buffer, position).await;
-            if result.is_err() {
-                error!("Failed to read payload length");
-                break;
-            }
-
-            let size = u32::from_le_bytes(buffer.try_into().unwrap());
-            position += 4;
-
-            let buffer = vec![0; size as usize];
-            let (data_result, data) = file.read_exact_at(buffer, position).await;
-            if data_result.is_err() {
-                error!("Failed to read payload");
+            let Some(entry) = file.get(position..).and_then(read_entry) else {
+                if position < file.len() {
+                    error!("Failed to read entry at position: {position}");
+                }
                 break;
-            }
+            };
 
-            let data = Bytes::from(data);
-            position += size as u64;
-            let entry = LogEntry { index, size, data };
-            on_entry(entry);
+            let entry_position = position as u64;
+            position += LOG_ENTRY_HEADER_SIZE + entry.data.len();
+            index = entry.index;
+            on_entry(entry, entry_position);
 
             if let Some(to_index) = to_index {
                 if index >= to_index {
@@ -163,43 +258,73 @@ impl State {
             }
         }
 
-        (position, term, index)
+        Ok((position as u64, index))
     }
 
+    /// The vote is kept only within its term.
     pub fn set_term(&mut self, term: Term) {
+        if term != self.term {
+            self.voted_for = None;
+        }
         self.term = term;
     }
 
-    pub async fn append(&mut self, payload: Bytes) -> Result<LogEntry, SystemError> {
-        if self.commit_index > 0 {
-            self.commit_index += 1;
+    /// Returns the term of the entry at the index, the index 0 precedes the first entry of the log.
+    pub fn term_at(&self, index: Index) -> Option<Term> {
+        if index == 0 {
+            return Some(0);
+        }
+
+        self.entries
+            .get(index as usize - 1)
+            .map(|location| location.term)
+    }
+
+    pub fn last_log_term(&self) -> Term {
+        self.entries.last().map_or(0, |location| location.term)
+    }
+
+    /// Appends the payload as the new entry of the leader's term, which must still be the current one,
+    /// otherwise the entry could conflict with the one appended by the leader of the newer term.
+    pub async fn append(&mut self, term: Term, payload: Bytes) -> Result<LogEntry, SystemError> {
+        if term != self.term {
+            warn!(
+                "Cannot append entry in term: {term}, current term: {}.",
+                self.term
+            );
+            return Err(SystemError::NotLeader);
         }
+
         let entry = LogEntry {
-            index: self.commit_index,
-            size: payload.len() as u32,
-            data: payload.clone(),
-        };
-        self.sync(LogEntry {
-            index: self.commit_index,
+            index: self.last_index + 1,
+            term,
             size: payload.len() as u32,
             data: payload,
-        })
-        .await?;
+        };
+        self.sync(entry.clone()).await?;
+        self.flush().await?;
         Ok(entry)
     }
 
+    /// Writes the entry at the end of the log, it must directly follow the last entry.
     pub async fn sync(&mut self, entry: LogEntry) -> Result<(), SystemError> {
-        let file = file::append(&self.log_path).await.unwrap();
-        let size = 20 + entry.data.len();
+        if entry.index != self.last_index + 1 {
+            error!(
+                "Cannot append entry at index: {}, last index: {}.",
+                entry.index, self.last_index
+            );
+            return Err(SystemError::InvalidPreviousLogIndex);
+        }
+
+        let size = LOG_ENTRY_HEADER_SIZE + entry.data.len();
         let mut bytes = Vec::with_capacity(size);
         bytes.put_u64_le(entry.index);
-        bytes.put_u64_le(self.term);
+        bytes.put_u64_le(entry.term);
         bytes.put_u32_le(entry.data.len() as u32);
         bytes.put_slice(&entry.data);
-        if file
-            .write_all_at(bytes, self.current_position)
+        if environment::storage()
+            .write_at(&self.log_path, self.current_position, bytes)
             .await
-            .0
             .is_err()
         {
             return Err(SystemError::CannotAppendToState);
@@ -208,11 +333,191 @@ impl State {
             "Appended entry at position: {}, size: {size}",
             self.current_position
         );
+        self.entries.push(EntryLocation {
+            term: entry.term,
+            position: self.current_position,
+        });
         self.current_position += size as u64;
+        self.last_index = entry.index;
+        Ok(())
+    }
+
+    /// Makes the written entries durable, they must not be acknowledged or counted toward the quorum before.
+    pub async fn flush(&self) -> Result<(), SystemError> {
+        if let Err(error) = environment::storage().sync(&self.log_path).await {
+            error!("Failed to sync the log: {}, {error}", self.log_path);
+            return Err(SystemError::CannotAppendToState);
+        }
+
         Ok(())
     }
 
-    pub fn update_last_applied_to_commit_index(&mut self) {
-        self.last_applied = self.commit_index;
+    /// Returns true, if the commit index has advanced.
+    pub fn set_commit_index(&mut self, commit_index: Index) -> bool {
+        let commit_index = commit_index.min(self.last_index);
+        if commit_index > self.commit_index {
+            self.commit_index = commit_index;
+            return true;
+        }
+
+        false
+    }
+}
+
+fn read_entry(bytes: &[u8]) -> Option<LogEntry> {
+    let index = u64::from_le_bytes(bytes.get(0..8)?.try_into().ok()?);
+    let term = u64::from_le_bytes(bytes.get(8..16)?.try_into().ok()?);
+    let size = u32::from_le_bytes(bytes.get(16..20)?.try_into().ok()?);
+    let data = bytes.get(LOG_ENTRY_HEADER_SIZE..LOG_ENTRY_HEADER_SIZE + size as usize)?;
+    Some(LogEntry {
+        index,
+        term,
+        size,
+        data: Bytes::copy_from_slice(data),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::memory::MemoryStorage;
+    use crate::environment::{EnterGuard, Environment};
+    use std::rc::Rc;
+
+    fn enter_memory_storage() -> EnterGuard {
+        Environment::system()
+            .with_storage(Rc::new(MemoryStorage::default()))
+            .enter()
+    }
+
+    async fn init_state(path: &str) -> State {
+        let mut state = State::new(0, path);
+        state.init().await.unwrap();
+        state
+    }
+
+    fn entry(index: Index, term: Term) -> LogEntry {
+        let data = Bytes::from(format!("entry {index}"));
+        LogEntry {
+            index,
+            term,
+            size: data.len() as u32,
+            data,
+        }
+    }
+
+    #[monoio::test]
+    async fn entry_should_not_be_appended_in_previous_term() {
+        let _guard = enter_memory_storage();
+        let mut state = init_state("test_state/previous_term").await;
+        state.set_term(2);
+        assert!(state.append(2, Bytes::from("first")).await.is_ok());
+        assert!(matches!(
+            state.append(1, Bytes::from("second")).await,
+            Err(SystemError::NotLeader)
+        ));
+        assert_eq!(state.last_index, 1);
+        assert_eq!(state.term_at(1), Some(2));
+        assert_eq!(state.term_at(2), None);
+    }
+
+    #[monoio::test]
+    async fn entry_should_only_directly_follow_last_entry() {
+        let _guard = enter_memory_storage();
+        let mut state = init_state("test_state/gap").await;
+        state.sync(entry(1, 1)).await.unwrap();
+        assert!(matches!(
+            state.sync(entry(3, 1)).await,
+            Err(SystemError::InvalidPreviousLogIndex)
+        ));
+        assert_eq!(state.last_index, 1);
+    }
+
+    #[monoio::test]
+    async fn conflicting_entries_should_be_replaced_and_kept_after_restart() {
+        let _guard = enter_memory_storage();
+        let path = "test_state/conflict";
+        let mut state = init_state(path).await;
+        for (index, term) in [(1, 1), (2, 1), (3, 2)] {
+            state.sync(entry(index, term)).await.unwrap();
+        }
+        state.set_commit_index(1);
+
+        state.truncate_from(2).await.unwrap();
+        assert_eq!(state.last_index, 1);
+        state.sync(entry(2, 3)).await.unwrap();
+
+        let state = init_state(path).await;
+        assert_eq!(state.last_index, 2);
+        assert_eq!(state.last_log_term(), 3);
+        assert_eq!(state.term_at(1), Some(1));
+        assert_eq!(state.term_at(2), Some(3));
+        let entries = state.load_entries_from(1, 10).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].data, entry(2, 3).data);
+    }
+
+    #[monoio::test]
+    async fn only_requested_entries_should_be_loaded() {
+        let _guard = enter_memory_storage();
+        let mut state = init_state("test_state/range").await;
+        for index in 1..=5 {
+            state.sync(entry(index, 1)).await.unwrap();
+        }
+
+        let indexes =
+            |entries: Vec<LogEntry>| entries.iter().map(|entry| entry.index).collect::<Vec<_>>();
+        let entries = state.load_entries_from(2, 2).await.unwrap();
+        assert_eq!(indexes(entries.clone()), vec![2, 3]);
+        assert_eq!(entries[1].data, entry(3, 1).data);
+        assert_eq!(
+            indexes(state.load_entries_from(4, 10).await.unwrap()),
+            vec![4, 5]
+        );
+        assert!(state.load_entries_from(6, 1).await.unwrap().is_empty());
+    }
+
+    #[monoio::test]
+    async fn committed_entries_should_not_be_truncated() {
+        let _guard = enter_memory_storage();
+        let mut state = init_state("test_state/committed").await;
+        for index in 1..=3 {
+            state.sync(entry(index, 1)).await.unwrap();
+        }
+        assert!(state.set_commit_index(2));
+
+        assert!(state.truncate_from(2).await.is_err());
+        assert_eq!(state.last_index, 3);
+        assert!(state.truncate_from(3).await.is_ok());
+        assert_eq!(state.last_index, 2);
+    }
+
+    #[monoio::test]
+    async fn commit_index_should_not_exceed_last_entry() {
+        let _guard = enter_memory_storage();
+        let mut state = init_state("test_state/commit_index").await;
+        state.sync(entry(1, 1)).await.unwrap();
+        assert!(state.set_commit_index(5));
+        assert_eq!(state.commit_index, 1);
+        assert!(!state.set_commit_index(1));
+    }
+
+    #[monoio::test]
+    async fn hard_state_should_be_loaded_after_restart() {
+        let _guard = enter_memory_storage();
+        let path = "test_state/hard_state";
+        let mut state = init_state(path).await;
+        state.sync(entry(1, 1)).await.unwrap();
+        state.sync(entry(2, 1)).await.unwrap();
+        state.set_term(4);
+        state.voted_for = Some(3);
+        state.set_commit_index(1);
+        state.save_hard_state().await.unwrap();
+
+        let state = init_state(path).await;
+        assert_eq!(state.term, 4);
+        assert_eq!(state.voted_for, Some(3));
+        assert_eq!(state.commit_index, 1);
+        assert_eq!(state.last_index, 2);
     }
 }
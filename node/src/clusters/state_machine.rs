@@ -0,0 +1,259 @@
+use bytes::BufMut;
+use futures::future::LocalBoxFuture;
+use sdk::commands::command::Command;
+use sdk::error::SystemError;
+use std::fmt::{Debug, Formatter};
+use tracing::{error, info, warn};
+
+/// The replicated state machine, which applies the committed log entries in the same order on every node.
+/// Apart from the cluster membership, the commands of the log are handled by the registered machines,
+/// so that the new replicated commands do not require any changes to the consensus.
+/// The snapshots are kept by the metadata store, if it supports them, to skip replaying the older entries on start.
+pub trait StateMachine {
+    fn name(&self) -> &'static str;
+
+    fn handles(&self, command: &Command) -> bool;
+
+    fn apply<'a>(&'a self, command: &'a Command) -> LocalBoxFuture<'a, Result<(), SystemError>>;
+
+    fn snapshot(&self) -> LocalBoxFuture<'_, Result<Vec<u8>, SystemError>>;
+
+    fn restore<'a>(&'a self, snapshot: &'a [u8]) -> LocalBoxFuture<'a, Result<(), SystemError>>;
+}
+
+#[derive(Default)]
+pub struct StateMachines {
+    machines: Vec<Box<dyn StateMachine>>,
+}
+
+impl Debug for StateMachines {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.machines.iter().map(|machine| machine.name()))
+            .finish()
+    }
+}
+
+impl StateMachines {
+    pub fn register(&mut self, machine: Box<dyn StateMachine>) {
+        info!("Registered state machine: {}.", machine.name());
+        self.machines.push(machine);
+    }
+
+    pub async fn apply(&self, command: &Command) -> Result<(), SystemError> {
+        let Some(machine) = self
+            .machines
+            .iter()
+            .find(|machine| machine.handles(command))
+        else {
+            warn!("Received an unknown log entry command: {command}",);
+            return Err(SystemError::InvalidCommand);
+        };
+
+        machine.apply(command).await
+    }
+
+    /// Returns the snapshots of all the machines, each one prefixed with the machine name and the snapshot length.
+    pub async fn snapshot(&self) -> Result<Vec<u8>, SystemError> {
+        let mut bytes = Vec::new();
+        for machine in &self.machines {
+            let name = machine.name();
+            let snapshot = machine.snapshot().await?;
+            bytes.put_u8(name.len() as u8);
+            bytes.put_slice(name.as_bytes());
+            bytes.put_u32_le(snapshot.len() as u32);
+            bytes.put_slice(&snapshot);
+        }
+        Ok(bytes)
+    }
+
+    /// Restores the machines only once the whole snapshot has been read, so that the truncated or corrupted one
+    /// is rejected without changing any of them.
+    pub async fn restore(&self, bytes: &[u8]) -> Result<(), SystemError> {
+        let mut snapshots = Vec::new();
+        let mut position = 0;
+        while position < bytes.len() {
+            let name_length = read_bytes(bytes, &mut position, 1)?[0] as usize;
+            let name = std::str::from_utf8(read_bytes(bytes, &mut position, name_length)?)
+                .map_err(|_| SystemError::InvalidCommand)?;
+            let snapshot_length =
+                u32::from_le_bytes(read_bytes(bytes, &mut position, 4)?.try_into()?) as usize;
+            let snapshot = read_bytes(bytes, &mut position, snapshot_length)?;
+            let Some(machine) = self.machines.iter().find(|machine| machine.name() == name) else {
+                error!("Cannot restore the snapshot of unknown state machine: {name}.");
+                return Err(SystemError::InvalidCommand);
+            };
+
+            snapshots.push((machine, snapshot));
+        }
+
+        for (machine, snapshot) in snapshots {
+            machine.restore(snapshot).await?;
+            info!(
+                "Restored the snapshot of state machine: {}.",
+                machine.name()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn read_bytes<'a>(
+    bytes: &'a [u8],
+    position: &mut usize,
+    length: usize,
+) -> Result<&'a [u8], SystemError> {
+    let Some(read_bytes) = bytes.get(*position..*position + length) else {
+        error!("Cannot restore the truncated snapshot at position: {position}.");
+        return Err(SystemError::InvalidCommand);
+    };
+
+    *position += length;
+    Ok(read_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::lock::Mutex;
+    use sdk::commands::create_stream::CreateStream;
+    use sdk::commands::delete_stream::DeleteStream;
+    use sdk::commands::ping::Ping;
+    use std::rc::Rc;
+
+    /// Keeps the IDs of the created streams in the order of the applied commands.
+    #[derive(Default)]
+    struct ToyMachine {
+        ids: Rc<Mutex<Vec<u64>>>,
+    }
+
+    impl StateMachine for ToyMachine {
+        fn name(&self) -> &'static str {
+            "toy"
+        }
+
+        fn handles(&self, command: &Command) -> bool {
+            matches!(command, Command::CreateStream(_) | Command::DeleteStream(_))
+        }
+
+        fn apply<'a>(
+            &'a self,
+            command: &'a Command,
+        ) -> LocalBoxFuture<'a, Result<(), SystemError>> {
+            Box::pin(async move {
+                let mut ids = self.ids.lock().await;
+                match command {
+                    Command::CreateStream(create_stream) => ids.push(create_stream.id),
+                    Command::DeleteStream(delete_stream) => {
+                        ids.retain(|id| *id != delete_stream.id)
+                    }
+                    _ => return Err(SystemError::InvalidCommand),
+                }
+                Ok(())
+            })
+        }
+
+        fn snapshot(&self) -> LocalBoxFuture<'_, Result<Vec<u8>, SystemError>> {
+            Box::pin(async move {
+                let ids = self.ids.lock().await;
+                let mut bytes = Vec::with_capacity(8 * ids.len());
+                for id in ids.iter() {
+                    bytes.put_u64_le(*id);
+                }
+                Ok(bytes)
+            })
+        }
+
+        fn restore<'a>(
+            &'a self,
+            snapshot: &'a [u8],
+        ) -> LocalBoxFuture<'a, Result<(), SystemError>> {
+            Box::pin(async move {
+                let mut ids = self.ids.lock().await;
+                ids.clear();
+                for chunk in snapshot.chunks(8) {
+                    ids.push(u64::from_le_bytes(chunk.try_into()?));
+                }
+                Ok(())
+            })
+        }
+    }
+
+    fn create_machines() -> (StateMachines, Rc<Mutex<Vec<u64>>>) {
+        let machine = ToyMachine::default();
+        let ids = machine.ids.clone();
+        let mut machines = StateMachines::default();
+        machines.register(Box::new(machine));
+        (machines, ids)
+    }
+
+    #[monoio::test]
+    async fn commands_should_be_applied_in_order() {
+        let (machines, ids) = create_machines();
+        let commands = vec![
+            CreateStream::new_command(1, None, None, vec![]),
+            CreateStream::new_command(2, None, None, vec![]),
+            DeleteStream::new_command(1),
+            CreateStream::new_command(3, None, None, vec![]),
+        ];
+        for command in &commands {
+            machines.apply(command).await.unwrap();
+        }
+
+        assert_eq!(*ids.lock().await, vec![2, 3]);
+    }
+
+    #[monoio::test]
+    async fn unknown_command_should_be_rejected() {
+        let (machines, ids) = create_machines();
+        let result = machines.apply(&Ping::new_command()).await;
+
+        assert!(matches!(result, Err(SystemError::InvalidCommand)));
+        assert!(ids.lock().await.is_empty());
+    }
+
+    #[monoio::test]
+    async fn snapshot_should_be_restored_in_another_machine() {
+        let (machines, _) = create_machines();
+        machines
+            .apply(&CreateStream::new_command(1, None, None, vec![]))
+            .await
+            .unwrap();
+        machines
+            .apply(&CreateStream::new_command(2, None, None, vec![]))
+            .await
+            .unwrap();
+        let snapshot = machines.snapshot().await.unwrap();
+
+        let (restored_machines, restored_ids) = create_machines();
+        restored_machines.restore(&snapshot).await.unwrap();
+        restored_machines
+            .apply(&CreateStream::new_command(3, None, None, vec![]))
+            .await
+            .unwrap();
+
+        assert_eq!(*restored_ids.lock().await, vec![1, 2, 3]);
+    }
+
+    #[monoio::test]
+    async fn truncated_snapshot_should_be_rejected() {
+        let (machines, _) = create_machines();
+        machines
+            .apply(&CreateStream::new_command(1, None, None, vec![]))
+            .await
+            .unwrap();
+        let snapshot = machines.snapshot().await.unwrap();
+
+        let (restored_machines, restored_ids) = create_machines();
+        restored_machines
+            .apply(&CreateStream::new_command(2, None, None, vec![]))
+            .await
+            .unwrap();
+        for length in 1..snapshot.len() {
+            let result = restored_machines.restore(&snapshot[..length]).await;
+            assert!(matches!(result, Err(SystemError::InvalidCommand)));
+        }
+
+        assert_eq!(*restored_ids.lock().await, vec![2]);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::clusters::cluster::Cluster;
+use crate::environment;
+use std::rc::Rc;
+use tracing::{error, info};
+
+pub fn subscribe(cluster: Rc<Cluster>) {
+    if !cluster.leader_rebalance_interval.is_zero() {
+        let cluster = cluster.clone();
+        environment::spawn(async move {
+            rebalance(cluster).await;
+        });
+    }
+
+    environment::spawn(async move {
+        elect(cluster).await;
+    });
+}
+
+async fn elect(cluster: Rc<Cluster>) {
+    let interval = cluster.heartbeat_interval;
+    loop {
+        environment::sleep(interval).await;
+        cluster.elect_stream_leaders().await;
+    }
+}
+
+async fn rebalance(cluster: Rc<Cluster>) {
+    let interval = cluster.leader_rebalance_interval;
+    loop {
+        environment::sleep(interval).await;
+        if !cluster.is_leader().await {
+            continue;
+        }
+
+        match cluster.rebalance_stream_leaders().await {
+            Ok(0) => {}
+            Ok(moved_leaders) => {
+                info!("Moved {moved_leaders} stream leaders back to preferred replicas.")
+            }
+            Err(error) => error!("Failed to rebalance stream leaders, {error}"),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use crate::clusters::cluster::{Cluster, ClusterNode};
+use sdk::error::SystemError;
+use tracing::{error, info, warn};
+
+impl Cluster {
+    /// Copies the committed messages of the streams stored by this node from their leaders in chunks,
+    /// before the fetcher starts tailing their logs. Each chunk is committed once written, so the interrupted
+    /// copy is resumed at the log end offset, also after the restart.
+    pub(crate) async fn bootstrap_streams(&self) {
+        let stream_ids = self
+            .streamer
+            .lock()
+            .await
+            .get_streams()
+            .iter()
+            .map(|stream| stream.stream_id)
+            .collect::<Vec<u64>>();
+        self.bootstrap_assigned_streams(&stream_ids).await;
+    }
+
+    /// Bootstraps the stream once it has been assigned to this node by the applied entry, e.g. after its storage
+    /// has been wiped, so that its log isn't rebuilt by the fetcher alone. The copy ends at once, if the log end
+    /// offset has already reached the leader's high watermark.
+    pub(crate) fn trigger_bootstrap(&self, stream_id: u64) {
+        if self.bootstrap_trigger.unbounded_send(stream_id).is_err() {
+            error!("Failed to trigger the bootstrap of stream with ID: {stream_id}.");
+        }
+    }
+
+    /// Bootstraps the given streams, which are stored by this node, but led by the other ones.
+    pub(crate) async fn bootstrap_assigned_streams(&self, stream_ids: &[u64]) {
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        let streams = self
+            .streamer
+            .lock()
+            .await
+            .get_streams()
+            .iter()
+            .filter(|stream| {
+                stream_ids.contains(&stream.stream_id)
+                    && stream.leader_id != self_node_id
+                    && stream.is_replica(self_node_id)
+            })
+            .map(|stream| (stream.stream_id, stream.leader_id))
+            .collect::<Vec<(u64, u64)>>();
+        for (stream_id, leader_id) in streams {
+            let Some(leader) = self.get_node(leader_id).await else {
+                continue;
+            };
+
+            if leader.node.is_self_node() || !leader.node.is_connected().await {
+                info!("Leader ID: {leader_id} of stream with ID: {stream_id} is not available, skipping its bootstrap.");
+                continue;
+            }
+
+            if let Err(error) = self.bootstrap_stream(&leader, stream_id).await {
+                warn!(
+                    "Failed to bootstrap stream with ID: {stream_id} from leader ID: {leader_id}, it will be fetched in the background, {error}"
+                );
+            }
+        }
+    }
+
+    async fn bootstrap_stream(
+        &self,
+        leader: &ClusterNode,
+        stream_id: u64,
+    ) -> Result<(), SystemError> {
+        let leader_id = leader.node.id;
+        // The messages above the high watermark might not be stored by the leader, so the log is truncated
+        // to match the leader's one first, as the chunks are appended at its end.
+        let Some((leader_epoch, reconciled_leader_epoch)) = self
+            .streamer
+            .lock()
+            .await
+            .get_stream(stream_id)
+            .map(|stream| (stream.leader_epoch, stream.reconciled_leader_epoch))
+        else {
+            return Ok(());
+        };
+
+        if reconciled_leader_epoch != Some(leader_epoch) {
+            self.truncate_to_leader(leader, stream_id, leader_epoch)
+                .await?;
+        }
+
+        let mut copied_messages = 0;
+        loop {
+            let Some(offset) = self
+                .streamer
+                .lock()
+                .await
+                .get_stream(stream_id)
+                .map(|stream| stream.get_log_end_offset())
+            else {
+                return Ok(());
+            };
+
+            let stream_chunk = leader
+                .node
+                .get_stream_chunk(stream_id, offset, self.bootstrap_chunk_size)
+                .await?;
+            let high_watermark = stream_chunk.high_watermark;
+            let messages_count = stream_chunk.messages.len() as u64;
+            if messages_count == 0 {
+                break;
+            }
+
+            let mut streamer = self.streamer.lock().await;
+            let Some(stream) = streamer.get_stream_mut(stream_id) else {
+                return Ok(());
+            };
+
+            if stream.get_log_end_offset() != offset {
+                warn!("Stream with ID: {stream_id} has changed while bootstrapping it from offset: {offset}, discarding the chunk.");
+                continue;
+            }
+
+            let result = stream.write_fetched_messages(stream_chunk).await;
+            let log_end_offset = stream.get_log_end_offset();
+            drop(streamer);
+            self.fence_on_error(result).await?;
+            copied_messages += messages_count;
+            info!(
+                "Bootstrapped {messages_count} messages of stream with ID: {stream_id} from leader ID: {leader_id}, log end offset: {log_end_offset}, leader high watermark: {high_watermark}."
+            );
+            if log_end_offset >= high_watermark {
+                break;
+            }
+        }
+
+        info!("Completed bootstrap of stream with ID: {stream_id} from leader ID: {leader_id}, copied {copied_messages} messages.");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{as_command, run_scenario, send, Simulation, NODES_COUNT};
+    use crate::types::NodeId;
+    use linearizability::history::Operation;
+    use std::time::Duration;
+
+    const MESSAGES_COUNT: u64 = 100;
+
+    async fn log_end_offset(simulation: &Simulation, node_id: NodeId) -> Option<u64> {
+        simulation
+            .cluster(node_id)?
+            .streamer
+            .lock()
+            .await
+            .get_stream(1)
+            .map(|stream| stream.get_log_end_offset())
+    }
+
+    #[test]
+    fn wiped_replica_should_be_bootstrapped_in_resumable_chunks_and_rejoin_isr() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                let appended = simulation.spawn_client(async move {
+                    send(&as_command(&Operation::CreateStream { stream_id: 1 })).await?;
+                    send(&as_command(&Operation::AppendMessages {
+                        stream_id: 1,
+                        ids: (1..=MESSAGES_COUNT).collect(),
+                    }))
+                    .await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(appended.take().is_some_and(|result| result.is_some()));
+
+                let metadata = simulation.cluster(leader_id).unwrap().get_metadata().await;
+                let stream_leader_id = metadata.streams[&1].leader_id;
+                let replica_id = (1..=NODES_COUNT)
+                    .find(|node_id| *node_id != stream_leader_id && *node_id != leader_id)
+                    .unwrap();
+                simulation.crash_node(replica_id);
+                simulation.storage(replica_id).wipe();
+                simulation.start_node(replica_id);
+
+                // The copy is interrupted once the first chunks have been written.
+                let mut copied_offset = 0;
+                for _ in 0..10_000 {
+                    simulation.run_for(Duration::from_millis(1)).await?;
+                    copied_offset = log_end_offset(simulation, replica_id).await.unwrap_or(0);
+                    if copied_offset > 0 {
+                        break;
+                    }
+                }
+                assert!(copied_offset > 0 && copied_offset < MESSAGES_COUNT);
+                simulation.crash_node(replica_id);
+                simulation.start_node(replica_id);
+                let resumed_offset = loop {
+                    if let Some(offset) = log_end_offset(simulation, replica_id).await {
+                        break offset;
+                    }
+                    simulation.run_for(Duration::from_millis(1)).await?;
+                };
+                assert!(resumed_offset >= copied_offset);
+
+                simulation.run_for(Duration::from_secs(10)).await?;
+                assert_eq!(
+                    log_end_offset(simulation, replica_id).await,
+                    Some(MESSAGES_COUNT)
+                );
+                let leader_id = simulation.wait_for_leader().await?;
+                let metadata = simulation.cluster(leader_id).unwrap().get_metadata().await;
+                assert!(metadata.streams[&1].isr.contains(&replica_id));
+                Ok(())
+            })
+        });
+    }
+}
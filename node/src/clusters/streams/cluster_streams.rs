@@ -1,20 +1,22 @@
 use crate::clusters::cluster::Cluster;
-use crate::clusters::nodes::node::Node;
 use crate::connection::handler::ConnectionHandler;
 use crate::types::{NodeId, Term};
+use sdk::commands::command::Command;
 use sdk::commands::create_stream::CreateStream;
 use sdk::commands::delete_stream::DeleteStream;
 use sdk::error::SystemError;
-use sdk::models::stream::Stream;
 use tracing::{error, info};
 
+pub(crate) const DEFAULT_REPLICATION_FACTOR: u8 = 3;
+
 impl Cluster {
+    /// Validates the stream settings and assigns its replicas unless they're given,
+    /// returns the command to be appended to the replicated state.
     pub async fn create_stream(
         &self,
         term: Option<Term>,
-        stream_id: u64,
-        replication_factor: u8,
-    ) -> Result<(), SystemError> {
+        command: &CreateStream,
+    ) -> Result<CreateStream, SystemError> {
         if let Some(term) = term {
             let current_term = self.election_manager.get_current_term().await;
             if current_term != term {
@@ -23,60 +25,84 @@ impl Cluster {
                 );
                 return Err(SystemError::InvalidTerm(term));
             }
+
+            // The creations appended by the previous leaders are applied before the ID is checked.
+            self.verify_term_start_committed(term).await?;
         }
 
-        let nodes_count = self.nodes.len() as u8;
-        if replication_factor > nodes_count {
+        if self.streamer.lock().await.get_stream(command.id).is_some() {
             error!(
-                "Failed to create stream, replication factor: {replication_factor} is greater than number of nodes: {nodes_count}.",
+                "Failed to create stream, stream with ID: {} already exists.",
+                command.id
             );
-            return Err(SystemError::InvalidReplicationFactor(replication_factor));
+            return Err(SystemError::StreamAlreadyExists(command.id));
         }
 
-        self.streamer
-            .lock()
-            .await
-            .create_stream(stream_id, replication_factor)
-            .await
-    }
+        let replication_factor = match (command.replication_factor, command.replicas.len()) {
+            (Some(replication_factor), _) => replication_factor,
+            (None, 0) => DEFAULT_REPLICATION_FACTOR,
+            (None, replicas_count) => replicas_count as u8,
+        };
+        let min_in_sync_replicas = command
+            .min_in_sync_replicas
+            .unwrap_or_else(|| default_min_in_sync_replicas(replication_factor));
+        let voters = self.get_voter_ids().await;
+        validate_replication(replication_factor, min_in_sync_replicas, voters.len())?;
 
-    pub async fn delete_stream(
-        &self,
-        term: Option<Term>,
-        stream_id: u64,
-    ) -> Result<(), SystemError> {
-        if let Some(term) = term {
-            let current_term = self.election_manager.get_current_term().await;
-            if current_term != term {
+        let replicas = if command.replicas.is_empty() {
+            let streamer = self.streamer.lock().await;
+            let assignments = streamer
+                .get_streams()
+                .iter()
+                .map(|stream| stream.replicas.clone())
+                .collect::<Vec<Vec<NodeId>>>();
+            assign_replicas(&voters, &assignments, replication_factor)
+        } else {
+            let mut unique_replicas = command.replicas.clone();
+            unique_replicas.sort();
+            unique_replicas.dedup();
+            if command.replicas.len() != replication_factor as usize
+                || unique_replicas.len() != command.replicas.len()
+                || unique_replicas
+                    .iter()
+                    .any(|node_id| !voters.contains(node_id))
+            {
                 error!(
-                    "Failed to delete stream, term: {term} is not equal to current term: {current_term}.",
+                    "Failed to create stream, replicas: {:?} must be {replication_factor} distinct voters: {voters:?}.",
+                    command.replicas
                 );
-                return Err(SystemError::InvalidTerm(term));
+                return Err(SystemError::InvalidReplicaAssignment(
+                    command.replicas.clone(),
+                ));
             }
-        }
+            command.replicas.clone()
+        };
 
-        self.streamer.lock().await.delete_stream(stream_id).await;
-        Ok(())
+        info!(
+            "Assigned replicas: {replicas:?} to stream with ID: {}.",
+            command.id
+        );
+        Ok(CreateStream::new(
+            command.id,
+            Some(replication_factor),
+            Some(min_in_sync_replicas),
+            replicas,
+        ))
     }
 
     pub async fn sync_created_stream(
         &self,
         handler: &mut ConnectionHandler,
         term: Term,
-        stream_id: u64,
-        replication_factor: Option<u8>,
+        command: CreateStream,
     ) -> Result<(), SystemError> {
+        let stream_id = command.id;
         info!("Syncing created stream with ID: {stream_id} to quorum of nodes.");
         if let Err(error) = self
-            .sync_state(
-                handler,
-                term,
-                CreateStream::new_command(stream_id, replication_factor),
-            )
+            .sync_state(handler, term, Command::CreateStream(command))
             .await
         {
             error!("Failed to sync created stream with ID: {stream_id}, {error}",);
-            self.streamer.lock().await.delete_stream(stream_id).await;
             return Err(SystemError::CannotSyncCreatedStream);
         }
         info!("Successfully synced created stream with ID: {stream_id} to quorum of nodes.");
@@ -101,112 +127,228 @@ impl Cluster {
         Ok(())
     }
 
-    pub async fn sync_state_and_streams_from_other_nodes(
-        &self,
-        available_leaders: &[NodeId],
-    ) -> Result<(), SystemError> {
-        let mut completed = true;
-        let self_state = self.get_node_state().await?;
-        let last_applied = self_state.last_applied;
-        for node in self.nodes.values() {
-            if node.node.is_self_node() {
-                continue;
-            }
+    /// The log is caught up by the leader, while the messages of the streams are copied from their leaders.
+    pub async fn sync_streams_from_other_nodes(&self) {
+        self.bootstrap_streams().await;
+        let self_node = self.get_self_node().unwrap();
+        self_node.node.complete_initial_sync().await;
+        info!("Successfully synced streams from other nodes.");
+    }
+}
 
-            let node_id = node.node.id;
-            if !available_leaders.contains(&node_id) {
-                continue;
-            }
+/// The majority of the replicas must store the messages before they're acknowledged, so that they survive
+/// the failure of the minority, including the leader.
+pub(crate) fn default_min_in_sync_replicas(replication_factor: u8) -> u8 {
+    replication_factor / 2 + 1
+}
 
-            if let Err(error) = self.sync_state_from_leader(last_applied, &node.node).await {
-                error!(
-                    "Failed to sync state from cluster node with ID: {}, {error}",
-                    node.node.id
-                );
-                completed = false;
-                continue;
-            }
+/// The stream must be stored by at least one and at most all the voters, and the min in-sync replicas
+/// must be a subset of its replicas.
+fn validate_replication(
+    replication_factor: u8,
+    min_in_sync_replicas: u8,
+    voters_count: usize,
+) -> Result<(), SystemError> {
+    if replication_factor == 0 || replication_factor as usize > voters_count {
+        error!(
+            "Failed to create stream, replication factor: {replication_factor} must be between 1 and number of voters: {voters_count}.",
+        );
+        return Err(SystemError::InvalidReplicationFactor(replication_factor));
+    }
 
-            let streams = node.node.get_streams().await;
-            if streams.is_err() {
-                let error = streams.unwrap_err();
-                error!(
-                    "Failed to get streams from cluster node with ID: {}, {error}",
-                    node.node.id
-                );
-                completed = false;
-                continue;
-            }
+    if min_in_sync_replicas == 0 || min_in_sync_replicas > replication_factor {
+        error!(
+            "Failed to create stream, min in-sync replicas: {min_in_sync_replicas} must be between 1 and replication factor: {replication_factor}.",
+        );
+        return Err(SystemError::InvalidMinInSyncReplicas(min_in_sync_replicas));
+    }
 
-            let streams = streams.unwrap();
-            if streams.is_empty() {
-                completed = true;
-                continue;
-            }
+    Ok(())
+}
 
-            for stream in streams {
-                if let Err(error) = self.sync_stream_from_leader(&stream, &node.node).await {
-                    error!(
-                        "Failed to sync stream: {stream} from cluster node with ID: {node_id}, {error}",
-                    );
-                    completed = false;
-                }
-            }
+/// Picks the preferred leader with the fewest preferred leaderships, followed by the voters
+/// storing the fewest replicas, the ties are broken by the lowest node ID.
+fn assign_replicas(
+    voters: &[NodeId],
+    assignments: &[Vec<NodeId>],
+    replication_factor: u8,
+) -> Vec<NodeId> {
+    let replicas_count = |node_id: &NodeId| {
+        assignments
+            .iter()
+            .filter(|replicas| replicas.contains(node_id))
+            .count()
+    };
+    let preferred_count = |node_id: &NodeId| {
+        assignments
+            .iter()
+            .filter(|replicas| replicas.first() == Some(node_id))
+            .count()
+    };
+    let Some(preferred_leader) = voters
+        .iter()
+        .min_by_key(|node_id| (preferred_count(node_id), replicas_count(node_id), **node_id))
+        .copied()
+    else {
+        return Vec::new();
+    };
+
+    let mut followers = voters
+        .iter()
+        .filter(|node_id| **node_id != preferred_leader)
+        .copied()
+        .collect::<Vec<NodeId>>();
+    followers.sort_by_key(|node_id| (replicas_count(node_id), *node_id));
+    let mut replicas = vec![preferred_leader];
+    replicas.extend(
+        followers
+            .into_iter()
+            .take((replication_factor as usize).saturating_sub(1)),
+    );
+    replicas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{run_scenario, send_to, NODES_COUNT};
+    use std::time::Duration;
+
+    #[test]
+    fn replicas_should_be_spread_evenly_across_voters() {
+        let voters = vec![1, 2, 3];
+        let mut assignments = Vec::new();
+        for _ in 0..6 {
+            let replicas = assign_replicas(&voters, &assignments, 2);
+            assignments.push(replicas);
         }
 
-        if !completed {
-            return Err(SystemError::CannotSyncStreams);
+        for node_id in &voters {
+            let preferred = assignments
+                .iter()
+                .filter(|replicas| replicas[0] == *node_id)
+                .count();
+            let stored = assignments
+                .iter()
+                .filter(|replicas| replicas.contains(node_id))
+                .count();
+            assert_eq!(preferred, 2);
+            assert_eq!(stored, 4);
         }
+        assert!(assignments
+            .iter()
+            .all(|replicas| replicas[0] != replicas[1]));
+    }
 
-        let self_node = self.get_self_node().unwrap();
-        self_node.node.complete_initial_sync().await;
-        info!("Successfully synced streams from other nodes.");
-        Ok(())
+    #[test]
+    fn replication_settings_should_be_validated() {
+        assert!(validate_replication(3, 2, 3).is_ok());
+        assert!(validate_replication(1, 1, 3).is_ok());
+        assert!(matches!(
+            validate_replication(0, 1, 3),
+            Err(SystemError::InvalidReplicationFactor(0))
+        ));
+        assert!(matches!(
+            validate_replication(0, 0, 3),
+            Err(SystemError::InvalidReplicationFactor(0))
+        ));
+        assert!(matches!(
+            validate_replication(4, 2, 3),
+            Err(SystemError::InvalidReplicationFactor(4))
+        ));
+        assert!(matches!(
+            validate_replication(3, 0, 3),
+            Err(SystemError::InvalidMinInSyncReplicas(0))
+        ));
+        assert!(matches!(
+            validate_replication(2, 3, 3),
+            Err(SystemError::InvalidMinInSyncReplicas(3))
+        ));
     }
 
-    async fn sync_stream_from_leader(
-        &self,
-        stream: &Stream,
-        node: &Node,
-    ) -> Result<(), SystemError> {
-        let node_id = node.id;
-        info!("Syncing stream: {stream} from cluster node with ID: {node_id}");
-        let mut streamer = self.streamer.lock().await;
-        streamer.create_stream(stream.id, 3).await?;
-        let self_stream = streamer.get_stream_mut(stream.id).unwrap();
-        if self_stream.high_watermark == stream.high_watermark {
-            info!("Stream: {stream} is already in sync with cluster node with ID: {node_id}, high watermark: {}", stream.high_watermark);
-            return Ok(());
-        }
+    #[test]
+    fn duplicate_stream_should_be_rejected_and_keep_its_assignment() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                let created = simulation.spawn_client(async move {
+                    let created = send_to(
+                        leader_id,
+                        &CreateStream::new_command(1, Some(2), Some(1), vec![1, 2]),
+                    )
+                    .await;
+                    let duplicated = send_to(
+                        leader_id,
+                        &CreateStream::new_command(1, Some(2), Some(2), vec![3, 2]),
+                    )
+                    .await;
+                    (created, duplicated)
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                let (created, duplicated) = created.take().unwrap();
+                assert!(created.is_ok());
+                assert!(matches!(
+                    duplicated,
+                    Err(SystemError::InvalidResponse(status, _))
+                        if status == SystemError::StreamAlreadyExists(1).as_code()
+                ));
 
-        if self_stream.high_watermark > stream.high_watermark {
-            info!(
-                        "Stream: {stream} is ahead of cluster node with ID: {node_id} and will be truncated. Current high watermark: {} is greater than cluster node high watermark: {}",
-                        self_stream.high_watermark, stream.high_watermark
-                     );
-            self_stream.truncate(stream.high_watermark).await?;
-        }
+                for node_id in 1..=NODES_COUNT {
+                    let streamer = simulation.cluster(node_id).unwrap().streamer.clone();
+                    let streamer = streamer.lock().await;
+                    let stream = streamer.get_stream(1).unwrap();
+                    assert_eq!(stream.replicas, vec![1, 2]);
+                    assert_eq!(stream.min_in_sync_replicas, 1);
+                }
+                Ok(())
+            })
+        });
+    }
 
-        let offset = self_stream.high_watermark + 1;
-        let count = stream.high_watermark - self_stream.high_watermark;
-        info!(
-                    "Polling messages for stream: {stream} from cluster node with ID: {node_id}, offset: {offset}, count: {count}..."
-                );
-        let messages = node.poll_messages(stream.id, offset, count).await;
-        if messages.is_err() {
-            let error = messages.unwrap_err();
-            error!(
-                        "Failed to poll messages for stream: {stream} from cluster node with ID: {node_id}, {error}",
-                    );
-            return Err(error);
-        }
-        let messages = messages.unwrap();
-        info!(
-                    "Successfully polled {} messages for stream: {stream} from cluster node with ID: {node_id}", messages.len()
-                );
-        self_stream.commit_messages(messages).await?;
-        self_stream.set_offset(stream.high_watermark);
-        self_stream.set_high_watermark(stream.high_watermark).await;
-        Ok(())
+    #[test]
+    fn concurrent_duplicate_streams_should_be_created_once() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                let creations = [vec![1, 2], vec![3, 2]].map(|replicas| {
+                    simulation.spawn_client(async move {
+                        let command = CreateStream::new_command(1, Some(2), Some(1), replicas);
+                        send_to(leader_id, &command).await
+                    })
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                let [first, second] = creations.map(|created| created.take().unwrap());
+                assert!(first.is_ok() != second.is_ok());
+                let rejected = if first.is_ok() { second } else { first };
+                assert!(matches!(
+                    rejected,
+                    Err(SystemError::InvalidResponse(status, _))
+                        if status == SystemError::StreamAlreadyExists(1).as_code()
+                ));
+
+                let leader_streamer = simulation.cluster(leader_id).unwrap().streamer.clone();
+                let replicas = leader_streamer
+                    .lock()
+                    .await
+                    .get_stream(1)
+                    .unwrap()
+                    .replicas
+                    .clone();
+                for node_id in 1..=NODES_COUNT {
+                    let streamer = simulation.cluster(node_id).unwrap().streamer.clone();
+                    let streamer = streamer.lock().await;
+                    assert_eq!(streamer.get_stream(1).unwrap().replicas, replicas);
+                }
+                Ok(())
+            })
+        });
+    }
+
+    #[test]
+    fn min_in_sync_replicas_should_default_to_majority_of_replicas() {
+        assert_eq!(default_min_in_sync_replicas(1), 1);
+        assert_eq!(default_min_in_sync_replicas(2), 2);
+        assert_eq!(default_min_in_sync_replicas(3), 2);
+        assert_eq!(default_min_in_sync_replicas(5), 3);
     }
 }
@@ -0,0 +1,75 @@
+use crate::clusters::cluster::Cluster;
+use crate::environment;
+use tracing::{error, info};
+
+impl Cluster {
+    /// Shrinks the ISR of every stream by the replicas, which haven't caught up with the leader within the max lag,
+    /// and expands it by the ones which have. The changes are applied once they're committed to the replicated state.
+    pub(crate) async fn update_in_sync_replicas(&self) {
+        let term = self.election_manager.get_current_term().await;
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        let voters = self.get_voter_ids().await;
+        let now = environment::now();
+        let mut changes = Vec::new();
+        {
+            let mut streamer = self.streamer.lock().await;
+            for stream in streamer.get_streams_mut() {
+                if stream.leader_id != self_node_id {
+                    continue;
+                }
+
+                let replicas = if stream.replicas.is_empty() {
+                    voters.clone()
+                } else {
+                    stream.replicas.clone()
+                };
+                let isr = if stream.replicas.is_empty() && stream.isr.is_empty() {
+                    voters.clone()
+                } else {
+                    stream.isr.clone()
+                };
+                // The followers of a new leader get the max lag to start fetching from it.
+                for node_id in isr {
+                    if node_id != self_node_id {
+                        stream.init_replica(node_id, now);
+                    }
+                }
+
+                let updated_isr = replicas
+                    .iter()
+                    .filter(|node_id| {
+                        **node_id == self_node_id
+                            || stream.replica_states.get(node_id).is_some_and(|replica| {
+                                replica.is_in_sync(now, self.max_replica_lag)
+                            })
+                    })
+                    .copied()
+                    .collect::<Vec<u64>>();
+                if updated_isr != stream.isr {
+                    changes.push((
+                        stream.stream_id,
+                        stream.leader_epoch,
+                        stream.isr.clone(),
+                        updated_isr,
+                    ));
+                }
+            }
+        }
+
+        for (stream_id, leader_epoch, isr, updated_isr) in changes {
+            info!("Updating ISR for stream with ID: {stream_id}: {isr:?} -> {updated_isr:?}...");
+            if let Err(error) = self
+                .propose_isr(term, stream_id, leader_epoch, updated_isr)
+                .await
+            {
+                error!("Failed to update ISR for stream with ID: {stream_id}, {error}");
+                continue;
+            }
+
+            let result = self.advance_high_watermark(stream_id).await;
+            if let Err(error) = self.fence_on_error(result).await {
+                error!("Failed to advance high watermark for stream with ID: {stream_id}, {error}");
+            }
+        }
+    }
+}
@@ -0,0 +1,576 @@
+use crate::clusters::cluster::Cluster;
+use crate::clusters::streams::replication::COMMIT_CHECK_INTERVAL;
+use crate::environment;
+use crate::types::{NodeId, Term};
+use sdk::commands::update_isr::UpdateIsr;
+use sdk::commands::update_stream_leader::UpdateStreamLeader;
+use sdk::error::SystemError;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use tracing::{error, info, warn};
+
+/// The streams led by other nodes than their preferred replicas, the first ones in the assignment.
+#[derive(Debug, Default)]
+pub struct LeaderImbalance {
+    pub streams: usize,
+    pub imbalanced_streams: usize,
+    pub led_streams: usize,
+    pub preferred_streams: usize,
+    pub lost_preferred_streams: usize,
+}
+
+impl LeaderImbalance {
+    /// The ratio of the streams preferring this node, which are led by other nodes.
+    pub fn ratio(&self) -> f64 {
+        if self.preferred_streams == 0 {
+            return 0.0;
+        }
+
+        self.lost_preferred_streams as f64 / self.preferred_streams as f64
+    }
+}
+
+impl Display for LeaderImbalance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "led: {}, preferred: {}, lost preferred: {}, imbalance ratio: {:.2}, imbalanced in cluster: {}/{}",
+            self.led_streams,
+            self.preferred_streams,
+            self.lost_preferred_streams,
+            self.ratio(),
+            self.imbalanced_streams,
+            self.streams
+        )
+    }
+}
+
+impl Cluster {
+    pub(crate) async fn is_stream_leader(&self, stream_id: u64) -> bool {
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        self.streamer
+            .lock()
+            .await
+            .get_stream(stream_id)
+            .is_some_and(|stream| stream.leader_id == self_node_id)
+    }
+
+    pub async fn verify_is_stream_leader(&self, stream_id: u64) -> Result<(), SystemError> {
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        let streamer = self.streamer.lock().await;
+        let Some(stream) = streamer.get_stream(stream_id) else {
+            return Err(SystemError::InvalidStreamId);
+        };
+
+        if stream.leader_id != self_node_id {
+            error!(
+                "This node is not a leader of stream with ID: {stream_id}, leader ID: {}.",
+                stream.leader_id
+            );
+            return Err(SystemError::NotStreamLeader(stream.leader_id));
+        }
+
+        Ok(())
+    }
+
+    /// The stream leaders are elected by the metadata leader, so the linearizable read must be confirmed by it.
+    /// The new stream leader might not have learned yet, which of its messages have been committed by the previous one,
+    /// so the read waits until all the messages stored at its start are below the high watermark.
+    pub async fn confirm_stream_leadership(&self, stream_id: u64) -> Result<(), SystemError> {
+        let Some(log_end_offset) = self
+            .streamer
+            .lock()
+            .await
+            .get_stream(stream_id)
+            .map(|stream| stream.get_log_end_offset())
+        else {
+            return Err(SystemError::InvalidStreamId);
+        };
+
+        self.confirm_stream_leader(stream_id).await?;
+        if !self
+            .wait_for_high_watermark(stream_id, log_end_offset)
+            .await
+        {
+            error!("Stream with ID: {stream_id} hasn't committed the messages up to offset: {log_end_offset}, leadership is not confirmed.");
+            return Err(SystemError::LeadershipNotConfirmed);
+        }
+
+        Ok(())
+    }
+
+    async fn confirm_stream_leader(&self, stream_id: u64) -> Result<(), SystemError> {
+        if self.is_leader().await {
+            self.confirm_leadership().await?;
+            return self.verify_is_stream_leader(stream_id).await;
+        }
+
+        let Some(leader_id) = self.election_manager.get_leader_id().await else {
+            return Err(SystemError::LeaderNotElected);
+        };
+
+        let Some(leader) = self.get_node(leader_id).await else {
+            return Err(SystemError::InvalidNode(leader_id));
+        };
+
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        let metadata = leader.node.get_metadata().await?;
+        match metadata.streams.get(&stream_id) {
+            Some(stream) if stream.leader_id == self_node_id => {
+                self.verify_is_stream_leader(stream_id).await
+            }
+            Some(stream) => Err(SystemError::NotStreamLeader(stream.leader_id)),
+            None => Err(SystemError::InvalidStreamId),
+        }
+    }
+
+    /// Elects the new leaders of the streams led by the unavailable nodes among their in-sync replicas,
+    /// the replica leading the fewest streams is preferred.
+    pub(crate) async fn elect_stream_leaders(&self) {
+        if !self.is_leader().await {
+            return;
+        }
+
+        let term = self.election_manager.get_current_term().await;
+        let available_nodes = self.get_available_node_ids().await;
+
+        let mut elections = Vec::new();
+        {
+            let streamer = self.streamer.lock().await;
+            let mut led_streams = HashMap::<NodeId, usize>::new();
+            for stream in streamer.get_streams() {
+                *led_streams.entry(stream.leader_id).or_default() += 1;
+            }
+
+            for stream in streamer.get_streams() {
+                if stream.replicas.is_empty() || available_nodes.contains(&stream.leader_id) {
+                    continue;
+                }
+
+                let Some(leader_id) = stream
+                    .replicas
+                    .iter()
+                    .filter(|node_id| {
+                        stream.isr.contains(node_id) && available_nodes.contains(node_id)
+                    })
+                    .min_by_key(|node_id| led_streams.get(node_id).copied().unwrap_or_default())
+                    .copied()
+                else {
+                    warn!(
+                        "Stream with ID: {} has no available in-sync replicas to replace leader ID: {}.",
+                        stream.stream_id, stream.leader_id
+                    );
+                    continue;
+                };
+
+                *led_streams.entry(leader_id).or_default() += 1;
+                elections.push((stream.stream_id, stream.leader_id, leader_id));
+            }
+        }
+
+        for (stream_id, previous_leader_id, leader_id) in elections {
+            info!("Electing leader for stream with ID: {stream_id}: {previous_leader_id} -> {leader_id}...");
+            if let Err(error) = self.move_stream_leader(term, stream_id, leader_id).await {
+                error!("Failed to elect leader for stream with ID: {stream_id}, {error}");
+            }
+        }
+    }
+
+    /// Moves the leadership of the streams back to their preferred replicas, which are available and in sync,
+    /// returns the number of the moved leaders.
+    pub async fn rebalance_stream_leaders(&self) -> Result<usize, SystemError> {
+        if !self.is_leader().await {
+            return Err(SystemError::NotLeader);
+        }
+
+        let term = self.election_manager.get_current_term().await;
+        let available_nodes = self.get_available_node_ids().await;
+
+        let moves = self
+            .streamer
+            .lock()
+            .await
+            .get_streams()
+            .iter()
+            .filter_map(|stream| {
+                let preferred_leader_id = *stream.replicas.first()?;
+                if preferred_leader_id == stream.leader_id
+                    || !stream.isr.contains(&preferred_leader_id)
+                    || !available_nodes.contains(&preferred_leader_id)
+                {
+                    return None;
+                }
+
+                Some((stream.stream_id, stream.leader_id, preferred_leader_id))
+            })
+            .collect::<Vec<(u64, NodeId, NodeId)>>();
+
+        let mut moved_leaders = 0;
+        for (stream_id, previous_leader_id, leader_id) in moves {
+            info!("Moving leader for stream with ID: {stream_id} back to preferred replica: {previous_leader_id} -> {leader_id}...");
+            if let Err(error) = self.move_stream_leader(term, stream_id, leader_id).await {
+                error!("Failed to move leader for stream with ID: {stream_id}, {error}");
+                continue;
+            }
+            moved_leaders += 1;
+        }
+        Ok(moved_leaders)
+    }
+
+    pub async fn get_leader_imbalance(&self) -> LeaderImbalance {
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        let mut imbalance = LeaderImbalance::default();
+        for stream in self.streamer.lock().await.get_streams() {
+            let Some(preferred_leader_id) = stream.replicas.first() else {
+                continue;
+            };
+
+            imbalance.streams += 1;
+            if stream.leader_id != *preferred_leader_id {
+                imbalance.imbalanced_streams += 1;
+            }
+            if stream.leader_id == self_node_id {
+                imbalance.led_streams += 1;
+            }
+            if *preferred_leader_id == self_node_id {
+                imbalance.preferred_streams += 1;
+                if stream.leader_id != self_node_id {
+                    imbalance.lost_preferred_streams += 1;
+                }
+            }
+        }
+        imbalance
+    }
+
+    /// The fenced nodes and the ones past the hard disk limit are connected, but can't lead the streams,
+    /// as they reject the writes.
+    async fn get_available_node_ids(&self) -> Vec<NodeId> {
+        let mut available_nodes = Vec::new();
+        for node in self.get_nodes().await {
+            if node.node.is_connected().await
+                && !node.node.is_fenced().await
+                && !node.node.is_disk_full().await
+            {
+                available_nodes.push(node.node.id);
+            }
+        }
+        available_nodes
+    }
+
+    /// Each leader change starts the next leader epoch of the stream.
+    async fn move_stream_leader(
+        &self,
+        term: Term,
+        stream_id: u64,
+        leader_id: NodeId,
+    ) -> Result<(), SystemError> {
+        let Some(leader_epoch) = self
+            .streamer
+            .lock()
+            .await
+            .get_stream(stream_id)
+            .map(|stream| stream.leader_epoch + 1)
+        else {
+            return Err(SystemError::InvalidStreamId);
+        };
+
+        self.propose_state(
+            term,
+            UpdateStreamLeader::new_command(stream_id, leader_id, leader_epoch),
+        )
+        .await
+    }
+
+    /// Sends the ISR of the stream led by this node to the metadata leader, which appends it to the replicated state.
+    pub(crate) async fn propose_isr(
+        &self,
+        term: Term,
+        stream_id: u64,
+        leader_epoch: u64,
+        isr: Vec<NodeId>,
+    ) -> Result<(), SystemError> {
+        if self.is_leader().await {
+            return self
+                .propose_state(term, UpdateIsr::new_command(stream_id, leader_epoch, isr))
+                .await;
+        }
+
+        let Some(leader_id) = self.election_manager.get_leader_id().await else {
+            return Err(SystemError::LeaderNotElected);
+        };
+
+        let Some(leader) = self.get_node(leader_id).await else {
+            return Err(SystemError::InvalidNode(leader_id));
+        };
+
+        leader
+            .node
+            .update_isr(stream_id, leader_epoch, isr.clone())
+            .await?;
+        // The ISR is committed, but it's used by this node only once the entry is applied from its own log.
+        if !self.wait_for_isr(stream_id, &isr).await {
+            return Err(SystemError::CannotSyncState);
+        }
+
+        Ok(())
+    }
+
+    async fn wait_for_isr(&self, stream_id: u64, isr: &[NodeId]) -> bool {
+        let now = environment::now();
+        loop {
+            let applied = self
+                .streamer
+                .lock()
+                .await
+                .get_stream(stream_id)
+                .map(|stream| stream.isr == isr);
+            match applied {
+                Some(true) => return true,
+                None => return false,
+                Some(false) if environment::elapsed(now) >= self.commit_timeout => return false,
+                Some(false) => environment::sleep(COMMIT_CHECK_INTERVAL).await,
+            }
+        }
+    }
+
+    /// Handles the ISR change of the stream leader on the metadata leader, the ISR measured by the leader
+    /// of the previous epoch is rejected, even if the same node leads the stream again.
+    pub async fn update_isr(
+        &self,
+        node_id: NodeId,
+        stream_id: u64,
+        leader_epoch: u64,
+        isr: Vec<NodeId>,
+    ) -> Result<(), SystemError> {
+        let leader = self
+            .streamer
+            .lock()
+            .await
+            .get_stream(stream_id)
+            .map(|stream| (stream.leader_id, stream.leader_epoch));
+        match leader {
+            Some((leader_id, _)) if leader_id != node_id => {
+                warn!("Rejected ISR: {isr:?} for stream with ID: {stream_id} from node ID: {node_id}, leader ID: {leader_id}.");
+                return Err(SystemError::NotStreamLeader(leader_id));
+            }
+            Some((_, current_leader_epoch)) if current_leader_epoch != leader_epoch => {
+                warn!("Rejected ISR: {isr:?} for stream with ID: {stream_id} from node ID: {node_id} in leader epoch: {leader_epoch}, current leader epoch: {current_leader_epoch}.");
+                return Err(SystemError::StaleLeaderEpoch(current_leader_epoch));
+            }
+            None => return Err(SystemError::InvalidStreamId),
+            _ => {}
+        }
+
+        let term = self.election_manager.get_current_term().await;
+        self.propose_state(term, UpdateIsr::new_command(stream_id, leader_epoch, isr))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{as_command, run_scenario, send, send_to, NODES_COUNT};
+    use linearizability::history::Operation;
+    use sdk::commands::rebalance_leaders::RebalanceLeaders;
+    use sdk::error::SystemError;
+    use sdk::models::message::messages_from_bytes;
+    use std::time::Duration;
+
+    #[test]
+    fn new_stream_leader_should_be_elected_from_isr_and_serve_routed_requests() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                simulation.wait_for_leader().await?;
+                let created = simulation.spawn_client(async move {
+                    send(&as_command(&Operation::CreateStream { stream_id: 1 })).await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(created.take().is_some_and(|result| result.is_some()));
+
+                let leader_id = simulation.wait_for_leader().await?;
+                let metadata = simulation.cluster(leader_id).unwrap().get_metadata().await;
+                let stream = &metadata.streams[&1];
+                let (old_stream_leader_id, isr, replicas) = (
+                    stream.leader_id,
+                    stream.isr.clone(),
+                    stream.replicas.clone(),
+                );
+                simulation.crash_node(old_stream_leader_id);
+                simulation.run_for(Duration::from_secs(10)).await?;
+
+                let leader_id = simulation.wait_for_leader().await?;
+                let metadata = simulation.cluster(leader_id).unwrap().get_metadata().await;
+                let stream_leader_id = metadata.streams[&1].leader_id;
+                assert_ne!(stream_leader_id, old_stream_leader_id);
+                assert!(isr.contains(&stream_leader_id));
+
+                let follower_id = replicas
+                    .into_iter()
+                    .find(|node_id| {
+                        *node_id != old_stream_leader_id && *node_id != stream_leader_id
+                    })
+                    .unwrap();
+                let append = Operation::AppendMessages {
+                    stream_id: 1,
+                    ids: vec![1],
+                };
+                let rejected = simulation.spawn_client({
+                    let append = as_command(&append);
+                    async move { send_to(follower_id, &append).await }
+                });
+                let appended =
+                    simulation.spawn_client(async move { send(&as_command(&append)).await });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(matches!(
+                    rejected.take(),
+                    Some(Err(SystemError::InvalidResponse(status, _)))
+                        if status == SystemError::NotStreamLeader(0).as_code()
+                ));
+                assert_eq!(
+                    appended.take().flatten().map(|(node_id, _)| node_id),
+                    Some(stream_leader_id)
+                );
+
+                let polled = simulation.spawn_client(async move {
+                    send(&as_command(&Operation::PollMessages {
+                        stream_id: 1,
+                        offset: 0,
+                        count: 10,
+                    }))
+                    .await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                let (node_id, bytes) = polled.take().flatten().unwrap();
+                assert_eq!(node_id, stream_leader_id);
+                let ids = messages_from_bytes(&bytes)
+                    .iter()
+                    .map(|message| message.id)
+                    .collect::<Vec<_>>();
+                assert_eq!(ids, vec![1]);
+                Ok(())
+            })
+        });
+    }
+
+    #[test]
+    fn stream_leader_should_move_back_to_preferred_replica_once_it_is_in_sync() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                simulation.wait_for_leader().await?;
+                let created = simulation.spawn_client(async move {
+                    send(&as_command(&Operation::CreateStream { stream_id: 1 })).await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(created.take().is_some_and(|result| result.is_some()));
+
+                let leader_id = simulation.wait_for_leader().await?;
+                let metadata = simulation.cluster(leader_id).unwrap().get_metadata().await;
+                let preferred_leader_id = metadata.streams[&1].replicas[0];
+                assert_eq!(metadata.streams[&1].leader_id, preferred_leader_id);
+                simulation.crash_node(preferred_leader_id);
+                simulation.run_for(Duration::from_secs(10)).await?;
+                simulation.start_node(preferred_leader_id);
+                let mut in_sync = false;
+                for _ in 0..100 {
+                    simulation.run_for(Duration::from_millis(100)).await?;
+                    let Some(leader_id) = simulation.leader_id().await else {
+                        continue;
+                    };
+                    let metadata = simulation.cluster(leader_id).unwrap().get_metadata().await;
+                    if metadata.streams[&1].isr.contains(&preferred_leader_id) {
+                        in_sync = true;
+                        break;
+                    }
+                }
+                assert!(in_sync);
+
+                let leader_id = simulation.wait_for_leader().await?;
+                let leader = simulation.cluster(leader_id).unwrap();
+                let preferred_leader = simulation.cluster(preferred_leader_id).unwrap();
+                assert_ne!(
+                    leader.get_metadata().await.streams[&1].leader_id,
+                    preferred_leader_id
+                );
+                let imbalance = leader.get_leader_imbalance().await;
+                assert_eq!((imbalance.streams, imbalance.imbalanced_streams), (1, 1));
+                let imbalance = preferred_leader.get_leader_imbalance().await;
+                assert_eq!(
+                    (
+                        imbalance.led_streams,
+                        imbalance.preferred_streams,
+                        imbalance.lost_preferred_streams
+                    ),
+                    (0, 1, 1)
+                );
+                assert_eq!(imbalance.ratio(), 1.0);
+
+                let rebalanced = simulation.spawn_client(async move {
+                    send_to(leader_id, &RebalanceLeaders::new_command()).await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(rebalanced.take().is_some_and(|result| result.is_ok()));
+                assert_eq!(
+                    leader.get_metadata().await.streams[&1].leader_id,
+                    preferred_leader_id
+                );
+                let imbalance = leader.get_leader_imbalance().await;
+                assert_eq!((imbalance.streams, imbalance.imbalanced_streams), (1, 0));
+                let imbalance = preferred_leader.get_leader_imbalance().await;
+                assert_eq!(
+                    (
+                        imbalance.led_streams,
+                        imbalance.preferred_streams,
+                        imbalance.lost_preferred_streams
+                    ),
+                    (1, 1, 0)
+                );
+                assert_eq!(imbalance.ratio(), 0.0);
+                Ok(())
+            })
+        });
+    }
+
+    #[test]
+    fn isr_of_previous_stream_leader_should_be_rejected() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                simulation.wait_for_leader().await?;
+                let created = simulation.spawn_client(async move {
+                    send(&as_command(&Operation::CreateStream { stream_id: 1 })).await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(created.take().is_some_and(|result| result.is_some()));
+
+                let leader_id = simulation.wait_for_leader().await?;
+                let leader = simulation.cluster(leader_id).unwrap();
+                let (old_stream_leader_id, old_leader_epoch, old_isr) = {
+                    let streamer = leader.streamer.lock().await;
+                    let stream = streamer.get_stream(1).unwrap();
+                    (stream.leader_id, stream.leader_epoch, stream.isr.clone())
+                };
+                simulation.crash_node(old_stream_leader_id);
+                simulation.run_for(Duration::from_secs(10)).await?;
+
+                let leader_id = simulation.wait_for_leader().await?;
+                let leader = simulation.cluster(leader_id).unwrap();
+                let (stream_leader_id, leader_epoch, isr) = {
+                    let streamer = leader.streamer.lock().await;
+                    let stream = streamer.get_stream(1).unwrap();
+                    (stream.leader_id, stream.leader_epoch, stream.isr.clone())
+                };
+                assert!(leader_epoch > old_leader_epoch);
+                assert_ne!(isr, old_isr);
+                let rejected = leader
+                    .update_isr(stream_leader_id, 1, old_leader_epoch, old_isr.clone())
+                    .await;
+                assert!(matches!(rejected, Err(SystemError::StaleLeaderEpoch(_))));
+
+                for node_id in (1..=NODES_COUNT).filter(|id| *id != old_stream_leader_id) {
+                    let cluster = simulation.cluster(node_id).unwrap();
+                    assert_eq!(cluster.get_metadata().await.streams[&1].isr, isr);
+                }
+                Ok(())
+            })
+        });
+    }
+}
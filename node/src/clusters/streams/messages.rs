@@ -1,20 +1,24 @@
-use crate::clusters::cluster::Cluster;
-use crate::configs::config::RequiredAcknowledgements;
-use crate::connection::handler::ConnectionHandler;
-use crate::models::appended_messages::AppendedMessages;
-use crate::types::Term;
+use crate::clusters::cluster::{Cluster, ClusterNode};
+use crate::environment;
+use crate::types::{NodeId, Term};
 use sdk::commands::append_messages::AppendableMessage;
 use sdk::error::SystemError;
-use sdk::models::message::Message;
+use sdk::models::fetched_messages::FetchedMessages;
+use sdk::models::leader_epoch::EpochEndOffset;
+use std::time::Duration;
 use tracing::{error, info, warn};
 
+const HIGH_WATERMARK_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
 impl Cluster {
+    /// Appends the messages to the leader's log and returns its log end offset,
+    /// the messages are committed once the followers have fetched them.
     pub async fn append_messages(
         &self,
         term: Term,
         stream_id: u64,
         messages: &[AppendableMessage],
-    ) -> Result<AppendedMessages, SystemError> {
+    ) -> Result<u64, SystemError> {
         let current_term = self.election_manager.get_current_term().await;
         if current_term != term {
             error!(
@@ -23,124 +27,495 @@ impl Cluster {
             return Err(SystemError::InvalidTerm(term));
         }
 
-        let mut streamer = self.streamer.lock().await;
-        streamer.append_messages(stream_id, messages).await
+        let result;
+        {
+            let mut streamer = self.streamer.lock().await;
+            let Some(stream) = streamer.get_stream_mut(stream_id) else {
+                return Err(SystemError::InvalidStreamId);
+            };
+
+            result = stream.append_and_write_messages(messages).await;
+        }
+
+        // The messages might have been written partially, or the stream not truncated back, so no more are accepted.
+        let log_end_offset = self.fence_on_error(result).await?;
+        let result = self.advance_high_watermark(stream_id).await;
+        self.fence_on_error(result).await?;
+        Ok(log_end_offset)
     }
 
-    pub async fn commit_messages(
+    /// Handles the fetch request of the follower, its offset is the log end offset of the follower's replica.
+    /// The follower must know the current leader epoch, so that it has truncated its log to match this one.
+    pub async fn fetch_messages(
         &self,
+        node_id: NodeId,
         term: Term,
         stream_id: u64,
-        messages: Vec<Message>,
-    ) -> Result<(), SystemError> {
+        leader_epoch: u64,
+        offset: u64,
+        count: u64,
+    ) -> Result<FetchedMessages, SystemError> {
         let current_term = self.election_manager.get_current_term().await;
         if current_term != term {
-            error!(
-                "Failed to commit messages to stream with ID: {stream_id}, term: {term} is not equal to current term: {current_term}.",
+            warn!(
+                "Received fetch messages for stream with ID: {stream_id} from node ID: {node_id} in term: {term}, but current term is: {current_term}."
             );
-            return Err(SystemError::InvalidTerm(term));
+            return Err(SystemError::InvalidTerm(current_term));
         }
 
-        let mut streamer = self.streamer.lock().await;
-        streamer.commit_messages(stream_id, messages).await
+        let mut fetched_messages;
+        {
+            let mut streamer = self.streamer.lock().await;
+            let Some(stream) = streamer.get_stream_mut(stream_id) else {
+                return Err(SystemError::InvalidStreamId);
+            };
+
+            if stream.leader_epoch != leader_epoch {
+                warn!(
+                    "Received fetch messages for stream with ID: {stream_id} from node ID: {node_id} in leader epoch: {leader_epoch}, but current leader epoch is: {}.",
+                    stream.leader_epoch
+                );
+                return Err(SystemError::StaleLeaderEpoch(stream.leader_epoch));
+            }
+
+            fetched_messages = match stream.fetch_messages_with_epochs(offset, count) {
+                Ok(fetched_messages) => fetched_messages,
+                Err(error) => {
+                    error!(
+                        "Node ID: {node_id} is ahead of the leader for stream with ID: {stream_id}, offset: {offset} > {}.",
+                        stream.get_log_end_offset()
+                    );
+                    return Err(error);
+                }
+            };
+            if stream.is_replica(node_id) {
+                stream.update_replica(node_id, offset, environment::now());
+            }
+        }
+
+        // The follower learns the high watermark, which might have been advanced by this fetch.
+        let result = self.advance_high_watermark(stream_id).await;
+        self.fence_on_error(result).await?;
+        if let Some(stream) = self.streamer.lock().await.get_stream(stream_id) {
+            fetched_messages.high_watermark = stream.high_watermark;
+        }
+        Ok(fetched_messages)
     }
 
-    pub async fn reset_offset(&self, stream_id: u64, offset: u64) {
-        warn!("Resetting offset for stream with ID: {stream_id} to: {offset}...",);
-        let mut streamer = self.streamer.lock().await;
-        streamer.reset_offset(stream_id, offset).await;
-        warn!("Successfully reset offset for stream with ID: {stream_id} to: {offset}.");
+    /// Handles the follower's request for the end offset of its last leader epoch, where its log should be truncated.
+    pub async fn get_epoch_end_offset(
+        &self,
+        stream_id: u64,
+        current_leader_epoch: u64,
+        leader_epoch: u64,
+    ) -> Result<EpochEndOffset, SystemError> {
+        let streamer = self.streamer.lock().await;
+        let Some(stream) = streamer.get_stream(stream_id) else {
+            return Err(SystemError::InvalidStreamId);
+        };
+
+        if stream.leader_epoch != current_leader_epoch {
+            return Err(SystemError::StaleLeaderEpoch(stream.leader_epoch));
+        }
+
+        Ok(stream.get_epoch_end_offset(leader_epoch))
     }
 
-    pub async fn sync_appended_messages(
+    /// Handles the new replica's request for the chunk of the committed messages to be copied in bulk.
+    pub async fn get_stream_chunk(
         &self,
-        handler: &mut ConnectionHandler,
-        term: Term,
         stream_id: u64,
-        messages: &[Message],
-    ) -> Result<(), SystemError> {
-        if !self.is_leader().await {
-            handler.send_empty_ok_response().await?;
+        offset: u64,
+        max_bytes: u32,
+    ) -> Result<FetchedMessages, SystemError> {
+        let streamer = self.streamer.lock().await;
+        let Some(stream) = streamer.get_stream(stream_id) else {
+            return Err(SystemError::InvalidStreamId);
+        };
+
+        stream.read_chunk(offset, max_bytes)
+    }
+
+    /// The high watermark is the lowest log end offset of the in-sync replicas.
+    pub(crate) async fn advance_high_watermark(&self, stream_id: u64) -> Result<(), SystemError> {
+        let voters = self.get_voter_ids().await;
+        let self_node_id = self.get_self_node().unwrap().node.id;
+        let mut streamer = self.streamer.lock().await;
+        let Some(stream) = streamer.get_stream_mut(stream_id) else {
             return Ok(());
-        }
+        };
 
-        let current_offset;
-        let replication_factor;
-        {
-            let streamer = self.streamer.lock().await;
-            let stream = streamer.get_stream(stream_id).unwrap();
-            current_offset = stream.current_offset;
-            replication_factor = stream.replication_factor as u64;
+        if stream.leader_id != self_node_id {
+            return Ok(());
         }
 
-        if replication_factor == 1 {
-            info!("Replication factor is 1, no need to sync appended messages.");
+        let isr = if stream.replicas.is_empty() && stream.isr.is_empty() {
+            &voters
+        } else {
+            &stream.isr
+        };
+        let high_watermark = isr
+            .iter()
+            .map(|node_id| {
+                if *node_id == self_node_id {
+                    stream.get_log_end_offset()
+                } else {
+                    stream
+                        .replica_states
+                        .get(node_id)
+                        .map(|replica| replica.log_end_offset)
+                        .unwrap_or_default()
+                }
+            })
+            .min()
+            .unwrap_or_default();
+        if high_watermark <= stream.high_watermark {
             return Ok(());
         }
 
-        let current_term = self.election_manager.get_current_term().await;
-        if current_term != term {
+        stream.set_high_watermark(high_watermark).await?;
+        info!("Advanced high watermark for stream with ID: {stream_id} to: {high_watermark}.");
+        Ok(())
+    }
+
+    pub(crate) async fn verify_in_sync_replicas(&self, stream_id: u64) -> Result<(), SystemError> {
+        let voters_count = self.get_voters_count().await as usize;
+        let streamer = self.streamer.lock().await;
+        let Some(stream) = streamer.get_stream(stream_id) else {
+            return Err(SystemError::InvalidStreamId);
+        };
+
+        let isr_count = if stream.replicas.is_empty() && stream.isr.is_empty() {
+            voters_count
+        } else {
+            stream.isr.len()
+        };
+        if isr_count < stream.min_in_sync_replicas as usize {
             error!(
-                "Failed to sync messages, term: {term} is not equal to current term: {current_term}.",
+                "Stream with ID: {stream_id} has {isr_count} in-sync replicas, min in-sync replicas: {}.",
+                stream.min_in_sync_replicas
             );
-            return Err(SystemError::InvalidTerm(term));
+            return Err(SystemError::NotEnoughReplicas(isr_count as u8));
         }
 
-        let majority_required =
-            self.required_acknowledgements == RequiredAcknowledgements::Majority;
-        if !majority_required {
-            handler.send_empty_ok_response().await?;
-        }
+        Ok(())
+    }
 
-        let mut synced_nodes = 1;
-        let appendable_messages = messages
-            .iter()
-            .map(|message| AppendableMessage {
-                id: message.id,
-                payload: message.payload.clone(),
-            })
-            .collect::<Vec<AppendableMessage>>();
-        for node in self.nodes.values() {
-            if node.node.is_self_node() {
-                continue;
+    pub(crate) async fn wait_for_high_watermark(&self, stream_id: u64, offset: u64) -> bool {
+        let now = environment::now();
+        loop {
+            let high_watermark = self
+                .streamer
+                .lock()
+                .await
+                .get_stream(stream_id)
+                .map(|stream| stream.high_watermark);
+            match high_watermark {
+                Some(high_watermark) if high_watermark >= offset => return true,
+                None => return false,
+                _ => {}
             }
 
-            if let Err(error) = node
-                .node
-                .sync_messages(
-                    current_term,
-                    stream_id,
-                    current_offset,
-                    &appendable_messages,
-                )
-                .await
+            if environment::elapsed(now) >= self.commit_timeout
+                || !self.is_stream_leader(stream_id).await
             {
-                error!(
-                    "Failed to sync appended messages to cluster node with ID: {}, {error}",
-                    node.node.id
-                );
-                continue;
+                return false;
             }
 
-            synced_nodes += 1;
-            if synced_nodes >= replication_factor {
-                info!("Successfully synced appended messages to replication factor of {replication_factor} nodes.");
-                if majority_required {
-                    handler.send_empty_ok_response().await?;
-                }
+            environment::sleep(HIGH_WATERMARK_CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Fetches the next batch of messages from the stream leader starting at this replica's log end offset
+    /// and returns the number of fetched messages. After the leader changes, the log is truncated first.
+    /// The high watermark follows the leader's one, even if there are no new messages.
+    pub(crate) async fn fetch_from_leader(
+        &self,
+        term: Term,
+        leader: &ClusterNode,
+        stream_id: u64,
+    ) -> Result<usize, SystemError> {
+        let Some((offset, leader_epoch, reconciled_leader_epoch)) = self
+            .streamer
+            .lock()
+            .await
+            .get_stream(stream_id)
+            .map(|stream| {
+                (
+                    stream.get_log_end_offset(),
+                    stream.leader_epoch,
+                    stream.reconciled_leader_epoch,
+                )
+            })
+        else {
+            return Ok(0);
+        };
 
-                return Ok(());
+        if reconciled_leader_epoch != Some(leader_epoch) {
+            self.truncate_to_leader(leader, stream_id, leader_epoch)
+                .await?;
+            return Ok(0);
+        }
+
+        let fetched_messages = match leader
+            .node
+            .fetch_messages(
+                term,
+                stream_id,
+                leader_epoch,
+                offset,
+                self.max_fetch_messages,
+            )
+            .await
+        {
+            Ok(fetched_messages) => fetched_messages,
+            Err(SystemError::InvalidResponse(status, _))
+                if status == SystemError::InvalidOffset.as_code() =>
+            {
+                // The log is ahead of the leader's one, so it has to be truncated again.
+                if let Some(stream) = self.streamer.lock().await.get_stream_mut(stream_id) {
+                    stream.reconciled_leader_epoch = None;
+                }
+                return Err(SystemError::InvalidOffset);
             }
+            Err(error) => return Err(error),
+        };
+        let mut streamer = self.streamer.lock().await;
+        let Some(stream) = streamer.get_stream_mut(stream_id) else {
+            return Ok(0);
+        };
+
+        if stream.get_log_end_offset() != offset || stream.leader_epoch != leader_epoch {
+            warn!(
+                "Stream with ID: {stream_id} has changed while fetching messages from offset: {offset}, discarding them."
+            );
+            return Ok(0);
+        }
+
+        let fetched_messages_count = fetched_messages.messages.len();
+        let result = stream.write_fetched_messages(fetched_messages).await;
+        let log_end_offset = stream.get_log_end_offset();
+        drop(streamer);
+        self.fence_on_error(result).await?;
+        if fetched_messages_count == 0 {
+            return Ok(0);
         }
 
-        error!(
-            "Failed to sync appended messages to replication factor of {replication_factor} nodes.",
+        info!(
+            "Fetched {fetched_messages_count} messages for stream with ID: {stream_id} from leader ID: {}, log end offset: {log_end_offset}.",
+            leader.node.id
         );
+        Ok(fetched_messages_count)
+    }
+
+    /// Truncates the log to match the leader's one, before fetching from it in the new leader epoch.
+    pub(crate) async fn truncate_to_leader(
+        &self,
+        leader: &ClusterNode,
+        stream_id: u64,
+        leader_epoch: u64,
+    ) -> Result<(), SystemError> {
+        let Some(last_leader_epoch) = self
+            .streamer
+            .lock()
+            .await
+            .get_stream(stream_id)
+            .map(|stream| stream.get_last_leader_epoch())
+        else {
+            return Ok(());
+        };
+
+        let leader_end_offset = leader
+            .node
+            .get_epoch_end_offset(stream_id, leader_epoch, last_leader_epoch)
+            .await?;
+        let mut streamer = self.streamer.lock().await;
+        let Some(stream) = streamer.get_stream_mut(stream_id) else {
+            return Ok(());
+        };
 
-        if !majority_required {
+        if stream.leader_epoch != leader_epoch {
             return Ok(());
         }
 
-        Err(SystemError::CannotSyncAppendedMessages)
+        let result = stream.truncate_to_leader(leader_end_offset).await;
+        if result.is_ok() {
+            stream.reconciled_leader_epoch = Some(leader_epoch);
+        }
+        drop(streamer);
+        let log_end_offset = self.fence_on_error(result).await?;
+        info!(
+            "Stream with ID: {stream_id} matches the log of leader ID: {} in leader epoch: {leader_epoch}, log end offset: {log_end_offset}.",
+            leader.node.id
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{as_command, run_scenario, send, send_to, NODES_COUNT};
+    use linearizability::history::Operation;
+    use sdk::bytes_serializable::BytesSerializable;
+    use sdk::commands::create_stream::CreateStream;
+    use sdk::commands::get_streams::GetStreams;
+    use sdk::commands::poll_messages::PollMessages;
+    use sdk::error::SystemError;
+    use sdk::models::message::messages_from_bytes;
+    use sdk::models::read_consistency::ReadConsistency;
+    use sdk::models::stream::Stream;
+    use std::time::Duration;
+
+    #[test]
+    fn replicas_should_end_identical_once_stream_leader_crashes_mid_replication() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                simulation.wait_for_leader().await?;
+                let created = simulation.spawn_client(async move {
+                    send(&as_command(&Operation::CreateStream { stream_id: 1 })).await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(created.take().is_some_and(|result| result.is_some()));
+
+                let leader_id = simulation.wait_for_leader().await?;
+                let leader = simulation.cluster(leader_id).unwrap();
+                let stream_leader_id = leader.get_metadata().await.streams[&1].leader_id;
+                let stream_leader = simulation.cluster(stream_leader_id).unwrap();
+                let _ = simulation.spawn_client(async move {
+                    send(&as_command(&Operation::AppendMessages {
+                        stream_id: 1,
+                        ids: vec![1, 2],
+                    }))
+                    .await
+                });
+                // The stream leader crashes once it has stored the messages, before the followers fetch them.
+                loop {
+                    let log_end_offset = stream_leader
+                        .streamer
+                        .lock()
+                        .await
+                        .get_stream(1)
+                        .map_or(0, |stream| stream.get_log_end_offset());
+                    if log_end_offset > 0 {
+                        break;
+                    }
+                    simulation.run_for(Duration::from_millis(1)).await?;
+                }
+                simulation.crash_node(stream_leader_id);
+                simulation.run_for(Duration::from_secs(10)).await?;
+
+                let appended = simulation.spawn_client(async move {
+                    send(&as_command(&Operation::AppendMessages {
+                        stream_id: 1,
+                        ids: vec![3],
+                    }))
+                    .await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(appended.take().is_some_and(|result| result.is_some()));
+
+                simulation.start_node(stream_leader_id);
+                simulation.run_for(Duration::from_secs(10)).await?;
+                let mut logs = Vec::new();
+                for node_id in 1..=NODES_COUNT {
+                    let cluster = simulation.cluster(node_id).unwrap();
+                    let streamer = cluster.streamer.lock().await;
+                    let stream = streamer.get_stream(1).unwrap();
+                    let ids = stream
+                        .fetch_messages(0, u64::MAX)
+                        .unwrap()
+                        .iter()
+                        .map(|message| message.id)
+                        .collect::<Vec<_>>();
+                    logs.push((ids, stream.high_watermark));
+                }
+                assert!(logs[0].0.contains(&3));
+                assert_eq!(logs[0].1, logs[0].0.len() as u64);
+                assert!(logs.iter().all(|log| *log == logs[0]));
+                Ok(())
+            })
+        });
+    }
+
+    #[test]
+    fn relaxed_reads_should_be_served_only_by_stream_replicas() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let leader_id = simulation.wait_for_leader().await?;
+                // The cluster leader lists all the streams, so it's kept among the replicas and the outsider is a follower.
+                let replicas = vec![leader_id, leader_id % NODES_COUNT + 1];
+                let outsider_id = (1..=NODES_COUNT)
+                    .find(|node_id| !replicas.contains(node_id))
+                    .unwrap();
+                let created = simulation.spawn_client(async move {
+                    send(&CreateStream::new_command(1, Some(2), Some(1), replicas)).await?;
+                    send(&as_command(&Operation::AppendMessages {
+                        stream_id: 1,
+                        ids: vec![1, 2],
+                    }))
+                    .await
+                });
+                simulation.run_for(Duration::from_secs(5)).await?;
+                assert!(created.take().is_some_and(|result| result.is_some()));
+
+                let leader_id = simulation.wait_for_leader().await?;
+                assert_ne!(outsider_id, leader_id);
+                let stream = &simulation
+                    .cluster(leader_id)
+                    .unwrap()
+                    .get_metadata()
+                    .await
+                    .streams[&1];
+                let stream_leader_id = stream.leader_id;
+                let follower_id = *stream
+                    .replicas
+                    .iter()
+                    .find(|node_id| **node_id != stream_leader_id)
+                    .unwrap();
+
+                let reads = simulation.spawn_client(async move {
+                    let relaxed_poll =
+                        PollMessages::new_command(1, 0, 10, ReadConsistency::Relaxed);
+                    let linearizable_poll =
+                        PollMessages::new_command(1, 0, 10, ReadConsistency::Linearizable);
+                    let get_streams = GetStreams::new_command(ReadConsistency::Relaxed);
+                    (
+                        send_to(follower_id, &relaxed_poll).await,
+                        send_to(follower_id, &linearizable_poll).await,
+                        send_to(outsider_id, &relaxed_poll).await,
+                        send_to(follower_id, &get_streams).await,
+                        send_to(outsider_id, &get_streams).await,
+                    )
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                let (
+                    follower_poll,
+                    follower_linearizable_poll,
+                    outsider_poll,
+                    follower_streams,
+                    outsider_streams,
+                ) = reads.take().unwrap();
+                let ids = messages_from_bytes(&follower_poll.unwrap())
+                    .iter()
+                    .map(|message| message.id)
+                    .collect::<Vec<_>>();
+                assert_eq!(ids, vec![1, 2]);
+                assert!(matches!(
+                    follower_linearizable_poll,
+                    Err(SystemError::InvalidResponse(status, _))
+                        if status == SystemError::NotStreamLeader(0).as_code()
+                ));
+                assert!(matches!(
+                    outsider_poll,
+                    Err(SystemError::InvalidResponse(status, _))
+                        if status == SystemError::NotStreamReplica(0).as_code()
+                ));
+                let follower_streams = follower_streams.unwrap();
+                let stream = Stream::from_bytes(&follower_streams).unwrap();
+                assert_eq!((stream.id, stream.high_watermark), (1, 2));
+                assert!(outsider_streams.unwrap().is_empty());
+                Ok(())
+            })
+        });
     }
 }
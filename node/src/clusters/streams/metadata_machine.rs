@@ -0,0 +1,296 @@
+use crate::clusters::state_machine::StateMachine;
+use crate::clusters::streams::cluster_streams::{
+    default_min_in_sync_replicas, DEFAULT_REPLICATION_FACTOR,
+};
+use crate::streaming::streamer::Streamer;
+use bytes::BufMut;
+use futures::future::LocalBoxFuture;
+use futures::lock::Mutex;
+use sdk::commands::command::Command;
+use sdk::error::SystemError;
+use std::rc::Rc;
+
+/// The fixed size part of the stream in the snapshot: its ID, replication factor, min in-sync replicas,
+/// leader ID and leader epoch, which is followed by the replicas and the ISR.
+const STREAM_SNAPSHOT_SIZE: usize = 8 + 1 + 1 + 8 + 8;
+
+/// Applies the stream metadata commands, each stream is described by its ID, replication factor,
+/// min in-sync replicas, the leader and its epoch, the assigned replicas and the current in-sync replicas.
+#[derive(Debug)]
+pub(crate) struct StreamMetadataMachine {
+    streamer: Rc<Mutex<Streamer>>,
+}
+
+impl StreamMetadataMachine {
+    pub fn new(streamer: Rc<Mutex<Streamer>>) -> Self {
+        Self { streamer }
+    }
+}
+
+impl StateMachine for StreamMetadataMachine {
+    fn name(&self) -> &'static str {
+        "stream_metadata"
+    }
+
+    fn handles(&self, command: &Command) -> bool {
+        matches!(
+            command,
+            Command::CreateStream(_)
+                | Command::DeleteStream(_)
+                | Command::UpdateIsr(_)
+                | Command::UpdateStreamLeader(_)
+        )
+    }
+
+    fn apply<'a>(&'a self, command: &'a Command) -> LocalBoxFuture<'a, Result<(), SystemError>> {
+        Box::pin(async move {
+            let mut streamer = self.streamer.lock().await;
+            match command {
+                Command::CreateStream(create_stream) => {
+                    let replication_factor = create_stream
+                        .replication_factor
+                        .unwrap_or(DEFAULT_REPLICATION_FACTOR);
+                    streamer
+                        .create_stream(
+                            create_stream.id,
+                            replication_factor,
+                            create_stream.min_in_sync_replicas.unwrap_or_else(|| {
+                                default_min_in_sync_replicas(replication_factor)
+                            }),
+                            create_stream.replicas.clone(),
+                        )
+                        .await
+                }
+                Command::DeleteStream(delete_stream) => {
+                    streamer.delete_stream(delete_stream.id).await
+                }
+                Command::UpdateIsr(update_isr) => {
+                    streamer
+                        .set_isr(
+                            update_isr.stream_id,
+                            update_isr.leader_epoch,
+                            update_isr.isr.clone(),
+                        )
+                        .await
+                }
+                Command::UpdateStreamLeader(update_stream_leader) => {
+                    streamer
+                        .set_stream_leader(
+                            update_stream_leader.stream_id,
+                            update_stream_leader.leader_id,
+                            update_stream_leader.leader_epoch,
+                        )
+                        .await
+                }
+                _ => Err(SystemError::InvalidCommand),
+            }
+        })
+    }
+
+    fn snapshot(&self) -> LocalBoxFuture<'_, Result<Vec<u8>, SystemError>> {
+        Box::pin(async move {
+            let streamer = self.streamer.lock().await;
+            let mut streams = streamer.get_streams();
+            streams.sort_by_key(|stream| stream.stream_id);
+            let mut bytes = Vec::new();
+            for stream in streams {
+                bytes.put_u64_le(stream.stream_id);
+                bytes.put_u8(stream.replication_factor);
+                bytes.put_u8(stream.min_in_sync_replicas);
+                bytes.put_u64_le(stream.leader_id);
+                bytes.put_u64_le(stream.leader_epoch);
+                put_node_ids(&mut bytes, &stream.replicas);
+                put_node_ids(&mut bytes, &stream.isr);
+            }
+            Ok(bytes)
+        })
+    }
+
+    fn restore<'a>(&'a self, snapshot: &'a [u8]) -> LocalBoxFuture<'a, Result<(), SystemError>> {
+        Box::pin(async move {
+            let mut streams = Vec::new();
+            let mut position = 0;
+            while position < snapshot.len() {
+                if snapshot.len() < position + STREAM_SNAPSHOT_SIZE {
+                    return Err(SystemError::InvalidCommand);
+                }
+
+                let stream_id = u64::from_le_bytes(snapshot[position..position + 8].try_into()?);
+                let replication_factor = snapshot[position + 8];
+                let min_in_sync_replicas = snapshot[position + 9];
+                let leader_id =
+                    u64::from_le_bytes(snapshot[position + 10..position + 18].try_into()?);
+                let leader_epoch =
+                    u64::from_le_bytes(snapshot[position + 18..position + 26].try_into()?);
+                position += STREAM_SNAPSHOT_SIZE;
+                let replicas = read_node_ids(snapshot, &mut position)?;
+                let isr = read_node_ids(snapshot, &mut position)?;
+                streams.push((
+                    stream_id,
+                    replication_factor,
+                    min_in_sync_replicas,
+                    leader_id,
+                    leader_epoch,
+                    replicas,
+                    isr,
+                ));
+            }
+
+            // The streams missing from the snapshot have been deleted, as the snapshot is saved once the streams
+            // are created, so none of the stored ones could have been created by the entries above it.
+            let mut streamer = self.streamer.lock().await;
+            let deleted_stream_ids = streamer
+                .get_streams()
+                .iter()
+                .map(|stream| stream.stream_id)
+                .filter(|stream_id| !streams.iter().any(|stream| stream.0 == *stream_id))
+                .collect::<Vec<_>>();
+            for stream_id in deleted_stream_ids {
+                streamer.delete_stream(stream_id).await?;
+            }
+
+            for (
+                stream_id,
+                replication_factor,
+                min_in_sync_replicas,
+                leader_id,
+                leader_epoch,
+                replicas,
+                isr,
+            ) in streams
+            {
+                let replicas_empty = replicas.is_empty();
+                streamer
+                    .create_stream(
+                        stream_id,
+                        replication_factor,
+                        min_in_sync_replicas,
+                        replicas.clone(),
+                    )
+                    .await?;
+                // The snapshot is the source of truth, also for the streams which are already stored.
+                streamer
+                    .set_replicas(stream_id, min_in_sync_replicas, replicas)
+                    .await?;
+                // The streams without the assigned replicas follow the cluster leader instead.
+                if !replicas_empty {
+                    streamer
+                        .set_stream_leader(stream_id, leader_id, leader_epoch)
+                        .await?;
+                }
+                streamer.set_isr(stream_id, leader_epoch, isr).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+fn put_node_ids(bytes: &mut Vec<u8>, node_ids: &[u64]) {
+    bytes.put_u8(node_ids.len() as u8);
+    for node_id in node_ids {
+        bytes.put_u64_le(*node_id);
+    }
+}
+
+fn read_node_ids(bytes: &[u8], position: &mut usize) -> Result<Vec<u64>, SystemError> {
+    let Some(len) = bytes.get(*position).map(|len| *len as usize) else {
+        return Err(SystemError::InvalidCommand);
+    };
+    *position += 1;
+    if bytes.len() < *position + 8 * len {
+        return Err(SystemError::InvalidCommand);
+    }
+
+    let mut node_ids = Vec::with_capacity(len);
+    for _ in 0..len {
+        node_ids.push(u64::from_le_bytes(
+            bytes[*position..*position + 8].try_into()?,
+        ));
+        *position += 8;
+    }
+    Ok(node_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment;
+    use crate::environment::memory::MemoryStorage;
+    use crate::environment::Environment;
+    use crate::metadata::file_store::FileMetadataStore;
+    use sdk::commands::create_stream::CreateStream;
+    use sdk::commands::delete_stream::DeleteStream;
+    use sdk::commands::update_isr::UpdateIsr;
+    use sdk::commands::update_stream_leader::UpdateStreamLeader;
+
+    const STREAMS_PATH: &str = "test_metadata_machine";
+
+    async fn new_machine(path: &str) -> StreamMetadataMachine {
+        let mut streamer = Streamer::new(1, path, Rc::new(FileMetadataStore::new(path)));
+        streamer.init().await.unwrap();
+        StreamMetadataMachine::new(Rc::new(Mutex::new(streamer)))
+    }
+
+    async fn get_isr(machine: &StreamMetadataMachine, stream_id: u64) -> Vec<u64> {
+        let streamer = machine.streamer.lock().await;
+        streamer.get_stream(stream_id).unwrap().isr.clone()
+    }
+
+    #[monoio::test]
+    async fn isr_of_previous_leader_epoch_should_be_rejected() {
+        let _guard = Environment::system()
+            .with_storage(Rc::new(MemoryStorage::default()))
+            .enter();
+        let machine = new_machine(STREAMS_PATH).await;
+        machine
+            .apply(&CreateStream::new_command(
+                1,
+                Some(3),
+                Some(2),
+                vec![1, 2, 3],
+            ))
+            .await
+            .unwrap();
+        machine
+            .apply(&UpdateStreamLeader::new_command(1, 2, 1))
+            .await
+            .unwrap();
+
+        let stale_isr = UpdateIsr::new_command(1, 0, vec![1, 2]);
+        assert!(matches!(
+            machine.apply(&stale_isr).await,
+            Err(SystemError::StaleLeaderEpoch(1))
+        ));
+        assert_eq!(get_isr(&machine, 1).await, vec![1, 2, 3]);
+
+        machine
+            .apply(&UpdateIsr::new_command(1, 1, vec![2, 3]))
+            .await
+            .unwrap();
+        assert_eq!(get_isr(&machine, 1).await, vec![2, 3]);
+    }
+
+    #[monoio::test]
+    async fn stream_deleted_before_snapshot_should_be_deleted_on_restore() {
+        let _guard = Environment::system()
+            .with_storage(Rc::new(MemoryStorage::default()))
+            .enter();
+        let machine = new_machine(STREAMS_PATH).await;
+        let lagging_path = format!("{STREAMS_PATH}_lagging");
+        let lagging_machine = new_machine(&lagging_path).await;
+        for stream_id in [1, 2] {
+            let create_stream = CreateStream::new_command(stream_id, Some(1), Some(1), vec![1]);
+            machine.apply(&create_stream).await.unwrap();
+            lagging_machine.apply(&create_stream).await.unwrap();
+        }
+        machine.apply(&DeleteStream::new_command(2)).await.unwrap();
+        let snapshot = machine.snapshot().await.unwrap();
+
+        lagging_machine.restore(&snapshot).await.unwrap();
+        assert_eq!(lagging_machine.snapshot().await.unwrap(), snapshot);
+        let streamer = lagging_machine.streamer.lock().await;
+        assert!(streamer.get_stream(1).is_some());
+        assert!(streamer.get_stream(2).is_none());
+        assert!(!environment::storage().exists(&format!("{lagging_path}/2")));
+    }
+}
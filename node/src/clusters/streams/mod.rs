@@ -1,3 +1,8 @@
+mod bootstrap;
 mod cluster_streams;
+mod isr;
+mod leaders;
 mod messages;
+pub(crate) mod metadata_machine;
+mod replication;
 mod state;
@@ -0,0 +1,397 @@
+use crate::clusters::cluster::{Cluster, ClusterNode};
+use crate::clusters::nodes::node::AppendEntriesBatch;
+use crate::environment;
+use crate::types::{Index, Term};
+use bytes::Bytes;
+use sdk::error::SystemError;
+use sdk::models::log_entry::LogEntry;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+pub(crate) const COMMIT_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+impl Cluster {
+    pub(crate) async fn reset_replication_progress(&self) {
+        let next_index = self.state.lock().await.last_index + 1;
+        for node in self.get_nodes().await {
+            *node.progress.next_index.lock().await = next_index;
+            *node.progress.match_index.lock().await = 0;
+            *node.progress.commit_index.lock().await = 0;
+            node.node.set_commit_index(0).await;
+        }
+    }
+
+    /// Appends the empty entry at the start of the leader's term, as the leader commits only the entries
+    /// of its own term, the entries of the previous terms are committed along with it.
+    pub(crate) async fn append_term_start_entry(&self, term: Term) {
+        if let Err(error) = self.append_state(term, Bytes::new()).await {
+            error!("Failed to append the entry at the start of the term, {error}");
+        }
+        self.trigger_replication();
+    }
+
+    pub(crate) fn trigger_replication(&self) {
+        if self.replication_trigger.unbounded_send(()).is_err() {
+            error!("Failed to trigger the state replication.");
+        }
+    }
+
+    pub(crate) async fn replicate_to_follower(&self, term: Term, cluster_node: &ClusterNode) {
+        // The replication which is already in progress will also send the recently appended entries.
+        let Some(_replicating) = cluster_node.progress.replicating.try_lock() else {
+            return;
+        };
+
+        if let Err(error) = self.replicate_to_node(term, cluster_node).await {
+            error!(
+                "Failed to replicate state to cluster node with ID: {}, {error}",
+                cluster_node.node.id
+            );
+        }
+    }
+
+    /// Waits for the replication which is already in progress, as the node is no longer replicated to afterwards.
+    pub(crate) async fn replicate_to_removed_node(&self, term: Term, cluster_node: &ClusterNode) {
+        let _replicating = cluster_node.progress.replicating.lock().await;
+        if let Err(error) = self.replicate_to_node(term, cluster_node).await {
+            warn!(
+                "Failed to replicate state to the removed cluster node with ID: {}, {error}",
+                cluster_node.node.id
+            );
+        }
+    }
+
+    /// Sends the batches of entries starting at the node's next index until it has caught up with the leader,
+    /// up to the configured number of batches are sent at once, without waiting for the previous responses.
+    /// The empty batch confirms that the node's log matches the leader's one, or propagates the latest commit index.
+    /// The caller must hold the node's replication lock.
+    async fn replicate_to_node(
+        &self,
+        term: Term,
+        cluster_node: &ClusterNode,
+    ) -> Result<(), SystemError> {
+        let node_id = cluster_node.node.id;
+        let progress = &cluster_node.progress;
+        loop {
+            if !self.is_leader().await || self.election_manager.get_current_term().await != term {
+                return Err(SystemError::NotLeader);
+            }
+
+            let mut next_index = *progress.next_index.lock().await;
+            if next_index == 0 {
+                next_index = cluster_node.node.get_node_state().await?.last_index + 1;
+            }
+
+            let state = self.state.lock().await;
+            let last_index = state.last_index;
+            let commit_index = state.commit_index;
+            next_index = next_index.min(last_index + 1);
+            *progress.next_index.lock().await = next_index;
+            let match_index = *progress.match_index.lock().await;
+            let node_commit_index = *progress.commit_index.lock().await;
+            if match_index >= last_index && node_commit_index >= commit_index {
+                return Ok(());
+            }
+
+            let mut batches = Vec::new();
+            let mut batch_start = next_index;
+            while batches.len() < self.max_append_entries_in_flight {
+                let prev_log_index = batch_start - 1;
+                let Some(prev_log_term) = state.term_at(prev_log_index) else {
+                    break;
+                };
+
+                let entries = state
+                    .load_entries_from(batch_start, self.max_append_entries)
+                    .await?;
+                batch_start += entries.len() as u64;
+                batches.push(AppendEntriesBatch {
+                    prev_log_index,
+                    prev_log_term,
+                    entries,
+                });
+                if batch_start > last_index {
+                    break;
+                }
+            }
+            drop(state);
+
+            let sent_batches = batches
+                .iter()
+                .map(|batch| (batch.prev_log_index, batch.entries.len() as u64))
+                .collect::<Vec<_>>();
+            let results = cluster_node
+                .node
+                .append_entries(term, commit_index, batches)
+                .await;
+            for ((prev_log_index, entries_count), result) in sent_batches.into_iter().zip(results) {
+                match result {
+                    Ok(()) => {
+                        let match_index = prev_log_index + entries_count;
+                        // The node has learned the commit index only as far as its log matches the leader's one.
+                        let node_commit_index = commit_index.min(match_index);
+                        *progress.match_index.lock().await = match_index;
+                        *progress.next_index.lock().await = match_index + 1;
+                        *progress.commit_index.lock().await = node_commit_index;
+                        cluster_node.node.set_commit_index(node_commit_index).await;
+                    }
+                    Err(SystemError::InvalidResponse(status, _))
+                        if status == SystemError::InvalidPreviousLogIndex.as_code() =>
+                    {
+                        let node_state = cluster_node.node.get_node_state().await?;
+                        let next_index = (node_state.last_index + 1).min(prev_log_index).max(1);
+                        warn!(
+                            "Cluster node with ID: {node_id} doesn't match the log at index: {prev_log_index}, last index: {}, next index: {next_index}.",
+                            node_state.last_index
+                        );
+                        *progress.next_index.lock().await = next_index;
+                        break;
+                    }
+                    Err(error) => {
+                        self.advance_commit_index().await;
+                        return Err(error);
+                    }
+                }
+            }
+            self.advance_commit_index().await;
+        }
+    }
+
+    /// Commits the highest entry stored by the quorum of voters, as long as it has been appended in the current term,
+    /// the entries of the previous terms are committed along with it. The committed entries are applied
+    /// by the replication task, which has been triggered.
+    pub(crate) async fn advance_commit_index(&self) {
+        let term = self.election_manager.get_current_term().await;
+        let last_index = self.state.lock().await.last_index;
+        let mut match_indexes = Vec::new();
+        for node in self.get_nodes().await {
+            if node.is_learner().await {
+                continue;
+            }
+
+            if node.node.is_self_node() {
+                match_indexes.push(last_index);
+                continue;
+            }
+
+            match_indexes.push(*node.progress.match_index.lock().await);
+        }
+
+        let quorum = self.get_quorum_count().await as usize;
+        if match_indexes.len() < quorum {
+            return;
+        }
+
+        match_indexes.sort_unstable_by(|a, b| b.cmp(a));
+        let commit_index = match_indexes[quorum - 1];
+        {
+            let mut state = self.state.lock().await;
+            if commit_index <= state.commit_index || state.term_at(commit_index) != Some(term) {
+                return;
+            }
+
+            state.set_commit_index(commit_index);
+        }
+
+        info!("Advanced commit index to: {commit_index} in term: {term}.");
+        self.trigger_replication();
+    }
+
+    /// Waits until the entry is committed and applied, unless it has been replaced by the entry of another leader.
+    pub(crate) async fn wait_for_commit(&self, index: Index, term: Term) -> bool {
+        let now = environment::now();
+        loop {
+            if *self.applied_index.lock().await >= index {
+                return self.state.lock().await.term_at(index) == Some(term);
+            }
+
+            if environment::elapsed(now) >= self.commit_timeout || !self.is_leader().await {
+                return false;
+            }
+
+            environment::sleep(COMMIT_CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Waits until the entry appended by the leader at the start of the term is committed and applied,
+    /// along with all the entries of the previous terms.
+    pub(crate) async fn wait_for_term_start(&self, term: Term) -> bool {
+        let now = environment::now();
+        loop {
+            let applied_index = *self.applied_index.lock().await;
+            if self.state.lock().await.term_at(applied_index) == Some(term) {
+                return true;
+            }
+
+            if environment::elapsed(now) >= self.commit_timeout || !self.is_leader().await {
+                return false;
+            }
+
+            environment::sleep(COMMIT_CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Replicates all the entries to the node, its conflicting entries are replaced by the leader's ones.
+    pub(crate) async fn catch_up_node(
+        &self,
+        term: Term,
+        target: &ClusterNode,
+    ) -> Result<(), SystemError> {
+        let node_id = target.node.id;
+        let _replicating = target.progress.replicating.lock().await;
+        let last_index = self.state.lock().await.last_index;
+        // The next index is taken from the node's last index, which is then confirmed by the leader's log.
+        *target.progress.next_index.lock().await = 0;
+        *target.progress.match_index.lock().await = 0;
+        info!("Catching up node ID: {node_id}, last index: {last_index}...");
+        self.replicate_to_node(term, target).await?;
+        let match_index = *target.progress.match_index.lock().await;
+        if match_index < last_index {
+            error!(
+                "Node ID: {node_id} is still behind the leader, match index: {match_index} < {last_index}."
+            );
+            return Err(SystemError::CannotSyncState);
+        }
+
+        info!("Node ID: {node_id} has caught up, last index: {last_index}.");
+        Ok(())
+    }
+
+    /// Appends the entries of the leader, once the log matches the leader's one at the previous index.
+    /// The conflicting entries are removed along with all the following ones, the entries which are already
+    /// stored are skipped, so that the delayed request cannot remove the entries appended afterwards.
+    pub async fn append_entries(
+        &self,
+        term: Term,
+        prev_log_index: Index,
+        prev_log_term: Term,
+        leader_commit: Index,
+        entries: &[LogEntry],
+    ) -> Result<(), SystemError> {
+        let current_term = self.election_manager.get_current_term().await;
+        if term != current_term {
+            error!("Received append entries in term: {term}, but current term is: {current_term}.");
+            return Err(SystemError::InvalidTerm(current_term));
+        }
+
+        let result = self
+            .append_leader_entries(prev_log_index, prev_log_term, leader_commit, entries)
+            .await;
+        if let Err(error) = &result {
+            if !matches!(error, SystemError::InvalidPreviousLogIndex) {
+                self.fence(error).await;
+            }
+            return result;
+        }
+
+        self.apply_committed_state(Some(term)).await
+    }
+
+    async fn append_leader_entries(
+        &self,
+        prev_log_index: Index,
+        prev_log_term: Term,
+        leader_commit: Index,
+        entries: &[LogEntry],
+    ) -> Result<(), SystemError> {
+        let mut state = self.state.lock().await;
+        if state.term_at(prev_log_index) != Some(prev_log_term) {
+            warn!(
+                "Log doesn't match the leader's one at index: {prev_log_index}, term: {prev_log_term}, last index: {}.",
+                state.last_index
+            );
+            return Err(SystemError::InvalidPreviousLogIndex);
+        }
+
+        for entry in entries {
+            match state.term_at(entry.index) {
+                Some(term) if term == entry.term => continue,
+                Some(term) => {
+                    warn!(
+                        "Entry at index: {} in term: {term} conflicts with the leader's one in term: {}.",
+                        entry.index, entry.term
+                    );
+                    state.truncate_from(entry.index).await?;
+                }
+                None => {}
+            }
+            state.sync(entry.clone()).await?;
+        }
+        // The entries are acknowledged to the leader once this node returns, so they must survive the crash.
+        if !entries.is_empty() {
+            state.flush().await?;
+        }
+
+        let last_new_index = prev_log_index + entries.len() as u64;
+        state.set_commit_index(leader_commit.min(last_new_index));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{as_command, run_scenario, send_to, NODES_COUNT};
+    use linearizability::history::Operation;
+    use std::time::Duration;
+
+    #[test]
+    fn uncommitted_entries_of_previous_leader_should_be_replaced_once_it_rejoins() {
+        run_scenario(|simulation| {
+            Box::pin(async move {
+                let old_leader_id = simulation.wait_for_leader().await?;
+                let old_leader = simulation.cluster(old_leader_id).unwrap();
+                let last_index = old_leader.state.lock().await.last_index;
+                let uncommitted = simulation.spawn_client(async move {
+                    send_to(
+                        old_leader_id,
+                        &as_command(&Operation::CreateStream { stream_id: 1 }),
+                    )
+                    .await
+                });
+                // The leader is partitioned before the appended entry reaches the followers.
+                while old_leader.state.lock().await.last_index == last_index {
+                    simulation.run_for(Duration::from_millis(1)).await?;
+                }
+                for node_id in (1..=NODES_COUNT).filter(|node_id| *node_id != old_leader_id) {
+                    simulation.partition(old_leader_id, node_id);
+                }
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(uncommitted.take().is_some_and(|result| result.is_err()));
+                let uncommitted_index = {
+                    let state = old_leader.state.lock().await;
+                    assert!(state.commit_index < state.last_index);
+                    state.last_index
+                };
+
+                simulation.crash_node(old_leader_id);
+                let leader_id = simulation.wait_for_leader().await?;
+                let committed = simulation.spawn_client(async move {
+                    send_to(
+                        leader_id,
+                        &as_command(&Operation::CreateStream { stream_id: 2 }),
+                    )
+                    .await
+                });
+                simulation.run_for(Duration::from_secs(3)).await?;
+                assert!(committed.take().is_some_and(|result| result.is_ok()));
+
+                simulation.heal_partitions();
+                simulation.start_node(old_leader_id);
+                simulation.run_for(Duration::from_secs(5)).await?;
+                let old_leader = simulation.cluster(old_leader_id).unwrap();
+                let leader = simulation.cluster(leader_id).unwrap();
+                let leader_state = leader.state.lock().await;
+                let old_leader_state = old_leader.state.lock().await;
+                assert!(leader_state.commit_index >= uncommitted_index);
+                assert_eq!(old_leader_state.commit_index, leader_state.commit_index);
+                for index in 1..=leader_state.commit_index {
+                    assert_eq!(old_leader_state.term_at(index), leader_state.term_at(index));
+                }
+                let streamer = old_leader.streamer.lock().await;
+                assert!(streamer.get_stream(1).is_none());
+                assert!(streamer.get_stream(2).is_some());
+                Ok(())
+            })
+        });
+    }
+}
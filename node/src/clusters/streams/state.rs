@@ -1,14 +1,13 @@
 use crate::clusters::cluster::Cluster;
-use crate::clusters::nodes::node::Node;
 use crate::configs::config::RequiredAcknowledgements;
 use crate::connection::handler::ConnectionHandler;
+use crate::metadata::StateSnapshot;
 use crate::types::{Index, Term};
 use bytes::Bytes;
 use sdk::commands::command;
 use sdk::commands::command::Command;
 use sdk::error::SystemError;
 use sdk::models::log_entry::LogEntry;
-use std::cmp::Ordering;
 use tracing::{error, info, warn};
 
 impl Cluster {
@@ -18,9 +17,9 @@ impl Cluster {
         term: Term,
         command: Command,
     ) -> Result<(), SystemError> {
+        // The leadership could've been lost in the meantime, so nothing is appended and the change isn't confirmed.
         if !self.is_leader().await {
-            handler.send_empty_ok_response().await?;
-            return Ok(());
+            return Err(SystemError::NotLeader);
         }
 
         let current_term = self.election_manager.get_current_term().await;
@@ -31,49 +30,21 @@ impl Cluster {
             return Err(SystemError::InvalidTerm(term));
         }
 
-        let leader_commit;
-        let prev_log_index;
-        let log_entry;
-        {
-            let bytes = Bytes::from(command.as_bytes());
-            (leader_commit, prev_log_index, log_entry) = self.append_state(bytes).await?;
-        }
-
+        let log_entry = self
+            .append_state(term, Bytes::from(command.as_bytes()))
+            .await?;
         let majority_required =
             self.required_acknowledgements == RequiredAcknowledgements::Majority;
         if !majority_required {
             handler.send_empty_ok_response().await?;
         }
 
-        let mut synced_nodes = 1;
-        for node in self.nodes.values() {
-            if node.node.is_self_node() {
-                continue;
-            }
-
-            let entries = vec![LogEntry {
-                index: log_entry.index,
-                size: log_entry.size,
-                data: log_entry.data.clone(),
-            }];
-            if let Err(error) = node
-                .node
-                .append_entry(current_term, leader_commit, prev_log_index, entries)
-                .await
-            {
-                error!(
-                    "Failed to sync state to cluster node with ID: {}, {error}",
-                    node.node.id
-                );
-                continue;
-            }
-
-            synced_nodes += 1;
-        }
-
-        let quorum = self.get_quorum_count();
-        if synced_nodes >= quorum {
-            info!("Successfully synced state to quorum of nodes.");
+        self.trigger_replication();
+        if self.wait_for_commit(log_entry.index, log_entry.term).await {
+            info!(
+                "Successfully synced state to quorum of nodes, commit index: {}.",
+                log_entry.index
+            );
             if majority_required {
                 handler.send_empty_ok_response().await?;
             }
@@ -82,7 +53,8 @@ impl Cluster {
         }
 
         error!(
-            "Failed to sync state to quorum of nodes, synced nodes: {synced_nodes} < quorum: {quorum}",
+            "Failed to sync state to quorum of nodes, entry at index: {} is not committed.",
+            log_entry.index
         );
 
         if !majority_required {
@@ -92,85 +64,205 @@ impl Cluster {
         Err(SystemError::CannotSyncState)
     }
 
-    pub async fn sync_state_from_leader(
+    /// Appends the command to the replicated state and waits until it's committed by the quorum of nodes.
+    pub(crate) async fn propose_state(
         &self,
-        last_applied: Index,
-        node: &Node,
+        term: Term,
+        command: Command,
     ) -> Result<(), SystemError> {
-        let start_index = last_applied + 1;
-        let node_id = node.id;
-        let node_state = node.get_node_state().await;
-        if node_state.is_err() {
-            let error = node_state.unwrap_err();
+        if !self.is_leader().await {
+            return Err(SystemError::NotLeader);
+        }
+
+        let current_term = self.election_manager.get_current_term().await;
+        if current_term != term {
+            error!("Failed to propose state, term: {term} is not equal to current term: {current_term}.");
+            return Err(SystemError::InvalidTerm(term));
+        }
+
+        let log_entry = self
+            .append_state(term, Bytes::from(command.as_bytes()))
+            .await?;
+        self.trigger_replication();
+        if !self.wait_for_commit(log_entry.index, log_entry.term).await {
             error!(
-                "Failed to get node state from cluster node with ID: {}, {error}",
-                node.id
+                "Failed to propose state, entry at index: {} is not committed.",
+                log_entry.index
             );
-            return Err(error);
+            return Err(SystemError::CannotSyncState);
         }
 
-        let node_state = node_state.unwrap();
-        match last_applied.cmp(&node_state.last_applied) {
-            Ordering::Less => {
-                warn!(
-                    "This node is ahead of cluster node with ID: {node_id} and will be truncated."
-                );
-                let mut state = self.state.lock().await;
-                state.truncate(start_index).await?;
-                return Ok(());
-            }
-            Ordering::Equal => {
-                info!("This node and cluster node with ID: {node_id} are in sync.");
-            }
-            Ordering::Greater => {
-                let loaded_state = node.load_state(start_index).await;
-                if loaded_state.is_err() {
-                    let error = loaded_state.unwrap_err();
-                    error!(
-                        "Failed to load state from cluster node with ID: {}, {error}",
-                        node.id
-                    );
-                    return Err(error);
-                }
+        Ok(())
+    }
+
+    /// Restores the state machines from the snapshot, so that only the entries above it are applied.
+    /// The snapshot contains only the committed entries, so the commit index is moved up to it if needed.
+    /// The snapshot is ignored if the log doesn't reach it anymore, e.g. once it has been removed.
+    pub async fn restore_state_snapshot(&self) -> Result<(), SystemError> {
+        let Some(snapshot) = self.metadata.load_state_snapshot().await? else {
+            return Ok(());
+        };
 
-                let loaded_state = loaded_state.unwrap();
-                for entry in loaded_state.entries {
-                    self.append_entry(&entry).await?;
+        let mut state = self.state.lock().await;
+        if snapshot.index > state.last_index {
+            warn!(
+                "State snapshot at index: {} is ahead of the last log index: {}, applying the whole state.",
+                snapshot.index, state.last_index
+            );
+            return Ok(());
+        }
+
+        self.state_machines.restore(&snapshot.data).await?;
+        state.set_commit_index(snapshot.index);
+        *self.applied_index.lock().await = snapshot.index;
+        *self.snapshot_index.lock().await = snapshot.index;
+        info!("Restored state snapshot at index: {}.", snapshot.index);
+        Ok(())
+    }
+
+    /// Applies the membership changes up to the restored snapshot, as the snapshot contains the state machines only.
+    pub async fn restore_membership(&self) -> Result<(), SystemError> {
+        let applied_index = *self.applied_index.lock().await;
+        let entries = self
+            .state
+            .lock()
+            .await
+            .load_entries(Some(applied_index))
+            .await?;
+        for entry in entries.iter().filter(|entry| entry.index <= applied_index) {
+            match map_entry(entry)? {
+                Some(Command::AddNode(add_node)) => self.apply_added_node(None, &add_node).await?,
+                Some(Command::RemoveNode(remove_node)) => {
+                    self.apply_removed_node(None, &remove_node).await?
                 }
+                _ => {}
             }
         }
         Ok(())
     }
 
-    pub async fn replay_state(
+    /// Applies the committed entries, which haven't been applied yet, in the log order, and fences this node
+    /// if it has failed to write them to its storage. The other failures, e.g. of the network or the invalid
+    /// entries, are only returned. The entries above the commit index are never applied,
+    /// as they might still be replaced by the leader.
+    pub async fn apply_committed_state(&self, term: Option<Term>) -> Result<(), SystemError> {
+        let result = self.apply_committed_entries(term).await;
+        self.fence_on_error(result).await?
+    }
+
+    /// Returns the storage failure as the outer error, and the other ones as the inner result.
+    async fn apply_committed_entries(
         &self,
         term: Option<Term>,
-        entries: &[LogEntry],
-    ) -> Result<(), SystemError> {
+    ) -> Result<Result<(), SystemError>, SystemError> {
+        let mut applied_index = self.applied_index.lock().await;
+        let entries = {
+            let state = self.state.lock().await;
+            if state.commit_index <= *applied_index {
+                return Ok(Ok(()));
+            }
+
+            // The commit index is kept, so that the committed entries are applied again once the node is restarted.
+            state.save_hard_state().await?;
+            let count = (state.commit_index - *applied_index) as usize;
+            state.load_entries_from(*applied_index + 1, count).await?
+        };
+
         info!(
-            "Replaying state for term: {} entries, term: {:?}.",
+            "Applying {} committed entries, term: {:?}.",
             entries.len(),
             term
         );
-        for entry in entries {
-            match command::map_from_bytes(&entry.data)? {
-                Command::CreateStream(create_stream) => {
-                    self.create_stream(
-                        term,
-                        create_stream.id,
-                        create_stream.replication_factor.unwrap_or(3),
-                    )
-                    .await?;
+        let mut streams_created = false;
+        for entry in &entries {
+            let command = match map_entry(entry) {
+                Ok(command) => command,
+                Err(error) => {
+                    error!("Failed to decode entry at index: {}, {error}", entry.index);
+                    return Ok(Err(error));
+                }
+            };
+            let result = match command {
+                Some(Command::AddNode(add_node)) => self.apply_added_node(term, &add_node).await,
+                Some(Command::RemoveNode(remove_node)) => {
+                    self.apply_removed_node(term, &remove_node).await
                 }
-                Command::DeleteStream(delete_stream) => {
-                    self.delete_stream(term, delete_stream.id).await?;
+                // The state machines write the applied commands to the storage.
+                Some(command) => match self.state_machines.apply(&command).await {
+                    Ok(()) => {
+                        if let Command::CreateStream(create_stream) = &command {
+                            streams_created = true;
+                            self.trigger_bootstrap(create_stream.id);
+                        }
+                        Ok(())
+                    }
+                    Err(
+                        error @ (SystemError::InvalidStreamId | SystemError::StaleLeaderEpoch(_)),
+                    ) => Err(error),
+                    Err(error) => {
+                        error!("Failed to apply entry at index: {}, {error}", entry.index);
+                        return Err(error);
+                    }
+                },
+                None => Ok(()),
+            };
+            match result {
+                // The command refers to the stream, which has been deleted by one of the previous entries.
+                Err(SystemError::InvalidStreamId) => {
+                    warn!(
+                        "Skipped entry at index: {}, the stream doesn't exist.",
+                        entry.index
+                    );
+                }
+                // The ISR has been measured by the previous leader of the stream.
+                Err(SystemError::StaleLeaderEpoch(leader_epoch)) => {
+                    warn!(
+                        "Skipped entry at index: {}, the stream has a newer leader epoch: {leader_epoch}.",
+                        entry.index
+                    );
                 }
-                other => {
-                    warn!("Received an unknown log entry command: {other}",);
-                    return Err(SystemError::InvalidCommand);
+                Err(error) => {
+                    error!("Failed to apply entry at index: {}, {error}", entry.index);
+                    return Ok(Err(error));
                 }
+                Ok(()) => {}
             }
+            *applied_index = entry.index;
+        }
+        self.save_state_snapshot(*applied_index, streams_created)
+            .await?;
+        Ok(Ok(()))
+    }
+
+    /// Saves the snapshot of the state machines at the applied index, which never exceeds the commit index,
+    /// once the threshold of entries has been applied since the previous one. The applied index stays locked
+    /// by the caller, so that no other entries are applied until the snapshot is taken. It's saved right away
+    /// once the streams have been created, so that the restored snapshot contains all the stored streams.
+    async fn save_state_snapshot(
+        &self,
+        index: Index,
+        streams_created: bool,
+    ) -> Result<(), SystemError> {
+        let mut snapshot_index = self.snapshot_index.lock().await;
+        if !streams_created && index < *snapshot_index + self.snapshot_threshold {
+            return Ok(());
         }
+
+        let data = self.state_machines.snapshot().await?;
+        self.metadata
+            .save_state_snapshot(StateSnapshot { index, data })
+            .await?;
+        *snapshot_index = index;
+        info!("Saved state snapshot at index: {index}.");
         Ok(())
     }
 }
+
+/// Returns the command of the entry, or none for the empty entry, which the leader appends at the start of its term.
+fn map_entry(entry: &LogEntry) -> Result<Option<Command>, SystemError> {
+    if entry.data.is_empty() {
+        return Ok(None);
+    }
+
+    command::map_from_bytes(&entry.data).map(Some)
+}
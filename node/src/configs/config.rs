@@ -19,12 +19,28 @@ impl Display for RequiredAcknowledgements {
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq)]
+pub enum MetadataBackend {
+    Files,
+    Sled,
+}
+
+impl Display for MetadataBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetadataBackend::Files => write!(f, "files"),
+            MetadataBackend::Sled => write!(f, "sled"),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub(crate) struct SystemConfig {
     pub node: NodeConfig,
     pub cluster: ClusterConfig,
     pub stream: StreamConfig,
     pub server: ServerConfig,
+    pub metadata: MetadataConfig,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -32,6 +48,13 @@ pub(crate) struct StreamConfig {
     pub path: String,
 }
 
+/// The sled database is kept at the path, the files are kept within the stream directories.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct MetadataConfig {
+    pub backend: MetadataBackend,
+    pub path: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct ServerConfig {
     pub address: String,
@@ -57,6 +80,26 @@ pub(crate) struct ClusterConfig {
     pub election_timeout_range_to: u64,
     pub required_acknowledgements: RequiredAcknowledgements,
     pub state_path: String,
+    pub leadership_transfer_timeout: u64,
+    pub join_existing: bool,
+    pub learner: bool,
+    pub replication_retry_interval: u64,
+    pub commit_timeout: u64,
+    pub max_append_entries: u32,
+    /// The number of append entries requests, which are sent to the node without waiting for the responses.
+    pub max_append_entries_in_flight: u32,
+    pub fetch_interval: u64,
+    pub max_fetch_messages: u64,
+    pub max_replica_lag: u64,
+    pub leader_rebalance_interval: u64,
+    pub bootstrap_chunk_size: u32,
+    pub disk_check_interval: u64,
+    /// The percentage of the disk usage, past which the node warns that it's running out of space.
+    pub disk_soft_limit: u8,
+    /// The percentage of the disk usage, past which the node rejects the writes and the leadership.
+    pub disk_hard_limit: u8,
+    /// The number of the applied entries, after which the state snapshot is saved again.
+    pub snapshot_threshold: u64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -65,6 +108,8 @@ pub(crate) struct ClusterNodeConfig {
     pub name: String,
     pub public_address: String,
     pub internal_address: String,
+    #[serde(default)]
+    pub learner: bool,
 }
 
 impl FromStr for RequiredAcknowledgements {
@@ -87,6 +132,26 @@ impl Default for StreamConfig {
     }
 }
 
+impl FromStr for MetadataBackend {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "files" => Ok(MetadataBackend::Files),
+            "sled" => Ok(MetadataBackend::Sled),
+            _ => Err(format!("Invalid metadata backend value: {}", s)),
+        }
+    }
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            backend: MetadataBackend::Files,
+            path: "local_data/metadata".to_string(),
+        }
+    }
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
@@ -119,6 +184,22 @@ impl Default for ClusterConfig {
             election_timeout_range_to: 300,
             required_acknowledgements: RequiredAcknowledgements::Majority,
             state_path: "local_data/state".to_string(),
+            leadership_transfer_timeout: 5000,
+            join_existing: false,
+            learner: false,
+            replication_retry_interval: 1000,
+            commit_timeout: 1000,
+            max_append_entries: 100,
+            max_append_entries_in_flight: 4,
+            fetch_interval: 100,
+            max_fetch_messages: 1000,
+            max_replica_lag: 2000,
+            leader_rebalance_interval: 30000,
+            bootstrap_chunk_size: 1048576,
+            disk_check_interval: 5000,
+            disk_soft_limit: 80,
+            disk_hard_limit: 95,
+            snapshot_threshold: 1000,
         }
     }
 }
@@ -137,8 +218,8 @@ impl Display for ClusterConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{{ max_timeout: {}, heartbeat_interval: {}, reconnection_interval: {}, reconnection_retries: {}, secret: {}, nodes: {:?}, election_timeout_range_from: {}, election_timeout_range_to: {}, required_acknowledgements: {} }}",
-            self.max_timeout, self.heartbeat_interval, self.reconnection_interval, self.reconnection_retries, self.secret, self.nodes, self.election_timeout_range_from, self.election_timeout_range_to, self.required_acknowledgements
+            "{{ max_timeout: {}, heartbeat_interval: {}, reconnection_interval: {}, reconnection_retries: {}, secret: {}, nodes: {:?}, election_timeout_range_from: {}, election_timeout_range_to: {}, required_acknowledgements: {}, leadership_transfer_timeout: {}, join_existing: {}, learner: {}, replication_retry_interval: {}, commit_timeout: {}, max_append_entries: {}, max_append_entries_in_flight: {}, fetch_interval: {}, max_fetch_messages: {}, max_replica_lag: {}, leader_rebalance_interval: {}, bootstrap_chunk_size: {}, disk_check_interval: {}, disk_soft_limit: {}, disk_hard_limit: {}, snapshot_threshold: {} }}",
+            self.max_timeout, self.heartbeat_interval, self.reconnection_interval, self.reconnection_retries, self.secret, self.nodes, self.election_timeout_range_from, self.election_timeout_range_to, self.required_acknowledgements, self.leadership_transfer_timeout, self.join_existing, self.learner, self.replication_retry_interval, self.commit_timeout, self.max_append_entries, self.max_append_entries_in_flight, self.fetch_interval, self.max_fetch_messages, self.max_replica_lag, self.leader_rebalance_interval, self.bootstrap_chunk_size, self.disk_check_interval, self.disk_soft_limit, self.disk_hard_limit, self.snapshot_threshold
         )
     }
 }
@@ -147,18 +228,24 @@ impl Display for ClusterNodeConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{{ name: {}, address: {} }}",
-            self.name, self.internal_address
+            "{{ name: {}, address: {}, learner: {} }}",
+            self.name, self.internal_address, self.learner
         )
     }
 }
 
+impl Display for MetadataConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{ backend: {}, path: {} }}", self.backend, self.path)
+    }
+}
+
 impl Display for SystemConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Config -> {{ node: {}, cluster: {} }}",
-            self.node, self.cluster
+            "Config -> {{ node: {}, cluster: {}, metadata: {} }}",
+            self.node, self.cluster, self.metadata
         )
     }
 }
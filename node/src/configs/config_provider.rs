@@ -213,7 +213,7 @@ impl FileConfigProvider {
         }
 
         let config_builder = Figment::new();
-        let extension = self.path.split('.').last().unwrap_or("");
+        let extension = self.path.split('.').next_back().unwrap_or("");
         let config_builder = match extension {
             "toml" => config_builder.merge(Toml::file(&self.path)),
             e => {
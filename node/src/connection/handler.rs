@@ -1,7 +1,6 @@
+use crate::environment::Connection;
 use crate::types::NodeId;
 use bytes::BufMut;
-use monoio::io::{AsyncReadRent, AsyncWriteRentExt};
-use monoio::net::TcpStream;
 use sdk::commands::command::Command;
 use sdk::error::SystemError;
 use std::net::SocketAddr;
@@ -13,13 +12,13 @@ const EMPTY_BYTES: Vec<u8> = vec![];
 
 #[derive(Debug)]
 pub(crate) struct ConnectionHandler {
-    stream: TcpStream,
+    stream: Box<dyn Connection>,
     pub address: SocketAddr,
     pub node_id: NodeId,
 }
 
 impl ConnectionHandler {
-    pub fn new(stream: TcpStream, address: SocketAddr, node_id: NodeId) -> Self {
+    pub fn new(stream: Box<dyn Connection>, address: SocketAddr, node_id: NodeId) -> Self {
         Self {
             stream,
             address,
@@ -74,6 +73,42 @@ impl ConnectionHandler {
         Ok(())
     }
 
+    /// Writes all the requests before reading any response, the responses are read in the order of the requests,
+    /// as the remote node handles the requests of the connection one by one.
+    pub async fn send_requests(
+        &mut self,
+        commands: &[Command],
+    ) -> Vec<Result<(usize, Vec<u8>), SystemError>> {
+        let mut sent = 0;
+        let mut results = Vec::with_capacity(commands.len());
+        for command in commands {
+            if let Err(error) = self.stream.write_all(command.as_bytes()).await {
+                error!("Failed to send a request: {error}");
+                break;
+            }
+            sent += 1;
+        }
+
+        for _ in 0..sent {
+            let result = self.read_response().await;
+            // The responses which follow the unreadable one cannot be matched with the requests anymore.
+            let failed = match &result {
+                Ok(_) => false,
+                Err(SystemError::InvalidResponse(status, _)) => *status == 0,
+                Err(_) => true,
+            };
+            results.push(result);
+            if failed {
+                break;
+            }
+        }
+
+        while results.len() < commands.len() {
+            results.push(Err(SystemError::InvalidResponse(0, None)));
+        }
+        results
+    }
+
     async fn send(
         &mut self,
         payload: Vec<u8>,
@@ -81,16 +116,17 @@ impl ConnectionHandler {
     ) -> Result<(usize, Vec<u8>), SystemError> {
         let payload_length = payload.len();
         debug!("Sending data with payload length: {payload_length}...");
-        let result = self.stream.write_all(payload).await;
-        if result.0.is_err() {
-            return Err(SystemError::from(result.0.unwrap_err()));
-        }
+        self.stream.write_all(payload).await?;
 
         debug!("Sent data with payload length: {payload_length}.");
         if !read_response {
             return Ok((0, EMPTY_BYTES));
         }
 
+        self.read_response().await
+    }
+
+    async fn read_response(&mut self) -> Result<(usize, Vec<u8>), SystemError> {
         let buffer = vec![0u8; RESPONSE_INITIAL_BYTES_LENGTH];
         let (read_bytes, buffer) = self.stream.read(buffer).await;
         if read_bytes.is_err() {
@@ -0,0 +1,248 @@
+use crate::environment::{DiskUsage, Storage};
+use futures::future::LocalBoxFuture;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::Path;
+
+/// The storage kept in memory, which outlives the node using it, so that the node can be restarted
+/// with the same data. The writes and removals can be made to fail, in order to simulate the faulty disk, and the disk
+/// capacity can be limited, in order to simulate the full one.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryStorage {
+    directories: RefCell<BTreeSet<String>>,
+    files: RefCell<BTreeMap<String, Vec<u8>>>,
+    write_fault: Cell<Option<WriteFault>>,
+    capacity: Cell<Option<u64>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WriteFault {
+    /// The write fails without changing the file.
+    Failed,
+    /// Only the first half of the bytes is written before the write fails.
+    Torn,
+}
+
+impl MemoryStorage {
+    /// All the following writes fail in the same way, until the fault is removed.
+    pub fn set_write_fault(&self, write_fault: Option<WriteFault>) {
+        self.write_fault.set(write_fault);
+    }
+
+    /// The disk usage is reported against the capacity, the writes don't fail once it's exceeded.
+    pub fn set_capacity(&self, capacity: Option<u64>) {
+        self.capacity.set(capacity);
+    }
+
+    /// Removes all the directories and files, as if the disk was replaced.
+    pub fn wipe(&self) {
+        self.directories.borrow_mut().clear();
+        self.files.borrow_mut().clear();
+    }
+
+    fn has_parent(&self, path: &str) -> bool {
+        match Path::new(path).parent().and_then(Path::to_str) {
+            None | Some("") | Some("/") => true,
+            Some(parent) => self.directories.borrow().contains(parent),
+        }
+    }
+
+    fn write(
+        &self,
+        path: &str,
+        position: u64,
+        mut bytes: Vec<u8>,
+        truncate: bool,
+    ) -> io::Result<()> {
+        if !self.has_parent(path) {
+            return Err(not_found(path));
+        }
+
+        let torn = match self.write_fault.get() {
+            Some(WriteFault::Failed) => return Err(write_fault()),
+            Some(WriteFault::Torn) => {
+                bytes.truncate(bytes.len() / 2);
+                true
+            }
+            None => false,
+        };
+
+        let mut files = self.files.borrow_mut();
+        let file = files.entry(path.to_string()).or_default();
+        if truncate {
+            file.clear();
+        }
+        let position = position as usize;
+        if file.len() < position + bytes.len() {
+            file.resize(position + bytes.len(), 0);
+        }
+        file[position..position + bytes.len()].copy_from_slice(&bytes);
+        if torn {
+            return Err(write_fault());
+        }
+
+        Ok(())
+    }
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("Not found: {path}"))
+}
+
+fn write_fault() -> io::Error {
+    io::Error::other("Injected write fault.")
+}
+
+impl Storage for MemoryStorage {
+    fn exists(&self, path: &str) -> bool {
+        self.directories.borrow().contains(path) || self.files.borrow().contains_key(path)
+    }
+
+    fn create_dir_all(&self, path: &str) -> io::Result<()> {
+        let mut directories = self.directories.borrow_mut();
+        for directory in Path::new(path).ancestors().filter_map(Path::to_str) {
+            if !directory.is_empty() && directory != "/" {
+                directories.insert(directory.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &str) -> io::Result<()> {
+        if self.write_fault.get().is_some() {
+            return Err(write_fault());
+        }
+
+        if !self.directories.borrow_mut().remove(path) {
+            return Err(not_found(path));
+        }
+
+        let prefix = format!("{path}/");
+        self.directories
+            .borrow_mut()
+            .retain(|directory| !directory.starts_with(&prefix));
+        self.files
+            .borrow_mut()
+            .retain(|file, _| !file.starts_with(&prefix));
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &str) -> io::Result<()> {
+        if self.write_fault.get().is_some() {
+            return Err(write_fault());
+        }
+
+        self.files
+            .borrow_mut()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn read_dir(&self, path: &str) -> io::Result<Vec<String>> {
+        if !self.directories.borrow().contains(path) {
+            return Err(not_found(path));
+        }
+
+        Ok(self
+            .directories
+            .borrow()
+            .iter()
+            .filter(|directory| Path::new(directory).parent() == Some(Path::new(path)))
+            .filter_map(|directory| Path::new(directory).file_name()?.to_str())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn size(&self, path: &str) -> io::Result<u64> {
+        self.files
+            .borrow()
+            .get(path)
+            .map(|file| file.len() as u64)
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn read(&self, path: &str) -> LocalBoxFuture<'_, io::Result<Vec<u8>>> {
+        let result = self
+            .files
+            .borrow()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| not_found(path));
+        Box::pin(async move { result })
+    }
+
+    fn read_at(
+        &self,
+        path: &str,
+        position: u64,
+        length: usize,
+    ) -> LocalBoxFuture<'_, io::Result<Vec<u8>>> {
+        let result = self
+            .files
+            .borrow()
+            .get(path)
+            .ok_or_else(|| not_found(path))
+            .and_then(|file| {
+                let position = position as usize;
+                file.get(position..position + length)
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+            });
+        Box::pin(async move { result })
+    }
+
+    fn write_at(
+        &self,
+        path: &str,
+        position: u64,
+        bytes: Vec<u8>,
+    ) -> LocalBoxFuture<'_, io::Result<()>> {
+        let result = self.write(path, position, bytes, false);
+        Box::pin(async move { result })
+    }
+
+    /// The written bytes are kept in memory, so only the faulty disk fails to sync them.
+    fn sync(&self, path: &str) -> LocalBoxFuture<'_, io::Result<()>> {
+        let result = if !self.files.borrow().contains_key(path) {
+            Err(not_found(path))
+        } else if self.write_fault.get().is_some() {
+            Err(write_fault())
+        } else {
+            Ok(())
+        };
+        Box::pin(async move { result })
+    }
+
+    /// The faulty write leaves the temporary file behind, without changing the replaced one.
+    fn replace(&self, path: &str, bytes: Vec<u8>) -> LocalBoxFuture<'_, io::Result<()>> {
+        let temporary_path = format!("{path}.tmp");
+        let result = self.write(&temporary_path, 0, bytes, true).map(|_| {
+            let mut files = self.files.borrow_mut();
+            let bytes = files.remove(&temporary_path).unwrap_or_default();
+            files.insert(path.to_string(), bytes);
+        });
+        Box::pin(async move { result })
+    }
+
+    fn truncate(&self, path: &str, size: u64) -> io::Result<()> {
+        let mut files = self.files.borrow_mut();
+        let file = files.get_mut(path).ok_or_else(|| not_found(path))?;
+        file.truncate(size as usize);
+        Ok(())
+    }
+
+    fn disk_usage(&self, _path: &str) -> io::Result<DiskUsage> {
+        let used_bytes = self
+            .files
+            .borrow()
+            .values()
+            .map(|file| file.len() as u64)
+            .sum();
+        Ok(DiskUsage {
+            used_bytes,
+            total_bytes: self.capacity.get().unwrap_or(u64::MAX),
+        })
+    }
+}
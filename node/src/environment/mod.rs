@@ -0,0 +1,267 @@
+#[cfg(test)]
+pub(crate) mod memory;
+mod system;
+
+use futures::future::{select, Either, LocalBoxFuture};
+use std::cell::{Cell, RefCell};
+use std::fmt::Debug;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::{pin, Pin};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// The source of time, the simulated one is advanced only by the simulator.
+pub(crate) trait Clock: Debug {
+    fn now(&self) -> Instant;
+    /// The microseconds since the UNIX epoch.
+    fn timestamp(&self) -> u64;
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()>;
+}
+
+pub(crate) trait Random: Debug {
+    /// Returns the random number within the inclusive range.
+    fn gen_range(&self, from: u64, to: u64) -> u64;
+}
+
+pub(crate) trait Network: Debug {
+    fn connect(
+        &self,
+        address: SocketAddr,
+    ) -> LocalBoxFuture<'static, io::Result<Box<dyn Connection>>>;
+    fn bind(&self, address: &str) -> io::Result<Box<dyn Listener>>;
+}
+
+pub(crate) trait Connection: Debug {
+    fn read(&mut self, buffer: Vec<u8>) -> LocalBoxFuture<'_, (io::Result<usize>, Vec<u8>)>;
+    fn write_all(&mut self, buffer: Vec<u8>) -> LocalBoxFuture<'_, io::Result<()>>;
+    fn peer_address(&self) -> io::Result<SocketAddr>;
+}
+
+pub(crate) trait Listener: Debug {
+    fn accept(&self) -> LocalBoxFuture<'_, io::Result<(Box<dyn Connection>, SocketAddr)>>;
+}
+
+/// The directories and files of the logs and the metadata.
+pub(crate) trait Storage: Debug {
+    fn exists(&self, path: &str) -> bool;
+    fn create_dir_all(&self, path: &str) -> io::Result<()>;
+    fn remove_dir_all(&self, path: &str) -> io::Result<()>;
+    fn remove_file(&self, path: &str) -> io::Result<()>;
+    /// Returns the names of the directories within the directory.
+    fn read_dir(&self, path: &str) -> io::Result<Vec<String>>;
+    fn size(&self, path: &str) -> io::Result<u64>;
+    fn read(&self, path: &str) -> LocalBoxFuture<'_, io::Result<Vec<u8>>>;
+    /// Reads exactly the length of bytes at the position, so that the part of the large file is read alone.
+    fn read_at(
+        &self,
+        path: &str,
+        position: u64,
+        length: usize,
+    ) -> LocalBoxFuture<'_, io::Result<Vec<u8>>>;
+    /// Writes the bytes at the position, the file is created if it doesn't exist.
+    fn write_at(
+        &self,
+        path: &str,
+        position: u64,
+        bytes: Vec<u8>,
+    ) -> LocalBoxFuture<'_, io::Result<()>>;
+    /// Flushes the bytes written to the file to the disk, as the writes aren't durable until then.
+    fn sync(&self, path: &str) -> LocalBoxFuture<'_, io::Result<()>>;
+    /// Replaces the file atomically and durably, so that either the previous or the new content is found
+    /// after a crash, the bytes are synced to a temporary file first, which is then renamed to the path.
+    fn replace(&self, path: &str, bytes: Vec<u8>) -> LocalBoxFuture<'_, io::Result<()>>;
+    fn truncate(&self, path: &str, size: u64) -> io::Result<()>;
+    /// Returns the usage of the disk, which contains the path or its closest existing ancestor.
+    fn disk_usage(&self, path: &str) -> io::Result<DiskUsage>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DiskUsage {
+    pub used_bytes: u64,
+    pub total_bytes: u64,
+}
+
+impl DiskUsage {
+    pub fn percentage(&self) -> u8 {
+        if self.total_bytes == 0 {
+            return 100;
+        }
+
+        (self.used_bytes.min(self.total_bytes) as u128 * 100 / self.total_bytes as u128) as u8
+    }
+}
+
+/// The network, clock, randomness and storage used by the node. The tasks spawned within the environment
+/// see it as the current one, so that many nodes can run in a single process when simulated.
+#[derive(Debug, Clone)]
+pub(crate) struct Environment {
+    pub clock: Rc<dyn Clock>,
+    pub network: Rc<dyn Network>,
+    pub random: Rc<dyn Random>,
+    pub storage: Rc<dyn Storage>,
+    stopped: Rc<Cell<bool>>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Environment>> = const { RefCell::new(None) };
+    static SYSTEM: Environment = Environment::system();
+    static POLLS: Cell<u64> = const { Cell::new(0) };
+}
+
+impl Environment {
+    pub fn new(
+        clock: Rc<dyn Clock>,
+        network: Rc<dyn Network>,
+        random: Rc<dyn Random>,
+        storage: Rc<dyn Storage>,
+    ) -> Self {
+        Self {
+            clock,
+            network,
+            random,
+            storage,
+            stopped: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn system() -> Self {
+        Self::new(
+            Rc::new(system::SystemClock),
+            Rc::new(system::TcpNetwork),
+            Rc::new(system::ThreadRandom),
+            Rc::new(system::FileStorage::default()),
+        )
+    }
+
+    #[cfg(test)]
+    pub fn with_storage(mut self, storage: Rc<dyn Storage>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Makes the environment the current one outside the spawned tasks, until the guard is dropped.
+    #[cfg(test)]
+    pub fn enter(&self) -> EnterGuard {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        EnterGuard { previous }
+    }
+
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        monoio::spawn(Scoped {
+            environment: self.clone(),
+            future: Box::pin(future),
+        });
+    }
+
+    /// The tasks spawned within the stopped environment complete, once they're polled again.
+    #[cfg(test)]
+    pub fn stop(&self) {
+        self.stopped.set(true);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.get()
+    }
+}
+
+#[cfg(test)]
+pub(crate) struct EnterGuard {
+    previous: Option<Environment>,
+}
+
+#[cfg(test)]
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| current.replace(previous));
+    }
+}
+
+struct Scoped<F> {
+    environment: Environment,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future<Output = ()>> Future for Scoped<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.environment.is_stopped() {
+            return Poll::Ready(());
+        }
+
+        POLLS.with(|polls| polls.set(polls.get() + 1));
+        let environment = self.environment.clone();
+        let previous = CURRENT.with(|current| current.replace(Some(environment)));
+        let poll = self.future.as_mut().poll(cx);
+        CURRENT.with(|current| current.replace(previous));
+        poll
+    }
+}
+
+/// Returns the environment of the running task, or the system one outside the spawned tasks.
+pub(crate) fn current() -> Environment {
+    CURRENT
+        .with(|current| current.borrow().clone())
+        .unwrap_or_else(|| SYSTEM.with(Environment::clone))
+}
+
+/// The number of times the tasks spawned within any environment have been polled, used to detect they're idle.
+#[cfg(test)]
+pub(crate) fn polls() -> u64 {
+    POLLS.with(Cell::get)
+}
+
+pub(crate) fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    current().spawn(future);
+}
+
+pub(crate) fn now() -> Instant {
+    current().clock.now()
+}
+
+pub(crate) fn elapsed(since: Instant) -> Duration {
+    now().saturating_duration_since(since)
+}
+
+pub(crate) fn timestamp() -> u64 {
+    current().clock.timestamp()
+}
+
+pub(crate) fn sleep(duration: Duration) -> LocalBoxFuture<'static, ()> {
+    current().clock.sleep(duration)
+}
+
+/// Returns none if the future hasn't completed within the duration.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    let future = pin!(future);
+    match select(future, sleep(duration)).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+pub(crate) fn gen_range(from: u64, to: u64) -> u64 {
+    current().random.gen_range(from, to)
+}
+
+pub(crate) async fn connect(address: SocketAddr) -> io::Result<Box<dyn Connection>> {
+    current().network.connect(address).await
+}
+
+pub(crate) fn bind(address: &str) -> io::Result<Box<dyn Listener>> {
+    current().network.bind(address)
+}
+
+pub(crate) fn storage() -> Rc<dyn Storage> {
+    current().storage
+}
@@ -0,0 +1,264 @@
+use crate::environment::{Clock, Connection, DiskUsage, Listener, Network, Random, Storage};
+use futures::future::LocalBoxFuture;
+use monoio::fs::{File, OpenOptions};
+use monoio::io::{AsyncReadRent, AsyncWriteRentExt};
+use monoio::net::{TcpListener, TcpStream};
+use rand::Rng;
+use sdk::timestamp::TimeStamp;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+use std::mem::MaybeUninit;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn timestamp(&self) -> u64 {
+        TimeStamp::now().to_micros()
+    }
+
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+        Box::pin(monoio::time::sleep(duration))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ThreadRandom;
+
+impl Random for ThreadRandom {
+    fn gen_range(&self, from: u64, to: u64) -> u64 {
+        rand::thread_rng().gen_range(from..=to)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TcpNetwork;
+
+impl Network for TcpNetwork {
+    fn connect(
+        &self,
+        address: SocketAddr,
+    ) -> LocalBoxFuture<'static, io::Result<Box<dyn Connection>>> {
+        Box::pin(async move {
+            let stream = TcpStream::connect(address).await?;
+            Ok(Box::new(TcpConnection(stream)) as Box<dyn Connection>)
+        })
+    }
+
+    fn bind(&self, address: &str) -> io::Result<Box<dyn Listener>> {
+        Ok(Box::new(TcpConnectionListener(TcpListener::bind(address)?)))
+    }
+}
+
+#[derive(Debug)]
+struct TcpConnection(TcpStream);
+
+impl Connection for TcpConnection {
+    fn read(&mut self, buffer: Vec<u8>) -> LocalBoxFuture<'_, (io::Result<usize>, Vec<u8>)> {
+        Box::pin(self.0.read(buffer))
+    }
+
+    fn write_all(&mut self, buffer: Vec<u8>) -> LocalBoxFuture<'_, io::Result<()>> {
+        Box::pin(async move { self.0.write_all(buffer).await.0.map(|_| ()) })
+    }
+
+    fn peer_address(&self) -> io::Result<SocketAddr> {
+        self.0.peer_addr()
+    }
+}
+
+#[derive(Debug)]
+struct TcpConnectionListener(TcpListener);
+
+impl Listener for TcpConnectionListener {
+    fn accept(&self) -> LocalBoxFuture<'_, io::Result<(Box<dyn Connection>, SocketAddr)>> {
+        Box::pin(async move {
+            let (stream, address) = self.0.accept().await?;
+            Ok((
+                Box::new(TcpConnection(stream)) as Box<dyn Connection>,
+                address,
+            ))
+        })
+    }
+}
+
+/// The files written to are kept open, as the log is appended to and synced on every entry.
+#[derive(Debug, Default)]
+pub(crate) struct FileStorage {
+    files: RefCell<HashMap<String, Rc<File>>>,
+}
+
+impl FileStorage {
+    async fn open_file(&self, path: &str, create: bool) -> io::Result<Rc<File>> {
+        if let Some(file) = self.files.borrow().get(path) {
+            return Ok(file.clone());
+        }
+
+        let file = OpenOptions::new()
+            .create(create)
+            .write(true)
+            .open(path)
+            .await?;
+        let file = Rc::new(file);
+        self.files
+            .borrow_mut()
+            .insert(path.to_string(), file.clone());
+        Ok(file)
+    }
+
+    fn close_file(&self, path: &str) {
+        self.files.borrow_mut().remove(path);
+    }
+}
+
+impl Storage for FileStorage {
+    fn exists(&self, path: &str) -> bool {
+        Path::new(path).exists()
+    }
+
+    fn create_dir_all(&self, path: &str) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &str) -> io::Result<()> {
+        let directory = format!("{}/", path.trim_end_matches('/'));
+        self.files
+            .borrow_mut()
+            .retain(|file_path, _| !file_path.starts_with(&directory));
+        std::fs::remove_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &str) -> io::Result<()> {
+        self.close_file(path);
+        std::fs::remove_file(path)
+    }
+
+    fn read_dir(&self, path: &str) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    fn size(&self, path: &str) -> io::Result<u64> {
+        Ok(std::fs::metadata(path)?.len())
+    }
+
+    fn read(&self, path: &str) -> LocalBoxFuture<'_, io::Result<Vec<u8>>> {
+        let path = path.to_string();
+        Box::pin(async move {
+            let file = OpenOptions::new().read(true).open(&path).await?;
+            let size = std::fs::metadata(&path)?.len() as usize;
+            let (result, buffer) = file.read_exact_at(vec![0u8; size], 0).await;
+            result?;
+            Ok(buffer)
+        })
+    }
+
+    fn read_at(
+        &self,
+        path: &str,
+        position: u64,
+        length: usize,
+    ) -> LocalBoxFuture<'_, io::Result<Vec<u8>>> {
+        let path = path.to_string();
+        Box::pin(async move {
+            let file = OpenOptions::new().read(true).open(&path).await?;
+            let (result, buffer) = file.read_exact_at(vec![0u8; length], position).await;
+            result?;
+            Ok(buffer)
+        })
+    }
+
+    fn write_at(
+        &self,
+        path: &str,
+        position: u64,
+        bytes: Vec<u8>,
+    ) -> LocalBoxFuture<'_, io::Result<()>> {
+        let path = path.to_string();
+        Box::pin(async move {
+            let file = self.open_file(&path, true).await?;
+            file.write_all_at(bytes, position).await.0
+        })
+    }
+
+    fn sync(&self, path: &str) -> LocalBoxFuture<'_, io::Result<()>> {
+        let path = path.to_string();
+        Box::pin(async move {
+            let file = self.open_file(&path, false).await?;
+            file.sync_all().await
+        })
+    }
+
+    fn replace(&self, path: &str, bytes: Vec<u8>) -> LocalBoxFuture<'_, io::Result<()>> {
+        let path = path.to_string();
+        Box::pin(async move {
+            let temporary_path = format!("{path}.tmp");
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&temporary_path)
+                .await?;
+            file.write_all_at(bytes, 0).await.0?;
+            file.sync_all().await?;
+            file.close().await?;
+            // The open file is replaced by the renamed one, so it's closed rather than written to anymore.
+            self.close_file(&path);
+            std::fs::rename(&temporary_path, &path)?;
+            // The rename itself is durable only once the directory has been synced.
+            if let Some(directory) = Path::new(&path).parent() {
+                if !directory.as_os_str().is_empty() {
+                    std::fs::File::open(directory)?.sync_all()?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn truncate(&self, path: &str, size: u64) -> io::Result<()> {
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_len(size)
+    }
+
+    fn disk_usage(&self, path: &str) -> io::Result<DiskUsage> {
+        let path = Path::new(path)
+            .ancestors()
+            .find(|path| !path.as_os_str().is_empty() && path.exists())
+            .unwrap_or(Path::new("."));
+        let path = CString::new(path.to_string_lossy().as_bytes())?;
+        let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+        // SAFETY: the path is a valid C string and the stats are initialized once the call succeeds.
+        let stats = unsafe {
+            if libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            stats.assume_init()
+        };
+        let block_size = stats.f_frsize as u64;
+        let total_bytes = stats.f_blocks as u64 * block_size;
+        // The blocks reserved for the root are counted as used, as the node can't write to them.
+        let available_bytes = stats.f_bavail as u64 * block_size;
+        Ok(DiskUsage {
+            used_bytes: total_bytes.saturating_sub(available_bytes),
+            total_bytes,
+        })
+    }
+}
@@ -0,0 +1,16 @@
+use crate::clusters::cluster::Cluster;
+use crate::connection::handler::ConnectionHandler;
+use sdk::commands::add_node::AddNode;
+use sdk::error::SystemError;
+use std::rc::Rc;
+
+pub(crate) async fn handle(
+    handler: &mut ConnectionHandler,
+    command: &AddNode,
+    cluster: Rc<Cluster>,
+) -> Result<(), SystemError> {
+    cluster.verify_is_healthy().await?;
+    cluster.verify_accepts_writes().await?;
+    let term = cluster.election_manager.get_current_term().await;
+    cluster.add_node(handler, term, command.clone()).await
+}
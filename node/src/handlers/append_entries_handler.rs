@@ -13,10 +13,13 @@ pub(crate) async fn handle(
     cluster.verify_is_healthy().await?;
     info!("Received append entries command.",);
     cluster
-        .can_sync_state(command.leader_commit, command.prev_log_index)
-        .await?;
-    cluster
-        .replay_state(Some(command.term), &command.entries)
+        .append_entries(
+            command.term,
+            command.prev_log_index,
+            command.prev_log_term,
+            command.leader_commit,
+            &command.entries,
+        )
         .await?;
     handler.send_empty_ok_response().await?;
     info!("Sent an append entries response.");
@@ -1,4 +1,5 @@
 use crate::clusters::cluster::Cluster;
+use crate::configs::config::RequiredAcknowledgements;
 use crate::connection::handler::ConnectionHandler;
 use sdk::commands::append_messages::AppendMessages;
 use sdk::error::SystemError;
@@ -11,46 +12,45 @@ pub(crate) async fn handle(
     cluster: Rc<Cluster>,
 ) -> Result<(), SystemError> {
     cluster.verify_is_healthy().await?;
-    cluster.verify_is_leader().await?;
+    cluster.verify_is_not_fenced().await?;
+    cluster.verify_has_disk_space().await?;
+    cluster.verify_is_stream_leader(command.stream_id).await?;
+    let majority_required = cluster.required_acknowledgements == RequiredAcknowledgements::Majority;
+    if majority_required {
+        cluster.verify_in_sync_replicas(command.stream_id).await?;
+    }
+
     let term = cluster.election_manager.get_current_term().await;
-    let appended_messages = cluster
+    let log_end_offset = cluster
         .append_messages(term, command.stream_id, &command.messages)
         .await?;
-    if cluster
-        .sync_appended_messages(
-            handler,
-            term,
-            command.stream_id,
-            &appended_messages.uncommited_messages,
-        )
-        .await
-        .is_err()
+    if majority_required
+        && !cluster
+            .wait_for_high_watermark(command.stream_id, log_end_offset)
+            .await
     {
-        cluster
-            .reset_offset(command.stream_id, appended_messages.previous_offset)
-            .await;
         error!(
-            "Failed to sync appended messages for stream with ID: {}.",
+            "Appended messages for stream with ID: {} have not been replicated, log end offset: {log_end_offset}.",
             command.stream_id
         );
-        return Ok(());
+        return Err(SystemError::CannotSyncAppendedMessages);
     }
-    if cluster
-        .commit_messages(
-            term,
-            command.stream_id,
-            appended_messages.uncommited_messages,
-        )
-        .await
-        .is_err()
+
+    // The ISR might have shrunk while the messages were being replicated, so they could've been committed
+    // by fewer replicas than required. They're stored already, so the error must not make the client resend them.
+    if majority_required
+        && cluster
+            .verify_in_sync_replicas(command.stream_id)
+            .await
+            .is_err()
     {
-        cluster
-            .reset_offset(command.stream_id, appended_messages.previous_offset)
-            .await;
         error!(
-            "Failed to commit messages for stream with ID: {}.",
+            "Appended messages for stream with ID: {} have been committed by too few in-sync replicas, log end offset: {log_end_offset}.",
             command.stream_id
         );
+        return Err(SystemError::CannotSyncAppendedMessages);
     }
+
+    handler.send_empty_ok_response().await?;
     Ok(())
 }
@@ -10,16 +10,11 @@ pub(crate) async fn handle(
     cluster: Rc<Cluster>,
 ) -> Result<(), SystemError> {
     cluster.verify_is_healthy().await?;
-    cluster.verify_is_leader().await?;
+    cluster.verify_accepts_writes().await?;
+    cluster.verify_has_disk_space().await?;
     let term = cluster.election_manager.get_current_term().await;
-    cluster
-        .create_stream(
-            Some(term),
-            command.id,
-            command.replication_factor.unwrap_or(3),
-        )
-        .await?;
-    cluster
-        .sync_created_stream(handler, term, command.id, command.replication_factor)
-        .await
+    // The stream is created only once the previous creation has been applied, so its ID cannot be taken twice.
+    let _stream_creation = cluster.stream_creation.lock().await;
+    let command = cluster.create_stream(Some(term), command).await?;
+    cluster.sync_created_stream(handler, term, command).await
 }
@@ -10,8 +10,7 @@ pub(crate) async fn handle(
     cluster: Rc<Cluster>,
 ) -> Result<(), SystemError> {
     cluster.verify_is_healthy().await?;
-    cluster.verify_is_leader().await?;
+    cluster.verify_accepts_writes().await?;
     let term = cluster.election_manager.get_current_term().await;
-    cluster.delete_stream(Some(term), command.id).await?;
     cluster.sync_deleted_stream(handler, term, command.id).await
 }
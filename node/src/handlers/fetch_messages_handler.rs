@@ -0,0 +1,28 @@
+use crate::clusters::cluster::Cluster;
+use crate::connection::handler::ConnectionHandler;
+use sdk::bytes_serializable::BytesSerializable;
+use sdk::commands::fetch_messages::FetchMessages;
+use sdk::error::SystemError;
+use std::rc::Rc;
+
+pub(crate) async fn handle(
+    handler: &mut ConnectionHandler,
+    command: &FetchMessages,
+    cluster: Rc<Cluster>,
+) -> Result<(), SystemError> {
+    cluster.verify_is_stream_leader(command.stream_id).await?;
+    let fetched_messages = cluster
+        .fetch_messages(
+            handler.node_id,
+            command.term,
+            command.stream_id,
+            command.leader_epoch,
+            command.offset,
+            command.count,
+        )
+        .await?;
+    handler
+        .send_ok_response(&fetched_messages.as_bytes())
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use crate::clusters::cluster::Cluster;
+use crate::connection::handler::ConnectionHandler;
+use sdk::bytes_serializable::BytesSerializable;
+use sdk::commands::get_epoch_end_offset::GetEpochEndOffset;
+use sdk::error::SystemError;
+use std::rc::Rc;
+
+pub(crate) async fn handle(
+    handler: &mut ConnectionHandler,
+    command: &GetEpochEndOffset,
+    cluster: Rc<Cluster>,
+) -> Result<(), SystemError> {
+    cluster.verify_is_stream_leader(command.stream_id).await?;
+    let epoch_end_offset = cluster
+        .get_epoch_end_offset(
+            command.stream_id,
+            command.current_leader_epoch,
+            command.leader_epoch,
+        )
+        .await?;
+    handler
+        .send_ok_response(&epoch_end_offset.as_bytes())
+        .await?;
+    Ok(())
+}
@@ -1,14 +1,21 @@
 use crate::clusters::cluster::Cluster;
 use crate::connection::handler::ConnectionHandler;
 use sdk::bytes_serializable::BytesSerializable;
+use sdk::commands::get_metadata::GetMetadata;
 use sdk::error::SystemError;
+use sdk::models::read_consistency::ReadConsistency;
 use std::rc::Rc;
 
 pub(crate) async fn handle(
     handler: &mut ConnectionHandler,
+    command: &GetMetadata,
     cluster: Rc<Cluster>,
 ) -> Result<(), SystemError> {
     cluster.verify_is_healthy().await?;
+    if command.consistency == ReadConsistency::Linearizable {
+        cluster.confirm_leadership().await?;
+    }
+
     let metadata = cluster.get_metadata().await;
     handler.send_ok_response(&metadata.as_bytes()).await?;
     Ok(())
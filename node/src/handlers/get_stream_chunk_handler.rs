@@ -0,0 +1,19 @@
+use crate::clusters::cluster::Cluster;
+use crate::connection::handler::ConnectionHandler;
+use sdk::bytes_serializable::BytesSerializable;
+use sdk::commands::get_stream_chunk::GetStreamChunk;
+use sdk::error::SystemError;
+use std::rc::Rc;
+
+pub(crate) async fn handle(
+    handler: &mut ConnectionHandler,
+    command: &GetStreamChunk,
+    cluster: Rc<Cluster>,
+) -> Result<(), SystemError> {
+    cluster.verify_is_stream_leader(command.stream_id).await?;
+    let stream_chunk = cluster
+        .get_stream_chunk(command.stream_id, command.offset, command.max_bytes)
+        .await?;
+    handler.send_ok_response(&stream_chunk.as_bytes()).await?;
+    Ok(())
+}
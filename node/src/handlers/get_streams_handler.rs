@@ -1,14 +1,21 @@
 use crate::clusters::cluster::Cluster;
 use crate::connection::handler::ConnectionHandler;
 use sdk::bytes_serializable::BytesSerializable;
+use sdk::commands::get_streams::GetStreams;
 use sdk::error::SystemError;
+use sdk::models::read_consistency::ReadConsistency;
 use std::rc::Rc;
 
 pub(crate) async fn handle(
     handler: &mut ConnectionHandler,
+    command: &GetStreams,
     cluster: Rc<Cluster>,
 ) -> Result<(), SystemError> {
     cluster.verify_is_healthy().await?;
+    if command.consistency == ReadConsistency::Linearizable {
+        cluster.confirm_leadership().await?;
+    }
+
     let streams = cluster.get_streams().await?;
     let mut bytes: Vec<u8> = Vec::new();
     for stream in streams {
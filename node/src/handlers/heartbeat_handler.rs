@@ -1,29 +1,63 @@
 use crate::clusters::cluster::Cluster;
 use crate::connection::handler::ConnectionHandler;
+use crate::environment;
 use sdk::commands::heartbeat::Heartbeat;
 use sdk::error::SystemError;
-use sdk::timestamp::TimeStamp;
+use sdk::models::node_state::DiskStatus;
 use std::rc::Rc;
+use tracing::warn;
 
 pub(crate) async fn handle(
     handler: &mut ConnectionHandler,
     command: &Heartbeat,
     cluster: Rc<Cluster>,
 ) -> Result<(), SystemError> {
+    // The acknowledgements confirm the leadership, so only the members of the current term acknowledge it.
+    if cluster.get_node(handler.node_id).await.is_none() {
+        warn!(
+            "Rejected a heartbeat from node ID: {}, which is not a cluster member.",
+            handler.node_id
+        );
+        return Err(SystemError::NotClusterMember(handler.node_id));
+    }
+
+    let current_term = cluster.election_manager.get_current_term().await;
+    if command.term < current_term {
+        warn!(
+            "Rejected a heartbeat from node ID: {} in term: {}, current term is: {current_term}.",
+            handler.node_id, command.term
+        );
+        return Err(SystemError::InvalidTerm(current_term));
+    }
+
     cluster.election_manager.set_term(command.term).await;
     if let Some(leader_id) = command.leader_id {
         cluster.set_leader(command.term, leader_id).await;
+        // The leader of the current term caps the commit index by the last entry matching its own log.
+        let is_current_leader = cluster.election_manager.get_current_term().await == command.term
+            && cluster.election_manager.get_leader_id().await == Some(handler.node_id);
+        if leader_id == handler.node_id && is_current_leader {
+            cluster
+                .commit_state(command.term, command.commit_index)
+                .await?;
+        }
     }
 
+    // Only the heartbeats from the leader itself prove that it's still alive.
     if let Some(leader_id) = cluster.election_manager.get_leader_id().await {
-        if leader_id != cluster.get_self_node().unwrap().node.id {
+        if leader_id == handler.node_id {
             cluster
                 .election_manager
-                .set_last_heartbeat(TimeStamp::now().to_micros())
+                .set_last_heartbeat(environment::timestamp())
                 .await;
         }
     }
 
+    // The heartbeat is still handled, but the rejection lets the other nodes know this one is fenced or full.
+    cluster.verify_is_not_fenced().await?;
+    if cluster.get_disk_status().await == DiskStatus::HardLimitExceeded {
+        return Err(SystemError::InsufficientDiskSpace);
+    }
     handler.send_empty_ok_response().await?;
     Ok(())
 }
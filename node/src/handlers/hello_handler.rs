@@ -3,7 +3,7 @@ use crate::connection::handler::ConnectionHandler;
 use sdk::commands::hello::Hello;
 use sdk::error::SystemError;
 use std::rc::Rc;
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 
 pub(crate) async fn handle(
     handler: &mut ConnectionHandler,
@@ -15,9 +15,6 @@ pub(crate) async fn handle(
             "Invalid cluster secret: {} from node ID: {}.",
             command.secret, command.node_id
         );
-        handler
-            .send_error_response(SystemError::InvalidClusterSecret)
-            .await?;
         return Err(SystemError::InvalidClusterSecret);
     }
 
@@ -26,9 +23,22 @@ pub(crate) async fn handle(
         command.node_id
     );
     handler.node_id = command.node_id;
+    cluster
+        .node_connections
+        .lock()
+        .await
+        .insert(command.node_id, handler.address);
     handler.send_empty_ok_response().await?;
     info!("Sent a hello response to node ID: {}.", command.node_id);
 
+    if cluster.get_node(command.node_id).await.is_none() {
+        info!(
+            "The node: {}, ID: {} is not a cluster member yet.",
+            command.name, command.node_id
+        );
+        return Ok(());
+    }
+
     cluster.election_manager.set_term(command.term).await;
     if let Some(leader_id) = command.leader_id {
         cluster.set_leader(command.term, leader_id).await;
@@ -46,7 +56,15 @@ pub(crate) async fn handle(
         "Connecting to the disconnected node: {}, ID: {}...",
         command.name, command.node_id
     );
-    cluster.connect_to(command.node_id).await?;
+    // The hello has already been responded to, another response would be read as the response to the next request.
+    if let Err(error) = cluster.connect_to(command.node_id).await {
+        error!(
+            "Failed to connect to the previously disconnected node: {}, ID: {}, error: {error}",
+            command.name, command.node_id
+        );
+        return Ok(());
+    }
+
     info!(
         "Connected to the previously disconnected node: {}, ID: {}.",
         command.name, command.node_id
@@ -1,15 +1,23 @@
+pub mod add_node_handler;
 pub mod append_entries_handler;
 pub mod append_messages_handler;
 pub mod create_stream_handler;
 pub mod delete_stream_handler;
+pub mod fetch_messages_handler;
+pub mod get_epoch_end_offset_handler;
 pub mod get_metadata_handler;
 pub mod get_node_state_handler;
+pub mod get_stream_chunk_handler;
 pub mod get_streams_handler;
 pub mod heartbeat_handler;
 pub mod hello_handler;
 pub mod load_state_handler;
 pub mod ping_handler;
 pub mod poll_messages_handler;
+pub mod rebalance_leaders_handler;
+pub mod remove_node_handler;
 pub mod request_vote_handler;
-pub mod sync_messages_handler;
+pub mod timeout_now_handler;
+pub mod transfer_leadership_handler;
+pub mod update_isr_handler;
 pub mod update_leader_handler;
@@ -3,6 +3,7 @@ use crate::connection::handler::ConnectionHandler;
 use sdk::bytes_serializable::BytesSerializable;
 use sdk::commands::poll_messages::PollMessages;
 use sdk::error::SystemError;
+use sdk::models::read_consistency::ReadConsistency;
 use std::rc::Rc;
 
 pub(crate) async fn handle(
@@ -11,7 +12,12 @@ pub(crate) async fn handle(
     cluster: Rc<Cluster>,
 ) -> Result<(), SystemError> {
     cluster.verify_is_healthy().await?;
-    cluster.verify_is_leader().await?;
+    // The relaxed reads are also served by the followers, up to their high watermark.
+    if command.consistency == ReadConsistency::Linearizable {
+        cluster.verify_is_stream_leader(command.stream_id).await?;
+        cluster.confirm_stream_leadership(command.stream_id).await?;
+    }
+
     let messages = cluster
         .poll_messages(command.stream_id, command.offset, command.count)
         .await?;
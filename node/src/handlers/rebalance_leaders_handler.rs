@@ -0,0 +1,17 @@
+use crate::clusters::cluster::Cluster;
+use crate::connection::handler::ConnectionHandler;
+use sdk::error::SystemError;
+use std::rc::Rc;
+use tracing::info;
+
+pub(crate) async fn handle(
+    handler: &mut ConnectionHandler,
+    cluster: Rc<Cluster>,
+) -> Result<(), SystemError> {
+    cluster.verify_is_healthy().await?;
+    cluster.verify_accepts_writes().await?;
+    let moved_leaders = cluster.rebalance_stream_leaders().await?;
+    info!("Moved {moved_leaders} stream leaders back to preferred replicas.");
+    handler.send_empty_ok_response().await?;
+    Ok(())
+}
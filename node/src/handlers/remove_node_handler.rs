@@ -0,0 +1,16 @@
+use crate::clusters::cluster::Cluster;
+use crate::connection::handler::ConnectionHandler;
+use sdk::commands::remove_node::RemoveNode;
+use sdk::error::SystemError;
+use std::rc::Rc;
+
+pub(crate) async fn handle(
+    handler: &mut ConnectionHandler,
+    command: &RemoveNode,
+    cluster: Rc<Cluster>,
+) -> Result<(), SystemError> {
+    cluster.verify_is_healthy().await?;
+    cluster.verify_accepts_writes().await?;
+    let term = cluster.election_manager.get_current_term().await;
+    cluster.remove_node(handler, term, command.clone()).await
+}
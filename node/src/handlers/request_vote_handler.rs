@@ -1,9 +1,10 @@
 use crate::clusters::cluster::Cluster;
 use crate::connection::handler::ConnectionHandler;
+use crate::environment;
 use sdk::commands::request_vote::RequestVote;
 use sdk::error::SystemError;
 use std::rc::Rc;
-use tracing::info;
+use tracing::{error, info, warn};
 
 pub(crate) async fn handle(
     handler: &mut ConnectionHandler,
@@ -11,7 +12,61 @@ pub(crate) async fn handle(
     cluster: Rc<Cluster>,
 ) -> Result<(), SystemError> {
     cluster.verify_is_healthy().await?;
+    let Some(candidate) = cluster.get_node(handler.node_id).await else {
+        warn!(
+            "Rejected a request vote from non-member node ID: {}.",
+            handler.node_id
+        );
+        return Err(SystemError::NotClusterMember(handler.node_id));
+    };
+
+    if candidate.is_learner().await {
+        warn!(
+            "Rejected a request vote from learner node ID: {}.",
+            handler.node_id
+        );
+        return Err(SystemError::NodeIsLearner(handler.node_id));
+    }
+
     let self_node = cluster.get_self_node().unwrap();
+    if self_node.is_learner().await {
+        warn!("This node is a learner and cannot vote.");
+        return Err(SystemError::NodeIsLearner(self_node.node.id));
+    }
+
+    // The candidate's log must contain all the committed entries, so it's compared by the last term first.
+    let (last_index, last_log_term) = {
+        let state = cluster.state.lock().await;
+        (state.last_index, state.last_log_term())
+    };
+    if (command.last_log_term, command.last_log_index) < (last_log_term, last_index) {
+        warn!(
+            "Rejected a request vote from node ID: {} with log behind, last log term: {}, index: {} < term: {last_log_term}, index: {last_index}.",
+            handler.node_id, command.last_log_term, command.last_log_index
+        );
+        let current_term = cluster.election_manager.get_current_term().await;
+        if command.term > current_term && cluster.is_leader().await {
+            // Re-elect the leader in a newer term, so that the lagging candidate can follow it again.
+            info!(
+                "Candidate node ID: {} is in newer term: {} than the current term: {current_term}, starting election...",
+                handler.node_id, command.term
+            );
+            cluster.election_manager.set_term(command.term).await;
+            // The newer term and its reset vote are saved first, so that the node never returns to the older term after the restart.
+            {
+                let mut state = cluster.state.lock().await;
+                state.set_term(command.term);
+                state.save_hard_state().await?;
+            }
+            environment::spawn(async move {
+                if let Err(error) = cluster.start_election_now().await {
+                    error!("Failed to start election, error: {error}");
+                }
+            });
+        }
+        return Err(SystemError::CandidateLogBehind(last_index));
+    }
+
     info!(
         "Received a request vote from node ID: {}, address: {} in term: {}.",
         handler.node_id, handler.address, command.term
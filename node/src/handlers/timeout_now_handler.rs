@@ -0,0 +1,52 @@
+use crate::clusters::cluster::Cluster;
+use crate::connection::handler::ConnectionHandler;
+use crate::environment;
+use sdk::commands::timeout_now::TimeoutNow;
+use sdk::error::SystemError;
+use std::rc::Rc;
+use tracing::{error, info, warn};
+
+pub(crate) async fn handle(
+    handler: &mut ConnectionHandler,
+    command: &TimeoutNow,
+    cluster: Rc<Cluster>,
+) -> Result<(), SystemError> {
+    cluster.verify_is_healthy().await?;
+    cluster.verify_has_disk_space().await?;
+    let current_term = cluster.election_manager.get_current_term().await;
+    if command.term != current_term {
+        error!(
+            "Received a timeout now in term: {}, but current term is: {current_term}.",
+            command.term
+        );
+        return Err(SystemError::InvalidTerm(current_term));
+    }
+
+    // Only the leader hands over its leadership, so the other nodes cannot force the election.
+    let leader_id = cluster.election_manager.get_leader_id().await;
+    if leader_id != Some(handler.node_id) {
+        warn!(
+            "Rejected a timeout now from node ID: {}, which is not the leader: {leader_id:?}.",
+            handler.node_id
+        );
+        return Err(SystemError::InvalidNode(handler.node_id));
+    }
+
+    let self_node = cluster.get_self_node().unwrap();
+    if self_node.is_learner().await {
+        warn!("This node is a learner and cannot take over the leadership.");
+        return Err(SystemError::NodeIsLearner(self_node.node.id));
+    }
+
+    info!(
+        "Received a timeout now from node ID: {} in term: {}, starting election...",
+        handler.node_id, command.term
+    );
+    handler.send_empty_ok_response().await?;
+    environment::spawn(async move {
+        if let Err(error) = cluster.start_election_now().await {
+            error!("Failed to start election, error: {error}");
+        }
+    });
+    Ok(())
+}
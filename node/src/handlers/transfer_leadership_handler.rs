@@ -0,0 +1,16 @@
+use crate::clusters::cluster::Cluster;
+use crate::connection::handler::ConnectionHandler;
+use sdk::commands::transfer_leadership::TransferLeadership;
+use sdk::error::SystemError;
+use std::rc::Rc;
+
+pub(crate) async fn handle(
+    handler: &mut ConnectionHandler,
+    command: &TransferLeadership,
+    cluster: Rc<Cluster>,
+) -> Result<(), SystemError> {
+    cluster.verify_is_healthy().await?;
+    cluster.transfer_leadership(command.node_id).await?;
+    handler.send_empty_ok_response().await?;
+    Ok(())
+}
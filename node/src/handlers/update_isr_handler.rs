@@ -0,0 +1,23 @@
+use crate::clusters::cluster::Cluster;
+use crate::connection::handler::ConnectionHandler;
+use sdk::commands::update_isr::UpdateIsr;
+use sdk::error::SystemError;
+use std::rc::Rc;
+
+pub(crate) async fn handle(
+    handler: &mut ConnectionHandler,
+    command: &UpdateIsr,
+    cluster: Rc<Cluster>,
+) -> Result<(), SystemError> {
+    cluster.verify_is_leader().await?;
+    cluster
+        .update_isr(
+            handler.node_id,
+            command.stream_id,
+            command.leader_epoch,
+            command.isr.clone(),
+        )
+        .await?;
+    handler.send_empty_ok_response().await?;
+    Ok(())
+}
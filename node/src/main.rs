@@ -1,7 +1,13 @@
 use crate::clusters::cluster::{Cluster, SelfNode};
 use crate::clusters::state::State;
-use crate::clusters::{cluster_info, heartbeats};
+use crate::clusters::{
+    cluster_info, disk_guard, fetcher, heartbeats, isr, replication, stream_leaders,
+};
+use crate::configs::config::{MetadataBackend, SystemConfig};
 use crate::configs::config_provider::FileConfigProvider;
+use crate::metadata::file_store::FileMetadataStore;
+use crate::metadata::sled_store::SledMetadataStore;
+use crate::metadata::MetadataStore;
 use crate::server::{public_server, sync_server};
 use crate::streaming::streamer::Streamer;
 use figlet_rs::FIGfont;
@@ -13,9 +19,13 @@ use tracing::info;
 mod clusters;
 mod configs;
 mod connection;
+mod environment;
 mod handlers;
+mod metadata;
 mod models;
 mod server;
+#[cfg(test)]
+mod simulation;
 mod streaming;
 mod types;
 
@@ -33,10 +43,31 @@ async fn main() -> Result<(), SystemError> {
     let config_provider = FileConfigProvider::new(config_path);
     let system_config = config_provider.load_config().await?;
     println!("{system_config}");
+    let cluster = start(&system_config).await?;
+    info!("Press CTRL+C shutdown Iggy node...");
+    CtrlC::new().unwrap().await;
+    cluster.disconnect().await?;
+    info!("Iggy node has shutdown successfully.");
+    Ok(())
+}
+
+/// Starts the node within the current environment, so that it can also run in the simulation.
+pub(crate) async fn start(system_config: &SystemConfig) -> Result<Rc<Cluster>, SystemError> {
     let mut state = State::new(0, &system_config.cluster.state_path);
-    state.init().await;
-    let mut streamer = Streamer::new(system_config.node.id, &system_config.stream.path);
-    streamer.init().await;
+    state.init().await?;
+    let metadata: Rc<dyn MetadataStore> = match system_config.metadata.backend {
+        MetadataBackend::Files => Rc::new(FileMetadataStore::new(&system_config.stream.path)),
+        MetadataBackend::Sled => Rc::new(
+            SledMetadataStore::open(&system_config.metadata.path, &system_config.stream.path)
+                .await?,
+        ),
+    };
+    let mut streamer = Streamer::new(
+        system_config.node.id,
+        &system_config.stream.path,
+        metadata.clone(),
+    );
+    streamer.init().await?;
     let cluster = Cluster::new(
         SelfNode::new(
             system_config.node.id,
@@ -47,17 +78,19 @@ async fn main() -> Result<(), SystemError> {
         &system_config.cluster,
         streamer,
         state,
+        metadata,
     )?;
     let cluster = Rc::new(cluster);
     cluster_info::subscribe(cluster.clone());
+    disk_guard::subscribe(cluster.clone());
     sync_server::start(&system_config.node.address, cluster.clone());
     public_server::start(&system_config.server.address, cluster.clone());
     cluster.init().await?;
     cluster.start_election().await?;
     heartbeats::subscribe(cluster.clone());
-    info!("Press CTRL+C shutdown Iggy node...");
-    CtrlC::new().unwrap().await;
-    cluster.disconnect().await?;
-    info!("Iggy node has shutdown successfully.");
-    Ok(())
+    replication::subscribe(cluster.clone());
+    fetcher::subscribe(cluster.clone());
+    isr::subscribe(cluster.clone());
+    stream_leaders::subscribe(cluster.clone());
+    Ok(cluster)
 }
@@ -0,0 +1,228 @@
+use crate::environment;
+use crate::metadata::{MetadataStore, StateSnapshot, StreamMetadata};
+use futures::future::LocalBoxFuture;
+use sdk::bytes_serializable::BytesSerializable;
+use sdk::error::SystemError;
+use tracing::info;
+
+const MANIFEST_FILE: &str = "manifest";
+const LEGACY_REPLICATION_FACTOR_FILE: &str = "replication_factor";
+const LEGACY_HIGH_WATERMARK_FILE: &str = "high_watermark";
+const LEGACY_MIN_IN_SYNC_REPLICAS: u8 = 1;
+
+/// Keeps the metadata of each stream in the manifest file within the stream directory,
+/// the state snapshots are not stored, so the whole state log is replayed on start.
+#[derive(Debug)]
+pub(crate) struct FileMetadataStore {
+    streams_path: String,
+}
+
+impl FileMetadataStore {
+    pub fn new(streams_path: &str) -> Self {
+        Self {
+            streams_path: streams_path.to_string(),
+        }
+    }
+
+    fn path(&self, stream_id: u64, file: &str) -> String {
+        format!("{}/{stream_id}/{file}", self.streams_path)
+    }
+
+    /// The streams created before the manifest keep the replication factor and the high watermark
+    /// in the separate files, the other values are restored from the replicated state.
+    async fn load_legacy_stream(
+        &self,
+        stream_id: u64,
+    ) -> Result<Option<StreamMetadata>, SystemError> {
+        let storage = environment::storage();
+        let replication_factor_path = self.path(stream_id, LEGACY_REPLICATION_FACTOR_FILE);
+        if !storage.exists(&replication_factor_path) {
+            return Ok(None);
+        }
+
+        let buffer = storage.read(&replication_factor_path).await?;
+        let Some(replication_factor) = buffer.first() else {
+            return Err(SystemError::InvalidStreamManifest);
+        };
+
+        let high_watermark_path = self.path(stream_id, LEGACY_HIGH_WATERMARK_FILE);
+        let high_watermark = if storage.exists(&high_watermark_path) {
+            let buffer = storage.read(&high_watermark_path).await?;
+            let Some(buffer) = buffer.get(..8) else {
+                return Err(SystemError::InvalidStreamManifest);
+            };
+            u64::from_le_bytes(buffer.try_into()?)
+        } else {
+            0
+        };
+
+        info!("Loaded legacy metadata of stream with ID: {stream_id}");
+        Ok(Some(StreamMetadata {
+            stream_id,
+            replication_factor: *replication_factor,
+            min_in_sync_replicas: LEGACY_MIN_IN_SYNC_REPLICAS,
+            leader_id: 0,
+            leader_epoch: 0,
+            replicas: Vec::new(),
+            isr: Vec::new(),
+            high_watermark,
+        }))
+    }
+
+    fn remove_legacy_files(&self, stream_id: u64) -> Result<(), SystemError> {
+        self.remove_files(
+            stream_id,
+            &[LEGACY_REPLICATION_FACTOR_FILE, LEGACY_HIGH_WATERMARK_FILE],
+        )
+    }
+
+    /// Removes the files of the stream, once its metadata has been moved to another store.
+    pub fn remove_stream_files(&self, stream_id: u64) -> Result<(), SystemError> {
+        self.remove_files(
+            stream_id,
+            &[
+                MANIFEST_FILE,
+                LEGACY_REPLICATION_FACTOR_FILE,
+                LEGACY_HIGH_WATERMARK_FILE,
+            ],
+        )
+    }
+
+    fn remove_files(&self, stream_id: u64, files: &[&str]) -> Result<(), SystemError> {
+        let storage = environment::storage();
+        for file in files {
+            let path = self.path(stream_id, file);
+            if storage.exists(&path) {
+                storage.remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MetadataStore for FileMetadataStore {
+    fn load_stream(
+        &self,
+        stream_id: u64,
+    ) -> LocalBoxFuture<'_, Result<Option<StreamMetadata>, SystemError>> {
+        Box::pin(async move {
+            let storage = environment::storage();
+            let manifest_path = self.path(stream_id, MANIFEST_FILE);
+            if !storage.exists(&manifest_path) {
+                return self.load_legacy_stream(stream_id).await;
+            }
+
+            let buffer = storage.read(&manifest_path).await?;
+            let metadata = StreamMetadata::from_bytes(&buffer)?;
+            if metadata.stream_id != stream_id {
+                return Err(SystemError::InvalidStreamManifest);
+            }
+
+            Ok(Some(metadata))
+        })
+    }
+
+    /// The legacy files are removed only once the manifest replacing them has been saved.
+    fn save_stream(&self, metadata: StreamMetadata) -> LocalBoxFuture<'_, Result<(), SystemError>> {
+        Box::pin(async move {
+            environment::storage()
+                .replace(
+                    &self.path(metadata.stream_id, MANIFEST_FILE),
+                    metadata.as_bytes(),
+                )
+                .await?;
+            self.remove_legacy_files(metadata.stream_id)
+        })
+    }
+
+    /// The files are removed along with the stream directory.
+    fn delete_stream(&self, _stream_id: u64) -> LocalBoxFuture<'_, Result<(), SystemError>> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn load_state_snapshot(
+        &self,
+    ) -> LocalBoxFuture<'_, Result<Option<StateSnapshot>, SystemError>> {
+        Box::pin(async move { Ok(None) })
+    }
+
+    fn save_state_snapshot(
+        &self,
+        _snapshot: StateSnapshot,
+    ) -> LocalBoxFuture<'_, Result<(), SystemError>> {
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::memory::{MemoryStorage, WriteFault};
+    use crate::environment::{Environment, Storage};
+    use std::rc::Rc;
+
+    const STREAMS_PATH: &str = "streams";
+
+    fn metadata(high_watermark: u64) -> StreamMetadata {
+        StreamMetadata {
+            stream_id: 1,
+            replication_factor: 3,
+            min_in_sync_replicas: 2,
+            leader_id: 1,
+            leader_epoch: 1,
+            replicas: vec![1, 2, 3],
+            isr: vec![1, 2, 3],
+            high_watermark,
+        }
+    }
+
+    #[monoio::test]
+    async fn failed_save_should_keep_previous_manifest() {
+        let storage = Rc::new(MemoryStorage::default());
+        let _guard = Environment::system().with_storage(storage.clone()).enter();
+        storage
+            .create_dir_all(&format!("{STREAMS_PATH}/1"))
+            .unwrap();
+        let store = FileMetadataStore::new(STREAMS_PATH);
+        store.save_stream(metadata(1)).await.unwrap();
+
+        for write_fault in [WriteFault::Failed, WriteFault::Torn] {
+            storage.set_write_fault(Some(write_fault));
+            assert!(store.save_stream(metadata(2)).await.is_err());
+            storage.set_write_fault(None);
+
+            assert_eq!(store.load_stream(1).await.unwrap(), Some(metadata(1)));
+        }
+    }
+
+    #[monoio::test]
+    async fn legacy_files_should_be_replaced_by_manifest() {
+        let storage = Rc::new(MemoryStorage::default());
+        let _guard = Environment::system().with_storage(storage.clone()).enter();
+        storage
+            .create_dir_all(&format!("{STREAMS_PATH}/1"))
+            .unwrap();
+        let replication_factor_path = format!("{STREAMS_PATH}/1/{LEGACY_REPLICATION_FACTOR_FILE}");
+        let high_watermark_path = format!("{STREAMS_PATH}/1/{LEGACY_HIGH_WATERMARK_FILE}");
+        storage
+            .write_at(&replication_factor_path, 0, vec![3])
+            .await
+            .unwrap();
+        storage
+            .write_at(&high_watermark_path, 0, 5u64.to_le_bytes().to_vec())
+            .await
+            .unwrap();
+        let store = FileMetadataStore::new(STREAMS_PATH);
+
+        let mut metadata = store.load_stream(1).await.unwrap().unwrap();
+        assert_eq!(metadata.replication_factor, 3);
+        assert_eq!(metadata.high_watermark, 5);
+
+        metadata.leader_id = 2;
+        store.save_stream(metadata.clone()).await.unwrap();
+
+        assert!(!storage.exists(&replication_factor_path));
+        assert!(!storage.exists(&high_watermark_path));
+        assert_eq!(store.load_stream(1).await.unwrap(), Some(metadata));
+    }
+}
@@ -0,0 +1,160 @@
+use crate::types::{Index, NodeId};
+use bytes::BufMut;
+use futures::future::LocalBoxFuture;
+use sdk::bytes_serializable::BytesSerializable;
+use sdk::error::SystemError;
+use std::fmt::Debug;
+
+pub mod file_store;
+pub mod sled_store;
+
+const MANIFEST_VERSION: u8 = 1;
+
+/// The metadata of the node, which is small, but rewritten often: the settings and the high watermark
+/// of each stream, and the snapshot of the replicated state machines.
+pub(crate) trait MetadataStore: Debug {
+    /// Returns nothing for the stream, which has never been saved.
+    fn load_stream(
+        &self,
+        stream_id: u64,
+    ) -> LocalBoxFuture<'_, Result<Option<StreamMetadata>, SystemError>>;
+    fn save_stream(&self, metadata: StreamMetadata) -> LocalBoxFuture<'_, Result<(), SystemError>>;
+    fn delete_stream(&self, stream_id: u64) -> LocalBoxFuture<'_, Result<(), SystemError>>;
+    fn load_state_snapshot(&self)
+        -> LocalBoxFuture<'_, Result<Option<StateSnapshot>, SystemError>>;
+    /// The store might not keep the snapshots at all, then the whole state is replayed on start.
+    fn save_state_snapshot(
+        &self,
+        snapshot: StateSnapshot,
+    ) -> LocalBoxFuture<'_, Result<(), SystemError>>;
+}
+
+/// The manifest of the stream, which is always saved as a whole, so that its values are consistent
+/// with each other. It's prefixed with the version and suffixed with the checksum of the preceding bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StreamMetadata {
+    pub stream_id: u64,
+    pub replication_factor: u8,
+    pub min_in_sync_replicas: u8,
+    pub leader_id: NodeId,
+    pub leader_epoch: u64,
+    pub replicas: Vec<NodeId>,
+    pub isr: Vec<NodeId>,
+    pub high_watermark: Index,
+}
+
+impl BytesSerializable for StreamMetadata {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(41 + 8 * (self.replicas.len() + self.isr.len()));
+        bytes.put_u8(MANIFEST_VERSION);
+        bytes.put_u64_le(self.stream_id);
+        bytes.put_u8(self.replication_factor);
+        bytes.put_u8(self.min_in_sync_replicas);
+        bytes.put_u64_le(self.leader_id);
+        bytes.put_u64_le(self.leader_epoch);
+        bytes.put_u64_le(self.high_watermark);
+        for node_ids in [&self.replicas, &self.isr] {
+            bytes.put_u8(node_ids.len() as u8);
+            for node_id in node_ids {
+                bytes.put_u64_le(*node_id);
+            }
+        }
+        bytes.put_u32_le(crc32fast::hash(&bytes));
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SystemError> {
+        if bytes.len() < 4 {
+            return Err(SystemError::InvalidStreamManifest);
+        }
+
+        let (bytes, checksum) = bytes.split_at(bytes.len() - 4);
+        if crc32fast::hash(bytes) != u32::from_le_bytes(checksum.try_into()?) {
+            return Err(SystemError::InvalidStreamManifest);
+        }
+
+        if bytes.len() < 35 || bytes[0] != MANIFEST_VERSION {
+            return Err(SystemError::InvalidStreamManifest);
+        }
+
+        let mut position = 35;
+        let replicas = read_node_ids(bytes, &mut position)?;
+        let isr = read_node_ids(bytes, &mut position)?;
+        if position != bytes.len() {
+            return Err(SystemError::InvalidStreamManifest);
+        }
+
+        Ok(StreamMetadata {
+            stream_id: u64::from_le_bytes(bytes[1..9].try_into()?),
+            replication_factor: bytes[9],
+            min_in_sync_replicas: bytes[10],
+            leader_id: u64::from_le_bytes(bytes[11..19].try_into()?),
+            leader_epoch: u64::from_le_bytes(bytes[19..27].try_into()?),
+            high_watermark: u64::from_le_bytes(bytes[27..35].try_into()?),
+            replicas,
+            isr,
+        })
+    }
+}
+
+fn read_node_ids(bytes: &[u8], position: &mut usize) -> Result<Vec<NodeId>, SystemError> {
+    let Some(len) = bytes.get(*position).map(|len| *len as usize) else {
+        return Err(SystemError::InvalidStreamManifest);
+    };
+    *position += 1;
+    let Some(node_ids) = bytes.get(*position..*position + 8 * len) else {
+        return Err(SystemError::InvalidStreamManifest);
+    };
+
+    *position += 8 * len;
+    node_ids
+        .chunks(8)
+        .map(|node_id| Ok(u64::from_le_bytes(node_id.try_into()?)))
+        .collect()
+}
+
+/// The state machines with all the log entries up to the index applied to them.
+#[derive(Debug, PartialEq)]
+pub(crate) struct StateSnapshot {
+    pub index: Index,
+    pub data: Vec<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> StreamMetadata {
+        StreamMetadata {
+            stream_id: 1,
+            replication_factor: 3,
+            min_in_sync_replicas: 2,
+            leader_id: 2,
+            leader_epoch: 4,
+            replicas: vec![2, 1, 3],
+            isr: vec![2, 3],
+            high_watermark: 10,
+        }
+    }
+
+    #[test]
+    fn manifest_should_be_deserialized() {
+        let bytes = metadata().as_bytes();
+
+        assert_eq!(StreamMetadata::from_bytes(&bytes).unwrap(), metadata());
+    }
+
+    #[test]
+    fn corrupted_or_torn_manifest_should_be_rejected() {
+        let bytes = metadata().as_bytes();
+        let mut corrupted_bytes = bytes.clone();
+        corrupted_bytes[10] ^= 1;
+
+        for bytes in [&corrupted_bytes[..], &bytes[..bytes.len() / 2], &[]] {
+            assert!(matches!(
+                StreamMetadata::from_bytes(bytes),
+                Err(SystemError::InvalidStreamManifest)
+            ));
+        }
+    }
+}
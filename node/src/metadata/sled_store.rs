@@ -0,0 +1,252 @@
+use crate::environment;
+use crate::metadata::file_store::FileMetadataStore;
+use crate::metadata::{MetadataStore, StateSnapshot, StreamMetadata};
+use futures::future::LocalBoxFuture;
+use sdk::bytes_serializable::BytesSerializable;
+use sdk::error::SystemError;
+use std::io;
+use tracing::{info, warn};
+
+const STREAMS_TREE: &str = "streams";
+const STATE_SNAPSHOT_KEY: &str = "state_snapshot";
+const MIGRATED_KEY: &str = "migrated";
+
+/// Keeps all the metadata in the embedded sled database, instead of the files within each stream directory.
+/// The state snapshot is updated once enough committed entries are applied, so that only the newer ones are replayed on start.
+#[derive(Debug)]
+pub(crate) struct SledMetadataStore {
+    db: sled::Db,
+    streams: sled::Tree,
+}
+
+impl SledMetadataStore {
+    pub async fn open(path: &str, streams_path: &str) -> Result<Self, SystemError> {
+        let db = sled::open(path).map_err(to_error)?;
+        info!("Opened metadata database: {path}");
+        Self::init(db, streams_path).await
+    }
+
+    async fn init(db: sled::Db, streams_path: &str) -> Result<Self, SystemError> {
+        let store = Self {
+            streams: db.open_tree(STREAMS_TREE).map_err(to_error)?,
+            db,
+        };
+        if store.db.get(MIGRATED_KEY).map_err(to_error)?.is_none() {
+            store.migrate(streams_path).await?;
+        }
+        Ok(store)
+    }
+
+    /// Moves the metadata of the existing streams from their files, which are removed only once
+    /// the database has been flushed, so that the metadata is not lost if the node crashes in between.
+    async fn migrate(&self, streams_path: &str) -> Result<(), SystemError> {
+        let storage = environment::storage();
+        let mut stream_ids = Vec::new();
+        if storage.exists(streams_path) {
+            for name in storage.read_dir(streams_path)? {
+                let Ok(stream_id) = name.parse::<u64>() else {
+                    warn!("Skipping the migration of invalid stream directory: {name}");
+                    continue;
+                };
+                stream_ids.push(stream_id);
+            }
+        }
+
+        let files = FileMetadataStore::new(streams_path);
+        for stream_id in &stream_ids {
+            if let Some(metadata) = files.load_stream(*stream_id).await? {
+                self.insert_stream(&metadata)?;
+            }
+        }
+
+        self.db.insert(MIGRATED_KEY, &[1]).map_err(to_error)?;
+        self.flush().await?;
+        for stream_id in &stream_ids {
+            files.remove_stream_files(*stream_id)?;
+        }
+        info!(
+            "Migrated the metadata of {} streams from: {streams_path}",
+            stream_ids.len()
+        );
+        Ok(())
+    }
+
+    /// The database buffers the writes, which would be lost once the process is killed, unlike the written files.
+    async fn flush(&self) -> Result<(), SystemError> {
+        self.db.flush_async().await.map_err(to_error)?;
+        Ok(())
+    }
+
+    fn insert_stream(&self, metadata: &StreamMetadata) -> Result<(), SystemError> {
+        self.streams
+            .insert(metadata.stream_id.to_be_bytes(), metadata.as_bytes())
+            .map_err(to_error)?;
+        Ok(())
+    }
+}
+
+fn to_error(error: sled::Error) -> SystemError {
+    SystemError::IoError(error.into())
+}
+
+impl MetadataStore for SledMetadataStore {
+    fn load_stream(
+        &self,
+        stream_id: u64,
+    ) -> LocalBoxFuture<'_, Result<Option<StreamMetadata>, SystemError>> {
+        Box::pin(async move {
+            let Some(value) = self
+                .streams
+                .get(stream_id.to_be_bytes())
+                .map_err(to_error)?
+            else {
+                return Ok(None);
+            };
+
+            Ok(Some(StreamMetadata::from_bytes(&value)?))
+        })
+    }
+
+    fn save_stream(&self, metadata: StreamMetadata) -> LocalBoxFuture<'_, Result<(), SystemError>> {
+        Box::pin(async move {
+            self.insert_stream(&metadata)?;
+            self.flush().await
+        })
+    }
+
+    fn delete_stream(&self, stream_id: u64) -> LocalBoxFuture<'_, Result<(), SystemError>> {
+        Box::pin(async move {
+            self.streams
+                .remove(stream_id.to_be_bytes())
+                .map_err(to_error)?;
+            self.flush().await
+        })
+    }
+
+    fn load_state_snapshot(
+        &self,
+    ) -> LocalBoxFuture<'_, Result<Option<StateSnapshot>, SystemError>> {
+        Box::pin(async move {
+            let Some(value) = self.db.get(STATE_SNAPSHOT_KEY).map_err(to_error)? else {
+                return Ok(None);
+            };
+
+            let Some(index) = value.get(..8) else {
+                return Err(
+                    io::Error::new(io::ErrorKind::InvalidData, "Invalid state snapshot.").into(),
+                );
+            };
+
+            Ok(Some(StateSnapshot {
+                index: u64::from_le_bytes(index.try_into()?),
+                data: value[8..].to_vec(),
+            }))
+        })
+    }
+
+    fn save_state_snapshot(
+        &self,
+        snapshot: StateSnapshot,
+    ) -> LocalBoxFuture<'_, Result<(), SystemError>> {
+        Box::pin(async move {
+            let mut value = Vec::with_capacity(8 + snapshot.data.len());
+            value.extend_from_slice(&snapshot.index.to_le_bytes());
+            value.extend_from_slice(&snapshot.data);
+            self.db
+                .insert(STATE_SNAPSHOT_KEY, value)
+                .map_err(to_error)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::memory::MemoryStorage;
+    use crate::environment::{Environment, Storage};
+    use std::rc::Rc;
+
+    const STREAMS_PATH: &str = "streams";
+
+    fn temporary_db() -> sled::Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    fn metadata(stream_id: u64, high_watermark: u64) -> StreamMetadata {
+        StreamMetadata {
+            stream_id,
+            replication_factor: 3,
+            min_in_sync_replicas: 2,
+            leader_id: 1,
+            leader_epoch: 1,
+            replicas: vec![1, 2, 3],
+            isr: vec![1, 2, 3],
+            high_watermark,
+        }
+    }
+
+    #[monoio::test]
+    async fn stream_files_should_be_migrated_once() {
+        let storage = Rc::new(MemoryStorage::default());
+        let _guard = Environment::system().with_storage(storage.clone()).enter();
+        let files = FileMetadataStore::new(STREAMS_PATH);
+        storage
+            .create_dir_all(&format!("{STREAMS_PATH}/1"))
+            .unwrap();
+        storage
+            .create_dir_all(&format!("{STREAMS_PATH}/2"))
+            .unwrap();
+        files.save_stream(metadata(1, 10)).await.unwrap();
+        files.save_stream(metadata(2, 0)).await.unwrap();
+
+        let db = temporary_db();
+        let store = SledMetadataStore::init(db.clone(), STREAMS_PATH)
+            .await
+            .unwrap();
+
+        assert_eq!(store.load_stream(1).await.unwrap(), Some(metadata(1, 10)));
+        assert_eq!(store.load_stream(2).await.unwrap(), Some(metadata(2, 0)));
+        assert_eq!(files.load_stream(1).await.unwrap(), None);
+
+        store.save_stream(metadata(1, 20)).await.unwrap();
+        files.save_stream(metadata(1, 15)).await.unwrap();
+        let reopened_store = SledMetadataStore::init(db, STREAMS_PATH).await.unwrap();
+
+        assert_eq!(
+            reopened_store.load_stream(1).await.unwrap(),
+            Some(metadata(1, 20))
+        );
+    }
+
+    #[monoio::test]
+    async fn deleted_stream_and_state_snapshot_should_be_stored() {
+        let _guard = Environment::system()
+            .with_storage(Rc::new(MemoryStorage::default()))
+            .enter();
+        let store = SledMetadataStore::init(temporary_db(), STREAMS_PATH)
+            .await
+            .unwrap();
+        store.save_stream(metadata(1, 5)).await.unwrap();
+        store.delete_stream(1).await.unwrap();
+
+        assert_eq!(store.load_stream(1).await.unwrap(), None);
+        assert_eq!(store.load_state_snapshot().await.unwrap(), None);
+
+        store
+            .save_state_snapshot(StateSnapshot {
+                index: 7,
+                data: vec![1, 2, 3],
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.load_state_snapshot().await.unwrap(),
+            Some(StateSnapshot {
+                index: 7,
+                data: vec![1, 2, 3],
+            })
+        );
+    }
+}
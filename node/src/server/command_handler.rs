@@ -30,17 +30,32 @@ pub async fn handle(
         Command::UpdateLeader(command) => {
             update_leader_handler::handle(handler, command, cluster).await?;
         }
+        Command::TimeoutNow(command) => {
+            timeout_now_handler::handle(handler, command, cluster).await?;
+        }
+        Command::TransferLeadership(command) => {
+            transfer_leadership_handler::handle(handler, command, cluster).await?;
+        }
+        Command::AddNode(command) => {
+            add_node_handler::handle(handler, command, cluster).await?;
+        }
+        Command::RemoveNode(command) => {
+            remove_node_handler::handle(handler, command, cluster).await?;
+        }
+        Command::RebalanceLeaders(_) => {
+            rebalance_leaders_handler::handle(handler, cluster).await?;
+        }
         Command::GetNodeState(_) => {
             get_node_state_handler::handle(handler, cluster).await?;
         }
         Command::LoadState(command) => {
             load_state_handler::handle(handler, command, cluster).await?;
         }
-        Command::GetMetadata(_) => {
-            get_metadata_handler::handle(handler, cluster).await?;
+        Command::GetMetadata(command) => {
+            get_metadata_handler::handle(handler, command, cluster).await?;
         }
-        Command::GetStreams(_) => {
-            get_streams_handler::handle(handler, cluster).await?;
+        Command::GetStreams(command) => {
+            get_streams_handler::handle(handler, command, cluster).await?;
         }
         Command::CreateStream(command) => {
             create_stream_handler::handle(handler, command, cluster).await?;
@@ -48,14 +63,27 @@ pub async fn handle(
         Command::DeleteStream(command) => {
             delete_stream_handler::handle(handler, command, cluster).await?;
         }
+        Command::UpdateIsr(command) => {
+            update_isr_handler::handle(handler, command, cluster).await?;
+        }
+        Command::UpdateStreamLeader(_) => {
+            // The stream leaders are only appended to the replicated state by the metadata leader.
+            return Err(SystemError::InvalidCommand);
+        }
         Command::AppendMessages(command) => {
             append_messages_handler::handle(handler, command, cluster).await?;
         }
         Command::PollMessages(command) => {
             poll_messages_handler::handle(handler, command, cluster).await?;
         }
-        Command::SyncMessages(command) => {
-            sync_messages_handler::handle(handler, command, cluster).await?;
+        Command::FetchMessages(command) => {
+            fetch_messages_handler::handle(handler, command, cluster).await?;
+        }
+        Command::GetEpochEndOffset(command) => {
+            get_epoch_end_offset_handler::handle(handler, command, cluster).await?;
+        }
+        Command::GetStreamChunk(command) => {
+            get_stream_chunk_handler::handle(handler, command, cluster).await?;
         }
         Command::AppendEntries(command) => {
             append_entries_handler::handle(handler, command, cluster).await?;
@@ -1,7 +1,7 @@
 use crate::clusters::cluster::Cluster;
 use crate::connection::handler::ConnectionHandler;
+use crate::environment;
 use crate::server::tcp_listener::listen;
-use monoio::net::TcpListener;
 use sdk::error::SystemError;
 use std::io::ErrorKind;
 use std::rc::Rc;
@@ -12,8 +12,8 @@ pub fn start(name: &str, address: &str, cluster: Rc<Cluster>) {
     let address = address.to_string();
     let node_address = address.clone();
     let server_name = name.to_string();
-    monoio::spawn(async move {
-        let listener = TcpListener::bind(address.clone());
+    environment::spawn(async move {
+        let listener = environment::bind(&address);
         if listener.is_err() {
             panic!("Unable to start {server_name} on TCP address: {address}.");
         }
@@ -25,12 +25,12 @@ pub fn start(name: &str, address: &str, cluster: Rc<Cluster>) {
                 Ok((stream, address)) => {
                     info!("{server_name} has accepted new TCP connection: {address}");
                     let mut connection = ConnectionHandler::new(stream, address, 0);
-                    monoio::spawn(async move {
+                    environment::spawn(async move {
                         let cluster_error = cluster.clone();
                         if let Err(error) = listen(&mut connection, cluster).await {
                             handle_error(error, &connection);
                             cluster_error
-                                .handle_disconnected_node(connection.node_id)
+                                .handle_disconnected_node(connection.node_id, connection.address)
                                 .await;
                         }
                     });
@@ -0,0 +1,438 @@
+//! The model of the elections, in which the nodes, each with its own `ElectionManager`, exchange
+//! the vote requests, votes, leader updates and heartbeats in any order, while any of them might be
+//! delayed or lost. Every reachable state of the first term is explored exhaustively, and the later
+//! terms are covered by the seeded random walks, as there are too many states to visit all of them.
+//! The nodes also restart, keeping only the term and the vote they have saved before sending it.
+//! All the states are checked for the Raft election safety: at most one leader per term.
+
+use crate::clusters::elections::election::{
+    ElectionManager, ElectionSnapshot, ElectionState, ElectionTimeout,
+};
+use crate::types::{NodeId, Term};
+use futures::FutureExt;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+const NODES_COUNT: u64 = 3;
+const EXPLORED_TERMS: Term = 1;
+const WALKED_TERMS: Term = 5;
+const WALKS_COUNT: u64 = 5_000;
+const MAX_WALK_STEPS: usize = 200;
+const MAX_RESTARTS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Message {
+    RequestVote {
+        term: Term,
+        candidate_id: NodeId,
+        node_id: NodeId,
+    },
+    Vote {
+        term: Term,
+        candidate_id: NodeId,
+        node_id: NodeId,
+    },
+    UpdateLeader {
+        term: Term,
+        leader_id: NodeId,
+        node_id: NodeId,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Step {
+    /// The node hasn't heard from the leader, so it starts the election in the next term.
+    Timeout(NodeId),
+    /// The randomized election timeout of the node has passed, so it counts the votes.
+    ElectionTimeout(NodeId),
+    Deliver(Message),
+    /// The candidate gives up waiting for the remaining votes, which might have been lost.
+    CountVotes(NodeId),
+    /// The node sends the heartbeat with the leader known to it.
+    Heartbeat {
+        from: NodeId,
+        to: NodeId,
+    },
+    /// The node crashes and starts again, its messages which are already sent might still be delivered.
+    Restart(NodeId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Role {
+    Follower,
+    /// Waits for the election timeout in the term.
+    Electing(Term),
+    /// Waits for the votes requested in the term.
+    Candidate(Term),
+    Leader(Term),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct NodeState {
+    election: ElectionSnapshot,
+    role: Role,
+    /// The leader sent in the heartbeats, which is set once the node has accepted it.
+    known_leader: Option<(Term, NodeId)>,
+    /// The term and the vote of the node, which survive its restart.
+    hard_state: (Term, Option<NodeId>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct State {
+    nodes: Vec<NodeState>,
+    /// The messages which have been sent, but not delivered yet.
+    messages: BTreeSet<Message>,
+    /// The nodes which have become the leaders, by term.
+    leaders: BTreeMap<Term, NodeId>,
+    /// The nodes don't start the elections beyond this term.
+    max_term: Term,
+    restarts_left: usize,
+}
+
+impl State {
+    fn initial(max_term: Term) -> Self {
+        let nodes = (1..=NODES_COUNT)
+            .map(|node_id| NodeState {
+                election: ElectionManager::new(node_id, NODES_COUNT, ElectionTimeout::new(0, 0))
+                    .try_snapshot()
+                    .unwrap(),
+                role: Role::Follower,
+                known_leader: None,
+                hard_state: (0, None),
+            })
+            .collect();
+        Self {
+            nodes,
+            messages: BTreeSet::new(),
+            leaders: BTreeMap::new(),
+            max_term,
+            restarts_left: MAX_RESTARTS,
+        }
+    }
+
+    fn node(&mut self, node_id: NodeId) -> &mut NodeState {
+        &mut self.nodes[node_id as usize - 1]
+    }
+
+    fn steps(&self) -> Vec<Step> {
+        let mut steps = Vec::new();
+        for (node_id, node) in (1..=NODES_COUNT).zip(&self.nodes) {
+            match node.role {
+                Role::Follower if node.election.term < self.max_term => {
+                    steps.push(Step::Timeout(node_id))
+                }
+                Role::Electing(_) => steps.push(Step::ElectionTimeout(node_id)),
+                Role::Candidate(_) => steps.push(Step::CountVotes(node_id)),
+                _ => {}
+            }
+
+            if node.known_leader.is_some() {
+                for to in (1..=NODES_COUNT).filter(|to| *to != node_id) {
+                    steps.push(Step::Heartbeat { from: node_id, to });
+                }
+            }
+
+            if self.restarts_left > 0 {
+                steps.push(Step::Restart(node_id));
+            }
+        }
+        steps.extend(self.messages.iter().copied().map(Step::Deliver));
+        steps
+    }
+
+    /// Runs the call of the node's election manager, which must complete without waiting for anything.
+    fn run<T>(&mut self, node_id: NodeId, call: impl AsyncFnOnce(&ElectionManager) -> T) -> T {
+        let node = self.node(node_id);
+        let manager = ElectionManager::from_snapshot(node_id, NODES_COUNT, &node.election);
+        let result = call(&manager)
+            .now_or_never()
+            .expect("The election manager call has not completed.");
+        node.election = manager.try_snapshot().unwrap();
+        result
+    }
+
+    fn apply(&mut self, step: Step) -> Result<(), String> {
+        match step {
+            Step::Timeout(node_id) => {
+                let term = self.run(node_id, async |manager| {
+                    manager.remove_leader().await;
+                    let term = manager.next_term().await;
+                    manager.open_election(term).await;
+                    term
+                });
+                self.node(node_id).role = Role::Electing(term);
+            }
+            Step::ElectionTimeout(node_id) => {
+                let Role::Electing(term) = self.node(node_id).role else {
+                    return Ok(());
+                };
+
+                self.node(node_id).role = Role::Follower;
+                let state = self.run(node_id, async |manager| manager.close_election(term).await);
+                let term = self.node(node_id).election.term;
+                match state {
+                    ElectionState::LeaderElected(leader_id) if leader_id == node_id => {
+                        self.elect(node_id, term)?
+                    }
+                    ElectionState::NoLeaderElected => self.request_votes(node_id, term),
+                    _ => {}
+                }
+            }
+            Step::Deliver(message) => {
+                self.messages.remove(&message);
+                self.deliver(message)?;
+            }
+            Step::CountVotes(node_id) => {
+                let Role::Candidate(term) = self.node(node_id).role else {
+                    return Ok(());
+                };
+
+                if self.run(node_id, async |manager| {
+                    manager.has_majority_votes(term, node_id).await
+                }) {
+                    self.elect(node_id, term)?;
+                } else {
+                    self.run(node_id, async |manager| manager.remove_leader().await);
+                    self.node(node_id).role = Role::Follower;
+                }
+            }
+            Step::Heartbeat { from, to } => {
+                let Some((term, leader_id)) = self.node(from).known_leader else {
+                    return Ok(());
+                };
+
+                if term < self.node(to).election.term {
+                    return Ok(());
+                }
+
+                self.run(to, async |manager| manager.set_term(term).await);
+                self.set_leader(to, term, leader_id);
+            }
+            Step::Restart(node_id) => {
+                self.restarts_left -= 1;
+                let node = self.node(node_id);
+                let (term, voted_for) = node.hard_state;
+                let manager =
+                    ElectionManager::new(node_id, NODES_COUNT, ElectionTimeout::new(0, 0));
+                manager
+                    .restore(term, voted_for)
+                    .now_or_never()
+                    .expect("The election manager call has not completed.");
+                node.election = manager.try_snapshot().unwrap();
+                node.role = Role::Follower;
+                node.known_leader = None;
+            }
+        }
+        self.check()
+    }
+
+    /// Casts the vote of the node, which is saved before it's sent, as the cluster does.
+    fn vote(&mut self, node_id: NodeId, term: Term, candidate_id: NodeId) -> bool {
+        let vote = self.run(node_id, async |manager| {
+            manager.vote(term, candidate_id, node_id).await
+        });
+        if vote.is_err() {
+            return false;
+        }
+
+        self.node(node_id).hard_state = (term, Some(candidate_id));
+        true
+    }
+
+    fn request_votes(&mut self, candidate_id: NodeId, term: Term) {
+        if !self.vote(candidate_id, term, candidate_id) {
+            self.run(candidate_id, async |manager| manager.remove_leader().await);
+            return;
+        }
+
+        self.node(candidate_id).role = Role::Candidate(term);
+        for node_id in (1..=NODES_COUNT).filter(|node_id| *node_id != candidate_id) {
+            self.messages.insert(Message::RequestVote {
+                term,
+                candidate_id,
+                node_id,
+            });
+        }
+    }
+
+    fn deliver(&mut self, message: Message) -> Result<(), String> {
+        match message {
+            Message::RequestVote {
+                term,
+                candidate_id,
+                node_id,
+            } => {
+                if self.vote(node_id, term, candidate_id) {
+                    self.messages.insert(Message::Vote {
+                        term,
+                        candidate_id,
+                        node_id,
+                    });
+                }
+            }
+            Message::Vote {
+                term,
+                candidate_id,
+                node_id,
+            } => {
+                if self.node(candidate_id).role != Role::Candidate(term) {
+                    return Ok(());
+                }
+
+                let vote = self.run(candidate_id, async |manager| {
+                    manager.vote(term, candidate_id, node_id).await
+                });
+                if vote.is_err() {
+                    self.run(candidate_id, async |manager| manager.remove_leader().await);
+                    self.node(candidate_id).role = Role::Follower;
+                    return Ok(());
+                }
+
+                // The candidate stops requesting the votes, once it has the majority of them.
+                if self.run(candidate_id, async |manager| {
+                    manager.has_majority_votes(term, candidate_id).await
+                }) {
+                    self.elect(candidate_id, term)?;
+                }
+            }
+            Message::UpdateLeader {
+                term,
+                leader_id,
+                node_id,
+            } => self.set_leader(node_id, term, leader_id),
+        }
+        Ok(())
+    }
+
+    fn elect(&mut self, leader_id: NodeId, term: Term) -> Result<(), String> {
+        if let Some(elected_leader_id) = self.leaders.insert(term, leader_id) {
+            if elected_leader_id != leader_id {
+                return Err(format!(
+                    "Node ID: {leader_id} has been elected in term: {term}, which has already been won by node ID: {elected_leader_id}."
+                ));
+            }
+        }
+
+        let node = self.node(leader_id);
+        node.role = Role::Leader(term);
+        node.known_leader = Some((term, leader_id));
+        for node_id in (1..=NODES_COUNT).filter(|node_id| *node_id != leader_id) {
+            self.messages.insert(Message::UpdateLeader {
+                term,
+                leader_id,
+                node_id,
+            });
+        }
+        Ok(())
+    }
+
+    fn set_leader(&mut self, node_id: NodeId, term: Term, leader_id: NodeId) {
+        let result = self.run(node_id, async |manager| {
+            manager.set_leader(term, leader_id).await
+        });
+        if result.is_err() {
+            return;
+        }
+
+        let node = self.node(node_id);
+        node.known_leader = Some((term, leader_id));
+        if leader_id != node_id && matches!(node.role, Role::Leader(_)) {
+            node.role = Role::Follower;
+        }
+    }
+
+    /// Every node which knows the leader of the term must agree on it with the node which has won it.
+    fn check(&self) -> Result<(), String> {
+        let mut leaders = self.leaders.clone();
+        for (node_id, node) in (1..=NODES_COUNT).zip(&self.nodes) {
+            let Some(leader_id) = node.election.leader_id else {
+                continue;
+            };
+
+            let term = node.election.term;
+            let elected_leader_id = *leaders.entry(term).or_insert(leader_id);
+            if elected_leader_id != leader_id {
+                return Err(format!(
+                    "Node ID: {node_id} sees node ID: {leader_id} as the leader in term: {term}, which has been won by node ID: {elected_leader_id}."
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Visits every reachable state once, in the breadth-first order, so that the violation is reported
+/// with the shortest sequence of steps leading to it. Returns the number of the visited states.
+fn explore(max_term: Term) -> Result<usize, String> {
+    let mut states = vec![State::initial(max_term)];
+    let mut parents: Vec<Option<(usize, Step)>> = vec![None];
+    let mut indexes = HashMap::from([(states[0].clone(), 0)]);
+    let mut queue = VecDeque::from([0]);
+    while let Some(index) = queue.pop_front() {
+        for step in states[index].steps() {
+            let mut state = states[index].clone();
+            if let Err(error) = state.apply(step) {
+                let mut steps = vec![format!("{step:?}")];
+                let mut parent = parents[index];
+                while let Some((index, step)) = parent {
+                    steps.push(format!("{step:?}"));
+                    parent = parents[index];
+                }
+                steps.reverse();
+                return Err(format!("{error} Steps:\n{}", steps.join("\n")));
+            }
+
+            if let Entry::Vacant(entry) = indexes.entry(state.clone()) {
+                entry.insert(states.len());
+                queue.push_back(states.len());
+                states.push(state);
+                parents.push(Some((index, step)));
+            }
+        }
+    }
+    Ok(states.len())
+}
+
+/// Takes the random steps chosen with the seed, until there are none left or the limit is reached.
+fn walk(seed: u64, max_term: Term) -> Result<(), String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut state = State::initial(max_term);
+    let mut steps = Vec::new();
+    while steps.len() < MAX_WALK_STEPS {
+        let available_steps = state.steps();
+        if available_steps.is_empty() {
+            break;
+        }
+
+        let step = available_steps[rng.gen_range(0..available_steps.len())];
+        steps.push(format!("{step:?}"));
+        if let Err(error) = state.apply(step) {
+            return Err(format!("{error} Steps:\n{}", steps.join("\n")));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn at_most_one_leader_should_be_elected_in_the_first_term() {
+        match explore(EXPLORED_TERMS) {
+            Ok(states) => assert!(states > 1),
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    #[test]
+    fn at_most_one_leader_should_be_elected_in_each_term() {
+        for seed in 0..WALKS_COUNT {
+            if let Err(error) = walk(seed, WALKED_TERMS) {
+                panic!("Walk with seed: {seed} has failed: {error}");
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::clusters::cluster::{Cluster, ClusterNodeState};
+use crate::types::{NodeId, Term};
+use bytes::Bytes;
+use std::collections::BTreeMap;
+
+/// The safety properties checked between the simulation steps, based on what the nodes have observed so far.
+#[derive(Debug, Default)]
+pub(crate) struct Invariants {
+    leaders: BTreeMap<Term, NodeId>,
+    committed_messages: BTreeMap<(u64, u64), (u64, Bytes)>,
+}
+
+impl Invariants {
+    /// Checks the state of the node, which is skipped when any of it is locked by the running task.
+    pub fn check(&mut self, node_id: NodeId, cluster: &Cluster) -> Result<(), String> {
+        self.check_leader(node_id, cluster)?;
+        self.check_committed_messages(node_id, cluster)
+    }
+
+    /// At most one node can be the leader in the term.
+    fn check_leader(&mut self, node_id: NodeId, cluster: &Cluster) -> Result<(), String> {
+        let Some(self_node) = cluster.get_self_node() else {
+            return Ok(());
+        };
+
+        let Some(state) = self_node.state.try_lock().map(|state| *state) else {
+            return Ok(());
+        };
+
+        let Some((term, Some(leader_id))) = cluster.election_manager.try_get_leader() else {
+            return Ok(());
+        };
+
+        if state != ClusterNodeState::Leader || leader_id != node_id {
+            return Ok(());
+        }
+
+        let leader_id = *self.leaders.entry(term).or_insert(node_id);
+        if leader_id != node_id {
+            return Err(format!(
+                "Node ID: {node_id} is the leader in term: {term}, which has already been led by node ID: {leader_id}."
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The messages below the high watermark are the same on every replica and never change.
+    fn check_committed_messages(
+        &mut self,
+        node_id: NodeId,
+        cluster: &Cluster,
+    ) -> Result<(), String> {
+        let Some(streamer) = cluster.streamer.try_lock() else {
+            return Ok(());
+        };
+
+        for stream in streamer.get_streams() {
+            for message in stream
+                .messages
+                .iter()
+                .take_while(|message| message.offset < stream.high_watermark)
+            {
+                let (id, payload) = self
+                    .committed_messages
+                    .entry((stream.stream_id, message.offset))
+                    .or_insert_with(|| (message.id, message.payload.clone()));
+                if *id != message.id || *payload != message.payload {
+                    return Err(format!(
+                        "Node ID: {node_id} has committed message ID: {} at offset: {} in stream with ID: {}, but message ID: {id} has been committed before.",
+                        message.id, message.offset, stream.stream_id
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
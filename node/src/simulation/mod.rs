@@ -0,0 +1,748 @@
+//! The deterministic simulation of the cluster, which runs all of its nodes in a single thread,
+//! with the simulated clock, network and randomness driven by the seed, and the data of each node
+//! kept in memory. The time is advanced only once every task is idle, so the same seed always
+//! results in the same execution. The history of the client operations is checked for
+//! linearizability at the end of the run.
+
+mod elections;
+mod invariants;
+mod network;
+mod world;
+
+use crate::clusters::cluster::Cluster;
+use crate::configs::config::{
+    ClusterConfig, ClusterNodeConfig, MetadataConfig, NodeConfig, ServerConfig, StreamConfig,
+    SystemConfig,
+};
+use crate::connection::handler::ConnectionHandler;
+use crate::environment::memory::{MemoryStorage, WriteFault};
+use crate::environment::{self, Environment};
+use crate::simulation::invariants::Invariants;
+use crate::simulation::network::{close, node_address, SimNetwork};
+use crate::simulation::world::{SharedWorld, SimClock, SimRandom, World};
+use crate::types::NodeId;
+use bytes::Bytes;
+use futures::future::{poll_fn, LocalBoxFuture};
+use linearizability::history::{History, Operation, Outcome};
+use rand::Rng;
+use sdk::commands::add_node::AddNode;
+use sdk::commands::append_messages::{AppendMessages, AppendableMessage};
+use sdk::commands::command::Command;
+use sdk::commands::create_stream::CreateStream;
+use sdk::commands::delete_stream::DeleteStream;
+use sdk::commands::poll_messages::PollMessages;
+use sdk::error::SystemError;
+use sdk::models::message::messages_from_bytes;
+use sdk::models::read_consistency::ReadConsistency;
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+use std::rc::Rc;
+use std::task::Poll;
+use std::time::Duration;
+
+pub(crate) const NODES_COUNT: u64 = 3;
+const CLIENT_ID: NodeId = 0;
+const CLIENTS_COUNT: u64 = 2;
+const MESSAGE_IDS_PER_CLIENT: u64 = 1_000_000;
+const POLL_COUNT: u64 = 100;
+const STREAM_ID: u64 = 1;
+const INTERNAL_PORT: u16 = 8200;
+const PUBLIC_PORT: u16 = 8100;
+/// The number of yields without the time being advanced, after which the tasks are considered to be busy looping.
+const MAX_STEPS_WITHOUT_PROGRESS: u64 = 100_000;
+const SEED_ENV: &str = "IGGY_SIMULATION_SEED";
+/// The seeds run by default, unless a single one is replayed, each of them simulates a different fault schedule.
+const DEFAULT_SEEDS: Range<u64> = 0..100;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SimulationOptions {
+    /// The time for the cluster to elect the leader, before any fault is injected.
+    pub warmup: Duration,
+    /// The time in which the faults are injected.
+    pub faults: Duration,
+    /// The time for the cluster to recover, after all the faults have been healed.
+    pub recovery: Duration,
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        Self {
+            warmup: Duration::from_secs(5),
+            faults: Duration::from_secs(30),
+            recovery: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SimulatedNode {
+    id: NodeId,
+    environment: Option<Environment>,
+    /// The data of the node, which is kept when it crashes.
+    storage: Rc<MemoryStorage>,
+    /// The writes to the storage fail, until the fault is healed and the fenced node is restarted.
+    disk_fault: bool,
+    cluster: Rc<RefCell<Option<Rc<Cluster>>>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Simulation {
+    world: SharedWorld,
+    nodes: Vec<SimulatedNode>,
+    invariants: Invariants,
+    history: Rc<RefCell<History>>,
+}
+
+/// Runs the simulation for the seed and returns the trace of its events, or the violated invariant.
+pub(crate) fn run(seed: u64, options: SimulationOptions) -> Result<Vec<String>, String> {
+    simulate(seed, |simulation| Box::pin(simulation.run(options)))
+}
+
+/// Runs the scenario on the cluster started in the simulated world of the first seed,
+/// the test fails with the seed to replay it, once the scenario returns an error.
+pub(crate) fn run_scenario(
+    scenario: impl for<'a> FnOnce(&'a mut Simulation) -> LocalBoxFuture<'a, Result<(), String>>
+        + 'static,
+) {
+    let seed = seeds()[0];
+    let result = simulate(seed, |simulation| {
+        Box::pin(async move {
+            simulation.start_cluster();
+            scenario(simulation).await
+        })
+    });
+    if let Err(error) = result {
+        panic!("Simulation with seed: {seed} has failed: {error} Replay it with: {SEED_ENV}={seed} cargo test -p node");
+    }
+}
+
+/// Runs the scenario in the simulated world of the seed, all of its nodes are stopped at the end.
+fn simulate<T>(
+    seed: u64,
+    scenario: impl for<'a> FnOnce(&'a mut Simulation) -> LocalBoxFuture<'a, T>,
+) -> T {
+    let mut runtime = monoio::RuntimeBuilder::<monoio::LegacyDriver>::new()
+        .build()
+        .expect("Failed to build the simulation runtime.");
+    runtime.block_on(async {
+        let mut simulation = Simulation {
+            world: World::new(seed),
+            nodes: (1..=NODES_COUNT)
+                .map(|id| SimulatedNode {
+                    id,
+                    environment: None,
+                    storage: Rc::new(MemoryStorage::default()),
+                    disk_fault: false,
+                    cluster: Rc::new(RefCell::new(None)),
+                })
+                .collect(),
+            invariants: Invariants::default(),
+            history: Rc::new(RefCell::new(History::new())),
+        };
+        let result = scenario(&mut simulation).await;
+        simulation.stop();
+        result
+    })
+}
+
+impl Simulation {
+    async fn run(&mut self, options: SimulationOptions) -> Result<Vec<String>, String> {
+        self.start_cluster();
+        self.start_clients();
+
+        let faults_to = options.warmup + options.faults;
+        self.run_until(options.warmup).await?;
+        while self.world.borrow().elapsed < faults_to {
+            self.inject_fault();
+            let delay = self.world.borrow_mut().rng.gen_range(500..=3000);
+            let next_fault_at = self.world.borrow().elapsed + Duration::from_millis(delay);
+            self.run_until(next_fault_at.min(faults_to)).await?;
+        }
+        self.heal();
+        self.run_until(faults_to + options.recovery).await?;
+
+        linearizability::checker::check(&self.history.borrow())
+            .map_err(|violation| violation.to_string())?;
+        Ok(std::mem::take(&mut self.world.borrow_mut().trace))
+    }
+
+    /// Advances the time from one timer to the next, checking the invariants in between, up to the given time.
+    async fn run_until(&mut self, until: Duration) -> Result<(), String> {
+        loop {
+            let elapsed = self.world.borrow().elapsed;
+            self.wait_until_idle()
+                .await
+                .and_then(|_| self.check_invariants())
+                .map_err(|error| format!("{error} Elapsed: {} ms.", elapsed.as_millis()))?;
+            if elapsed >= until {
+                return Ok(());
+            }
+
+            let next_step = self
+                .world
+                .borrow()
+                .next_deadline()
+                .map_or(until, |deadline| deadline.min(until));
+            let wakers = self.world.borrow_mut().advance(next_step);
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Advances the time by the given duration, checking the invariants in between.
+    pub(crate) async fn run_for(&mut self, duration: Duration) -> Result<(), String> {
+        let elapsed = self.world.borrow().elapsed;
+        self.run_until(elapsed + duration).await
+    }
+
+    /// Returns the node which considers itself to be the leader, once all the other running nodes,
+    /// which aren't fenced, agree on it.
+    pub(crate) async fn leader_id(&self) -> Option<NodeId> {
+        let mut leader_id = None;
+        for node_id in 1..=NODES_COUNT {
+            let Some(cluster) = self.cluster(node_id) else {
+                continue;
+            };
+            if cluster.is_leader().await {
+                leader_id = Some(node_id);
+            }
+        }
+        let leader_id = leader_id?;
+        for node in &self.nodes {
+            if node.environment.is_none() {
+                continue;
+            }
+            // The node which is still starting doesn't have the cluster yet.
+            let cluster = self.cluster(node.id)?;
+            if !cluster.is_fenced().await
+                && cluster.get_metadata().await.leader_id != Some(leader_id)
+            {
+                return None;
+            }
+        }
+        Some(leader_id)
+    }
+
+    /// Runs the cluster, until all of its running nodes agree on the leader.
+    pub(crate) async fn wait_for_leader(&mut self) -> Result<NodeId, String> {
+        for _ in 0..100 {
+            if let Some(leader_id) = self.leader_id().await {
+                return Ok(leader_id);
+            }
+            self.run_for(Duration::from_millis(100)).await?;
+        }
+        Err("No leader has been elected.".into())
+    }
+
+    /// Spawns the client task, its output is available once it has completed.
+    pub(crate) fn spawn_client<T: 'static>(
+        &self,
+        future: impl std::future::Future<Output = T> + 'static,
+    ) -> Rc<RefCell<Option<T>>> {
+        let output = Rc::new(RefCell::new(None));
+        let task_output = output.clone();
+        self.environment(CLIENT_ID).spawn(async move {
+            task_output.replace(Some(future.await));
+        });
+        output
+    }
+
+    /// Yields to the node tasks, until none of them has been polled in the meantime.
+    async fn wait_until_idle(&self) -> Result<(), String> {
+        let mut steps = 0;
+        loop {
+            let polls = environment::polls();
+            yield_now().await;
+            if environment::polls() == polls {
+                return Ok(());
+            }
+
+            steps += 1;
+            if steps > MAX_STEPS_WITHOUT_PROGRESS {
+                return Err("The tasks are busy looping without the time being advanced.".into());
+            }
+        }
+    }
+
+    pub(crate) fn cluster(&self, node_id: NodeId) -> Option<Rc<Cluster>> {
+        self.nodes[node_id as usize - 1].cluster.borrow().clone()
+    }
+
+    fn check_invariants(&mut self) -> Result<(), String> {
+        for node in &self.nodes {
+            let cluster = node.cluster.borrow().clone();
+            if let Some(cluster) = cluster {
+                self.invariants.check(node.id, &cluster)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The clients don't store anything, so each of the environments created for them gets the empty storage.
+    fn environment(&self, node_id: NodeId) -> Environment {
+        let storage = match node_id {
+            CLIENT_ID => Rc::new(MemoryStorage::default()),
+            _ => self.nodes[node_id as usize - 1].storage.clone(),
+        };
+        Environment::new(
+            Rc::new(SimClock {
+                world: self.world.clone(),
+                node_id,
+            }),
+            Rc::new(SimNetwork {
+                world: self.world.clone(),
+                node_id,
+            }),
+            Rc::new(SimRandom {
+                world: self.world.clone(),
+            }),
+            storage,
+        )
+    }
+
+    pub(crate) fn start_cluster(&mut self) {
+        for node_id in 1..=NODES_COUNT {
+            self.start_node(node_id);
+        }
+    }
+
+    pub(crate) fn start_node(&mut self, node_id: NodeId) {
+        self.world
+            .borrow_mut()
+            .record(format!("start node {node_id}"));
+        let config = self.config(node_id);
+        let environment = self.environment(node_id);
+        let node = &mut self.nodes[node_id as usize - 1];
+        let cluster = node.cluster.clone();
+        let world = self.world.clone();
+        environment.spawn(async move {
+            match crate::start(&config).await {
+                Ok(started_cluster) => {
+                    cluster.borrow_mut().replace(started_cluster);
+                }
+                Err(error) => {
+                    world
+                        .borrow_mut()
+                        .record(format!("node {node_id} failed to start: {error}"));
+                }
+            }
+        });
+        node.environment = Some(environment);
+    }
+
+    /// Stops all the tasks of the node and closes its connections, the data in its storage is kept for the restart.
+    pub(crate) fn crash_node(&mut self, node_id: NodeId) {
+        let node = &mut self.nodes[node_id as usize - 1];
+        let Some(environment) = node.environment.take() else {
+            return;
+        };
+
+        environment.stop();
+        node.cluster.borrow_mut().take();
+        let mut world = self.world.borrow_mut();
+        world.record(format!("crash node {node_id}"));
+        world
+            .listeners
+            .retain(|_, listener| listener.node_id != node_id);
+        let connections = world
+            .connections
+            .iter()
+            .filter(|(_, connection)| connection.nodes.contains(&node_id))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        let mut wakers = world.take_timers(node_id);
+        for connection_id in connections {
+            wakers.extend(close(&mut world.connections, connection_id));
+        }
+        drop(world);
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn partition(&mut self, first: NodeId, second: NodeId) {
+        let mut world = self.world.borrow_mut();
+        world.record(format!("partition {first} <-> {second}"));
+        world
+            .partitions
+            .insert((first.min(second), first.max(second)));
+        let connections = world
+            .connections
+            .iter()
+            .filter(|(_, connection)| {
+                connection.nodes.contains(&first) && connection.nodes.contains(&second)
+            })
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        let mut wakers = Vec::new();
+        for connection_id in connections {
+            wakers.extend(close(&mut world.connections, connection_id));
+        }
+        drop(world);
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn heal_partitions(&mut self) {
+        let mut world = self.world.borrow_mut();
+        world.record("heal partitions".into());
+        world.partitions.clear();
+    }
+
+    pub(crate) fn storage(&self, node_id: NodeId) -> Rc<MemoryStorage> {
+        self.nodes[node_id as usize - 1].storage.clone()
+    }
+
+    pub(crate) fn fail_disk(&mut self, node_id: NodeId, write_fault: WriteFault) {
+        self.world
+            .borrow_mut()
+            .record(format!("disk fault on node {node_id}: {write_fault:?}"));
+        let node = &mut self.nodes[node_id as usize - 1];
+        node.storage.set_write_fault(Some(write_fault));
+        node.disk_fault = true;
+    }
+
+    fn inject_fault(&mut self) {
+        let crashed = self
+            .nodes
+            .iter()
+            .filter(|node| node.environment.is_none())
+            .map(|node| node.id)
+            .collect::<Vec<_>>();
+        let (fault, first, second) = {
+            let mut world = self.world.borrow_mut();
+            let fault = world.rng.gen_range(0..6);
+            let first = world.rng.gen_range(1..=NODES_COUNT);
+            let second = world.rng.gen_range(1..=NODES_COUNT);
+            (fault, first, second)
+        };
+        match fault {
+            0 if first != second => self.partition(first, second),
+            1 => self.heal_partitions(),
+            // Only the minority of the nodes is crashed at a time.
+            2 if crashed.is_empty() => self.crash_node(first),
+            3 => {
+                for node_id in crashed {
+                    self.start_node(node_id);
+                }
+            }
+            // Only a single disk fails at a time, as it fences the node like it was crashed.
+            4 if crashed.is_empty() && self.nodes.iter().all(|node| !node.disk_fault) => {
+                let write_fault = if self.world.borrow_mut().rng.gen_bool(0.5) {
+                    WriteFault::Failed
+                } else {
+                    WriteFault::Torn
+                };
+                self.fail_disk(first, write_fault)
+            }
+            _ => {
+                let mut world = self.world.borrow_mut();
+                let drop_probability = [0.0, 0.01, 0.05][world.rng.gen_range(0..3)];
+                let max_latency = [5, 50, 200][world.rng.gen_range(0..3)];
+                world.drop_probability = drop_probability;
+                world.latency_ms = (1, max_latency);
+                world.record(format!(
+                    "network drop probability: {drop_probability}, latency: 1 - {max_latency} ms"
+                ));
+            }
+        }
+    }
+
+    fn heal(&mut self) {
+        {
+            let mut world = self.world.borrow_mut();
+            world.record("heal all".into());
+            world.partitions.clear();
+            world.drop_probability = 0.0;
+            world.latency_ms = (1, 5);
+        }
+        // The fenced nodes stay fenced until they're restarted.
+        for node_id in 1..=NODES_COUNT {
+            let node = &mut self.nodes[node_id as usize - 1];
+            if node.disk_fault {
+                node.storage.set_write_fault(None);
+                node.disk_fault = false;
+                self.crash_node(node_id);
+            }
+        }
+        for node_id in 1..=NODES_COUNT {
+            if self.nodes[node_id as usize - 1].environment.is_none() {
+                self.start_node(node_id);
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        for node in &mut self.nodes {
+            if let Some(environment) = node.environment.take() {
+                environment.stop();
+            }
+            node.cluster.borrow_mut().take();
+        }
+    }
+
+    /// Starts the clients, the first one creates the stream, then all of them keep appending
+    /// and polling the messages, while their operations are recorded in the history.
+    fn start_clients(&self) {
+        let environment = self.environment(CLIENT_ID);
+        let stream_created = Rc::new(Cell::new(false));
+        for client_id in 1..=CLIENTS_COUNT {
+            let world = self.world.clone();
+            let history = self.history.clone();
+            let stream_created = stream_created.clone();
+            environment.spawn(async move {
+                let mut message_id = client_id * MESSAGE_IDS_PER_CLIENT;
+                let mut polled_count = 0;
+                // The failed creation might have taken effect, so the stream is polled before creating it again.
+                let mut creation_failed = false;
+                loop {
+                    let delay = world.borrow_mut().rng.gen_range(50..=200);
+                    environment::sleep(Duration::from_millis(delay)).await;
+                    if !stream_created.get() && client_id > 1 {
+                        continue;
+                    }
+
+                    let operation = if !stream_created.get() && !creation_failed {
+                        Operation::CreateStream {
+                            stream_id: STREAM_ID,
+                        }
+                    } else if stream_created.get() && world.borrow_mut().rng.gen_bool(0.5) {
+                        message_id += 1;
+                        Operation::AppendMessages {
+                            stream_id: STREAM_ID,
+                            ids: vec![message_id],
+                        }
+                    } else {
+                        let offset = world.borrow_mut().rng.gen_range(0..=polled_count);
+                        Operation::PollMessages {
+                            stream_id: STREAM_ID,
+                            offset,
+                            count: POLL_COUNT,
+                        }
+                    };
+                    let command = as_command(&operation);
+                    let index = history.borrow_mut().invoke(
+                        client_id,
+                        operation.clone(),
+                        environment::now(),
+                    );
+                    let response = send(&command).await;
+                    let outcome = match (&operation, &response) {
+                        (Operation::CreateStream { .. }, None) => {
+                            creation_failed = true;
+                            Outcome::Failed
+                        }
+                        (Operation::PollMessages { .. }, None) if !stream_created.get() => {
+                            creation_failed = false;
+                            Outcome::Failed
+                        }
+                        (_, None) => Outcome::Failed,
+                        (Operation::PollMessages { offset, .. }, Some((_, bytes))) => {
+                            stream_created.set(true);
+                            let messages = messages_from_bytes(bytes);
+                            polled_count = polled_count.max(
+                                messages
+                                    .last()
+                                    .map_or(*offset, |message| message.offset + 1),
+                            );
+                            Outcome::Messages(
+                                messages
+                                    .iter()
+                                    .map(|message| (message.offset, message.id))
+                                    .collect(),
+                            )
+                        }
+                        (Operation::CreateStream { .. }, Some(_)) => {
+                            stream_created.set(true);
+                            Outcome::Ok
+                        }
+                        (_, Some(_)) => Outcome::Ok,
+                    };
+                    history
+                        .borrow_mut()
+                        .complete(index, outcome.clone(), environment::now());
+                    let node_id = response.map_or("none".to_string(), |(id, _)| id.to_string());
+                    world.borrow_mut().record(format!(
+                        "client {client_id}: {operation} on node {node_id} -> {outcome}"
+                    ));
+                }
+            });
+        }
+    }
+
+    fn config(&self, node_id: NodeId) -> SystemConfig {
+        let directory = format!("node{node_id}");
+        SystemConfig {
+            node: NodeConfig {
+                id: node_id,
+                name: format!("node{node_id}"),
+                address: node_address(node_id, INTERNAL_PORT).to_string(),
+            },
+            cluster: ClusterConfig {
+                heartbeat_interval: 1000,
+                reconnection_interval: 200,
+                election_timeout_range_from: 150,
+                election_timeout_range_to: 300,
+                // The streams are bootstrapped in many chunks, so that the interrupted copy can be resumed.
+                bootstrap_chunk_size: 256,
+                state_path: directory.clone(),
+                nodes: (1..=NODES_COUNT)
+                    .filter(|id| *id != node_id)
+                    .map(|id| ClusterNodeConfig {
+                        id,
+                        name: format!("node{id}"),
+                        public_address: node_address(id, PUBLIC_PORT).to_string(),
+                        internal_address: node_address(id, INTERNAL_PORT).to_string(),
+                        learner: false,
+                    })
+                    .collect(),
+                ..ClusterConfig::default()
+            },
+            stream: StreamConfig {
+                path: format!("{directory}/streams"),
+            },
+            server: ServerConfig {
+                address: node_address(node_id, PUBLIC_PORT).to_string(),
+            },
+            metadata: MetadataConfig::default(),
+        }
+    }
+}
+
+pub(crate) fn as_command(operation: &Operation) -> Command {
+    match operation {
+        Operation::CreateStream { stream_id } => {
+            CreateStream::new_command(*stream_id, Some(NODES_COUNT as u8), Some(2), vec![])
+        }
+        Operation::DeleteStream { stream_id } => DeleteStream::new_command(*stream_id),
+        Operation::AppendMessages { stream_id, ids } => AppendMessages::new_command(
+            *stream_id,
+            ids.iter()
+                .map(|id| AppendableMessage {
+                    id: *id,
+                    payload: Bytes::from(format!("message-{id}")),
+                })
+                .collect(),
+        ),
+        Operation::PollMessages {
+            stream_id,
+            offset,
+            count,
+        } => PollMessages::new_command(*stream_id, *offset, *count, ReadConsistency::Linearizable),
+    }
+}
+
+/// Returns the command adding the simulated node to the cluster, either as a learner or as a voter.
+pub(crate) fn add_node_command(node_id: NodeId, learner: bool) -> Command {
+    AddNode::new_command(
+        node_id,
+        format!("node{node_id}"),
+        node_address(node_id, PUBLIC_PORT).to_string(),
+        node_address(node_id, INTERNAL_PORT).to_string(),
+        learner,
+    )
+}
+
+/// Sends the command to the given node only, returning the error the node has responded with.
+pub(crate) async fn send_to(node_id: NodeId, command: &Command) -> Result<Vec<u8>, SystemError> {
+    let address = node_address(node_id, PUBLIC_PORT);
+    let connection = environment::connect(address).await?;
+    let mut handler = ConnectionHandler::new(connection, address, CLIENT_ID);
+    environment::timeout(Duration::from_secs(3), handler.send_request(command))
+        .await
+        .ok_or(SystemError::CannotReadResponse)?
+        .map(|(_, bytes)| bytes)
+}
+
+/// Sends the command to the nodes in order, until one of them handles it successfully.
+pub(crate) async fn send(command: &Command) -> Option<(NodeId, Vec<u8>)> {
+    for node_id in 1..=NODES_COUNT {
+        let address = node_address(node_id, PUBLIC_PORT);
+        let Ok(connection) = environment::connect(address).await else {
+            continue;
+        };
+
+        let mut handler = ConnectionHandler::new(connection, address, CLIENT_ID);
+        let response =
+            environment::timeout(Duration::from_secs(3), handler.send_request(command)).await;
+        match response {
+            Some(Ok((_, bytes))) => return Some((node_id, bytes)),
+            Some(Err(SystemError::InvalidResponse(status, _))) if is_rejected(status) => continue,
+            // The command might have taken effect on this node, so sending it again could apply it twice.
+            _ if !matches!(command, Command::PollMessages(_)) => return None,
+            _ => continue,
+        }
+    }
+    None
+}
+
+/// The node has rejected the command before handling it, e.g. it's not the leader, so it can be sent to the next one.
+fn is_rejected(status: u32) -> bool {
+    [
+        SystemError::UnhealthyCluster,
+        SystemError::NotLeader,
+        SystemError::LeaderNotElected,
+        SystemError::NotEnoughReplicas(0),
+        SystemError::NotStreamLeader(0),
+        SystemError::NotStreamReplica(0),
+        SystemError::NodeFenced,
+        SystemError::InsufficientDiskSpace,
+    ]
+    .iter()
+    .any(|error| error.as_code() == status)
+}
+
+/// Lets all the tasks, which are already scheduled, to run first. Waking the running task itself
+/// would schedule it at the front of the queue, so it's woken by the task spawned at its back.
+async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+
+        yielded = true;
+        let waker = cx.waker().clone();
+        monoio::spawn(async move { waker.wake() });
+        Poll::Pending
+    })
+    .await
+}
+
+fn seeds() -> Vec<u64> {
+    match std::env::var(SEED_ENV) {
+        Ok(seed) => vec![seed.parse().expect("Invalid simulation seed.")],
+        Err(_) => DEFAULT_SEEDS.collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cluster_should_keep_invariants_under_faults() {
+        for seed in seeds() {
+            if let Err(error) = run(seed, SimulationOptions::default()) {
+                panic!("Simulation with seed: {seed} has failed: {error} Replay it with: {SEED_ENV}={seed} cargo test -p node simulation");
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_should_replay_the_same_execution() {
+        let seed = seeds()[0];
+        let options = SimulationOptions {
+            warmup: Duration::from_secs(2),
+            faults: Duration::from_secs(10),
+            recovery: Duration::from_secs(2),
+        };
+        let first = run(seed, options);
+        let second = run(seed, options);
+        assert!(first.as_ref().is_ok_and(|trace| !trace.is_empty()));
+        assert_eq!(first, second);
+    }
+}
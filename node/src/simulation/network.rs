@@ -0,0 +1,243 @@
+use crate::environment::{Clock, Connection, Listener, Network};
+use crate::simulation::world::{
+    Event, SharedWorld, SimClock, SimConnectionState, SimListenerState,
+};
+use crate::types::NodeId;
+use futures::future::{poll_fn, LocalBoxFuture};
+use rand::Rng;
+use std::collections::VecDeque;
+use std::io;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::task::{Poll, Waker};
+
+/// Returns the address of the simulated node, all of its servers share the same IP.
+pub(crate) fn node_address(node_id: NodeId, port: u16) -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, node_id as u8)), port)
+}
+
+#[derive(Debug)]
+pub(crate) struct SimNetwork {
+    pub world: SharedWorld,
+    pub node_id: NodeId,
+}
+
+impl Network for SimNetwork {
+    fn connect(
+        &self,
+        address: SocketAddr,
+    ) -> LocalBoxFuture<'static, io::Result<Box<dyn Connection>>> {
+        let world = self.world.clone();
+        let node_id = self.node_id;
+        let latency = world.borrow_mut().latency();
+        let clock = SimClock {
+            world: world.clone(),
+            node_id,
+        };
+        Box::pin(async move {
+            clock.sleep(latency).await;
+            let mut state = world.borrow_mut();
+            let Some(listener) = state.listeners.get(&address) else {
+                return Err(ErrorKind::ConnectionRefused.into());
+            };
+
+            let remote_node_id = listener.node_id;
+            let drop_probability = state.drop_probability;
+            if state.is_partitioned(node_id, remote_node_id) || state.rng.gen_bool(drop_probability)
+            {
+                state.record(format!("refused {node_id} -> {remote_node_id}"));
+                return Err(ErrorKind::ConnectionRefused.into());
+            }
+
+            state.next_port += 1;
+            let local_address = node_address(node_id, state.next_port);
+            let connection_id = state.next_port as u64;
+            state.connections.insert(
+                connection_id,
+                SimConnectionState {
+                    nodes: [node_id, remote_node_id],
+                    addresses: [local_address, address],
+                    incoming: [VecDeque::new(), VecDeque::new()],
+                    wakers: [None, None],
+                },
+            );
+            let listener = state.listeners.get_mut(&address).unwrap();
+            listener.pending.push_back((connection_id, local_address));
+            if let Some(waker) = listener.waker.take() {
+                waker.wake();
+            }
+            state.record(format!(
+                "connected {node_id} -> {remote_node_id}, connection: {connection_id}"
+            ));
+            Ok(Box::new(SimConnection {
+                world: world.clone(),
+                id: connection_id,
+                side: 0,
+            }) as Box<dyn Connection>)
+        })
+    }
+
+    fn bind(&self, address: &str) -> io::Result<Box<dyn Listener>> {
+        let address = address
+            .parse::<SocketAddr>()
+            .map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+        let mut world = self.world.borrow_mut();
+        if world.listeners.contains_key(&address) {
+            return Err(ErrorKind::AddrInUse.into());
+        }
+
+        world.listeners.insert(
+            address,
+            SimListenerState {
+                node_id: self.node_id,
+                pending: VecDeque::new(),
+                waker: None,
+            },
+        );
+        Ok(Box::new(SimListener {
+            world: self.world.clone(),
+            address,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct SimListener {
+    world: SharedWorld,
+    address: SocketAddr,
+}
+
+impl Listener for SimListener {
+    fn accept(&self) -> LocalBoxFuture<'_, io::Result<(Box<dyn Connection>, SocketAddr)>> {
+        Box::pin(poll_fn(move |cx| {
+            let mut world = self.world.borrow_mut();
+            // The listener of the crashed node is gone, its task won't be polled anymore.
+            let Some(listener) = world.listeners.get_mut(&self.address) else {
+                return Poll::Pending;
+            };
+
+            let Some((connection_id, address)) = listener.pending.pop_front() else {
+                listener.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            };
+
+            let connection = SimConnection {
+                world: self.world.clone(),
+                id: connection_id,
+                side: 1,
+            };
+            Poll::Ready(Ok((Box::new(connection) as Box<dyn Connection>, address)))
+        }))
+    }
+}
+
+/// The side of the connection, it reads the chunks which have been delivered by the current time.
+#[derive(Debug)]
+struct SimConnection {
+    world: SharedWorld,
+    id: u64,
+    side: usize,
+}
+
+impl Connection for SimConnection {
+    fn read(&mut self, mut buffer: Vec<u8>) -> LocalBoxFuture<'_, (io::Result<usize>, Vec<u8>)> {
+        Box::pin(poll_fn(move |cx| {
+            let mut world = self.world.borrow_mut();
+            let elapsed = world.elapsed;
+            let Some(connection) = world.connections.get_mut(&self.id) else {
+                return Poll::Ready((Err(ErrorKind::ConnectionReset.into()), vec![]));
+            };
+
+            let incoming = &mut connection.incoming[self.side];
+            let delivered = incoming
+                .iter()
+                .take_while(|(at, _)| *at <= elapsed)
+                .map(|(_, chunk)| chunk.len())
+                .sum::<usize>();
+            if delivered < buffer.len() {
+                connection.wakers[self.side] = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            let mut position = 0;
+            while position < buffer.len() {
+                let (_, chunk) = incoming.front_mut().unwrap();
+                let length = chunk.len().min(buffer.len() - position);
+                buffer[position..position + length].copy_from_slice(&chunk[..length]);
+                chunk.drain(..length);
+                if chunk.is_empty() {
+                    incoming.pop_front();
+                }
+                position += length;
+            }
+            Poll::Ready((Ok(position), std::mem::take(&mut buffer)))
+        }))
+    }
+
+    fn write_all(&mut self, buffer: Vec<u8>) -> LocalBoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            let mut world = self.world.borrow_mut();
+            let Some(connection) = world.connections.get(&self.id) else {
+                return Err(ErrorKind::ConnectionReset.into());
+            };
+
+            let from = connection.nodes[self.side];
+            let to = connection.nodes[1 - self.side];
+            let drop_probability = world.drop_probability;
+            if world.is_partitioned(from, to) || world.rng.gen_bool(drop_probability) {
+                world.record(format!("dropped {from} -> {to}, connection: {}", self.id));
+                for waker in close(&mut world.connections, self.id) {
+                    waker.wake();
+                }
+                return Ok(());
+            }
+
+            let latency = world.latency();
+            let deliver_at = world.elapsed + latency;
+            let connection = world.connections.get_mut(&self.id).unwrap();
+            let incoming = &mut connection.incoming[1 - self.side];
+            // The chunks are delivered in order, as the TCP would do.
+            let deliver_at = incoming
+                .back()
+                .map_or(deliver_at, |(at, _)| deliver_at.max(*at));
+            let length = buffer.len();
+            incoming.push_back((deliver_at, buffer));
+            world.schedule(deliver_at, Event::Deliver(self.id, 1 - self.side));
+            world.record(format!(
+                "sent {from} -> {to}, connection: {}, bytes: {length}",
+                self.id
+            ));
+            Ok(())
+        })
+    }
+
+    fn peer_address(&self) -> io::Result<SocketAddr> {
+        let world = self.world.borrow();
+        let connection = world
+            .connections
+            .get(&self.id)
+            .ok_or(io::Error::from(ErrorKind::NotConnected))?;
+        Ok(connection.addresses[1 - self.side])
+    }
+}
+
+impl Drop for SimConnection {
+    fn drop(&mut self) {
+        if let Ok(mut world) = self.world.try_borrow_mut() {
+            for waker in close(&mut world.connections, self.id) {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Removes the connection and returns the wakers of both sides, which will see it has been reset.
+pub(crate) fn close(
+    connections: &mut std::collections::BTreeMap<u64, SimConnectionState>,
+    connection_id: u64,
+) -> Vec<Waker> {
+    connections
+        .remove(&connection_id)
+        .map(|connection| connection.wakers.into_iter().flatten().collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,219 @@
+use crate::environment::{Clock, Random};
+use crate::types::NodeId;
+use futures::future::LocalBoxFuture;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// The simulated time starts at the fixed timestamp, so that the nodes see the same one in every run.
+const EPOCH_MICROS: u64 = 1_700_000_000_000_000;
+
+pub(crate) type SharedWorld = Rc<RefCell<World>>;
+
+/// The single source of time, randomness and network state shared by all the simulated nodes.
+#[derive(Debug)]
+pub(crate) struct World {
+    pub rng: StdRng,
+    pub elapsed: Duration,
+    base: Instant,
+    next_sequence: u64,
+    pub events: BTreeMap<(Duration, u64), Event>,
+    pub listeners: BTreeMap<SocketAddr, SimListenerState>,
+    pub connections: BTreeMap<u64, SimConnectionState>,
+    pub next_port: u16,
+    pub partitions: BTreeSet<(NodeId, NodeId)>,
+    pub drop_probability: f64,
+    pub latency_ms: (u64, u64),
+    pub trace: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Event {
+    Wake(NodeId, Waker),
+    Deliver(u64, usize),
+}
+
+#[derive(Debug)]
+pub(crate) struct SimListenerState {
+    pub node_id: NodeId,
+    pub pending: VecDeque<(u64, SocketAddr)>,
+    pub waker: Option<Waker>,
+}
+
+/// The connection between two nodes, each side has its own address and the queue of the incoming chunks.
+#[derive(Debug)]
+pub(crate) struct SimConnectionState {
+    pub nodes: [NodeId; 2],
+    pub addresses: [SocketAddr; 2],
+    pub incoming: [VecDeque<(Duration, Vec<u8>)>; 2],
+    pub wakers: [Option<Waker>; 2],
+}
+
+impl World {
+    pub fn new(seed: u64) -> SharedWorld {
+        Rc::new(RefCell::new(Self {
+            rng: StdRng::seed_from_u64(seed),
+            elapsed: Duration::ZERO,
+            base: Instant::now(),
+            next_sequence: 0,
+            events: BTreeMap::new(),
+            listeners: BTreeMap::new(),
+            connections: BTreeMap::new(),
+            next_port: 40000,
+            partitions: BTreeSet::new(),
+            drop_probability: 0.0,
+            latency_ms: (1, 5),
+            trace: Vec::new(),
+        }))
+    }
+
+    pub fn record(&mut self, event: String) {
+        let entry = format!("{} ms: {event}", self.elapsed.as_millis());
+        self.trace.push(entry);
+    }
+
+    pub fn schedule(&mut self, at: Duration, event: Event) -> (Duration, u64) {
+        self.next_sequence += 1;
+        let key = (at, self.next_sequence);
+        self.events.insert(key, event);
+        key
+    }
+
+    pub fn next_deadline(&self) -> Option<Duration> {
+        self.events.keys().next().map(|(at, _)| *at)
+    }
+
+    /// Moves the time forward and returns the wakers of the timers and the connections, which became ready.
+    pub fn advance(&mut self, to: Duration) -> Vec<Waker> {
+        if to > self.elapsed {
+            self.elapsed = to;
+        }
+
+        let mut wakers = Vec::new();
+        while let Some(entry) = self.events.first_entry() {
+            if entry.key().0 > self.elapsed {
+                break;
+            }
+
+            match entry.remove() {
+                Event::Wake(_, waker) => wakers.push(waker),
+                Event::Deliver(connection_id, side) => {
+                    if let Some(connection) = self.connections.get_mut(&connection_id) {
+                        if let Some(waker) = connection.wakers[side].take() {
+                            wakers.push(waker);
+                        }
+                    }
+                }
+            }
+        }
+        wakers
+    }
+
+    /// Returns the wakers of the sleeping tasks of the crashed node, so that they can complete.
+    pub fn take_timers(&mut self, node_id: NodeId) -> Vec<Waker> {
+        let keys = self
+            .events
+            .iter()
+            .filter(|(_, event)| matches!(event, Event::Wake(id, _) if *id == node_id))
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        keys.into_iter()
+            .filter_map(|key| match self.events.remove(&key) {
+                Some(Event::Wake(_, waker)) => Some(waker),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn is_partitioned(&self, first: NodeId, second: NodeId) -> bool {
+        self.partitions
+            .contains(&(first.min(second), first.max(second)))
+    }
+
+    pub fn latency(&mut self) -> Duration {
+        let (from, to) = self.latency_ms;
+        Duration::from_millis(self.rng.gen_range(from..=to))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SimClock {
+    pub world: SharedWorld,
+    pub node_id: NodeId,
+}
+
+impl Clock for SimClock {
+    fn now(&self) -> Instant {
+        let world = self.world.borrow();
+        world.base + world.elapsed
+    }
+
+    fn timestamp(&self) -> u64 {
+        EPOCH_MICROS + self.world.borrow().elapsed.as_micros() as u64
+    }
+
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+        let deadline = self.world.borrow().elapsed + duration;
+        Box::pin(SimSleep {
+            world: self.world.clone(),
+            node_id: self.node_id,
+            deadline,
+            key: None,
+        })
+    }
+}
+
+struct SimSleep {
+    world: SharedWorld,
+    node_id: NodeId,
+    deadline: Duration,
+    key: Option<(Duration, u64)>,
+}
+
+impl Future for SimSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let world = self.world.clone();
+        let mut world = world.borrow_mut();
+        if let Some(key) = self.key.take() {
+            world.events.remove(&key);
+        }
+
+        if world.elapsed >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        let key = world.schedule(self.deadline, Event::Wake(self.node_id, cx.waker().clone()));
+        self.key = Some(key);
+        Poll::Pending
+    }
+}
+
+impl Drop for SimSleep {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            if let Ok(mut world) = self.world.try_borrow_mut() {
+                world.events.remove(&key);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SimRandom {
+    pub world: SharedWorld,
+}
+
+impl Random for SimRandom {
+    fn gen_range(&self, from: u64, to: u64) -> u64 {
+        self.world.borrow_mut().rng.gen_range(from..=to)
+    }
+}
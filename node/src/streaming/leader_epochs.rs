@@ -0,0 +1,205 @@
+use crate::environment;
+use sdk::bytes_serializable::BytesSerializable;
+use sdk::error::SystemError;
+use sdk::models::leader_epoch::{EpochEndOffset, LeaderEpoch};
+use tracing::{error, info};
+
+pub(crate) const LEADER_EPOCH_SIZE: usize = 16;
+
+/// The start offsets of the leader epochs of the stream, ordered by both the epoch and the offset.
+/// They're stored in the separate file next to the stream log and rewritten on every change.
+#[derive(Debug)]
+pub(crate) struct LeaderEpochs {
+    path: String,
+    epochs: Vec<LeaderEpoch>,
+}
+
+impl LeaderEpochs {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            epochs: Vec::new(),
+        }
+    }
+
+    /// The epochs are required to reconcile the log with the leader's one, so they must be read in full,
+    /// otherwise the log would be truncated at the wrong offsets.
+    pub async fn init(&mut self) -> Result<(), SystemError> {
+        let storage = environment::storage();
+        if !storage.exists(&self.path) {
+            return Ok(());
+        }
+
+        let buffer = match storage.read(&self.path).await {
+            Ok(buffer) => buffer,
+            Err(error) => {
+                error!("Failed to read leader epochs: {}, {error}", self.path);
+                return Err(error.into());
+            }
+        };
+        if buffer.len() % LEADER_EPOCH_SIZE != 0 {
+            error!(
+                "Invalid leader epochs: {}, size: {} bytes is not a multiple of {LEADER_EPOCH_SIZE} bytes.",
+                self.path,
+                buffer.len()
+            );
+            return Err(SystemError::InvalidLeaderEpochs);
+        }
+
+        self.epochs = buffer
+            .chunks_exact(LEADER_EPOCH_SIZE)
+            .map(LeaderEpoch::from_bytes)
+            .collect::<Result<Vec<LeaderEpoch>, SystemError>>()?;
+        info!(
+            "Initialized {} leader epochs: {}",
+            self.epochs.len(),
+            self.path
+        );
+        Ok(())
+    }
+
+    pub fn get_last_epoch(&self) -> Option<u64> {
+        self.epochs.last().map(|leader_epoch| leader_epoch.epoch)
+    }
+
+    /// Starts the new epoch at the given offset, unless it's not newer than the last one.
+    pub async fn assign(&mut self, epoch: u64, start_offset: u64) -> Result<(), SystemError> {
+        if self
+            .epochs
+            .last()
+            .is_some_and(|last| last.epoch >= epoch || last.start_offset > start_offset)
+        {
+            return Ok(());
+        }
+
+        self.epochs.push(LeaderEpoch::new(epoch, start_offset));
+        info!(
+            "Assigned leader epoch: {epoch}, start offset: {start_offset}, path: {}",
+            self.path
+        );
+        self.save().await
+    }
+
+    /// Removes the epochs starting at or above the offset, when the log is truncated.
+    pub async fn truncate(&mut self, offset: u64) -> Result<(), SystemError> {
+        let epochs_count = self.epochs.len();
+        self.epochs
+            .retain(|leader_epoch| leader_epoch.start_offset < offset);
+        if self.epochs.len() == epochs_count {
+            return Ok(());
+        }
+
+        self.save().await
+    }
+
+    /// Returns the largest epoch not greater than the requested one along with the start offset of the next epoch,
+    /// or the log end offset if it's the last one. If all the epochs are newer, the requested epoch ends
+    /// where the first one starts.
+    pub fn get_end_offset(&self, epoch: u64, log_end_offset: u64) -> EpochEndOffset {
+        let next_epoch = self
+            .epochs
+            .iter()
+            .position(|leader_epoch| leader_epoch.epoch > epoch);
+        let end_offset = next_epoch
+            .map(|position| self.epochs[position].start_offset)
+            .unwrap_or(log_end_offset);
+        let epoch = match next_epoch {
+            Some(0) => epoch,
+            Some(position) => self.epochs[position - 1].epoch,
+            None => self.epochs.last().map_or(epoch, |last| last.epoch),
+        };
+        EpochEndOffset::new(epoch, end_offset.min(log_end_offset))
+    }
+
+    /// Returns the epochs of the messages in the given range of offsets.
+    pub fn get_epochs(&self, start_offset: u64, end_offset: u64) -> Vec<LeaderEpoch> {
+        let first_epoch = self
+            .epochs
+            .iter()
+            .rposition(|leader_epoch| leader_epoch.start_offset <= start_offset)
+            .unwrap_or_default();
+        self.epochs
+            .iter()
+            .skip(first_epoch)
+            .filter(|leader_epoch| leader_epoch.start_offset < end_offset)
+            .copied()
+            .collect()
+    }
+
+    /// The file is replaced atomically, so that the previous epochs are kept if the write fails.
+    async fn save(&self) -> Result<(), SystemError> {
+        let bytes = self
+            .epochs
+            .iter()
+            .flat_map(|leader_epoch| leader_epoch.as_bytes())
+            .collect::<Vec<u8>>();
+        if let Err(error) = environment::storage().replace(&self.path, bytes).await {
+            error!("Failed to save leader epochs: {}, {error}", self.path);
+            return Err(error.into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_leader_epochs(epochs: &[(u64, u64)]) -> LeaderEpochs {
+        let mut leader_epochs = LeaderEpochs::new("leader_epochs");
+        leader_epochs.epochs = epochs
+            .iter()
+            .map(|(epoch, start_offset)| LeaderEpoch::new(*epoch, *start_offset))
+            .collect();
+        leader_epochs
+    }
+
+    #[test]
+    fn end_offset_should_be_start_of_next_epoch_or_log_end_offset() {
+        let leader_epochs = new_leader_epochs(&[(1, 0), (3, 5), (4, 8)]);
+        assert_eq!(
+            leader_epochs.get_end_offset(1, 10),
+            EpochEndOffset::new(1, 5)
+        );
+        // The missing epoch ends where the next one starts.
+        assert_eq!(
+            leader_epochs.get_end_offset(2, 10),
+            EpochEndOffset::new(1, 5)
+        );
+        assert_eq!(
+            leader_epochs.get_end_offset(3, 10),
+            EpochEndOffset::new(3, 8)
+        );
+        assert_eq!(
+            leader_epochs.get_end_offset(4, 10),
+            EpochEndOffset::new(4, 10)
+        );
+        assert_eq!(
+            leader_epochs.get_end_offset(7, 10),
+            EpochEndOffset::new(4, 10)
+        );
+        assert_eq!(
+            new_leader_epochs(&[(2, 3)]).get_end_offset(1, 10),
+            EpochEndOffset::new(1, 3)
+        );
+        assert_eq!(
+            new_leader_epochs(&[]).get_end_offset(1, 10),
+            EpochEndOffset::new(1, 10)
+        );
+    }
+
+    #[test]
+    fn epochs_should_cover_fetched_range() {
+        let leader_epochs = new_leader_epochs(&[(1, 0), (3, 5), (4, 8)]);
+        assert_eq!(
+            leader_epochs.get_epochs(6, 9),
+            vec![LeaderEpoch::new(3, 5), LeaderEpoch::new(4, 8)]
+        );
+        assert_eq!(leader_epochs.get_epochs(2, 5), vec![LeaderEpoch::new(1, 0)]);
+        assert_eq!(
+            leader_epochs.get_epochs(9, 12),
+            vec![LeaderEpoch::new(4, 8)]
+        );
+    }
+}
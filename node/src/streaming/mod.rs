@@ -1,3 +1,3 @@
-pub mod file;
+mod leader_epochs;
 mod stream;
 pub mod streamer;
@@ -1,18 +1,24 @@
+use crate::environment;
+use crate::metadata::{MetadataStore, StreamMetadata};
 use crate::models::appended_messages::AppendedMessages;
-use crate::streaming::file;
-use crate::types::Index;
+use crate::streaming::leader_epochs::LeaderEpochs;
+use crate::types::{Index, NodeId};
 use bytes::Bytes;
 use sdk::bytes_serializable::BytesSerializable;
 use sdk::commands::append_messages::AppendableMessage;
 use sdk::error::SystemError;
+use sdk::models::fetched_messages::FetchedMessages;
+use sdk::models::leader_epoch::EpochEndOffset;
 use sdk::models::message::Message;
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
-use std::fs::create_dir_all;
-use std::path::Path;
-use tracing::{error, info};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
 
 const EMPTY_MESSAGES: &[Message] = &[];
-const LOG_FILE: &str = "stream.log";
+pub(crate) const LOG_FILE: &str = "stream.log";
+const LEADER_EPOCHS_FILE: &str = "leader_epochs";
 
 #[derive(Debug)]
 pub(crate) struct Stream {
@@ -25,9 +31,56 @@ pub(crate) struct Stream {
     pub current_position: u64,
     pub current_id: u64,
     pub replication_factor: u8,
-    replication_factor_path: String,
-    high_watermark_path: String,
+    metadata: Rc<dyn MetadataStore>,
+    /// The offset of the first message, which has not been replicated yet, all the messages below are committed.
     pub high_watermark: Index,
+    pub min_in_sync_replicas: u8,
+    /// The nodes assigned to store the stream, the first one is the preferred leader.
+    pub replicas: Vec<NodeId>,
+    /// The replicas, which are in sync with the leader, initially all the assigned replicas.
+    pub isr: Vec<NodeId>,
+    /// The followers fetching from this node, while it's the stream leader.
+    pub replica_states: BTreeMap<NodeId, ReplicaState>,
+    /// The epoch of the current leader, which is incremented whenever the leader changes.
+    pub leader_epoch: u64,
+    pub leader_epochs: LeaderEpochs,
+    /// The leader epoch in which the follower has truncated its log to match the leader's one.
+    pub reconciled_leader_epoch: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ReplicaState {
+    pub log_end_offset: u64,
+    last_fetch_at: Option<Instant>,
+    last_fetch_leader_offset: u64,
+    last_caught_up_at: Option<Instant>,
+}
+
+impl ReplicaState {
+    fn caught_up_at(time: Instant) -> Self {
+        Self {
+            last_caught_up_at: Some(time),
+            ..Default::default()
+        }
+    }
+
+    /// The replica has caught up if it has fetched up to the leader's log end offset,
+    /// or up to the leader's log end offset at the time of its previous fetch.
+    fn update(&mut self, offset: u64, leader_offset: u64, now: Instant) {
+        if offset >= leader_offset {
+            self.last_caught_up_at = Some(now);
+        } else if offset >= self.last_fetch_leader_offset && self.last_fetch_at.is_some() {
+            self.last_caught_up_at = self.last_fetch_at;
+        }
+        self.log_end_offset = offset;
+        self.last_fetch_leader_offset = leader_offset;
+        self.last_fetch_at = Some(now);
+    }
+
+    pub fn is_in_sync(&self, now: Instant, max_lag: Duration) -> bool {
+        self.last_caught_up_at
+            .is_some_and(|caught_up_at| now.duration_since(caught_up_at) <= max_lag)
+    }
 }
 
 impl Display for Stream {
@@ -43,14 +96,20 @@ impl Display for Stream {
 }
 
 impl Stream {
-    pub fn new(stream_id: u64, leader_id: u64, path: &str, replication_factor: u8) -> Self {
+    pub fn new(
+        stream_id: u64,
+        leader_id: u64,
+        path: &str,
+        replication_factor: u8,
+        metadata: Rc<dyn MetadataStore>,
+    ) -> Self {
         let directory_path = format!("{path}/{stream_id}");
         Self {
             stream_id,
             leader_id,
             log_path: format!("{directory_path}/{LOG_FILE}"),
-            high_watermark_path: format!("{directory_path}/high_watermark"),
-            replication_factor_path: format!("{directory_path}/replication_factor"),
+            leader_epochs: LeaderEpochs::new(&format!("{directory_path}/{LEADER_EPOCHS_FILE}")),
+            metadata,
             directory_path,
             messages: Vec::new(),
             current_offset: 0,
@@ -58,103 +117,86 @@ impl Stream {
             current_id: 0,
             replication_factor,
             high_watermark: 0,
+            min_in_sync_replicas: 1,
+            replicas: Vec::new(),
+            isr: Vec::new(),
+            replica_states: BTreeMap::new(),
+            leader_epoch: 0,
+            reconciled_leader_epoch: None,
         }
     }
 
-    pub async fn init(&mut self) {
-        if !Path::new(&self.directory_path).exists() {
-            create_dir_all(&self.directory_path).unwrap_or_else(|_| {
-                panic!("Failed to create stream directory: {}", self.directory_path)
-            });
+    pub async fn init(&mut self) -> Result<(), SystemError> {
+        let storage = environment::storage();
+        if !storage.exists(&self.directory_path) {
+            if let Err(error) = storage.create_dir_all(&self.directory_path) {
+                error!(
+                    "Failed to create stream directory: {}, {error}",
+                    self.directory_path
+                );
+                return Err(error.into());
+            }
             info!("Created stream directory: {}", self.directory_path);
         }
 
-        if !Path::new(&self.replication_factor_path).exists() {
-            file::write(&self.replication_factor_path)
-                .await
-                .unwrap_or_else(|_| {
-                    panic!(
-                        "Failed to create replication factor file: {}",
-                        self.replication_factor_path
-                    )
-                });
-            let file = file::append(&self.replication_factor_path).await.unwrap();
-            let buffer = self.replication_factor.to_le_bytes().to_vec();
-            if file.write_all_at(buffer, 0).await.0.is_err() {
-                error!("Failed to init replication factor");
-                return;
-            }
-            info!(
-                "Created default replication factor file: {}",
-                self.replication_factor_path
-            );
-        } else {
-            let replication_factor = file::open(&self.replication_factor_path).await.unwrap();
-            let buffer = vec![0u8; 1];
-            let (result, buffer) = replication_factor.read_exact_at(buffer, 0).await;
-            if result.is_err() {
-                error!("Failed to read replication factor");
-                return;
+        match self.metadata.load_stream(self.stream_id).await {
+            Ok(Some(metadata)) => {
+                self.replication_factor = metadata.replication_factor;
+                self.min_in_sync_replicas = metadata.min_in_sync_replicas;
+                self.leader_id = metadata.leader_id;
+                self.leader_epoch = metadata.leader_epoch;
+                self.replicas = metadata.replicas;
+                self.isr = metadata.isr;
+                self.high_watermark = metadata.high_watermark;
+                info!(
+                    "Initialized replication factor: {}, high watermark: {}",
+                    self.replication_factor, self.high_watermark
+                );
             }
-
-            let replication_factor = buffer[0];
-            self.replication_factor = replication_factor;
-            info!(
-                "Initialized replication factor: {}",
-                self.replication_factor_path
-            );
-        }
-
-        if !Path::new(&self.high_watermark_path).exists() {
-            file::write(&self.high_watermark_path)
-                .await
-                .unwrap_or_else(|_| {
-                    panic!(
-                        "Failed to create high watermark file: {}",
-                        self.high_watermark_path
-                    )
-                });
-            let file = file::append(&self.high_watermark_path).await.unwrap();
-            if file.write_all_at(vec![0u8; 8], 0).await.0.is_err() {
-                error!("Failed to init high watermark");
-                return;
+            Ok(None) => {
+                if let Err(error) = self.save_metadata().await {
+                    error!("Failed to init stream manifest, {error}");
+                    return Err(error);
+                }
+                info!("Saved stream manifest");
             }
-            info!(
-                "Created empty high watermark file: {}",
-                self.high_watermark_path
-            );
-        } else {
-            let high_watermark = file::open(&self.high_watermark_path).await.unwrap();
-            let buffer = vec![0u8; 8];
-            let (result, buffer) = high_watermark.read_exact_at(buffer, 0).await;
-            if result.is_err() {
-                error!("Failed to read high watermark");
-                return;
+            Err(error) => {
+                error!("Failed to load stream manifest, {error}");
+                return Err(error);
             }
+        }
 
-            let high_watermark = u64::from_le_bytes(buffer.try_into().unwrap());
-            self.high_watermark = high_watermark;
-            info!("Initialized high watermark: {}", self.high_watermark);
+        if let Err(error) = self.leader_epochs.init().await {
+            error!("Failed to init leader epochs, {error}");
+            return Err(error);
         }
 
-        if !Path::new(&self.log_path).exists() {
-            file::write(&self.log_path)
-                .await
-                .unwrap_or_else(|_| panic!("Failed to create stream file: {}", self.log_path));
+        if !storage.exists(&self.log_path) {
+            if let Err(error) = storage.write_at(&self.log_path, 0, Vec::new()).await {
+                error!("Failed to create stream file: {}, {error}", self.log_path);
+                return Err(error.into());
+            }
             info!("Created empty stream file: {}", self.log_path);
         } else {
-            let (messages, position) = self
-                .load_messages_from_disk(Some(self.high_watermark))
-                .await;
+            // The messages above the high watermark might have been acknowledged by the leader already,
+            // so they're kept, but not served until they're committed, or truncated to match the leader's log.
+            let (messages, position) = self.load_messages_from_disk(None).await?;
             if !messages.is_empty() {
                 self.messages = messages;
-                self.current_position = position;
                 self.current_offset = self.messages.len() as u64 - 1;
                 self.current_id = self.messages.iter().max_by_key(|m| m.id).unwrap().id;
-                if self.truncate(self.current_position).await.is_err() {
-                    error!("Failed to truncate stream file: {}", self.log_path);
-                    return;
-                }
+            }
+            // The torn message at the end of the log is removed.
+            if let Err(error) = self.truncate_at(position).await {
+                error!("Failed to truncate stream file: {}, {error}", self.log_path);
+                return Err(error);
+            }
+            self.current_position = position;
+            self.leader_epochs
+                .truncate(self.get_log_end_offset())
+                .await?;
+            if self.high_watermark > self.get_log_end_offset() {
+                self.set_high_watermark(self.get_log_end_offset()).await?;
             }
         }
 
@@ -164,11 +206,31 @@ impl Stream {
             self.log_path,
             self.messages.len()
         );
+        Ok(())
     }
 
-    pub async fn truncate(&mut self, high_watermark: u64) -> Result<(), SystemError> {
-        let file_size = std::fs::metadata(&self.log_path)?.len();
-        let position = self.load_messages(Some(high_watermark), &mut |_| {}).await;
+    /// Removes all the messages starting at the given offset, both from the memory and the disk,
+    /// along with the leader epochs and the high watermark above it.
+    pub async fn truncate(&mut self, offset: u64) -> Result<(), SystemError> {
+        let position = self.load_messages(Some(offset), &mut |_| {}).await?;
+        if (offset as usize) < self.messages.len() {
+            self.messages.truncate(offset as usize);
+        }
+        // The offset and ID might have been advanced by the messages, which were never written.
+        self.current_offset = self.get_log_end_offset().saturating_sub(1);
+        self.current_id = self.messages.last().map(|m| m.id).unwrap_or_default();
+        self.current_position = position;
+        self.leader_epochs
+            .truncate(self.get_log_end_offset())
+            .await?;
+        if self.high_watermark > self.get_log_end_offset() {
+            self.set_high_watermark(self.get_log_end_offset()).await?;
+        }
+        self.truncate_at(position).await
+    }
+
+    async fn truncate_at(&self, position: u64) -> Result<(), SystemError> {
+        let file_size = environment::storage().size(&self.log_path)?;
         if file_size <= position {
             return Ok(());
         }
@@ -177,29 +239,38 @@ impl Stream {
             "Truncating stream with ID: {}, path: {}, from: {file_size} bytes to {position} bytes...",
             self.stream_id, self.log_path
         );
-        let file = std::fs::File::open(&self.log_path)?;
-        file.set_len(position)?;
+        environment::storage().truncate(&self.log_path, position)?;
         Ok(())
     }
 
-    pub fn delete(&self) {
-        if !Path::new(&self.directory_path).exists() {
-            error!("Stream with ID: {} does not exist", self.stream_id);
+    pub async fn delete(&self) -> Result<(), SystemError> {
+        let storage = environment::storage();
+        if !storage.exists(&self.directory_path) {
+            warn!("Stream directory: {} does not exist", self.directory_path);
+        } else if let Err(error) = storage.remove_dir_all(&self.directory_path) {
+            error!(
+                "Failed to delete stream with ID: {}, {error}",
+                self.stream_id
+            );
+            return Err(error.into());
         }
 
-        if std::fs::remove_dir_all(&self.directory_path).is_err() {
-            error!("Failed to delete stream with ID: {}", self.stream_id);
-            return;
+        if let Err(error) = self.metadata.delete_stream(self.stream_id).await {
+            error!(
+                "Failed to delete metadata of stream with ID: {}, {error}",
+                self.stream_id
+            );
+            return Err(error);
         }
 
         info!("Deleted stream with ID: {}", self.stream_id);
+        Ok(())
     }
 
     pub async fn append_messages(
         &mut self,
         messages: &[AppendableMessage],
     ) -> Result<AppendedMessages, SystemError> {
-        let current_offset = self.current_offset;
         let mut uncommitted_messages = Vec::with_capacity(messages.len());
         for message_to_append in messages {
             if !self.messages.is_empty() || !uncommitted_messages.is_empty() {
@@ -220,180 +291,370 @@ impl Stream {
             uncommitted_messages.push(message);
         }
 
-        Ok(AppendedMessages::new(uncommitted_messages, current_offset))
+        Ok(AppendedMessages::new(uncommitted_messages))
     }
 
-    pub async fn commit_messages(&mut self, messages: Vec<Message>) -> Result<(), SystemError> {
-        for message in messages {
-            let size = message.get_size();
-            let bytes = message.as_bytes();
-            let file = file::append(&self.log_path)
-                .await
-                .unwrap_or_else(|_| panic!("Failed to open stream file: {}", &self.log_path));
-            let result = file.write_all_at(bytes, self.current_position).await;
-            if result.0.is_err() {
-                error!(
-                    "Failed to append message to stream file: {}",
-                    &self.log_path
-                );
-                return Err(SystemError::CannotAppendMessage);
-            }
-            self.current_position += size as u64;
-            if file.close().await.is_err() {
-                error!("Failed to close stream file: {}", &self.log_path);
-            }
+    pub fn get_log_end_offset(&self) -> u64 {
+        self.messages.len() as u64
+    }
 
-            info!(
-                "Appended message to stream file: {} at offset: {}, position: {}",
-                &self.log_path, self.current_offset, self.current_position
+    /// Writes the messages to the log at once, the high watermark is advanced separately once they are replicated.
+    pub async fn write_messages(&mut self, messages: Vec<Message>) -> Result<(), SystemError> {
+        let Some(last_message) = messages.last() else {
+            return Ok(());
+        };
+
+        let (last_offset, last_id) = (last_message.offset, last_message.id);
+        let bytes = messages
+            .iter()
+            .flat_map(|message| message.as_bytes())
+            .collect::<Vec<u8>>();
+        let size = bytes.len() as u64;
+        let result = environment::storage()
+            .write_at(&self.log_path, self.current_position, bytes)
+            .await;
+        if result.is_err() {
+            error!(
+                "Failed to append messages to stream file: {}",
+                &self.log_path
             );
-
-            self.messages.push(message);
+            return Err(SystemError::CannotAppendMessage);
         }
-        self.set_high_watermark(self.current_offset).await;
+
+        self.current_position += size;
+        self.current_offset = last_offset;
+        self.current_id = last_id;
+        info!(
+            "Appended {} messages to stream file: {} at offset: {}, position: {}",
+            messages.len(),
+            &self.log_path,
+            self.current_offset,
+            self.current_position
+        );
+        self.messages.extend(messages);
         Ok(())
     }
 
-    pub fn set_offset(&mut self, offset: u64) {
-        self.current_offset = offset;
+    /// Appends the messages as the leader and returns the new log end offset. If they fail to be written,
+    /// the log is truncated back, as the followers can't fetch the partially written messages, since the stream
+    /// is locked until it's truncated.
+    pub async fn append_and_write_messages(
+        &mut self,
+        messages: &[AppendableMessage],
+    ) -> Result<u64, SystemError> {
+        let previous_log_end_offset = self.get_log_end_offset();
+        self.begin_leader_epoch().await?;
+        let appended_messages = self.append_messages(messages).await?;
+        if let Err(error) = self
+            .write_messages(appended_messages.uncommited_messages)
+            .await
+        {
+            warn!(
+                "Truncating stream with ID: {} to offset: {previous_log_end_offset}...",
+                self.stream_id
+            );
+            self.truncate(previous_log_end_offset).await?;
+            return Err(error);
+        }
+
+        Ok(self.get_log_end_offset())
+    }
+
+    /// Starts the current leader epoch at the log end offset, once the leader appends the first messages in it.
+    pub async fn begin_leader_epoch(&mut self) -> Result<(), SystemError> {
+        let log_end_offset = self.get_log_end_offset();
+        self.leader_epochs
+            .assign(self.leader_epoch, log_end_offset)
+            .await
     }
 
-    pub fn reset_offset(&mut self, offset: u64) {
-        self.current_offset = offset;
+    pub fn get_epoch_end_offset(&self, leader_epoch: u64) -> EpochEndOffset {
+        self.leader_epochs
+            .get_end_offset(leader_epoch, self.get_log_end_offset())
     }
 
-    pub fn poll_messages(&self, offset: u64, count: u64) -> Result<&[Message], SystemError> {
-        if self.messages.is_empty() {
+    /// Returns only the committed messages, the reader ahead of the high watermark gets none of them.
+    pub fn poll_committed_messages(
+        &self,
+        offset: u64,
+        count: u64,
+    ) -> Result<&[Message], SystemError> {
+        if count == 0 {
+            return Err(SystemError::InvalidCount);
+        }
+
+        if offset >= self.high_watermark {
             return Ok(EMPTY_MESSAGES);
         }
 
-        if offset > self.current_offset {
+        let end_offset = self.high_watermark.min(offset.saturating_add(count));
+        Ok(&self.messages[offset as usize..end_offset as usize])
+    }
+
+    /// The replica states are tracked only by the current leader.
+    pub fn set_leader(&mut self, leader_id: NodeId, leader_epoch: u64) {
+        if self.leader_id != leader_id {
+            self.replica_states.clear();
+        }
+        self.leader_id = leader_id;
+        self.leader_epoch = leader_epoch;
+    }
+
+    /// The streams created before their replicas were assigned are stored by all the voters.
+    pub fn is_replica(&self, node_id: NodeId) -> bool {
+        self.replicas.is_empty() || self.replicas.contains(&node_id)
+    }
+
+    pub fn init_replica(&mut self, node_id: NodeId, now: Instant) {
+        self.replica_states
+            .entry(node_id)
+            .or_insert_with(|| ReplicaState::caught_up_at(now));
+    }
+
+    pub fn update_replica(&mut self, node_id: NodeId, offset: u64, now: Instant) {
+        let leader_offset = self.get_log_end_offset();
+        self.replica_states
+            .entry(node_id)
+            .or_default()
+            .update(offset, leader_offset, now);
+    }
+
+    /// Returns the messages starting at the follower's log end offset, which can't be ahead of this log.
+    pub fn fetch_messages(&self, offset: u64, count: u64) -> Result<&[Message], SystemError> {
+        let log_end_offset = self.get_log_end_offset();
+        if offset > log_end_offset {
             return Err(SystemError::InvalidOffset);
         }
 
-        if count == 0 {
-            return Err(SystemError::InvalidCount);
+        let end_offset = log_end_offset.min(offset.saturating_add(count));
+        Ok(&self.messages[offset as usize..end_offset as usize])
+    }
+
+    /// Returns the messages to be fetched by the follower along with the leader epochs they were appended in.
+    pub fn fetch_messages_with_epochs(
+        &self,
+        offset: u64,
+        count: u64,
+    ) -> Result<FetchedMessages, SystemError> {
+        let messages = self.fetch_messages(offset, count)?.to_vec();
+        let leader_epochs = self
+            .leader_epochs
+            .get_epochs(offset, offset + messages.len() as u64);
+        Ok(FetchedMessages {
+            high_watermark: self.high_watermark,
+            leader_epochs,
+            messages,
+        })
+    }
+
+    /// Returns the committed messages starting at the offset, up to the given size in bytes, but at least one,
+    /// along with their leader epochs. Used to copy the stream's existing data to the new replica in bulk.
+    pub fn read_chunk(&self, offset: u64, max_bytes: u32) -> Result<FetchedMessages, SystemError> {
+        if offset > self.high_watermark {
+            return Err(SystemError::InvalidOffset);
         }
 
-        let start_offset = offset;
-        let mut end_offset = offset + count - 1;
-        if end_offset > self.current_offset {
-            end_offset = self.current_offset;
+        let mut size = 0u64;
+        let messages = self.messages[offset as usize..self.high_watermark as usize]
+            .iter()
+            .take_while(|message| {
+                let fits = size == 0 || size + message.get_size() as u64 <= max_bytes as u64;
+                size += message.get_size() as u64;
+                fits
+            })
+            .cloned()
+            .collect::<Vec<Message>>();
+        let leader_epochs = self
+            .leader_epochs
+            .get_epochs(offset, offset + messages.len() as u64);
+        Ok(FetchedMessages {
+            high_watermark: self.high_watermark,
+            leader_epochs,
+            messages,
+        })
+    }
+
+    /// Writes the messages fetched from the leader at the log end offset, their leader epochs are written first.
+    /// The messages are committed only up to the leader's high watermark, the ones above it are kept uncommitted.
+    pub async fn write_fetched_messages(
+        &mut self,
+        fetched_messages: FetchedMessages,
+    ) -> Result<(), SystemError> {
+        let offset = self.get_log_end_offset();
+        for leader_epoch in fetched_messages.leader_epochs {
+            self.leader_epochs
+                .assign(leader_epoch.epoch, leader_epoch.start_offset.max(offset))
+                .await?;
+        }
+        self.write_messages(fetched_messages.messages).await?;
+        let high_watermark = fetched_messages
+            .high_watermark
+            .min(self.get_log_end_offset());
+        if high_watermark > self.high_watermark {
+            self.set_high_watermark(high_watermark).await?;
         }
+        Ok(())
+    }
 
-        let messages = &self.messages[start_offset as usize..=end_offset as usize];
-        Ok(messages)
+    pub fn get_last_leader_epoch(&self) -> u64 {
+        self.leader_epochs.get_last_epoch().unwrap_or_default()
     }
 
-    pub async fn load_messages_from_disk(&self, end_offset: Option<u64>) -> (Vec<Message>, u64) {
+    /// Truncates the log to the end offset of its last leader epoch on the leader, or the one in this log
+    /// if it's lower, which is where both logs are known to be identical. Returns the new log end offset.
+    pub async fn truncate_to_leader(
+        &mut self,
+        leader_end_offset: EpochEndOffset,
+    ) -> Result<u64, SystemError> {
+        let end_offset = self
+            .get_epoch_end_offset(leader_end_offset.epoch)
+            .end_offset
+            .min(leader_end_offset.end_offset);
+        if end_offset < self.get_log_end_offset() {
+            warn!(
+                "Truncating stream with ID: {} from log end offset: {} to the end offset: {end_offset} of leader epoch: {}.",
+                self.stream_id,
+                self.get_log_end_offset(),
+                leader_end_offset.epoch
+            );
+            self.truncate(end_offset).await?;
+        }
+        Ok(self.get_log_end_offset())
+    }
+
+    pub async fn load_messages_from_disk(
+        &self,
+        end_offset: Option<u64>,
+    ) -> Result<(Vec<Message>, u64), SystemError> {
         let mut messages = Vec::new();
         let position = self
             .load_messages(end_offset, &mut |message| messages.push(message))
-            .await;
-        (messages, position)
+            .await?;
+        Ok((messages, position))
     }
 
-    pub async fn load_messages<F>(&self, end_offset: Option<u64>, on_message: &mut F) -> u64
+    pub async fn load_messages<F>(
+        &self,
+        end_offset: Option<u64>,
+        on_message: &mut F,
+    ) -> Result<u64, SystemError>
     where
         F: FnMut(Message),
     {
-        let file = file::open(&self.log_path)
-            .await
-            .unwrap_or_else(|_| panic!("Failed to read stream file: {}", self.log_path));
-
-        let mut position = 0u64;
-        loop {
-            let buffer = vec![0u8; 8];
-            let (result, buffer) = file.read_exact_at(buffer, position).await;
-            if result.is_err() {
-                break;
+        let file = match environment::storage().read(&self.log_path).await {
+            Ok(file) => file,
+            Err(error) => {
+                error!("Failed to read stream file: {}, {error}", self.log_path);
+                return Err(error.into());
             }
+        };
 
-            let offset = u64::from_le_bytes(buffer.try_into().unwrap());
-            position += 8;
+        // The position after the last complete message, the torn one at the end is skipped.
+        let mut position = 0usize;
+        while let Some(buffer) = file.get(position..position + 8) {
+            let offset = u64::from_le_bytes(buffer.try_into()?);
+            if let Some(end_offset) = end_offset {
+                if offset >= end_offset {
+                    break;
+                }
+            }
+            let mut next_position = position + 8;
 
-            let buffer = vec![0u8; 8];
-            let (result, buffer) = file.read_exact_at(buffer, position).await;
-            if result.is_err() {
+            let Some(buffer) = file.get(next_position..next_position + 8) else {
                 break;
-            }
-            let id = u64::from_le_bytes(buffer.try_into().unwrap());
-            position += 8;
+            };
+            let id = u64::from_le_bytes(buffer.try_into()?);
+            next_position += 8;
 
-            let buffer = vec![0u8; 4];
-            let payload_length = file.read_exact_at(buffer, position).await;
-            if payload_length.0.is_err() {
+            let Some(buffer) = file.get(next_position..next_position + 4) else {
                 error!("Failed to read payload length");
                 break;
-            }
+            };
 
-            let payload_length = u32::from_le_bytes(payload_length.1.try_into().unwrap());
-            position += 4;
-            let buffer = vec![0; payload_length as usize];
-            let payload = file.read_exact_at(buffer, position).await;
-            if payload.0.is_err() {
+            let payload_length = u32::from_le_bytes(buffer.try_into()?);
+            next_position += 4;
+            let Some(payload) = file.get(next_position..next_position + payload_length as usize)
+            else {
                 error!("Failed to read payload");
                 break;
-            }
+            };
 
-            position += payload_length as u64;
-            let message = Message::new(offset, id, Bytes::from(payload.1));
+            position = next_position + payload_length as usize;
+            let message = Message::new(offset, id, Bytes::copy_from_slice(payload));
             on_message(message);
-
-            if let Some(end_offset) = end_offset {
-                if offset >= end_offset {
-                    break;
-                }
-            }
         }
 
-        position
+        Ok(position as u64)
     }
 
-    pub async fn set_high_watermark(&mut self, high_watermark: Index) {
-        self.high_watermark = high_watermark;
-        let file = file::write(&self.high_watermark_path).await.unwrap();
-        if file
-            .write_all_at(high_watermark.to_le_bytes().to_vec(), 0)
+    /// Saves the manifest with the current replication factor, leader, replicas, settings and high watermark.
+    pub async fn save_metadata(&self) -> Result<(), SystemError> {
+        self.metadata
+            .save_stream(StreamMetadata {
+                stream_id: self.stream_id,
+                replication_factor: self.replication_factor,
+                min_in_sync_replicas: self.min_in_sync_replicas,
+                leader_id: self.leader_id,
+                leader_epoch: self.leader_epoch,
+                replicas: self.replicas.clone(),
+                isr: self.isr.clone(),
+                high_watermark: self.high_watermark,
+            })
             .await
-            .0
-            .is_err()
-        {
-            error!("Failed to write high watermark");
-            return;
+    }
+
+    /// The high watermark is advanced in memory even if it fails to be saved, as the messages below it
+    /// are already stored by the in-sync replicas.
+    pub async fn set_high_watermark(&mut self, high_watermark: Index) -> Result<(), SystemError> {
+        self.high_watermark = high_watermark;
+        if let Err(error) = self.save_metadata().await {
+            error!("Failed to write high watermark, {error}");
+            return Err(error);
         }
         info!("Saved high watermark: {}", self.high_watermark);
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::remove_dir_all;
+    use crate::environment::memory::{MemoryStorage, WriteFault};
+    use crate::environment::{EnterGuard, Environment, Storage};
+    use crate::metadata::file_store::FileMetadataStore;
+    use crate::streaming::leader_epochs::LEADER_EPOCH_SIZE;
 
-    const BASE_DIR: &str = "local_data";
-
-    struct Test {}
+    struct Test {
+        name: &'static str,
+    }
 
     impl Test {
         fn streams_path(&self) -> String {
-            format!("{BASE_DIR}/test_streams")
+            format!("test_streams/{}", self.name)
         }
-    }
 
-    impl Drop for Test {
-        fn drop(&mut self) {
-            let _ = remove_dir_all(BASE_DIR);
+        fn stream(&self, stream_id: u64, leader_id: u64, replication_factor: u8) -> Stream {
+            let path = self.streams_path();
+            let metadata = Rc::new(FileMetadataStore::new(&path));
+            Stream::new(stream_id, leader_id, &path, replication_factor, metadata)
         }
     }
 
+    /// Keeps the streams in memory, until the guard is dropped at the end of the test.
+    fn enter_memory_storage() -> (EnterGuard, Rc<MemoryStorage>) {
+        let storage = Rc::new(MemoryStorage::default());
+        let guard = Environment::system().with_storage(storage.clone()).enter();
+        (guard, storage)
+    }
+
     #[monoio::test]
     async fn messages_should_be_stored_on_disk() {
-        let test = Test {};
+        let _storage = enter_memory_storage();
+        let test = Test { name: "stored" };
         let stream_id = 1;
         let node_id = 2;
-        let mut stream = Stream::new(stream_id, node_id, &test.streams_path(), 3);
-        stream.init().await;
+        let mut stream = test.stream(stream_id, node_id, 3);
+        stream.init().await.unwrap();
         let messages = vec![
             sdk::commands::append_messages::AppendableMessage {
                 id: 1,
@@ -413,14 +674,14 @@ mod tests {
 
         let appended_messages = result.unwrap();
         assert_eq!(stream.current_offset, 2); // Uncommited offset
-        assert_eq!(appended_messages.previous_offset, 0);
 
         stream
-            .commit_messages(appended_messages.uncommited_messages)
+            .write_messages(appended_messages.uncommited_messages)
             .await
             .unwrap();
+        stream.set_high_watermark(3).await.unwrap();
 
-        let polled_messages = stream.poll_messages(0, 1000);
+        let polled_messages = stream.poll_committed_messages(0, 1000);
         assert!(polled_messages.is_ok());
         let polled_messages = polled_messages.unwrap();
         assert_eq!(polled_messages.len(), 3);
@@ -431,7 +692,7 @@ mod tests {
         assert_message(polled_message2, 1, 2, b"message-2");
         assert_message(polled_message3, 2, 3, b"message-3");
 
-        let (loaded_messages, position) = stream.load_messages_from_disk(None).await;
+        let (loaded_messages, position) = stream.load_messages_from_disk(None).await.unwrap();
         assert!(position > 0);
         assert_eq!(loaded_messages.len(), 3);
         let loaded_message1 = &loaded_messages[0];
@@ -442,6 +703,283 @@ mod tests {
         assert_message(loaded_message3, 2, 3, b"message-3");
     }
 
+    #[monoio::test]
+    async fn messages_above_high_watermark_should_be_kept_on_init_but_not_polled() {
+        let _storage = enter_memory_storage();
+        let test = Test { name: "truncated" };
+        let mut stream = test.stream(1, 2, 3);
+        stream.init().await.unwrap();
+        let messages = (1..=3)
+            .map(|id| AppendableMessage {
+                id,
+                payload: Bytes::from(format!("message-{id}")),
+            })
+            .collect::<Vec<AppendableMessage>>();
+        let appended_messages = stream.append_messages(&messages).await.unwrap();
+        stream
+            .write_messages(appended_messages.uncommited_messages)
+            .await
+            .unwrap();
+        stream.set_high_watermark(2).await.unwrap();
+
+        assert_eq!(stream.get_log_end_offset(), 3);
+        assert_eq!(stream.fetch_messages(1, 10).unwrap().len(), 2);
+        assert!(stream.fetch_messages(3, 10).unwrap().is_empty());
+        assert!(stream.fetch_messages(4, 10).is_err());
+
+        let mut restarted_stream = test.stream(1, 2, 3);
+        restarted_stream.init().await.unwrap();
+        assert_eq!(restarted_stream.high_watermark, 2);
+        assert_eq!(restarted_stream.get_log_end_offset(), 3);
+        assert_eq!(restarted_stream.current_offset, 2);
+        assert_eq!(restarted_stream.current_id, 3);
+        let polled_messages = restarted_stream.poll_committed_messages(0, 10).unwrap();
+        assert_eq!(polled_messages.len(), 2);
+        assert_message(&polled_messages[1], 1, 2, b"message-2");
+        assert!(restarted_stream
+            .poll_committed_messages(2, 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[monoio::test]
+    async fn stream_with_torn_leader_epochs_should_fail_to_init() {
+        let (_guard, storage) = enter_memory_storage();
+        let test = Test {
+            name: "torn_epochs",
+        };
+        let mut stream = test.stream(1, 1, 3);
+        stream.init().await.unwrap();
+        stream.leader_epochs.assign(1, 0).await.unwrap();
+        let path = format!("{}/{LEADER_EPOCHS_FILE}", stream.directory_path);
+        storage
+            .write_at(&path, LEADER_EPOCH_SIZE as u64, vec![1; 8])
+            .await
+            .unwrap();
+
+        let mut restarted_stream = test.stream(1, 1, 3);
+        assert!(matches!(
+            restarted_stream.init().await,
+            Err(SystemError::InvalidLeaderEpochs)
+        ));
+    }
+
+    #[monoio::test]
+    async fn replicas_should_be_identical_after_leader_fails_mid_replication() {
+        let _storage = enter_memory_storage();
+        let tests = ["epochs_1", "epochs_2", "epochs_3"].map(|name| Test { name });
+        let mut replicas = Vec::new();
+        for test in &tests {
+            let mut stream = test.stream(1, 1, 3);
+            stream.init().await.unwrap();
+            replicas.push(stream);
+        }
+        let [mut first, mut second, mut third]: [Stream; 3] = replicas.try_into().unwrap();
+
+        append(&mut first, 1..=5).await;
+        fetch(&first, &mut second, 3).await;
+        fetch(&first, &mut third, 1).await;
+
+        // The first leader fails, while it still has the messages which haven't been replicated.
+        for stream in [&mut first, &mut second, &mut third] {
+            stream.set_leader(2, 1);
+        }
+        append(&mut second, 6..=7).await;
+        assert_eq!(first.get_log_end_offset(), 5);
+        assert_eq!(second.get_log_end_offset(), 5);
+        assert_message(&first.messages[3], 3, 4, b"message-4");
+        assert_message(&second.messages[3], 3, 6, b"message-6");
+
+        fetch(&second, &mut third, 10).await;
+        fetch(&second, &mut first, 10).await;
+        for stream in [&first, &third] {
+            assert_eq!(messages(stream), messages(&second));
+            assert_eq!(stream.get_last_leader_epoch(), 1);
+            assert_eq!(stream.get_epoch_end_offset(0), EpochEndOffset::new(0, 3));
+        }
+
+        second
+            .set_high_watermark(second.get_log_end_offset())
+            .await
+            .unwrap();
+        fetch(&second, &mut first, 10).await;
+        let mut restarted_first = tests[0].stream(1, 1, 3);
+        restarted_first.init().await.unwrap();
+        assert_eq!(messages(&restarted_first), messages(&second));
+        assert_eq!(
+            restarted_first.get_epoch_end_offset(0),
+            EpochEndOffset::new(0, 3)
+        );
+    }
+
+    #[monoio::test]
+    async fn follower_should_commit_messages_only_up_to_leader_high_watermark() {
+        let _storage = enter_memory_storage();
+        let tests = ["hw_leader", "hw_follower"].map(|name| Test { name });
+        let mut leader = tests[0].stream(1, 1, 2);
+        leader.init().await.unwrap();
+        let mut follower = tests[1].stream(1, 1, 2);
+        follower.init().await.unwrap();
+
+        append(&mut leader, 1..=3).await;
+        leader.set_high_watermark(1).await.unwrap();
+        fetch(&leader, &mut follower, 10).await;
+        assert_eq!(follower.get_log_end_offset(), 3);
+        assert_eq!(follower.high_watermark, 1);
+        assert_eq!(follower.poll_committed_messages(0, 10).unwrap().len(), 1);
+        assert!(follower.poll_committed_messages(1, 10).unwrap().is_empty());
+
+        // The uncommitted messages might have been acknowledged already, so they're kept on restart,
+        // but still not polled, until the leader's high watermark is fetched again.
+        let mut follower = tests[1].stream(1, 1, 2);
+        follower.init().await.unwrap();
+        assert_eq!(follower.get_log_end_offset(), 3);
+        assert_eq!(follower.high_watermark, 1);
+        assert!(follower.poll_committed_messages(1, 10).unwrap().is_empty());
+
+        leader.set_high_watermark(3).await.unwrap();
+        fetch(&leader, &mut follower, 10).await;
+        fetch(&leader, &mut follower, 10).await;
+        assert_eq!(messages(&follower), messages(&leader));
+        assert_eq!(follower.high_watermark, 3);
+    }
+
+    #[monoio::test]
+    async fn new_replica_should_resume_copying_committed_chunks_after_restart() {
+        let _storage = enter_memory_storage();
+        let tests = ["chunk_leader", "chunk_replica"].map(|name| Test { name });
+        let mut leader = tests[0].stream(1, 1, 2);
+        leader.init().await.unwrap();
+        append(&mut leader, 1..=5).await;
+        leader.set_high_watermark(4).await.unwrap();
+
+        // Each message takes 29 bytes, so the chunk fits 2 of them, but at least one is always returned.
+        let mut replica = tests[1].stream(1, 1, 2);
+        replica.init().await.unwrap();
+        let chunk = leader.read_chunk(0, 60).unwrap();
+        assert_eq!(chunk.messages.len(), 2);
+        replica.write_fetched_messages(chunk).await.unwrap();
+        assert_eq!(leader.read_chunk(0, 1).unwrap().messages.len(), 1);
+
+        let mut replica = tests[1].stream(1, 1, 2);
+        replica.init().await.unwrap();
+        assert_eq!(replica.get_log_end_offset(), 2);
+        let chunk = leader.read_chunk(2, 60).unwrap();
+        replica.write_fetched_messages(chunk).await.unwrap();
+        assert!(leader.read_chunk(4, 60).unwrap().messages.is_empty());
+        assert!(leader.read_chunk(5, 60).is_err());
+        assert_eq!(messages(&replica), messages(&leader)[..4]);
+        assert_eq!(replica.high_watermark, 4);
+        assert_eq!(replica.get_epoch_end_offset(0), EpochEndOffset::new(0, 4));
+    }
+
+    #[monoio::test]
+    async fn failed_writes_should_be_truncated_before_next_write() {
+        let (_guard, storage) = enter_memory_storage();
+        let test = Test { name: "failed" };
+        let mut stream = test.stream(1, 2, 1);
+        stream.init().await.unwrap();
+        append(&mut stream, 1..=2).await;
+
+        for (id, write_fault) in [(3, WriteFault::Failed), (4, WriteFault::Torn)] {
+            storage.set_write_fault(Some(write_fault));
+            let appended_messages = stream
+                .append_messages(&[AppendableMessage {
+                    id,
+                    payload: Bytes::from(format!("message-{id}")),
+                }])
+                .await
+                .unwrap();
+            assert!(stream
+                .write_messages(appended_messages.uncommited_messages)
+                .await
+                .is_err());
+
+            // The partially written message is truncated as soon as the disk recovers, before the next write.
+            storage.set_write_fault(None);
+            stream.truncate(2).await.unwrap();
+            assert_eq!(stream.get_log_end_offset(), 2);
+        }
+
+        append(&mut stream, 5..=5).await;
+        stream.set_high_watermark(3).await.unwrap();
+        let mut restarted_stream = test.stream(1, 2, 1);
+        restarted_stream.init().await.unwrap();
+        assert_eq!(messages(&restarted_stream), vec![(0, 1), (1, 2), (2, 5)]);
+    }
+
+    async fn append(stream: &mut Stream, ids: std::ops::RangeInclusive<u64>) {
+        let messages = ids
+            .map(|id| AppendableMessage {
+                id,
+                payload: Bytes::from(format!("message-{id}")),
+            })
+            .collect::<Vec<AppendableMessage>>();
+        stream.begin_leader_epoch().await.unwrap();
+        let appended_messages = stream.append_messages(&messages).await.unwrap();
+        stream
+            .write_messages(appended_messages.uncommited_messages)
+            .await
+            .unwrap();
+    }
+
+    /// Truncates the follower's log once per leader epoch and fetches the messages in small batches.
+    async fn fetch(leader: &Stream, follower: &mut Stream, count: u64) {
+        if follower.reconciled_leader_epoch != Some(leader.leader_epoch) {
+            let leader_end_offset = leader.get_epoch_end_offset(follower.get_last_leader_epoch());
+            follower
+                .truncate_to_leader(leader_end_offset)
+                .await
+                .unwrap();
+            follower.reconciled_leader_epoch = Some(leader.leader_epoch);
+        }
+
+        let mut remaining = count;
+        while remaining > 0 {
+            let fetched_messages = leader
+                .fetch_messages_with_epochs(follower.get_log_end_offset(), remaining.min(2))
+                .unwrap();
+            let fetched_messages_count = fetched_messages.messages.len() as u64;
+            follower
+                .write_fetched_messages(fetched_messages)
+                .await
+                .unwrap();
+            if fetched_messages_count == 0 {
+                break;
+            }
+            remaining -= fetched_messages_count;
+        }
+    }
+
+    fn messages(stream: &Stream) -> Vec<(u64, u64)> {
+        stream
+            .messages
+            .iter()
+            .map(|message| (message.offset, message.id))
+            .collect()
+    }
+
+    #[test]
+    fn replica_should_be_in_sync_only_if_caught_up_within_max_lag() {
+        let max_lag = Duration::from_secs(2);
+        let start = Instant::now();
+        let mut replica = ReplicaState::default();
+        assert!(!replica.is_in_sync(start, max_lag));
+
+        replica.update(10, 10, start);
+        assert!(replica.is_in_sync(start + max_lag, max_lag));
+
+        // Fetching up to the leader's previous log end offset still counts as caught up at the previous fetch.
+        replica.update(10, 15, start + Duration::from_secs(1));
+        replica.update(15, 20, start + Duration::from_secs(2));
+        assert!(replica.is_in_sync(start + Duration::from_secs(3), max_lag));
+        assert!(!replica.is_in_sync(start + Duration::from_secs(4), max_lag));
+
+        replica.update(15, 25, start + Duration::from_secs(5));
+        assert!(!replica.is_in_sync(start + Duration::from_secs(5), max_lag));
+        assert_eq!(replica.log_end_offset, 15);
+    }
+
     fn assert_message(message: &Message, offset: u64, id: u64, payload: &[u8]) {
         assert_eq!(message.offset, offset);
         assert_eq!(message.id, id);
@@ -1,30 +1,35 @@
-use crate::models::appended_messages::AppendedMessages;
-use crate::streaming::stream::Stream;
-use sdk::commands::append_messages::AppendableMessage;
+use crate::environment;
+use crate::metadata::MetadataStore;
+use crate::streaming::stream::{Stream, LOG_FILE};
+use crate::types::NodeId;
 use sdk::error::SystemError;
-use sdk::models::message::Message;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
-use std::fs::create_dir_all;
-use std::path::Path;
-use tracing::{info, warn};
+use std::rc::Rc;
+use tracing::{error, info, warn};
 
 #[derive(Debug)]
 pub(crate) struct Streamer {
     path: String,
     node_id: u64,
-    streams: HashMap<u64, Stream>,
+    streams: BTreeMap<u64, Stream>,
+    metadata: Rc<dyn MetadataStore>,
 }
 
 impl Streamer {
-    pub fn new(node_id: u64, path: &str) -> Self {
+    pub fn new(node_id: u64, path: &str, metadata: Rc<dyn MetadataStore>) -> Self {
         Self {
             node_id,
             path: path.to_string(),
-            streams: HashMap::new(),
+            streams: BTreeMap::new(),
+            metadata,
         }
     }
 
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
     pub fn get_stream(&self, id: u64) -> Option<&Stream> {
         self.streams.get(&id)
     }
@@ -37,124 +42,194 @@ impl Streamer {
         self.streams.values().collect::<Vec<&Stream>>()
     }
 
-    pub fn set_leader(&mut self, leader_id: u64) {
+    pub fn get_streams_mut(&mut self) -> Vec<&mut Stream> {
+        self.streams.values_mut().collect::<Vec<&mut Stream>>()
+    }
+
+    /// The streams without the assigned replicas are led by the cluster leader, its term is their leader epoch.
+    /// It's set on every heartbeat, so the manifest is saved only once the leader or its epoch changes.
+    pub async fn set_leader(&mut self, leader_id: u64, term: u64) {
         for stream in self.streams.values_mut() {
-            stream.leader_id = leader_id;
+            if !stream.replicas.is_empty()
+                || (stream.leader_id == leader_id && stream.leader_epoch == term)
+            {
+                continue;
+            }
+
+            stream.set_leader(leader_id, term);
+            if let Err(error) = stream.save_metadata().await {
+                error!(
+                    "Failed to save the leader of stream with ID: {}, {error}",
+                    stream.stream_id
+                );
+            }
         }
     }
 
+    pub async fn set_stream_leader(
+        &mut self,
+        id: u64,
+        leader_id: u64,
+        leader_epoch: u64,
+    ) -> Result<(), SystemError> {
+        let Some(stream) = self.streams.get_mut(&id) else {
+            return Err(SystemError::InvalidStreamId);
+        };
+
+        info!(
+            "Updated leader for stream with ID: {id}: {} -> {leader_id}, leader epoch: {} -> {leader_epoch}",
+            stream.leader_id, stream.leader_epoch
+        );
+        stream.set_leader(leader_id, leader_epoch);
+        stream.save_metadata().await
+    }
+
     pub async fn create_stream(
         &mut self,
         id: u64,
         replication_factor: u8,
+        min_in_sync_replicas: u8,
+        replicas: Vec<NodeId>,
     ) -> Result<(), SystemError> {
+        // The entries are replayed on restart, while the stream is already loaded from its manifest.
         if self.streams.contains_key(&id) {
             warn!("Stream: {id} already exists.");
             return Ok(());
         }
 
-        let mut stream = Stream::new(id, self.node_id, &self.path, replication_factor);
-        stream.init().await;
+        let mut stream = Stream::new(
+            id,
+            self.node_id,
+            &self.path,
+            replication_factor,
+            self.metadata.clone(),
+        );
+        stream.min_in_sync_replicas = min_in_sync_replicas;
+        if let Some(leader_id) = replicas.first() {
+            stream.leader_id = *leader_id;
+        }
+        stream.isr = replicas.clone();
+        stream.replicas = replicas;
+        stream.init().await?;
         self.streams.insert(id, stream);
         Ok(())
     }
 
-    pub async fn delete_stream(&mut self, id: u64) {
-        let stream = self.streams.remove(&id);
-        if stream.is_none() {
-            warn!("Stream with ID: {id} does not exist.");
-            return;
-        }
-
-        let stream = stream.unwrap();
-        stream.delete();
-        info!("Deleted stream with ID: {id}.");
-    }
-
-    pub async fn init(&mut self) {
-        if !Path::new(&self.path).exists() {
-            create_dir_all(&self.path)
-                .unwrap_or_else(|_| panic!("Failed to create streams directory: {}", self.path));
-            info!("Created streams directory: {}", self.path);
-            return;
-        }
-
-        let directory = std::fs::read_dir(&self.path)
-            .unwrap_or_else(|_| panic!("Failed to read streams directory: {}", self.path));
-
-        for entry in directory {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if !path.is_dir() {
-                continue;
-            }
-
-            let stream_id = path
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .parse::<u64>()
-                .unwrap();
-
-            let mut stream = Stream::new(stream_id, self.node_id, &self.path, 1);
-            stream.init().await;
-            self.streams.insert(stream_id, stream);
-            info!("Initialized stream with ID: {}", stream_id);
-        }
-    }
-
-    pub async fn append_messages(
+    pub async fn set_replicas(
         &mut self,
-        stream_id: u64,
-        messages: &[AppendableMessage],
-    ) -> Result<AppendedMessages, SystemError> {
-        let stream = self.streams.get_mut(&stream_id);
-        if stream.is_none() {
+        id: u64,
+        min_in_sync_replicas: u8,
+        replicas: Vec<NodeId>,
+    ) -> Result<(), SystemError> {
+        let Some(stream) = self.streams.get_mut(&id) else {
             return Err(SystemError::InvalidStreamId);
-        }
+        };
 
-        let stream = stream.unwrap();
-        stream.append_messages(messages).await
+        stream.min_in_sync_replicas = min_in_sync_replicas;
+        stream.replicas = replicas;
+        stream.save_metadata().await
     }
 
-    pub async fn commit_messages(
+    /// The ISR of the assigned replicas is measured by the leader of the given epoch, which is replicated,
+    /// so the one of the previous leader is rejected. The epoch of the other streams follows the cluster term,
+    /// which is set by each node on its own, so it can't be compared when the entry is applied.
+    pub async fn set_isr(
         &mut self,
-        stream_id: u64,
-        messages: Vec<Message>,
+        id: u64,
+        leader_epoch: u64,
+        isr: Vec<u64>,
     ) -> Result<(), SystemError> {
-        let stream = self.streams.get_mut(&stream_id);
-        if stream.is_none() {
+        let Some(stream) = self.streams.get_mut(&id) else {
             return Err(SystemError::InvalidStreamId);
+        };
+
+        if !stream.replicas.is_empty() && stream.leader_epoch != leader_epoch {
+            warn!(
+                "Rejected ISR: {isr:?} for stream with ID: {id} in leader epoch: {leader_epoch}, current leader epoch: {}.",
+                stream.leader_epoch
+            );
+            return Err(SystemError::StaleLeaderEpoch(stream.leader_epoch));
         }
 
-        let stream = stream.unwrap();
-        stream.commit_messages(messages).await
+        info!(
+            "Updated ISR for stream with ID: {id}: {:?} -> {isr:?}",
+            stream.isr
+        );
+        stream.isr = isr;
+        stream.save_metadata().await
     }
 
-    pub async fn reset_offset(&mut self, stream_id: u64, offset: u64) {
-        let stream = self.streams.get_mut(&stream_id);
-        if stream.is_none() {
-            return;
-        }
+    /// The stream is kept until its directory is removed, so that the failed deletion fences the node
+    /// instead of losing the stream, which would be loaded again on restart.
+    pub async fn delete_stream(&mut self, id: u64) -> Result<(), SystemError> {
+        let Some(stream) = self.streams.get(&id) else {
+            warn!("Stream with ID: {id} does not exist.");
+            return Ok(());
+        };
 
-        let stream = stream.unwrap();
-        stream.reset_offset(offset);
+        stream.delete().await?;
+        self.streams.remove(&id);
+        info!("Deleted stream with ID: {id}.");
+        Ok(())
     }
 
-    pub(crate) fn poll_messages(
-        &self,
-        stream_id: u64,
-        offset: u64,
-        count: u64,
-    ) -> Result<&[Message], SystemError> {
-        let stream = self.streams.get(&stream_id);
-        if stream.is_none() {
-            return Err(SystemError::InvalidStreamId);
+    pub async fn init(&mut self) -> Result<(), SystemError> {
+        let storage = environment::storage();
+        if !storage.exists(&self.path) {
+            if let Err(error) = storage.create_dir_all(&self.path) {
+                error!("Failed to create streams directory: {}, {error}", self.path);
+                return Err(error.into());
+            }
+            info!("Created streams directory: {}", self.path);
+            return Ok(());
         }
 
-        let stream = stream.unwrap();
-        stream.poll_messages(offset, count)
+        let directories = match storage.read_dir(&self.path) {
+            Ok(directories) => directories,
+            Err(error) => {
+                error!("Failed to read streams directory: {}, {error}", self.path);
+                return Err(error.into());
+            }
+        };
+
+        for name in directories {
+            let Ok(stream_id) = name.parse::<u64>() else {
+                warn!("Skipping invalid stream directory: {name}");
+                continue;
+            };
+            // The stream without the manifest has never been fully created, so it has no messages yet,
+            // and it's created once again by the replicated state. The manifest is saved before the log,
+            // so the messages without it mean that the manifest has been lost, and the node can't start.
+            let metadata = match self.metadata.load_stream(stream_id).await {
+                Ok(Some(metadata)) => metadata,
+                Ok(None) => {
+                    let log_path = format!("{}/{stream_id}/{LOG_FILE}", self.path);
+                    if storage.exists(&log_path) && storage.size(&log_path)? > 0 {
+                        error!("Stream with ID: {stream_id} has messages, but no manifest.");
+                        return Err(SystemError::InvalidStreamManifest);
+                    }
+
+                    warn!("Stream with ID: {stream_id} has no manifest, skipping.");
+                    continue;
+                }
+                Err(error) => {
+                    error!("Failed to load manifest of stream with ID: {stream_id}, {error}");
+                    return Err(error);
+                }
+            };
+
+            let mut stream = Stream::new(
+                stream_id,
+                metadata.leader_id,
+                &self.path,
+                metadata.replication_factor,
+                self.metadata.clone(),
+            );
+            stream.init().await?;
+            self.streams.insert(stream_id, stream);
+            info!("Initialized stream with ID: {}", stream_id);
+        }
+        Ok(())
     }
 }
 
@@ -168,3 +243,75 @@ impl Display for Streamer {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::memory::{MemoryStorage, WriteFault};
+    use crate::environment::Environment;
+    use crate::metadata::file_store::FileMetadataStore;
+
+    const STREAMS_PATH: &str = "test_streamer";
+
+    fn new_streamer() -> Streamer {
+        Streamer::new(
+            1,
+            STREAMS_PATH,
+            Rc::new(FileMetadataStore::new(STREAMS_PATH)),
+        )
+    }
+
+    #[monoio::test]
+    async fn stream_with_messages_but_without_valid_manifest_should_fail_init() {
+        let _guard = Environment::system()
+            .with_storage(Rc::new(MemoryStorage::default()))
+            .enter();
+        let storage = environment::storage();
+        storage
+            .create_dir_all(&format!("{STREAMS_PATH}/1"))
+            .unwrap();
+        storage
+            .create_dir_all(&format!("{STREAMS_PATH}/2"))
+            .unwrap();
+        let mut streamer = new_streamer();
+        streamer.init().await.unwrap();
+        assert!(streamer.get_stream(1).is_none());
+
+        let log_path = format!("{STREAMS_PATH}/1/{LOG_FILE}");
+        storage.write_at(&log_path, 0, vec![1; 64]).await.unwrap();
+        let mut streamer = new_streamer();
+        assert!(matches!(
+            streamer.init().await,
+            Err(SystemError::InvalidStreamManifest)
+        ));
+
+        storage.remove_file(&log_path).unwrap();
+        storage
+            .write_at(&format!("{STREAMS_PATH}/2/manifest"), 0, vec![1; 64])
+            .await
+            .unwrap();
+        let mut streamer = new_streamer();
+        assert!(streamer.init().await.is_err());
+    }
+
+    #[monoio::test]
+    async fn stream_should_be_kept_when_its_deletion_fails() {
+        let storage = Rc::new(MemoryStorage::default());
+        let _guard = Environment::system().with_storage(storage.clone()).enter();
+        let mut streamer = new_streamer();
+        streamer.init().await.unwrap();
+        streamer.create_stream(1, 1, 1, vec![1]).await.unwrap();
+
+        storage.set_write_fault(Some(WriteFault::Failed));
+        assert!(streamer.delete_stream(1).await.is_err());
+        assert!(streamer.get_stream(1).is_some());
+
+        storage.set_write_fault(None);
+        let mut restarted_streamer = new_streamer();
+        restarted_streamer.init().await.unwrap();
+        assert!(restarted_streamer.get_stream(1).is_some());
+        restarted_streamer.delete_stream(1).await.unwrap();
+        assert!(restarted_streamer.get_stream(1).is_none());
+        assert!(!environment::storage().exists(&format!("{STREAMS_PATH}/1")));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::faults::{FaultCommand, NodeId};
+
+/// Parses the command in the same format as the CLI does, e.g. `drop.1|2|0.1` or `partition.1|3`.
+pub(crate) fn parse(input: &str) -> Option<FaultCommand> {
+    let parts = input.trim().splitn(2, '.').collect::<Vec<&str>>();
+    let arguments = parts
+        .get(1)
+        .map(|arguments| arguments.split('|').collect::<Vec<&str>>())
+        .unwrap_or_default();
+    match (parts[0], arguments.as_slice()) {
+        ("drop", [from, to, probability]) => Some(FaultCommand::Drop(
+            parse_node_id(from)?,
+            parse_node_id(to)?,
+            parse_probability(probability)?,
+        )),
+        ("delay", [from, to, delay]) => {
+            let delay = delay.parse().ok()?;
+            Some(FaultCommand::Delay(
+                parse_node_id(from)?,
+                parse_node_id(to)?,
+                delay,
+                delay,
+            ))
+        }
+        ("delay", [from, to, min, max]) => Some(FaultCommand::Delay(
+            parse_node_id(from)?,
+            parse_node_id(to)?,
+            min.parse().ok()?,
+            max.parse().ok()?,
+        )),
+        ("duplicate", [from, to, probability]) => Some(FaultCommand::Duplicate(
+            parse_node_id(from)?,
+            parse_node_id(to)?,
+            parse_probability(probability)?,
+        )),
+        ("blackhole", [from, to]) => Some(FaultCommand::Blackhole(
+            parse_node_id(from)?,
+            parse_node_id(to)?,
+        )),
+        ("partition", [first, second]) => Some(FaultCommand::Partition(
+            parse_node_id(first)?,
+            parse_node_id(second)?,
+        )),
+        ("isolate", [node_id]) => Some(FaultCommand::Isolate(parse_node_id(node_id)?)),
+        ("heal", []) => Some(FaultCommand::Heal(None)),
+        ("heal", [from, to]) => Some(FaultCommand::Heal(Some((
+            parse_node_id(from)?,
+            parse_node_id(to)?,
+        )))),
+        ("status", []) => Some(FaultCommand::Status),
+        _ => None,
+    }
+}
+
+fn parse_node_id(input: &str) -> Option<NodeId> {
+    input.trim().parse().ok()
+}
+
+fn parse_probability(input: &str) -> Option<f64> {
+    input
+        .trim()
+        .parse()
+        .ok()
+        .filter(|probability| (0.0..=1.0).contains(probability))
+}
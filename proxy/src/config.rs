@@ -0,0 +1,77 @@
+use figment::providers::{Env, Format, Toml};
+use figment::Figment;
+use sdk::error::SystemError;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use tracing::info;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ProxyConfig {
+    pub control_address: String,
+    pub nodes: Vec<ProxyNodeConfig>,
+    pub links: Vec<LinkConfig>,
+}
+
+/// The node behind the proxy, with its actual internal address.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ProxyNodeConfig {
+    pub id: u64,
+    pub address: String,
+}
+
+/// The link is used by the node `from` to connect to the node `to`, which is configured
+/// in the `[[cluster.nodes]]` of the node `from` as the internal address of the node `to`.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LinkConfig {
+    pub from: u64,
+    pub to: u64,
+    pub address: String,
+}
+
+impl Display for ProxyConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ProxyConfig {{ control_address: {}, nodes: {:?}, links: {:?} }}",
+            self.control_address, self.nodes, self.links
+        )
+    }
+}
+
+impl ProxyConfig {
+    pub fn load(path: &str) -> Result<Self, SystemError> {
+        info!("Loading config from path: '{path}'...");
+        if !Path::new(path).exists() {
+            return Err(SystemError::ConfigNotFound(format!(
+                "Cannot find configuration file at path: '{path}'."
+            )));
+        }
+
+        let config: Self = Figment::new()
+            .merge(Toml::file(path))
+            .merge(Env::prefixed("IGGY_PROXY_"))
+            .extract()
+            .map_err(|error| {
+                SystemError::ConfigInvalid(format!("Failed to load configuration: {error}"))
+            })?;
+        for link in &config.links {
+            if config.node_address(link.to).is_none() {
+                return Err(SystemError::ConfigInvalid(format!(
+                    "Link from node ID: {} points to the unknown node ID: {}.",
+                    link.from, link.to
+                )));
+            }
+        }
+
+        info!("Config loaded from path: '{path}'");
+        Ok(config)
+    }
+
+    pub fn node_address(&self, node_id: u64) -> Option<&str> {
+        self.nodes
+            .iter()
+            .find(|node| node.id == node_id)
+            .map(|node| node.address.as_str())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::command_parser;
+use crate::faults::Faults;
+use monoio::io::{AsyncReadRent, AsyncWriteRentExt};
+use monoio::net::TcpListener;
+use std::cell::RefCell;
+use std::rc::Rc;
+use tracing::{error, info};
+
+const BUFFER_SIZE: usize = 1024;
+
+/// Executes the command and returns the response, which is also logged.
+pub(crate) fn execute(input: &str, faults: &RefCell<Faults>) -> String {
+    let input = input.trim();
+    let Some(command) = command_parser::parse(input) else {
+        error!("Invalid command: {input}");
+        return format!("error: invalid command: {input}");
+    };
+
+    let response = faults.borrow_mut().apply(command);
+    info!("Executed command: {input}, {response}");
+    response
+}
+
+/// Accepts the connections of the scripts, which send one command per line and receive one response per line.
+pub(crate) fn start(address: &str, faults: Rc<RefCell<Faults>>) {
+    let address = address.to_string();
+    monoio::spawn(async move {
+        let listener = match TcpListener::bind(&address) {
+            Ok(listener) => listener,
+            Err(error) => {
+                error!("Failed to bind the control server on address: {address}, error: {error}");
+                return;
+            }
+        };
+
+        info!("Control server has started on: {address}");
+        loop {
+            let Ok((mut stream, peer_address)) = listener.accept().await else {
+                error!("Failed to accept the control connection.");
+                continue;
+            };
+
+            let faults = faults.clone();
+            monoio::spawn(async move {
+                info!("Accepted the control connection from: {peer_address}");
+                let mut input = Vec::new();
+                loop {
+                    let (result, buffer) = stream.read(vec![0u8; BUFFER_SIZE]).await;
+                    let read_bytes = match result {
+                        Ok(0) | Err(_) => break,
+                        Ok(read_bytes) => read_bytes,
+                    };
+
+                    input.extend_from_slice(&buffer[..read_bytes]);
+                    while let Some(position) = input.iter().position(|byte| *byte == b'\n') {
+                        let line = input.drain(..=position).collect::<Vec<u8>>();
+                        let line = String::from_utf8_lossy(&line);
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+
+                        let response = format!("{}\n", execute(&line, &faults));
+                        if stream.write_all(response.into_bytes()).await.0.is_err() {
+                            return;
+                        }
+                    }
+                }
+                info!("Closed the control connection from: {peer_address}");
+            });
+        }
+    });
+}
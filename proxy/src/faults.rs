@@ -0,0 +1,186 @@
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+pub(crate) type NodeId = u64;
+
+/// The faults of the traffic sent from one node to the other, in both of their connections.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct LinkFaults {
+    /// The probability of the frame being dropped, which resets the connection, as TCP would never skip the bytes.
+    pub drop: f64,
+    pub delay: (u64, u64),
+    /// The probability of the request being sent twice, the extra response is discarded on the way back.
+    pub duplicate: f64,
+    /// The frames are held until the link is healed, while the connection stays open, as TCP would never skip the bytes.
+    pub blackhole: bool,
+}
+
+impl Display for LinkFaults {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "drop: {}, delay: {} - {} ms, duplicate: {}, blackhole: {}",
+            self.drop, self.delay.0, self.delay.1, self.duplicate, self.blackhole
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Action {
+    Forward { delay: Duration, copies: usize },
+    Hold,
+    Reset,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FaultCommand {
+    Drop(NodeId, NodeId, f64),
+    Delay(NodeId, NodeId, u64, u64),
+    Duplicate(NodeId, NodeId, f64),
+    Blackhole(NodeId, NodeId),
+    /// Blackholes the traffic in both directions.
+    Partition(NodeId, NodeId),
+    /// Blackholes all the traffic from and to the node.
+    Isolate(NodeId),
+    Heal(Option<(NodeId, NodeId)>),
+    Status,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Faults {
+    nodes: Vec<NodeId>,
+    links: BTreeMap<(NodeId, NodeId), LinkFaults>,
+}
+
+impl Faults {
+    pub fn new(nodes: Vec<NodeId>) -> Self {
+        Self {
+            nodes,
+            links: BTreeMap::new(),
+        }
+    }
+
+    /// Decides what happens to the next frame sent from one node to the other.
+    pub fn decide(&self, from: NodeId, to: NodeId) -> Action {
+        let Some(faults) = self.links.get(&(from, to)) else {
+            return Action::Forward {
+                delay: Duration::ZERO,
+                copies: 1,
+            };
+        };
+
+        if faults.blackhole {
+            return Action::Hold;
+        }
+
+        let mut rng = rand::thread_rng();
+        if rng.gen_bool(faults.drop) {
+            return Action::Reset;
+        }
+
+        let (from, to) = faults.delay;
+        let delay = Duration::from_millis(rng.gen_range(from..=to.max(from)));
+        let copies = if rng.gen_bool(faults.duplicate) { 2 } else { 1 };
+        Action::Forward { delay, copies }
+    }
+
+    /// Applies the command and returns the response, which describes the faults of the affected links.
+    pub fn apply(&mut self, command: FaultCommand) -> String {
+        match command {
+            FaultCommand::Drop(from, to, probability) => {
+                self.link(from, to).drop = probability;
+                self.describe(&[(from, to)])
+            }
+            FaultCommand::Delay(from, to, min, max) => {
+                self.link(from, to).delay = (min, max.max(min));
+                self.describe(&[(from, to)])
+            }
+            FaultCommand::Duplicate(from, to, probability) => {
+                self.link(from, to).duplicate = probability;
+                self.describe(&[(from, to)])
+            }
+            FaultCommand::Blackhole(from, to) => {
+                self.link(from, to).blackhole = true;
+                self.describe(&[(from, to)])
+            }
+            FaultCommand::Partition(first, second) => {
+                self.link(first, second).blackhole = true;
+                self.link(second, first).blackhole = true;
+                self.describe(&[(first, second), (second, first)])
+            }
+            FaultCommand::Isolate(node_id) => {
+                let links = self
+                    .nodes
+                    .iter()
+                    .filter(|id| **id != node_id)
+                    .flat_map(|id| [(node_id, *id), (*id, node_id)])
+                    .collect::<Vec<_>>();
+                for (from, to) in &links {
+                    self.link(*from, *to).blackhole = true;
+                }
+                self.describe(&links)
+            }
+            FaultCommand::Heal(Some((from, to))) => {
+                self.links.remove(&(from, to));
+                self.describe(&[(from, to)])
+            }
+            FaultCommand::Heal(None) => {
+                self.links.clear();
+                "healed all links".to_string()
+            }
+            FaultCommand::Status => {
+                let links = self.links.keys().copied().collect::<Vec<_>>();
+                if links.is_empty() {
+                    return "no faults".to_string();
+                }
+                self.describe(&links)
+            }
+        }
+    }
+
+    fn link(&mut self, from: NodeId, to: NodeId) -> &mut LinkFaults {
+        self.links.entry((from, to)).or_default()
+    }
+
+    fn describe(&self, links: &[(NodeId, NodeId)]) -> String {
+        links
+            .iter()
+            .map(|(from, to)| {
+                let faults = self.links.get(&(*from, *to)).copied().unwrap_or_default();
+                format!("{from} -> {to}: {faults}")
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partition_should_blackhole_both_directions_until_healed() {
+        let mut faults = Faults::new(vec![1, 2, 3]);
+        faults.apply(FaultCommand::Partition(1, 2));
+        assert_eq!(faults.decide(1, 2), Action::Hold);
+        assert_eq!(faults.decide(2, 1), Action::Hold);
+        assert!(matches!(faults.decide(1, 3), Action::Forward { .. }));
+
+        faults.apply(FaultCommand::Heal(Some((1, 2))));
+        assert!(matches!(faults.decide(1, 2), Action::Forward { .. }));
+        assert_eq!(faults.decide(2, 1), Action::Hold);
+    }
+
+    #[test]
+    fn isolated_node_should_neither_send_nor_receive() {
+        let mut faults = Faults::new(vec![1, 2, 3]);
+        faults.apply(FaultCommand::Isolate(3));
+        for node_id in [1, 2] {
+            assert_eq!(faults.decide(3, node_id), Action::Hold);
+            assert_eq!(faults.decide(node_id, 3), Action::Hold);
+        }
+        assert!(matches!(faults.decide(1, 2), Action::Forward { .. }));
+    }
+}
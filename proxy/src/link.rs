@@ -0,0 +1,318 @@
+use crate::faults::{Action, Faults, NodeId};
+use futures::channel::mpsc::unbounded;
+use futures::future::{select, try_join, Either};
+use futures::StreamExt;
+use monoio::io::{AsyncReadRent, AsyncWriteRent, AsyncWriteRentExt, Splitable};
+use monoio::net::{TcpListener, TcpStream};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+const FRAME_HEADER_LENGTH: usize = 8;
+const HOLD_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+const TARGET_CONNECT_INTERVAL: Duration = Duration::from_millis(100);
+const TARGET_CONNECT_RETRIES: u32 = 50;
+
+/// Accepts the connections of the node `from` and forwards them to the node `to`.
+pub(crate) fn start(
+    from: NodeId,
+    to: NodeId,
+    address: &str,
+    target_address: &str,
+    faults: Rc<RefCell<Faults>>,
+) {
+    let address = address.to_string();
+    let target_address = target_address.to_string();
+    monoio::spawn(async move {
+        let listener = match TcpListener::bind(&address) {
+            Ok(listener) => listener,
+            Err(error) => {
+                error!(
+                    "Failed to bind the link {from} -> {to} on address: {address}, error: {error}"
+                );
+                return;
+            }
+        };
+
+        info!("Proxying the link {from} -> {to} from address: {address} to: {target_address}");
+        loop {
+            let Ok((stream, peer_address)) = listener.accept().await else {
+                error!("Failed to accept the connection on the link {from} -> {to}.");
+                continue;
+            };
+
+            let target_address = target_address.clone();
+            let faults = faults.clone();
+            monoio::spawn(async move {
+                info!("Accepted the connection from: {peer_address} on the link {from} -> {to}.");
+                let Some(target) = connect(to, &target_address).await else {
+                    return;
+                };
+
+                if let Err(error) = proxy(from, to, stream, target, faults).await {
+                    info!("Closed the connection from: {peer_address} on the link {from} -> {to}: {error}");
+                } else {
+                    info!("Closed the connection from: {peer_address} on the link {from} -> {to}.");
+                }
+            });
+        }
+    });
+}
+
+/// Retries to connect to the node, which might not be listening yet, as the accepted connection
+/// would otherwise be closed right away, while the node `from` has already been connected.
+async fn connect(to: NodeId, target_address: &str) -> Option<TcpStream> {
+    let mut retry_count = 0;
+    loop {
+        match TcpStream::connect(target_address).await {
+            Ok(target) => return Some(target),
+            Err(error) if retry_count < TARGET_CONNECT_RETRIES => {
+                retry_count += 1;
+                debug!("Failed to connect to node ID: {to} on address: {target_address}, retrying ({retry_count}/{TARGET_CONNECT_RETRIES}), error: {error}");
+                monoio::time::sleep(TARGET_CONNECT_INTERVAL).await;
+            }
+            Err(error) => {
+                warn!("Failed to connect to node ID: {to} on address: {target_address}, error: {error}");
+                return None;
+            }
+        }
+    }
+}
+
+/// Forwards the traffic both ways, until either of the connections is closed or reset by the fault.
+/// The connection is opened by the node `from`, so its frames are the requests and the frames of `to` are the responses.
+async fn proxy(
+    from: NodeId,
+    to: NodeId,
+    source: TcpStream,
+    target: TcpStream,
+    faults: Rc<RefCell<Faults>>,
+) -> io::Result<()> {
+    let (source_reader, source_writer) = source.into_split();
+    let (target_reader, target_writer) = target.into_split();
+    let responses = Rc::new(RefCell::new(VecDeque::new()));
+    let outgoing = forward(
+        from,
+        to,
+        source_reader,
+        target_writer,
+        faults.clone(),
+        Frames::Requests(responses.clone()),
+    );
+    let incoming = forward(
+        to,
+        from,
+        target_reader,
+        source_writer,
+        faults,
+        Frames::Responses(responses),
+    );
+    match select(Box::pin(outgoing), Box::pin(incoming)).await {
+        Either::Left((result, _)) | Either::Right((result, _)) => result,
+    }
+}
+
+/// The responses expected by the node, in the order of its requests, the duplicated request gets the extra one,
+/// which is discarded, as the node reads a single response per request.
+type Responses = Rc<RefCell<VecDeque<bool>>>;
+
+enum Frames {
+    Requests(Responses),
+    Responses(Responses),
+}
+
+/// Reads the length-prefixed frames sent from one node to the other and writes them after their delay, in order.
+/// The faults are applied to the whole frames, so that the node always receives the valid ones.
+async fn forward(
+    from: NodeId,
+    to: NodeId,
+    mut reader: impl AsyncReadRent,
+    mut writer: impl AsyncWriteRent,
+    faults: Rc<RefCell<Faults>>,
+    frames: Frames,
+) -> io::Result<()> {
+    let (sender, mut receiver) = unbounded::<(Instant, Vec<u8>)>();
+    let read = async move {
+        let mut deliver_after = Instant::now();
+        let mut pending = Vec::new();
+        loop {
+            let (result, buffer) = reader.read(vec![0u8; BUFFER_SIZE]).await;
+            let read_bytes = result?;
+            if read_bytes == 0 {
+                return Ok::<(), io::Error>(());
+            }
+
+            pending.extend_from_slice(&buffer[..read_bytes]);
+            while let Some(length) = frame_length(&pending) {
+                let frame = pending.drain(..length).collect::<Vec<u8>>();
+                // The blackholed frame is held and nothing else is read, until the link is healed.
+                let (delay, copies) = loop {
+                    let action = faults.borrow().decide(from, to);
+                    match action {
+                        Action::Hold => monoio::time::sleep(HOLD_CHECK_INTERVAL).await,
+                        Action::Reset => {
+                            info!("Dropped {length} bytes sent from node ID: {from} to: {to}, resetting the connection.");
+                            return Err(io::ErrorKind::ConnectionReset.into());
+                        }
+                        Action::Forward { delay, copies } => break (delay, copies),
+                    }
+                };
+
+                let copies = match &frames {
+                    Frames::Requests(responses) => {
+                        let mut responses = responses.borrow_mut();
+                        responses.push_back(true);
+                        for _ in 1..copies {
+                            responses.push_back(false);
+                        }
+                        copies
+                    }
+                    // Only the requests are duplicated, the extra response would never be read by the node.
+                    Frames::Responses(responses) => {
+                        if responses.borrow_mut().pop_front() == Some(false) {
+                            info!("Discarded the response to the duplicated request sent from node ID: {to} to: {from}.");
+                            continue;
+                        }
+                        1
+                    }
+                };
+
+                // The frame can't overtake the previous one, which has been delayed for longer.
+                deliver_after = deliver_after.max(Instant::now() + delay);
+                for _ in 0..copies {
+                    let _ = sender.unbounded_send((deliver_after, frame.clone()));
+                }
+            }
+        }
+    };
+    let write = async move {
+        while let Some((deliver_after, buffer)) = receiver.next().await {
+            let delay = deliver_after.saturating_duration_since(Instant::now());
+            if !delay.is_zero() {
+                monoio::time::sleep(delay).await;
+            }
+            let (result, _) = writer.write_all(buffer).await;
+            result?;
+        }
+        Ok::<(), io::Error>(())
+    };
+    try_join(read, write).await?;
+    Ok(())
+}
+
+/// Returns the length of the first frame, i.e. its code or status, payload length and payload,
+/// once it has been fully read.
+fn frame_length(buffer: &[u8]) -> Option<usize> {
+    let header = buffer.get(..FRAME_HEADER_LENGTH)?;
+    let payload_length = u32::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+    let length = FRAME_HEADER_LENGTH + payload_length;
+    (buffer.len() >= length).then_some(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faults::FaultCommand;
+    use monoio::io::AsyncReadRentExt;
+
+    fn free_address() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    fn frame(code: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(FRAME_HEADER_LENGTH + payload.len());
+        frame.extend(code.to_le_bytes());
+        frame.extend((payload.len() as u32).to_le_bytes());
+        frame.extend(payload);
+        frame
+    }
+
+    async fn connect(faults: Rc<RefCell<Faults>>) -> (TcpStream, TcpStream) {
+        let address = free_address();
+        let target_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target_address = target_listener.local_addr().unwrap().to_string();
+        start(1, 2, &address, &target_address, faults);
+        monoio::time::sleep(Duration::from_millis(100)).await;
+        let source = TcpStream::connect(&address).await.unwrap();
+        let (target, _) = target_listener.accept().await.unwrap();
+        (source, target)
+    }
+
+    async fn write(stream: &mut TcpStream, bytes: Vec<u8>) {
+        let (result, _) = stream.write_all(bytes).await;
+        result.unwrap();
+    }
+
+    async fn read(stream: &mut TcpStream, length: usize) -> Vec<u8> {
+        let (result, bytes) = stream.read_exact(vec![0u8; length]).await;
+        result.unwrap();
+        bytes
+    }
+
+    async fn assert_nothing_to_read(stream: &mut TcpStream) {
+        let read = monoio::time::timeout(
+            Duration::from_millis(200),
+            stream.read(vec![0u8; BUFFER_SIZE]),
+        )
+        .await;
+        assert!(read.is_err());
+    }
+
+    #[monoio::test(timer_enabled = true)]
+    async fn blackholed_frames_should_be_held_and_delivered_in_order_once_healed() {
+        let faults = Rc::new(RefCell::new(Faults::new(vec![1, 2])));
+        let (mut source, mut target) = connect(faults.clone()).await;
+        faults.borrow_mut().apply(FaultCommand::Blackhole(1, 2));
+        let first = frame(1, b"first");
+        // The frame split into many chunks is still held as a whole.
+        write(&mut source, first[..4].to_vec()).await;
+        write(&mut source, first[4..].to_vec()).await;
+        assert_nothing_to_read(&mut target).await;
+
+        faults.borrow_mut().apply(FaultCommand::Heal(None));
+        let second = frame(2, b"second");
+        write(&mut source, second.clone()).await;
+        let received = read(&mut target, first.len() + second.len()).await;
+        assert_eq!(received, [first, second].concat());
+    }
+
+    #[monoio::test(timer_enabled = true)]
+    async fn duplicated_request_should_be_sent_twice_and_its_extra_response_discarded() {
+        let faults = Rc::new(RefCell::new(Faults::new(vec![1, 2])));
+        let (mut source, mut target) = connect(faults.clone()).await;
+        faults
+            .borrow_mut()
+            .apply(FaultCommand::Duplicate(1, 2, 1.0));
+        let request = frame(1, b"request");
+        write(&mut source, request.clone()).await;
+        let received = read(&mut target, 2 * request.len()).await;
+        assert_eq!(received, [request.clone(), request].concat());
+
+        faults.borrow_mut().apply(FaultCommand::Heal(None));
+        let next_request = frame(2, b"next");
+        write(&mut source, next_request.clone()).await;
+        assert_eq!(read(&mut target, next_request.len()).await, next_request);
+
+        let first_response = frame(0, b"first");
+        let extra_response = frame(0, b"extra");
+        let next_response = frame(0, b"next");
+        write(
+            &mut target,
+            [
+                first_response.clone(),
+                extra_response,
+                next_response.clone(),
+            ]
+            .concat(),
+        )
+        .await;
+        let received = read(&mut source, first_response.len() + next_response.len()).await;
+        assert_eq!(received, [first_response, next_response].concat());
+        assert_nothing_to_read(&mut source).await;
+    }
+}
@@ -0,0 +1,72 @@
+mod command_parser;
+mod config;
+mod control;
+mod faults;
+mod link;
+
+use crate::config::ProxyConfig;
+use crate::faults::Faults;
+use figlet_rs::FIGfont;
+use monoio::utils::CtrlC;
+use sdk::error::SystemError;
+use std::cell::RefCell;
+use std::io::BufRead;
+use std::rc::Rc;
+use tracing::info;
+
+const IGGY_PROXY_CONFIG_PATH: &str = "IGGY_PROXY_CONFIG_PATH";
+const DEFAULT_CONFIG_PATH: &str = "configs/proxy.toml";
+
+#[monoio::main(timer_enabled = true)]
+async fn main() -> Result<(), SystemError> {
+    tracing_subscriber::fmt::init();
+    let standard_font = FIGfont::standard().unwrap();
+    let figure = standard_font.convert("Iggy Proxy");
+    println!("{}", figure.unwrap());
+    let config_path =
+        std::env::var(IGGY_PROXY_CONFIG_PATH).unwrap_or(DEFAULT_CONFIG_PATH.to_string());
+    let config = ProxyConfig::load(&config_path)?;
+    println!("{config}");
+    let faults = Rc::new(RefCell::new(Faults::new(
+        config.nodes.iter().map(|node| node.id).collect(),
+    )));
+    for link in &config.links {
+        let target_address = config.node_address(link.to).unwrap();
+        link::start(
+            link.from,
+            link.to,
+            &link.address,
+            target_address,
+            faults.clone(),
+        );
+    }
+    control::start(&config.control_address, faults.clone());
+    read_commands(faults);
+    info!("Press CTRL+C shutdown Iggy proxy...");
+    CtrlC::new().unwrap().await;
+    info!("Iggy proxy has shutdown successfully.");
+    Ok(())
+}
+
+/// Reads the commands from the standard input on a separate thread, so that the traffic keeps flowing meanwhile.
+fn read_commands(faults: Rc<RefCell<Faults>>) {
+    let (sender, receiver) = flume::unbounded::<String>();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    monoio::spawn(async move {
+        while let Ok(line) = receiver.recv_async().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            println!("{}", control::execute(&line, &faults));
+        }
+    });
+}
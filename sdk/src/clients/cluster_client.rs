@@ -1,5 +1,6 @@
 use crate::bytes_serializable::BytesSerializable;
 use crate::clients::node_client::NodeClient;
+use crate::commands::add_node::AddNode;
 use crate::commands::append_messages::{AppendMessages, AppendableMessage};
 use crate::commands::command::Command;
 use crate::commands::create_stream::CreateStream;
@@ -8,9 +9,13 @@ use crate::commands::get_metadata::GetMetadata;
 use crate::commands::get_streams::GetStreams;
 use crate::commands::ping::Ping;
 use crate::commands::poll_messages::PollMessages;
+use crate::commands::rebalance_leaders::RebalanceLeaders;
+use crate::commands::remove_node::RemoveNode;
+use crate::commands::transfer_leadership::TransferLeadership;
 use crate::error::SystemError;
 use crate::models::message::{messages_from_bytes, Message};
 use crate::models::metadata::Metadata;
+use crate::models::read_consistency::ReadConsistency;
 use crate::models::stream::Stream;
 use futures::lock::Mutex;
 use monoio::time::sleep;
@@ -25,6 +30,13 @@ pub struct ClusterClient {
     metadata: Mutex<Option<Metadata>>,
     reconnection_interval: u64,
     reconnection_retries: u32,
+    /// The relaxed reads are spread across the stream replicas, unless they should be served by the leader.
+    prefer_leader_reads: bool,
+    /// The relaxed reads of each stream, and of the streams list under the none key, stick to a single replica,
+    /// as the replicas' high watermarks differ, so that a consumer doesn't get fewer messages than it has already read,
+    /// unless the replica is unavailable.
+    read_addresses: Mutex<HashMap<Option<u64>, String>>,
+    next_read: Mutex<usize>,
 }
 
 impl ClusterClient {
@@ -32,10 +44,14 @@ impl ClusterClient {
         addresses: Vec<&str>,
         reconnection_interval: u64,
         reconnection_retries: u32,
+        prefer_leader_reads: bool,
     ) -> Self {
         Self {
             reconnection_interval,
             reconnection_retries,
+            prefer_leader_reads,
+            read_addresses: Mutex::new(HashMap::new()),
+            next_read: Mutex::new(0),
             metadata: Mutex::new(None),
             clients: addresses
                 .iter()
@@ -102,18 +118,32 @@ impl ClusterClient {
         stream_id: u64,
         offset: u64,
         count: u64,
+        consistency: ReadConsistency,
     ) -> Result<Vec<Message>, SystemError> {
-        let leader_address = self.get_leader_address().await?;
-        let command = PollMessages::new_command(stream_id, offset, count);
-        let bytes = self.send(&command, &leader_address).await?;
+        let command = PollMessages::new_command(stream_id, offset, count, consistency);
+        let bytes = if consistency == ReadConsistency::Linearizable || self.prefer_leader_reads {
+            self.send_to_stream_leader(stream_id, &command).await?
+        } else {
+            self.send_to_stream_replica(stream_id, &command).await?
+        };
         let messages = messages_from_bytes(&bytes);
         Ok(messages)
     }
 
-    pub async fn get_streams(&self) -> Result<Vec<Stream>, SystemError> {
-        let leader_address = self.get_leader_address().await?;
-        let command = GetStreams::new_command();
-        let bytes = self.send(&command, &leader_address).await?;
+    pub async fn get_streams(
+        &self,
+        consistency: ReadConsistency,
+    ) -> Result<Vec<Stream>, SystemError> {
+        let address = if consistency == ReadConsistency::Linearizable || self.prefer_leader_reads {
+            self.get_leader_address().await?
+        } else {
+            let addresses = self.get_available_node_addresses().await;
+            self.get_next_read_address(None, addresses)
+                .await
+                .ok_or(SystemError::UnhealthyCluster)?
+        };
+        let command = GetStreams::new_command(consistency);
+        let bytes = self.send(&command, &address).await?;
         let mut streams = Vec::new();
         let mut position = 0;
         while position < bytes.len() {
@@ -135,10 +165,18 @@ impl ClusterClient {
         &self,
         stream_id: u64,
         replication_factor: Option<u8>,
+        min_in_sync_replicas: Option<u8>,
+        replicas: Vec<u64>,
     ) -> Result<(), SystemError> {
         let leader_address = self.get_leader_address().await?;
-        let command = CreateStream::new_command(stream_id, replication_factor);
+        let command = CreateStream::new_command(
+            stream_id,
+            replication_factor,
+            min_in_sync_replicas,
+            replicas,
+        );
         self.send(&command, &leader_address).await?;
+        self.update_metadata().await?;
         Ok(())
     }
 
@@ -154,22 +192,110 @@ impl ClusterClient {
         stream_id: u64,
         messages: Vec<AppendableMessage>,
     ) -> Result<(), SystemError> {
-        let leader_address = self.get_leader_address().await?;
         let command = AppendMessages::new_command(stream_id, messages);
+        self.send_to_stream_leader(stream_id, &command).await?;
+        Ok(())
+    }
+
+    pub async fn transfer_leadership(&self, node_id: u64) -> Result<(), SystemError> {
+        let leader_address = self.get_leader_address().await?;
+        let command = TransferLeadership::new_command(node_id);
         self.send(&command, &leader_address).await?;
+        self.update_metadata().await?;
         Ok(())
     }
 
-    pub async fn update_metadata(&self) -> Result<(), SystemError> {
-        let address = self.get_first_available_node_address().await?;
-        let command = GetMetadata::new_command();
-        let bytes = self.send(&command, &address).await?;
-        let metadata = Metadata::from_bytes(&bytes)?;
-        info!("Updated the metadata: {metadata}");
-        self.metadata.lock().await.replace(metadata);
+    pub async fn rebalance_leaders(&self) -> Result<(), SystemError> {
+        let leader_address = self.get_leader_address().await?;
+        let command = RebalanceLeaders::new_command();
+        self.send(&command, &leader_address).await?;
+        self.update_metadata().await?;
+        Ok(())
+    }
+
+    pub async fn add_node(
+        &self,
+        node_id: u64,
+        name: &str,
+        public_address: &str,
+        internal_address: &str,
+        learner: bool,
+    ) -> Result<(), SystemError> {
+        let leader_address = self.get_leader_address().await?;
+        let command = AddNode::new_command(
+            node_id,
+            name.to_string(),
+            public_address.to_string(),
+            internal_address.to_string(),
+            learner,
+        );
+        self.send(&command, &leader_address).await?;
+        self.update_metadata().await?;
+        Ok(())
+    }
+
+    pub async fn remove_node(&self, node_id: u64) -> Result<(), SystemError> {
+        let leader_address = self.get_leader_address().await?;
+        let command = RemoveNode::new_command(node_id);
+        self.send(&command, &leader_address).await?;
+        self.update_metadata().await?;
         Ok(())
     }
 
+    pub async fn update_metadata(&self) -> Result<(), SystemError> {
+        self.get_metadata(ReadConsistency::Linearizable).await
+    }
+
+    pub async fn get_metadata(&self, consistency: ReadConsistency) -> Result<(), SystemError> {
+        // Only the leader can serve the linearizable metadata, so try it first and then the other nodes.
+        let addresses = match consistency {
+            ReadConsistency::Linearizable => self.get_available_node_addresses().await,
+            ReadConsistency::Relaxed => vec![self.get_first_available_node_address().await?],
+        };
+        let command = GetMetadata::new_command(consistency);
+        let mut result = Err(SystemError::UnhealthyCluster);
+        for address in addresses {
+            let bytes = match self.send(&command, &address).await {
+                Ok(bytes) => bytes,
+                Err(error) => {
+                    warn!(
+                        "Failed to get the {consistency} metadata from node at: {address}, {error}"
+                    );
+                    result = Err(error);
+                    continue;
+                }
+            };
+
+            let metadata = Metadata::from_bytes(&bytes)?;
+            info!("Updated the {consistency} metadata: {metadata}");
+            self.metadata.lock().await.replace(metadata);
+            return Ok(());
+        }
+        result
+    }
+
+    async fn get_available_node_addresses(&self) -> Vec<String> {
+        let leader_address = self.get_leader_address().await.ok();
+        let mut addresses = Vec::new();
+        for (address, client) in &self.clients {
+            let client = client.lock().await;
+            let Some(client) = client.as_ref() else {
+                continue;
+            };
+
+            if !client.is_connected().await {
+                continue;
+            }
+
+            if leader_address.as_ref() == Some(address) {
+                addresses.insert(0, address.to_string());
+            } else {
+                addresses.push(address.to_string());
+            }
+        }
+        addresses
+    }
+
     async fn get_first_available_node_address(&self) -> Result<String, SystemError> {
         for (address, client) in &self.clients {
             let client = client.lock().await;
@@ -195,34 +321,149 @@ impl ClusterClient {
         }
 
         let leader_id = metadata.leader_id.unwrap();
-        let node = metadata.nodes.get(&leader_id);
+        self.get_node_address(metadata, leader_id).await
+    }
+
+    /// The streams unknown to the metadata are handled by the cluster leader.
+    async fn get_stream_leader_address(&self, stream_id: u64) -> Result<String, SystemError> {
+        let leader_id = self
+            .metadata
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|metadata| metadata.streams.get(&stream_id))
+            .map(|stream| stream.leader_id);
+        let Some(leader_id) = leader_id else {
+            return self.get_leader_address().await;
+        };
+
+        let metadata = self.metadata.lock().await;
+        let Some(metadata) = metadata.as_ref() else {
+            return Err(SystemError::UnhealthyCluster);
+        };
+
+        self.get_node_address(metadata, leader_id).await
+    }
+
+    /// The streams without the assigned replicas are read from the stream leader.
+    async fn get_stream_replica_address(&self, stream_id: u64) -> Option<String> {
+        let metadata = self.metadata.lock().await;
+        let stream = metadata.as_ref()?.streams.get(&stream_id)?;
+        let mut addresses = Vec::new();
+        for node_id in &stream.replicas {
+            if let Ok(address) = self.get_node_address(metadata.as_ref()?, *node_id).await {
+                addresses.push(address);
+            }
+        }
+        drop(metadata);
+        self.get_next_read_address(Some(stream_id), addresses).await
+    }
+
+    /// Returns the address already serving the reads of the stream, or of the streams list kept under the none key,
+    /// as long as it's still available, otherwise the next one of the available addresses, which is kept from now on.
+    async fn get_next_read_address(
+        &self,
+        stream_id: Option<u64>,
+        addresses: Vec<String>,
+    ) -> Option<String> {
+        if addresses.is_empty() {
+            return None;
+        }
+
+        let mut read_addresses = self.read_addresses.lock().await;
+        if let Some(address) = read_addresses.get(&stream_id) {
+            if addresses.contains(address) {
+                return Some(address.clone());
+            }
+        }
+
+        let mut next_read = self.next_read.lock().await;
+        *next_read = next_read.wrapping_add(1);
+        let address = addresses[*next_read % addresses.len()].clone();
+        read_addresses.insert(stream_id, address.clone());
+        Some(address)
+    }
+
+    async fn get_node_address(
+        &self,
+        metadata: &Metadata,
+        node_id: u64,
+    ) -> Result<String, SystemError> {
+        let node = metadata.nodes.get(&node_id);
         if node.is_none() {
-            return Err(SystemError::InvalidNode(leader_id));
+            return Err(SystemError::InvalidNode(node_id));
         }
 
         let node = node.unwrap();
         let address = &node.address;
         let node_client = self.clients.get(address);
         if node_client.is_none() {
-            error!("Leader not found for address: {address}");
+            error!("Node ID: {node_id} not found for address: {address}");
             return Err(SystemError::InvalidClusterNodeAddress(address.to_string()));
         }
 
         let node_client = node_client.unwrap().lock().await;
         if node_client.is_none() {
-            error!("Leader not found for address: {address}");
+            error!("Node ID: {node_id} not found for address: {address}");
             return Err(SystemError::InvalidClusterNodeAddress(address.to_string()));
         }
 
         let node_client = node_client.as_ref().unwrap();
         if !node_client.is_connected().await {
-            error!("Leader not found for address: {address}");
+            error!("Node ID: {node_id} is disconnected, address: {address}");
             return Err(SystemError::LeaderDisconnected);
         }
 
         Ok(address.to_string())
     }
 
+    /// Sends the command to the stream leader and retries it once with the updated metadata,
+    /// if the leader is not known or has rejected the command before handling it, e.g. it's not the leader anymore.
+    /// Any other error might have been returned after the command has taken effect, e.g. the appended messages
+    /// might have been stored despite the failed replication, so sending them again could duplicate them.
+    async fn send_to_stream_leader(
+        &self,
+        stream_id: u64,
+        command: &Command,
+    ) -> Result<Vec<u8>, SystemError> {
+        match self.get_stream_leader_address(stream_id).await {
+            Ok(address) => match self.send(command, &address).await {
+                Err(error) if is_rejected(&error) => {
+                    warn!("Stream with ID: {stream_id} leader has rejected the command, {error}");
+                }
+                result => return result,
+            },
+            Err(error) => {
+                warn!("Failed to get the leader address of stream with ID: {stream_id}, {error}");
+            }
+        }
+
+        warn!("Stream with ID: {stream_id} leader has changed, updating the metadata...");
+        self.update_metadata().await?;
+        let address = self.get_stream_leader_address(stream_id).await?;
+        self.send(command, &address).await
+    }
+
+    /// Sends the relaxed read to the next stream replica, which serves the committed messages,
+    /// and falls back to the stream leader if the replica has failed.
+    async fn send_to_stream_replica(
+        &self,
+        stream_id: u64,
+        command: &Command,
+    ) -> Result<Vec<u8>, SystemError> {
+        let Some(address) = self.get_stream_replica_address(stream_id).await else {
+            return self.send_to_stream_leader(stream_id, command).await;
+        };
+
+        match self.send(command, &address).await {
+            Ok(bytes) => Ok(bytes),
+            Err(error) => {
+                warn!("Failed to read stream with ID: {stream_id} from replica at: {address}, {error}, reading from the leader...");
+                self.send_to_stream_leader(stream_id, command).await
+            }
+        }
+    }
+
     async fn send(&self, command: &Command, address: &str) -> Result<Vec<u8>, SystemError> {
         let client = self.clients.get(address);
         if client.is_none() {
@@ -260,3 +501,92 @@ impl ClusterClient {
         Err(error)
     }
 }
+
+/// The node has rejected the command before handling it, so it can be sent again to another node.
+fn is_rejected(error: &SystemError) -> bool {
+    let SystemError::ErrorResponse(status) = error else {
+        return false;
+    };
+
+    [
+        SystemError::NotStreamLeader(0),
+        SystemError::NotStreamReplica(0),
+        SystemError::InvalidStreamId,
+        SystemError::NotLeader,
+        SystemError::LeaderNotElected,
+        SystemError::UnhealthyCluster,
+        SystemError::NodeFenced,
+        SystemError::InsufficientDiskSpace,
+    ]
+    .iter()
+    .any(|rejection| rejection.as_code() == *status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_commands_rejected_before_handling_should_be_sent_again() {
+        for rejection in [
+            SystemError::NotStreamLeader(1),
+            SystemError::InvalidStreamId,
+            SystemError::NodeFenced,
+        ] {
+            assert!(is_rejected(&SystemError::ErrorResponse(
+                rejection.as_code()
+            )));
+        }
+        for error in [
+            SystemError::ErrorResponse(SystemError::CannotSyncAppendedMessages.as_code()),
+            SystemError::CannotReadResponse,
+            SystemError::NotStreamLeader(1),
+        ] {
+            assert!(!is_rejected(&error));
+        }
+    }
+
+    #[monoio::test]
+    async fn stream_reads_should_stick_to_replica_until_it_is_unavailable() {
+        let client = ClusterClient::new(vec![], 0, 0, false);
+        let addresses = vec![
+            "node1".to_string(),
+            "node2".to_string(),
+            "node3".to_string(),
+        ];
+        let first_stream_address = client
+            .get_next_read_address(Some(1), addresses.clone())
+            .await
+            .unwrap();
+        let second_stream_address = client
+            .get_next_read_address(Some(2), addresses.clone())
+            .await
+            .unwrap();
+        assert_ne!(first_stream_address, second_stream_address);
+        for _ in 0..3 {
+            assert_eq!(
+                client
+                    .get_next_read_address(Some(1), addresses.clone())
+                    .await,
+                Some(first_stream_address.clone())
+            );
+        }
+
+        let available_addresses = addresses
+            .into_iter()
+            .filter(|address| *address != first_stream_address)
+            .collect::<Vec<_>>();
+        let next_address = client
+            .get_next_read_address(Some(1), available_addresses.clone())
+            .await
+            .unwrap();
+        assert_ne!(next_address, first_stream_address);
+        assert_eq!(
+            client
+                .get_next_read_address(Some(1), available_addresses)
+                .await,
+            Some(next_address)
+        );
+        assert_eq!(client.get_next_read_address(Some(1), vec![]).await, None);
+    }
+}
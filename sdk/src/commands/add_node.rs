@@ -0,0 +1,112 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::commands::command::Command;
+use crate::error::SystemError;
+use bytes::BufMut;
+use std::str::from_utf8;
+
+pub const ADD_NODE_CODE: u32 = 14;
+
+#[derive(Debug, Clone)]
+pub struct AddNode {
+    pub id: u64,
+    pub name: String,
+    pub public_address: String,
+    pub internal_address: String,
+    pub learner: bool,
+}
+
+impl AddNode {
+    pub fn new_command(
+        id: u64,
+        name: String,
+        public_address: String,
+        internal_address: String,
+        learner: bool,
+    ) -> Command {
+        Command::AddNode(AddNode {
+            id,
+            name,
+            public_address,
+            internal_address,
+            learner,
+        })
+    }
+}
+
+impl BytesSerializable for AddNode {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            12 + self.name.len() + self.public_address.len() + self.internal_address.len(),
+        );
+        bytes.put_u64_le(self.id);
+        bytes.put_u8(self.name.len() as u8);
+        bytes.extend(self.name.as_bytes());
+        bytes.put_u8(self.public_address.len() as u8);
+        bytes.extend(self.public_address.as_bytes());
+        bytes.put_u8(self.internal_address.len() as u8);
+        bytes.extend(self.internal_address.as_bytes());
+        bytes.put_u8(self.learner as u8);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<AddNode, SystemError> {
+        if bytes.len() < 12 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let id = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let mut position = 8;
+        let mut values = Vec::with_capacity(3);
+        for _ in 0..3 {
+            let length = *bytes.get(position).ok_or(SystemError::InvalidCommand)? as usize;
+            position += 1;
+            let value = bytes
+                .get(position..position + length)
+                .ok_or(SystemError::InvalidCommand)?;
+            let value = from_utf8(value).map_err(|_| SystemError::InvalidCommand)?;
+            values.push(value.to_string());
+            position += length;
+        }
+
+        let learner = *bytes.get(position).ok_or(SystemError::InvalidCommand)? == 1;
+        let internal_address = values.pop().unwrap();
+        let public_address = values.pop().unwrap();
+        let name = values.pop().unwrap();
+        Ok(AddNode {
+            id,
+            name,
+            public_address,
+            internal_address,
+            learner,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_serialize_and_deserialize_add_node() {
+        let add_node = AddNode {
+            id: 4,
+            name: "node4".to_string(),
+            public_address: "127.0.0.1:8104".to_string(),
+            internal_address: "0.0.0.0:8204".to_string(),
+            learner: true,
+        };
+        let bytes = add_node.as_bytes();
+        let deserialized_add_node = AddNode::from_bytes(&bytes).unwrap();
+        assert_eq!(add_node.id, deserialized_add_node.id);
+        assert_eq!(add_node.name, deserialized_add_node.name);
+        assert_eq!(
+            add_node.public_address,
+            deserialized_add_node.public_address
+        );
+        assert_eq!(
+            add_node.internal_address,
+            deserialized_add_node.internal_address
+        );
+        assert_eq!(add_node.learner, deserialized_add_node.learner);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::bytes_serializable::BytesSerializable;
 use crate::commands::command::Command;
 use crate::error::SystemError;
-use crate::models::log_entry::LogEntry;
+use crate::models::log_entry::{LogEntry, LOG_ENTRY_HEADER_SIZE};
 use bytes::BufMut;
 
 #[derive(Debug)]
@@ -20,13 +20,14 @@ impl AppendEntries {
         leader_id: u64,
         leader_commit: u64,
         prev_log_index: u64,
+        prev_log_term: u64,
         entries: Vec<LogEntry>,
     ) -> Command {
         Command::AppendEntries(AppendEntries {
             term,
             leader_id,
             prev_log_index,
-            prev_log_term: 0,
+            prev_log_term,
             leader_commit,
             entries,
         })
@@ -50,16 +51,20 @@ impl BytesSerializable for AppendEntries {
     where
         Self: Sized,
     {
-        let term = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
-        let leader_id = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
-        let prev_log_index = u64::from_le_bytes(bytes[16..24].try_into().unwrap());
-        let prev_log_term = u64::from_le_bytes(bytes[24..32].try_into().unwrap());
-        let leader_commit = u64::from_le_bytes(bytes[32..40].try_into().unwrap());
+        if bytes.len() < 40 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let term = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let leader_id = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let prev_log_index = u64::from_le_bytes(bytes[16..24].try_into()?);
+        let prev_log_term = u64::from_le_bytes(bytes[24..32].try_into()?);
+        let leader_commit = u64::from_le_bytes(bytes[32..40].try_into()?);
         let mut entries = Vec::new();
         let mut position = 40;
         while position < bytes.len() {
             let entry = LogEntry::from_bytes(&bytes[position..])?;
-            position += 12 + entry.data.len();
+            position += LOG_ENTRY_HEADER_SIZE + entry.data.len();
             entries.push(entry);
         }
         Ok(AppendEntries {
@@ -1,19 +1,28 @@
 use crate::bytes_serializable::BytesSerializable;
+use crate::commands::add_node::{AddNode, ADD_NODE_CODE};
 use crate::commands::append_entries::AppendEntries;
 use crate::commands::append_messages::AppendMessages;
 use crate::commands::create_stream::{CreateStream, CREATE_STREAM_CODE};
 use crate::commands::delete_stream::{DeleteStream, DELETE_STREAM_CODE};
+use crate::commands::fetch_messages::FetchMessages;
+use crate::commands::get_epoch_end_offset::{GetEpochEndOffset, GET_EPOCH_END_OFFSET_CODE};
 use crate::commands::get_metadata::GetMetadata;
 use crate::commands::get_node_state::GetNodeState;
+use crate::commands::get_stream_chunk::{GetStreamChunk, GET_STREAM_CHUNK_CODE};
 use crate::commands::get_streams::GetStreams;
 use crate::commands::heartbeat::Heartbeat;
 use crate::commands::hello::Hello;
 use crate::commands::load_state::LoadState;
 use crate::commands::ping::Ping;
 use crate::commands::poll_messages::PollMessages;
+use crate::commands::rebalance_leaders::{RebalanceLeaders, REBALANCE_LEADERS_CODE};
+use crate::commands::remove_node::{RemoveNode, REMOVE_NODE_CODE};
 use crate::commands::request_vote::RequestVote;
-use crate::commands::sync_messages::SyncMessages;
+use crate::commands::timeout_now::TimeoutNow;
+use crate::commands::transfer_leadership::TransferLeadership;
+use crate::commands::update_isr::{UpdateIsr, UPDATE_ISR_CODE};
 use crate::commands::update_leader::UpdateLeader;
+use crate::commands::update_stream_leader::{UpdateStreamLeader, UPDATE_STREAM_LEADER_CODE};
 use crate::error::SystemError;
 use bytes::BufMut;
 use std::fmt::{Display, Formatter};
@@ -26,7 +35,9 @@ const LOAD_STATE_CODE: u32 = 5;
 const GET_METADATA_CODE: u32 = 6;
 const REQUEST_VOTE_CODE: u32 = 10;
 const UPDATE_LEADER_CODE: u32 = 11;
-const SYNC_MESSAGES_CODE: u32 = 20;
+const TIMEOUT_NOW_CODE: u32 = 12;
+const TRANSFER_LEADERSHIP_CODE: u32 = 13;
+const FETCH_MESSAGES_CODE: u32 = 21;
 const GET_STREAMS_CODE: u32 = 30;
 const APPEND_MESSAGES_CODE: u32 = 40;
 const POLL_MESSAGES_CODE: u32 = 50;
@@ -39,15 +50,24 @@ pub enum Command {
     Ping(Ping),
     RequestVote(RequestVote),
     UpdateLeader(UpdateLeader),
+    TimeoutNow(TimeoutNow),
+    TransferLeadership(TransferLeadership),
+    AddNode(AddNode),
+    RemoveNode(RemoveNode),
+    RebalanceLeaders(RebalanceLeaders),
     GetNodeState(GetNodeState),
     LoadState(LoadState),
     GetMetadata(GetMetadata),
     GetStreams(GetStreams),
     CreateStream(CreateStream),
     DeleteStream(DeleteStream),
+    UpdateIsr(UpdateIsr),
+    UpdateStreamLeader(UpdateStreamLeader),
     AppendMessages(AppendMessages),
     PollMessages(PollMessages),
-    SyncMessages(SyncMessages),
+    FetchMessages(FetchMessages),
+    GetEpochEndOffset(GetEpochEndOffset),
+    GetStreamChunk(GetStreamChunk),
     AppendEntries(AppendEntries),
 }
 
@@ -59,15 +79,24 @@ impl Command {
             Command::Ping(_) => "ping",
             Command::RequestVote(_) => "request_vote",
             Command::UpdateLeader(_) => "update_leader",
+            Command::TimeoutNow(_) => "timeout_now",
+            Command::TransferLeadership(_) => "transfer_leadership",
+            Command::AddNode(_) => "add_node",
+            Command::RemoveNode(_) => "remove_node",
+            Command::RebalanceLeaders(_) => "rebalance_leaders",
             Command::GetNodeState(_) => "get_state",
             Command::LoadState(_) => "load_state",
             Command::GetMetadata(_) => "get_metadata",
             Command::GetStreams(_) => "get_streams",
             Command::CreateStream(_) => "create_stream",
             Command::DeleteStream(_) => "delete_stream",
+            Command::UpdateIsr(_) => "update_isr",
+            Command::UpdateStreamLeader(_) => "update_stream_leader",
             Command::AppendMessages(_) => "append_messages",
             Command::PollMessages(_) => "poll_messages",
-            Command::SyncMessages(_) => "sync_messages",
+            Command::FetchMessages(_) => "fetch_messages",
+            Command::GetEpochEndOffset(_) => "get_epoch_end_offset",
+            Command::GetStreamChunk(_) => "get_stream_chunk",
             Command::AppendEntries(_) => "append_entries",
         }
     }
@@ -79,15 +108,24 @@ impl Command {
             Command::Ping(command) => to_bytes(PING_CODE, command),
             Command::RequestVote(command) => to_bytes(REQUEST_VOTE_CODE, command),
             Command::UpdateLeader(command) => to_bytes(UPDATE_LEADER_CODE, command),
+            Command::TimeoutNow(command) => to_bytes(TIMEOUT_NOW_CODE, command),
+            Command::TransferLeadership(command) => to_bytes(TRANSFER_LEADERSHIP_CODE, command),
+            Command::AddNode(command) => to_bytes(ADD_NODE_CODE, command),
+            Command::RemoveNode(command) => to_bytes(REMOVE_NODE_CODE, command),
+            Command::RebalanceLeaders(command) => to_bytes(REBALANCE_LEADERS_CODE, command),
             Command::GetNodeState(command) => to_bytes(GET_NODE_STATE_CODE, command),
             Command::LoadState(command) => to_bytes(LOAD_STATE_CODE, command),
             Command::GetMetadata(command) => to_bytes(GET_METADATA_CODE, command),
             Command::GetStreams(command) => to_bytes(GET_STREAMS_CODE, command),
             Command::CreateStream(command) => to_bytes(CREATE_STREAM_CODE, command),
             Command::DeleteStream(command) => to_bytes(DELETE_STREAM_CODE, command),
+            Command::UpdateIsr(command) => to_bytes(UPDATE_ISR_CODE, command),
+            Command::UpdateStreamLeader(command) => to_bytes(UPDATE_STREAM_LEADER_CODE, command),
             Command::AppendMessages(command) => to_bytes(APPEND_MESSAGES_CODE, command),
             Command::PollMessages(command) => to_bytes(POLL_MESSAGES_CODE, command),
-            Command::SyncMessages(command) => to_bytes(SYNC_MESSAGES_CODE, command),
+            Command::FetchMessages(command) => to_bytes(FETCH_MESSAGES_CODE, command),
+            Command::GetEpochEndOffset(command) => to_bytes(GET_EPOCH_END_OFFSET_CODE, command),
+            Command::GetStreamChunk(command) => to_bytes(GET_STREAM_CHUNK_CODE, command),
             Command::AppendEntries(command) => to_bytes(APPEND_ENTRIES_CODE, command),
         }
     }
@@ -102,15 +140,34 @@ impl Command {
             PING_CODE => Ok(Command::Ping(Ping::from_bytes(bytes)?)),
             REQUEST_VOTE_CODE => Ok(Command::RequestVote(RequestVote::from_bytes(bytes)?)),
             UPDATE_LEADER_CODE => Ok(Command::UpdateLeader(UpdateLeader::from_bytes(bytes)?)),
+            TIMEOUT_NOW_CODE => Ok(Command::TimeoutNow(TimeoutNow::from_bytes(bytes)?)),
+            TRANSFER_LEADERSHIP_CODE => Ok(Command::TransferLeadership(
+                TransferLeadership::from_bytes(bytes)?,
+            )),
+            ADD_NODE_CODE => Ok(Command::AddNode(AddNode::from_bytes(bytes)?)),
+            REMOVE_NODE_CODE => Ok(Command::RemoveNode(RemoveNode::from_bytes(bytes)?)),
+            REBALANCE_LEADERS_CODE => Ok(Command::RebalanceLeaders(RebalanceLeaders::from_bytes(
+                bytes,
+            )?)),
             GET_NODE_STATE_CODE => Ok(Command::GetNodeState(GetNodeState::from_bytes(bytes)?)),
             LOAD_STATE_CODE => Ok(Command::LoadState(LoadState::from_bytes(bytes)?)),
             GET_METADATA_CODE => Ok(Command::GetMetadata(GetMetadata::from_bytes(bytes)?)),
             GET_STREAMS_CODE => Ok(Command::GetStreams(GetStreams::from_bytes(bytes)?)),
             CREATE_STREAM_CODE => Ok(Command::CreateStream(CreateStream::from_bytes(bytes)?)),
             DELETE_STREAM_CODE => Ok(Command::DeleteStream(DeleteStream::from_bytes(bytes)?)),
+            UPDATE_ISR_CODE => Ok(Command::UpdateIsr(UpdateIsr::from_bytes(bytes)?)),
+            UPDATE_STREAM_LEADER_CODE => Ok(Command::UpdateStreamLeader(
+                UpdateStreamLeader::from_bytes(bytes)?,
+            )),
             APPEND_MESSAGES_CODE => Ok(Command::AppendMessages(AppendMessages::from_bytes(bytes)?)),
             POLL_MESSAGES_CODE => Ok(Command::PollMessages(PollMessages::from_bytes(bytes)?)),
-            SYNC_MESSAGES_CODE => Ok(Command::SyncMessages(SyncMessages::from_bytes(bytes)?)),
+            FETCH_MESSAGES_CODE => Ok(Command::FetchMessages(FetchMessages::from_bytes(bytes)?)),
+            GET_EPOCH_END_OFFSET_CODE => Ok(Command::GetEpochEndOffset(
+                GetEpochEndOffset::from_bytes(bytes)?,
+            )),
+            GET_STREAM_CHUNK_CODE => {
+                Ok(Command::GetStreamChunk(GetStreamChunk::from_bytes(bytes)?))
+            }
             APPEND_ENTRIES_CODE => Ok(Command::AppendEntries(AppendEntries::from_bytes(bytes)?)),
             _ => Err(SystemError::InvalidCommandCode(code)),
         }
@@ -127,9 +184,15 @@ fn to_bytes<T: BytesSerializable>(code: u32, command: &T) -> Vec<u8> {
 }
 
 pub fn map_from_bytes(bytes: &[u8]) -> Result<Command, SystemError> {
+    if bytes.len() < 8 {
+        return Err(SystemError::InvalidCommand);
+    }
+
     let code = u32::from_le_bytes(bytes[..4].try_into()?);
-    let length = u32::from_le_bytes(bytes[4..8].try_into()?);
-    let payload = &bytes[8..8 + length as usize];
+    let length = u32::from_le_bytes(bytes[4..8].try_into()?) as usize;
+    let Some(payload) = bytes.get(8..8 + length) else {
+        return Err(SystemError::InvalidCommand);
+    };
     Command::from_bytes(code, payload)
 }
 
@@ -145,6 +208,17 @@ impl Display for Command {
             Command::UpdateLeader(update_leader) => {
                 write!(f, "Update leader: {}", update_leader.term)
             }
+            Command::TimeoutNow(timeout_now) => {
+                write!(f, "Timeout now: {}", timeout_now.term)
+            }
+            Command::TransferLeadership(transfer_leadership) => {
+                write!(f, "Transfer leadership: {}", transfer_leadership.node_id)
+            }
+            Command::AddNode(add_node) => {
+                write!(f, "Add node: {}, name: {}", add_node.id, add_node.name)
+            }
+            Command::RemoveNode(remove_node) => write!(f, "Remove node: {}", remove_node.id),
+            Command::RebalanceLeaders(_) => write!(f, "Rebalance leaders"),
             Command::GetNodeState(_) => write!(f, "Get node state"),
             Command::LoadState(load_state) => {
                 write!(f, "Load state -> start index: {}", load_state.start_index)
@@ -157,6 +231,22 @@ impl Display for Command {
             Command::DeleteStream(delete_stream) => {
                 write!(f, "Delete stream: {}", delete_stream.id)
             }
+            Command::UpdateIsr(update_isr) => {
+                write!(
+                    f,
+                    "Update ISR -> stream: {}, leader epoch: {}, ISR: {:?}",
+                    update_isr.stream_id, update_isr.leader_epoch, update_isr.isr
+                )
+            }
+            Command::UpdateStreamLeader(update_stream_leader) => {
+                write!(
+                    f,
+                    "Update stream leader -> stream: {}, leader ID: {}, leader epoch: {}",
+                    update_stream_leader.stream_id,
+                    update_stream_leader.leader_id,
+                    update_stream_leader.leader_epoch
+                )
+            }
             Command::AppendMessages(append_data) => {
                 write!(f, "Append messages: {:?}", append_data.messages)
             }
@@ -167,8 +257,29 @@ impl Display for Command {
                     poll_data.offset, poll_data.count
                 )
             }
-            Command::SyncMessages(sync_data) => {
-                write!(f, "Sync messages: {:?}", sync_data.messages)
+            Command::FetchMessages(fetch_data) => {
+                write!(
+                    f,
+                    "Fetch messages -> stream: {}, leader epoch: {}, offset: {}, count: {}",
+                    fetch_data.stream_id,
+                    fetch_data.leader_epoch,
+                    fetch_data.offset,
+                    fetch_data.count
+                )
+            }
+            Command::GetEpochEndOffset(get_epoch_end_offset) => {
+                write!(
+                    f,
+                    "Get epoch end offset -> stream: {}, leader epoch: {}",
+                    get_epoch_end_offset.stream_id, get_epoch_end_offset.leader_epoch
+                )
+            }
+            Command::GetStreamChunk(get_stream_chunk) => {
+                write!(
+                    f,
+                    "Get stream chunk -> stream: {}, offset: {}, max bytes: {}",
+                    get_stream_chunk.stream_id, get_stream_chunk.offset, get_stream_chunk.max_bytes
+                )
             }
             Command::AppendEntries(append_entries) => {
                 write!(f, "Append entries: {:?}", append_entries)
@@ -5,35 +5,60 @@ use bytes::BufMut;
 
 pub const CREATE_STREAM_CODE: u32 = 31;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CreateStream {
     pub id: u64,
     pub replication_factor: Option<u8>,
+    pub min_in_sync_replicas: Option<u8>,
+    /// The nodes storing the stream, the first one is the preferred leader. Assigned by the leader if empty.
+    pub replicas: Vec<u64>,
 }
 
 impl CreateStream {
-    pub fn new(id: u64, replication_factor: Option<u8>) -> CreateStream {
+    pub fn new(
+        id: u64,
+        replication_factor: Option<u8>,
+        min_in_sync_replicas: Option<u8>,
+        replicas: Vec<u64>,
+    ) -> CreateStream {
         CreateStream {
             id,
             replication_factor,
+            min_in_sync_replicas,
+            replicas,
         }
     }
 
-    pub fn new_command(id: u64, replication_factor: Option<u8>) -> Command {
-        Command::CreateStream(Self::new(id, replication_factor))
+    pub fn new_command(
+        id: u64,
+        replication_factor: Option<u8>,
+        min_in_sync_replicas: Option<u8>,
+        replicas: Vec<u64>,
+    ) -> Command {
+        Command::CreateStream(Self::new(
+            id,
+            replication_factor,
+            min_in_sync_replicas,
+            replicas,
+        ))
     }
 }
 
 impl BytesSerializable for CreateStream {
     fn as_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(9);
+        let mut bytes = Vec::with_capacity(11 + 8 * self.replicas.len());
         bytes.put_u64_le(self.id);
         bytes.put_u8(self.replication_factor.unwrap_or(0));
+        bytes.put_u8(self.min_in_sync_replicas.unwrap_or(0));
+        bytes.put_u8(self.replicas.len() as u8);
+        for node_id in &self.replicas {
+            bytes.put_u64_le(*node_id);
+        }
         bytes
     }
 
     fn from_bytes(bytes: &[u8]) -> Result<CreateStream, SystemError> {
-        if bytes.len() != 9 {
+        if bytes.len() < 11 {
             return Err(SystemError::InvalidCommand);
         }
 
@@ -44,9 +69,26 @@ impl BytesSerializable for CreateStream {
         } else {
             Some(replication_factor)
         };
+        let min_in_sync_replicas = bytes[9];
+        let min_in_sync_replicas = if min_in_sync_replicas == 0 {
+            None
+        } else {
+            Some(min_in_sync_replicas)
+        };
+        let replicas_len = bytes[10] as usize;
+        if bytes.len() != 11 + 8 * replicas_len {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let replicas = bytes[11..]
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
         let command = CreateStream {
             id,
             replication_factor,
+            min_in_sync_replicas,
+            replicas,
         };
         Ok(command)
     }
@@ -0,0 +1,65 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::commands::command::Command;
+use crate::error::SystemError;
+use bytes::BufMut;
+
+/// Sent by the follower to the stream leader, the offset is the follower's log end offset,
+/// which is the offset of the next message to be fetched. The leader epoch is the one known to the follower,
+/// the leader rejects the fetch if it doesn't match its own.
+#[derive(Debug)]
+pub struct FetchMessages {
+    pub term: u64,
+    pub stream_id: u64,
+    pub leader_epoch: u64,
+    pub offset: u64,
+    pub count: u64,
+}
+
+impl FetchMessages {
+    pub fn new_command(
+        term: u64,
+        stream_id: u64,
+        leader_epoch: u64,
+        offset: u64,
+        count: u64,
+    ) -> Command {
+        Command::FetchMessages(FetchMessages {
+            term,
+            stream_id,
+            leader_epoch,
+            offset,
+            count,
+        })
+    }
+}
+
+impl BytesSerializable for FetchMessages {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(40);
+        bytes.put_u64_le(self.term);
+        bytes.put_u64_le(self.stream_id);
+        bytes.put_u64_le(self.leader_epoch);
+        bytes.put_u64_le(self.offset);
+        bytes.put_u64_le(self.count);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SystemError> {
+        if bytes.len() != 40 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let term = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let stream_id = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let leader_epoch = u64::from_le_bytes(bytes[16..24].try_into()?);
+        let offset = u64::from_le_bytes(bytes[24..32].try_into()?);
+        let count = u64::from_le_bytes(bytes[32..40].try_into()?);
+        Ok(FetchMessages {
+            term,
+            stream_id,
+            leader_epoch,
+            offset,
+            count,
+        })
+    }
+}
@@ -0,0 +1,50 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::commands::command::Command;
+use crate::error::SystemError;
+use bytes::BufMut;
+
+pub const GET_EPOCH_END_OFFSET_CODE: u32 = 22;
+
+/// Sent by the follower to the new stream leader to find out where its log should be truncated,
+/// the current leader epoch is the one known to the follower and is used to fence the stale requests.
+#[derive(Debug)]
+pub struct GetEpochEndOffset {
+    pub stream_id: u64,
+    pub current_leader_epoch: u64,
+    pub leader_epoch: u64,
+}
+
+impl GetEpochEndOffset {
+    pub fn new_command(stream_id: u64, current_leader_epoch: u64, leader_epoch: u64) -> Command {
+        Command::GetEpochEndOffset(GetEpochEndOffset {
+            stream_id,
+            current_leader_epoch,
+            leader_epoch,
+        })
+    }
+}
+
+impl BytesSerializable for GetEpochEndOffset {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(24);
+        bytes.put_u64_le(self.stream_id);
+        bytes.put_u64_le(self.current_leader_epoch);
+        bytes.put_u64_le(self.leader_epoch);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SystemError> {
+        if bytes.len() != 24 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let stream_id = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let current_leader_epoch = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let leader_epoch = u64::from_le_bytes(bytes[16..24].try_into()?);
+        Ok(GetEpochEndOffset {
+            stream_id,
+            current_leader_epoch,
+            leader_epoch,
+        })
+    }
+}
@@ -1,27 +1,34 @@
 use crate::bytes_serializable::BytesSerializable;
 use crate::commands::command::Command;
 use crate::error::SystemError;
+use crate::models::read_consistency::ReadConsistency;
+use bytes::BufMut;
 
 #[derive(Debug)]
-pub struct GetMetadata {}
+pub struct GetMetadata {
+    pub consistency: ReadConsistency,
+}
 
 impl GetMetadata {
-    pub fn new_command() -> Command {
-        Command::GetMetadata(GetMetadata {})
+    pub fn new_command(consistency: ReadConsistency) -> Command {
+        Command::GetMetadata(GetMetadata { consistency })
     }
 }
 
 impl BytesSerializable for GetMetadata {
     fn as_bytes(&self) -> Vec<u8> {
-        Vec::with_capacity(0)
+        let mut bytes = Vec::with_capacity(1);
+        bytes.put_u8(self.consistency.as_code());
+        bytes
     }
 
     fn from_bytes(bytes: &[u8]) -> Result<GetMetadata, SystemError> {
-        if !bytes.is_empty() {
+        if bytes.len() != 1 {
             return Err(SystemError::InvalidCommand);
         }
 
-        let command = GetMetadata {};
+        let consistency = ReadConsistency::from_code(bytes[0])?;
+        let command = GetMetadata { consistency };
         Ok(command)
     }
 }
@@ -0,0 +1,50 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::commands::command::Command;
+use crate::error::SystemError;
+use bytes::BufMut;
+
+pub const GET_STREAM_CHUNK_CODE: u32 = 23;
+
+/// Sent by the new or wiped replica to the stream leader to copy the committed messages in bulk,
+/// starting at its log end offset, so that the transfer is resumed where it was interrupted.
+#[derive(Debug)]
+pub struct GetStreamChunk {
+    pub stream_id: u64,
+    pub offset: u64,
+    pub max_bytes: u32,
+}
+
+impl GetStreamChunk {
+    pub fn new_command(stream_id: u64, offset: u64, max_bytes: u32) -> Command {
+        Command::GetStreamChunk(GetStreamChunk {
+            stream_id,
+            offset,
+            max_bytes,
+        })
+    }
+}
+
+impl BytesSerializable for GetStreamChunk {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(20);
+        bytes.put_u64_le(self.stream_id);
+        bytes.put_u64_le(self.offset);
+        bytes.put_u32_le(self.max_bytes);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SystemError> {
+        if bytes.len() != 20 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let stream_id = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let offset = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let max_bytes = u32::from_le_bytes(bytes[16..20].try_into()?);
+        Ok(GetStreamChunk {
+            stream_id,
+            offset,
+            max_bytes,
+        })
+    }
+}
@@ -1,27 +1,34 @@
 use crate::bytes_serializable::BytesSerializable;
 use crate::commands::command::Command;
 use crate::error::SystemError;
+use crate::models::read_consistency::ReadConsistency;
+use bytes::BufMut;
 
 #[derive(Debug)]
-pub struct GetStreams {}
+pub struct GetStreams {
+    pub consistency: ReadConsistency,
+}
 
 impl GetStreams {
-    pub fn new_command() -> Command {
-        Command::GetStreams(GetStreams {})
+    pub fn new_command(consistency: ReadConsistency) -> Command {
+        Command::GetStreams(GetStreams { consistency })
     }
 }
 
 impl BytesSerializable for GetStreams {
     fn as_bytes(&self) -> Vec<u8> {
-        Vec::with_capacity(0)
+        let mut bytes = Vec::with_capacity(1);
+        bytes.put_u8(self.consistency.as_code());
+        bytes
     }
 
     fn from_bytes(bytes: &[u8]) -> Result<GetStreams, SystemError> {
-        if !bytes.is_empty() {
+        if bytes.len() != 1 {
             return Err(SystemError::InvalidCommand);
         }
 
-        let command = GetStreams {};
+        let consistency = ReadConsistency::from_code(bytes[0])?;
+        let command = GetStreams { consistency };
         Ok(command)
     }
 }
@@ -7,24 +7,30 @@ use bytes::BufMut;
 pub struct Heartbeat {
     pub term: u64,
     pub leader_id: Option<u64>,
+    pub commit_index: u64,
 }
 
 impl Heartbeat {
-    pub fn new_command(term: u64, leader_id: Option<u64>) -> Command {
-        Command::Heartbeat(Heartbeat { term, leader_id })
+    pub fn new_command(term: u64, leader_id: Option<u64>, commit_index: u64) -> Command {
+        Command::Heartbeat(Heartbeat {
+            term,
+            leader_id,
+            commit_index,
+        })
     }
 }
 
 impl BytesSerializable for Heartbeat {
     fn as_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(16);
+        let mut bytes = Vec::with_capacity(24);
         bytes.put_u64_le(self.term);
         bytes.put_u64_le(self.leader_id.unwrap_or(0));
+        bytes.put_u64_le(self.commit_index);
         bytes
     }
 
     fn from_bytes(bytes: &[u8]) -> Result<Heartbeat, SystemError> {
-        if bytes.len() != 16 {
+        if bytes.len() != 24 {
             return Err(SystemError::InvalidCommand);
         }
 
@@ -35,7 +41,12 @@ impl BytesSerializable for Heartbeat {
         } else {
             Some(leader_id)
         };
-        let command = Heartbeat { term, leader_id };
+        let commit_index = u64::from_le_bytes(bytes[16..24].try_into().unwrap());
+        let command = Heartbeat {
+            term,
+            leader_id,
+            commit_index,
+        };
         Ok(command)
     }
 }
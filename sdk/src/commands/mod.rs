@@ -1,16 +1,25 @@
+pub mod add_node;
 pub mod append_entries;
 pub mod append_messages;
 pub mod command;
 pub mod create_stream;
 pub mod delete_stream;
+pub mod fetch_messages;
+pub mod get_epoch_end_offset;
 pub mod get_metadata;
 pub mod get_node_state;
+pub mod get_stream_chunk;
 pub mod get_streams;
 pub mod heartbeat;
 pub mod hello;
 pub mod load_state;
 pub mod ping;
 pub mod poll_messages;
+pub mod rebalance_leaders;
+pub mod remove_node;
 pub mod request_vote;
-pub mod sync_messages;
+pub mod timeout_now;
+pub mod transfer_leadership;
+pub mod update_isr;
 pub mod update_leader;
+pub mod update_stream_leader;
@@ -1,6 +1,7 @@
 use crate::bytes_serializable::BytesSerializable;
 use crate::commands::command::Command;
 use crate::error::SystemError;
+use crate::models::read_consistency::ReadConsistency;
 use bytes::BufMut;
 
 #[derive(Debug)]
@@ -8,38 +9,48 @@ pub struct PollMessages {
     pub stream_id: u64,
     pub offset: u64,
     pub count: u64,
+    pub consistency: ReadConsistency,
 }
 
 impl PollMessages {
-    pub fn new_command(stream_id: u64, offset: u64, count: u64) -> Command {
+    pub fn new_command(
+        stream_id: u64,
+        offset: u64,
+        count: u64,
+        consistency: ReadConsistency,
+    ) -> Command {
         Command::PollMessages(PollMessages {
             stream_id,
             offset,
             count,
+            consistency,
         })
     }
 }
 
 impl BytesSerializable for PollMessages {
     fn as_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(12);
+        let mut bytes = Vec::with_capacity(25);
         bytes.put_u64_le(self.stream_id);
         bytes.put_u64_le(self.offset);
         bytes.put_u64_le(self.count);
+        bytes.put_u8(self.consistency.as_code());
         bytes
     }
 
     fn from_bytes(bytes: &[u8]) -> Result<Self, SystemError> {
-        if bytes.len() != 24 {
+        if bytes.len() != 25 {
             return Err(SystemError::InvalidCommand);
         }
         let stream_id = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
         let offset = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
         let count = u64::from_le_bytes(bytes[16..24].try_into().unwrap());
+        let consistency = ReadConsistency::from_code(bytes[24])?;
         Ok(PollMessages {
             stream_id,
             offset,
             count,
+            consistency,
         })
     }
 }
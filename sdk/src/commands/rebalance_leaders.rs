@@ -0,0 +1,31 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::commands::command::Command;
+use crate::error::SystemError;
+
+pub const REBALANCE_LEADERS_CODE: u32 = 16;
+
+const EMPTY_BYTES: Vec<u8> = vec![];
+
+/// Moves the leadership of the streams back to their preferred replicas, which are in sync.
+#[derive(Debug, Default, PartialEq)]
+pub struct RebalanceLeaders {}
+
+impl RebalanceLeaders {
+    pub fn new_command() -> Command {
+        Command::RebalanceLeaders(RebalanceLeaders {})
+    }
+}
+
+impl BytesSerializable for RebalanceLeaders {
+    fn as_bytes(&self) -> Vec<u8> {
+        EMPTY_BYTES
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<RebalanceLeaders, SystemError> {
+        if !bytes.is_empty() {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        Ok(RebalanceLeaders {})
+    }
+}
@@ -0,0 +1,34 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::commands::command::Command;
+use crate::error::SystemError;
+use bytes::BufMut;
+
+pub const REMOVE_NODE_CODE: u32 = 15;
+
+#[derive(Debug, Clone)]
+pub struct RemoveNode {
+    pub id: u64,
+}
+
+impl RemoveNode {
+    pub fn new_command(id: u64) -> Command {
+        Command::RemoveNode(RemoveNode { id })
+    }
+}
+
+impl BytesSerializable for RemoveNode {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8);
+        bytes.put_u64_le(self.id);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<RemoveNode, SystemError> {
+        if bytes.len() != 8 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let id = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        Ok(RemoveNode { id })
+    }
+}
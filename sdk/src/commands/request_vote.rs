@@ -6,26 +6,40 @@ use bytes::BufMut;
 #[derive(Debug)]
 pub struct RequestVote {
     pub term: u64,
+    pub last_log_index: u64,
+    pub last_log_term: u64,
 }
 
 impl RequestVote {
-    pub fn new_command(term: u64) -> Command {
-        Command::RequestVote(RequestVote { term })
+    pub fn new_command(term: u64, last_log_index: u64, last_log_term: u64) -> Command {
+        Command::RequestVote(RequestVote {
+            term,
+            last_log_index,
+            last_log_term,
+        })
     }
 }
 
 impl BytesSerializable for RequestVote {
     fn as_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(16);
+        let mut bytes = Vec::with_capacity(24);
         bytes.put_u64_le(self.term);
+        bytes.put_u64_le(self.last_log_index);
+        bytes.put_u64_le(self.last_log_term);
         bytes
     }
 
     fn from_bytes(bytes: &[u8]) -> Result<Self, SystemError> {
-        if bytes.len() != 8 {
+        if bytes.len() != 24 {
             return Err(SystemError::InvalidCommand);
         }
-        let term = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
-        Ok(RequestVote { term })
+        let term = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let last_log_index = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let last_log_term = u64::from_le_bytes(bytes[16..24].try_into()?);
+        Ok(RequestVote {
+            term,
+            last_log_index,
+            last_log_term,
+        })
     }
 }
@@ -0,0 +1,31 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::commands::command::Command;
+use crate::error::SystemError;
+use bytes::BufMut;
+
+#[derive(Debug)]
+pub struct TransferLeadership {
+    pub node_id: u64,
+}
+
+impl TransferLeadership {
+    pub fn new_command(node_id: u64) -> Command {
+        Command::TransferLeadership(TransferLeadership { node_id })
+    }
+}
+
+impl BytesSerializable for TransferLeadership {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8);
+        bytes.put_u64_le(self.node_id);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SystemError> {
+        if bytes.len() != 8 {
+            return Err(SystemError::InvalidCommand);
+        }
+        let node_id = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        Ok(TransferLeadership { node_id })
+    }
+}
@@ -0,0 +1,65 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::commands::command::Command;
+use crate::error::SystemError;
+use bytes::BufMut;
+
+pub const UPDATE_ISR_CODE: u32 = 33;
+
+/// Replaces the in-sync replicas of the stream, it's sent by the stream leader to the metadata leader,
+/// which appends it to the replicated state. The leader epoch is the one of the leader, which has measured the ISR.
+#[derive(Debug)]
+pub struct UpdateIsr {
+    pub stream_id: u64,
+    pub leader_epoch: u64,
+    pub isr: Vec<u64>,
+}
+
+impl UpdateIsr {
+    pub fn new(stream_id: u64, leader_epoch: u64, isr: Vec<u64>) -> UpdateIsr {
+        UpdateIsr {
+            stream_id,
+            leader_epoch,
+            isr,
+        }
+    }
+
+    pub fn new_command(stream_id: u64, leader_epoch: u64, isr: Vec<u64>) -> Command {
+        Command::UpdateIsr(Self::new(stream_id, leader_epoch, isr))
+    }
+}
+
+impl BytesSerializable for UpdateIsr {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(17 + 8 * self.isr.len());
+        bytes.put_u64_le(self.stream_id);
+        bytes.put_u64_le(self.leader_epoch);
+        bytes.put_u8(self.isr.len() as u8);
+        for node_id in &self.isr {
+            bytes.put_u64_le(*node_id);
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<UpdateIsr, SystemError> {
+        if bytes.len() < 17 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let stream_id = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let leader_epoch = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let isr_len = bytes[16] as usize;
+        if bytes.len() != 17 + 8 * isr_len {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let mut isr = Vec::with_capacity(isr_len);
+        for chunk in bytes[17..].chunks(8) {
+            isr.push(u64::from_le_bytes(chunk.try_into()?));
+        }
+        Ok(UpdateIsr {
+            stream_id,
+            leader_epoch,
+            isr,
+        })
+    }
+}
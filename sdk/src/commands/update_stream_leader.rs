@@ -0,0 +1,54 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::commands::command::Command;
+use crate::error::SystemError;
+use bytes::BufMut;
+
+pub const UPDATE_STREAM_LEADER_CODE: u32 = 34;
+
+/// Elects the leader of the stream among its replicas, it's appended to the replicated state by the metadata leader.
+/// Each leader change starts the next leader epoch of the stream.
+#[derive(Debug)]
+pub struct UpdateStreamLeader {
+    pub stream_id: u64,
+    pub leader_id: u64,
+    pub leader_epoch: u64,
+}
+
+impl UpdateStreamLeader {
+    pub fn new(stream_id: u64, leader_id: u64, leader_epoch: u64) -> UpdateStreamLeader {
+        UpdateStreamLeader {
+            stream_id,
+            leader_id,
+            leader_epoch,
+        }
+    }
+
+    pub fn new_command(stream_id: u64, leader_id: u64, leader_epoch: u64) -> Command {
+        Command::UpdateStreamLeader(Self::new(stream_id, leader_id, leader_epoch))
+    }
+}
+
+impl BytesSerializable for UpdateStreamLeader {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(24);
+        bytes.put_u64_le(self.stream_id);
+        bytes.put_u64_le(self.leader_id);
+        bytes.put_u64_le(self.leader_epoch);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<UpdateStreamLeader, SystemError> {
+        if bytes.len() != 24 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let stream_id = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let leader_id = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let leader_epoch = u64::from_le_bytes(bytes[16..24].try_into()?);
+        Ok(UpdateStreamLeader {
+            stream_id,
+            leader_id,
+            leader_epoch,
+        })
+    }
+}
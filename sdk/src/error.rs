@@ -84,6 +84,46 @@ pub enum SystemError {
     InvalidReplicationFactor(u8),
     #[error("Node cannot be leader")]
     NodeCannotBeLeader,
+    #[error("Leadership transfer in progress")]
+    LeadershipTransferInProgress,
+    #[error("Cannot transfer leadership to node with ID: {0}")]
+    CannotTransferLeadership(u64),
+    #[error("Node with ID: {0} already exists")]
+    NodeAlreadyExists(u64),
+    #[error("Cannot remove node with ID: {0}")]
+    CannotRemoveNode(u64),
+    #[error("Membership change in progress")]
+    MembershipChangeInProgress,
+    #[error("Node with ID: {0} is not a cluster member")]
+    NotClusterMember(u64),
+    #[error("Node with ID: {0} is a learner")]
+    NodeIsLearner(u64),
+    #[error("Candidate log is behind, last log index: {0}")]
+    CandidateLogBehind(u64),
+    #[error("Leadership could not be confirmed")]
+    LeadershipNotConfirmed,
+    #[error("Not enough in-sync replicas: {0}")]
+    NotEnoughReplicas(u8),
+    #[error("Invalid min in-sync replicas: {0}")]
+    InvalidMinInSyncReplicas(u8),
+    #[error("Invalid replica assignment: {0:?}")]
+    InvalidReplicaAssignment(Vec<u64>),
+    #[error("Not a stream leader, leader ID: {0}")]
+    NotStreamLeader(u64),
+    #[error("Stale leader epoch, current leader epoch: {0}")]
+    StaleLeaderEpoch(u64),
+    #[error("Not a stream replica, leader ID: {0}")]
+    NotStreamReplica(u64),
+    #[error("Invalid stream manifest")]
+    InvalidStreamManifest,
+    #[error("Node is fenced after a storage failure")]
+    NodeFenced,
+    #[error("Insufficient disk space")]
+    InsufficientDiskSpace,
+    #[error("Stream with ID: {0} already exists")]
+    StreamAlreadyExists(u64),
+    #[error("Invalid leader epochs")]
+    InvalidLeaderEpochs,
 }
 
 impl SystemError {
@@ -129,6 +169,26 @@ impl SystemError {
             SystemError::InvalidReplicationFactor(_) => 38,
             SystemError::NodeCannotBeLeader => 39,
             SystemError::CannotSyncStreams => 40,
+            SystemError::LeadershipTransferInProgress => 41,
+            SystemError::CannotTransferLeadership(_) => 42,
+            SystemError::NodeAlreadyExists(_) => 43,
+            SystemError::CannotRemoveNode(_) => 44,
+            SystemError::MembershipChangeInProgress => 45,
+            SystemError::NotClusterMember(_) => 46,
+            SystemError::NodeIsLearner(_) => 47,
+            SystemError::CandidateLogBehind(_) => 48,
+            SystemError::LeadershipNotConfirmed => 49,
+            SystemError::NotEnoughReplicas(_) => 50,
+            SystemError::InvalidMinInSyncReplicas(_) => 51,
+            SystemError::InvalidReplicaAssignment(_) => 52,
+            SystemError::NotStreamLeader(_) => 53,
+            SystemError::StaleLeaderEpoch(_) => 54,
+            SystemError::NotStreamReplica(_) => 55,
+            SystemError::InvalidStreamManifest => 56,
+            SystemError::NodeFenced => 57,
+            SystemError::InsufficientDiskSpace => 58,
+            SystemError::StreamAlreadyExists(_) => 59,
+            SystemError::InvalidLeaderEpochs => 60,
         }
     }
 }
@@ -1,12 +1,12 @@
 use crate::bytes_serializable::BytesSerializable;
 use crate::error::SystemError;
-use crate::models::log_entry::LogEntry;
-use bytes::{BufMut, Bytes};
+use crate::models::log_entry::{LogEntry, LOG_ENTRY_HEADER_SIZE};
+use bytes::BufMut;
 
 #[derive(Debug, Default)]
 pub struct AppendedState {
     pub term: u64,
-    pub last_applied: u64,
+    pub last_index: u64,
     pub entries: Vec<LogEntry>,
 }
 
@@ -20,7 +20,7 @@ impl BytesSerializable for AppendedState {
             .concat();
         let mut bytes = Vec::with_capacity(24 + entries_bytes.len());
         bytes.put_u64_le(self.term);
-        bytes.put_u64_le(self.last_applied);
+        bytes.put_u64_le(self.last_index);
         bytes.put_slice(&entries_bytes);
         bytes
     }
@@ -34,21 +34,18 @@ impl BytesSerializable for AppendedState {
         }
 
         let term = u64::from_le_bytes(bytes[0..8].try_into()?);
-        let last_applied = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let last_index = u64::from_le_bytes(bytes[8..16].try_into()?);
         let mut entries = Vec::new();
         let mut offset = 16;
         while offset < bytes.len() {
-            let index = u64::from_le_bytes(bytes[offset..offset + 8].try_into()?);
-            let size = u32::from_le_bytes(bytes[offset + 8..offset + 12].try_into()?);
-            let data = Bytes::copy_from_slice(&bytes[offset + 12..offset + 12 + size as usize]);
-            let entry = LogEntry { index, size, data };
+            let entry = LogEntry::from_bytes(&bytes[offset..])?;
+            offset += LOG_ENTRY_HEADER_SIZE + entry.data.len();
             entries.push(entry);
-            offset += 12 + size as usize;
         }
 
         Ok(AppendedState {
             term,
-            last_applied,
+            last_index,
             entries,
         })
     }
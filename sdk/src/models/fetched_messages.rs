@@ -0,0 +1,82 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::error::SystemError;
+use crate::models::leader_epoch::LeaderEpoch;
+use crate::models::message::{messages_from_bytes, Message};
+use bytes::BufMut;
+
+/// The messages fetched by the follower along with the leader epochs they were appended in,
+/// so that the follower's epochs match the leader's ones. The high watermark is the leader's one,
+/// the follower doesn't expose the messages above it.
+#[derive(Debug, Default)]
+pub struct FetchedMessages {
+    pub high_watermark: u64,
+    pub leader_epochs: Vec<LeaderEpoch>,
+    pub messages: Vec<Message>,
+}
+
+impl BytesSerializable for FetchedMessages {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(12 + 16 * self.leader_epochs.len());
+        bytes.put_u64_le(self.high_watermark);
+        bytes.put_u32_le(self.leader_epochs.len() as u32);
+        for leader_epoch in &self.leader_epochs {
+            bytes.extend(leader_epoch.as_bytes());
+        }
+        for message in &self.messages {
+            bytes.extend(message.as_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SystemError>
+    where
+        Self: Sized,
+    {
+        if bytes.len() < 12 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let high_watermark = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let leader_epochs_count = u32::from_le_bytes(bytes[8..12].try_into()?) as usize;
+        let messages_position = 12 + 16 * leader_epochs_count;
+        if bytes.len() < messages_position {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let mut leader_epochs = Vec::with_capacity(leader_epochs_count);
+        for chunk in bytes[12..messages_position].chunks(16) {
+            leader_epochs.push(LeaderEpoch::from_bytes(chunk)?);
+        }
+        let messages = messages_from_bytes(&bytes[messages_position..]);
+        Ok(FetchedMessages {
+            high_watermark,
+            leader_epochs,
+            messages,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[test]
+    fn should_serialize_and_deserialize_fetched_messages() {
+        let fetched_messages = FetchedMessages {
+            high_watermark: 2,
+            leader_epochs: vec![LeaderEpoch::new(1, 0), LeaderEpoch::new(3, 2)],
+            messages: vec![
+                Message::new(1, 2, Bytes::from("message-2")),
+                Message::new(2, 3, Bytes::from("message-3")),
+            ],
+        };
+        let bytes = fetched_messages.as_bytes();
+        let deserialized = FetchedMessages::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized.high_watermark, 2);
+        assert_eq!(deserialized.leader_epochs, fetched_messages.leader_epochs);
+        assert_eq!(deserialized.messages.len(), 2);
+        assert_eq!(deserialized.messages[1].offset, 2);
+        assert_eq!(deserialized.messages[1].payload, "message-3");
+    }
+}
@@ -0,0 +1,98 @@
+use crate::bytes_serializable::BytesSerializable;
+use crate::error::SystemError;
+use bytes::BufMut;
+use std::fmt::{Display, Formatter};
+
+/// The first offset of the messages appended by the stream leader in the given leader epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaderEpoch {
+    pub epoch: u64,
+    pub start_offset: u64,
+}
+
+/// The offset following the last message appended in the leader epoch, which is the largest one
+/// not greater than the requested epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochEndOffset {
+    pub epoch: u64,
+    pub end_offset: u64,
+}
+
+impl LeaderEpoch {
+    pub fn new(epoch: u64, start_offset: u64) -> Self {
+        Self {
+            epoch,
+            start_offset,
+        }
+    }
+}
+
+impl EpochEndOffset {
+    pub fn new(epoch: u64, end_offset: u64) -> Self {
+        Self { epoch, end_offset }
+    }
+}
+
+impl Display for LeaderEpoch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "LeaderEpoch {{ epoch: {}, start_offset: {} }}",
+            self.epoch, self.start_offset
+        )
+    }
+}
+
+impl Display for EpochEndOffset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "EpochEndOffset {{ epoch: {}, end_offset: {} }}",
+            self.epoch, self.end_offset
+        )
+    }
+}
+
+impl BytesSerializable for LeaderEpoch {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16);
+        bytes.put_u64_le(self.epoch);
+        bytes.put_u64_le(self.start_offset);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SystemError>
+    where
+        Self: Sized,
+    {
+        if bytes.len() != 16 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let epoch = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let start_offset = u64::from_le_bytes(bytes[8..16].try_into()?);
+        Ok(LeaderEpoch::new(epoch, start_offset))
+    }
+}
+
+impl BytesSerializable for EpochEndOffset {
+    fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16);
+        bytes.put_u64_le(self.epoch);
+        bytes.put_u64_le(self.end_offset);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SystemError>
+    where
+        Self: Sized,
+    {
+        if bytes.len() != 16 {
+            return Err(SystemError::InvalidCommand);
+        }
+
+        let epoch = u64::from_le_bytes(bytes[0..8].try_into()?);
+        let end_offset = u64::from_le_bytes(bytes[8..16].try_into()?);
+        Ok(EpochEndOffset::new(epoch, end_offset))
+    }
+}
@@ -3,9 +3,14 @@ use crate::error::SystemError;
 use bytes::{BufMut, Bytes};
 use std::fmt::Display;
 
-#[derive(Debug)]
+/// The length of the index, term and size, which precede the data of the serialized entry.
+pub const LOG_ENTRY_HEADER_SIZE: usize = 20;
+
+#[derive(Debug, Clone)]
 pub struct LogEntry {
     pub index: u64,
+    /// The term of the leader, which has appended the entry.
+    pub term: u64,
     pub size: u32,
     pub data: Bytes,
 }
@@ -14,16 +19,17 @@ impl Display for LogEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "index: {}, size: {}, data: {:?}",
-            self.index, self.size, self.data
+            "index: {}, term: {}, size: {}, data: {:?}",
+            self.index, self.term, self.size, self.data
         )
     }
 }
 
 impl BytesSerializable for LogEntry {
     fn as_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(12 + self.data.len());
+        let mut bytes = Vec::with_capacity(LOG_ENTRY_HEADER_SIZE + self.data.len());
         bytes.put_u64_le(self.index);
+        bytes.put_u64_le(self.term);
         bytes.put_u32_le(self.data.len() as u32);
         bytes.extend(&self.data);
         bytes
@@ -33,13 +39,23 @@ impl BytesSerializable for LogEntry {
     where
         Self: Sized,
     {
-        if bytes.len() < 12 {
+        if bytes.len() < LOG_ENTRY_HEADER_SIZE {
             return Err(SystemError::InvalidCommand);
         }
 
         let index = u64::from_le_bytes(bytes[0..8].try_into()?);
-        let size = u32::from_le_bytes(bytes[8..12].try_into()?);
-        let data = Bytes::copy_from_slice(&bytes[12..12 + size as usize]);
-        Ok(LogEntry { index, size, data })
+        let term = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let size = u32::from_le_bytes(bytes[16..20].try_into()?);
+        let Some(data) = bytes.get(LOG_ENTRY_HEADER_SIZE..LOG_ENTRY_HEADER_SIZE + size as usize)
+        else {
+            return Err(SystemError::InvalidCommand);
+        };
+
+        Ok(LogEntry {
+            index,
+            term,
+            size,
+            data: Bytes::copy_from_slice(data),
+        })
     }
 }
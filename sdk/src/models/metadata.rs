@@ -26,21 +26,63 @@ pub struct NodeInfo {
     pub id: u64,
     pub name: String,
     pub address: String,
+    pub role: NodeRole,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum NodeRole {
+    Leader,
+    Follower,
+    Learner,
+    /// The node has failed to write to its storage, so it only serves the reads of the committed data.
+    Fenced,
 }
 
 impl Display for NodeInfo {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "NodeInfo {{ id: {}, name: {}, address: {} }}",
-            self.id, self.name, self.address
+            "NodeInfo {{ id: {}, name: {}, address: {}, role: {} }}",
+            self.id, self.name, self.address, self.role
         )
     }
 }
 
 impl NodeInfo {
     fn get_size_bytes(&self) -> usize {
-        8 + 1 + self.name.len() + 1 + self.address.len()
+        8 + 1 + self.name.len() + 1 + self.address.len() + 1
+    }
+}
+
+impl Display for NodeRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeRole::Leader => write!(f, "leader"),
+            NodeRole::Follower => write!(f, "follower"),
+            NodeRole::Learner => write!(f, "learner"),
+            NodeRole::Fenced => write!(f, "fenced"),
+        }
+    }
+}
+
+impl NodeRole {
+    fn as_code(&self) -> u8 {
+        match self {
+            NodeRole::Leader => 1,
+            NodeRole::Follower => 2,
+            NodeRole::Learner => 3,
+            NodeRole::Fenced => 4,
+        }
+    }
+
+    fn from_code(code: u8) -> Result<Self, SystemError> {
+        match code {
+            1 => Ok(NodeRole::Leader),
+            2 => Ok(NodeRole::Follower),
+            3 => Ok(NodeRole::Learner),
+            4 => Ok(NodeRole::Fenced),
+            _ => Err(SystemError::InvalidResponse(code as u32, None)),
+        }
     }
 }
 
@@ -48,21 +90,23 @@ impl NodeInfo {
 pub struct StreamInfo {
     pub stream_id: u64,
     pub leader_id: u64,
+    pub replicas: Vec<u64>,
+    pub isr: Vec<u64>,
 }
 
 impl Display for StreamInfo {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "StreamInfo {{ id: {}, leader_id: {} }}",
-            self.stream_id, self.leader_id
+            "StreamInfo {{ id: {}, leader_id: {}, replicas: {:?}, isr: {:?} }}",
+            self.stream_id, self.leader_id, self.replicas, self.isr
         )
     }
 }
 
 impl StreamInfo {
     fn get_size_bytes(&self) -> usize {
-        16
+        16 + 1 + 8 * self.replicas.len() + 1 + 8 * self.isr.len()
     }
 }
 
@@ -74,6 +118,7 @@ impl BytesSerializable for NodeInfo {
         bytes.extend(self.name.as_bytes());
         bytes.put_u8(self.address.len() as u8);
         bytes.extend(self.address.as_bytes());
+        bytes.put_u8(self.role.as_code());
         bytes
     }
 
@@ -87,7 +132,13 @@ impl BytesSerializable for NodeInfo {
         let address_len = bytes[9 + name_len] as usize;
         let address =
             String::from_utf8(bytes[10 + name_len..10 + name_len + address_len].to_vec()).unwrap();
-        Ok(NodeInfo { id, name, address })
+        let role = NodeRole::from_code(bytes[10 + name_len + address_len])?;
+        Ok(NodeInfo {
+            id,
+            name,
+            address,
+            role,
+        })
     }
 }
 
@@ -96,6 +147,14 @@ impl BytesSerializable for StreamInfo {
         let mut bytes = Vec::new();
         bytes.put_u64_le(self.stream_id);
         bytes.put_u64_le(self.leader_id);
+        bytes.put_u8(self.replicas.len() as u8);
+        for node_id in &self.replicas {
+            bytes.put_u64_le(*node_id);
+        }
+        bytes.put_u8(self.isr.len() as u8);
+        for node_id in &self.isr {
+            bytes.put_u64_le(*node_id);
+        }
         bytes
     }
 
@@ -105,9 +164,26 @@ impl BytesSerializable for StreamInfo {
     {
         let id = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
         let leader_id = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+        let replicas_len = bytes[16] as usize;
+        let mut replicas = Vec::with_capacity(replicas_len);
+        for position in (17..17 + 8 * replicas_len).step_by(8) {
+            replicas.push(u64::from_le_bytes(
+                bytes[position..position + 8].try_into().unwrap(),
+            ));
+        }
+        let isr_position = 17 + 8 * replicas_len;
+        let isr_len = bytes[isr_position] as usize;
+        let mut isr = Vec::with_capacity(isr_len);
+        for position in (isr_position + 1..isr_position + 1 + 8 * isr_len).step_by(8) {
+            isr.push(u64::from_le_bytes(
+                bytes[position..position + 8].try_into().unwrap(),
+            ));
+        }
         Ok(StreamInfo {
             stream_id: id,
             leader_id,
+            replicas,
+            isr,
         })
     }
 }
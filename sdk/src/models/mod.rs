@@ -1,6 +1,9 @@
 pub mod appended_state;
+pub mod fetched_messages;
+pub mod leader_epoch;
 pub mod log_entry;
 pub mod message;
 pub mod metadata;
 pub mod node_state;
+pub mod read_consistency;
 pub mod stream;
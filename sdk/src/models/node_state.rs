@@ -9,30 +9,74 @@ pub struct NodeState {
     pub address: String,
     pub term: u64,
     pub commit_index: u64,
-    pub last_applied: u64,
+    pub last_index: u64,
     pub initial_sync_completed: bool,
+    /// The percentage of the disk space used by the streams and the state.
+    pub disk_usage: u8,
+    pub disk_status: DiskStatus,
+}
+
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum DiskStatus {
+    #[default]
+    Normal,
+    /// The disk usage is past the soft limit, the node keeps accepting the writes.
+    SoftLimitExceeded,
+    /// The disk usage is past the hard limit, the node rejects the writes and the leadership.
+    HardLimitExceeded,
+}
+
+impl Display for DiskStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiskStatus::Normal => write!(f, "normal"),
+            DiskStatus::SoftLimitExceeded => write!(f, "soft_limit_exceeded"),
+            DiskStatus::HardLimitExceeded => write!(f, "hard_limit_exceeded"),
+        }
+    }
+}
+
+impl DiskStatus {
+    fn as_code(&self) -> u8 {
+        match self {
+            DiskStatus::Normal => 1,
+            DiskStatus::SoftLimitExceeded => 2,
+            DiskStatus::HardLimitExceeded => 3,
+        }
+    }
+
+    fn from_code(code: u8) -> Result<Self, SystemError> {
+        match code {
+            1 => Ok(DiskStatus::Normal),
+            2 => Ok(DiskStatus::SoftLimitExceeded),
+            3 => Ok(DiskStatus::HardLimitExceeded),
+            _ => Err(SystemError::InvalidCommand),
+        }
+    }
 }
 
 impl Display for NodeState {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "NodeState {{ id: {}, address: {}, term: {}, commit_index: {}, last_applied: {}, initial_sync_completed: {} }}",
-            self.id, self.address, self.term, self.commit_index, self.last_applied, self.initial_sync_completed
+            "NodeState {{ id: {}, address: {}, term: {}, commit_index: {}, last_index: {}, initial_sync_completed: {}, disk_usage: {}%, disk_status: {} }}",
+            self.id, self.address, self.term, self.commit_index, self.last_index, self.initial_sync_completed, self.disk_usage, self.disk_status
         )
     }
 }
 
 impl BytesSerializable for NodeState {
     fn as_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(40);
+        let mut bytes = Vec::with_capacity(42);
         bytes.put_u64_le(self.id);
         bytes.put_u8(self.address.len() as u8);
         bytes.put_slice(self.address.as_bytes());
         bytes.put_u64_le(self.term);
         bytes.put_u64_le(self.commit_index);
-        bytes.put_u64_le(self.last_applied);
+        bytes.put_u64_le(self.last_index);
         bytes.put_u8(self.initial_sync_completed as u8);
+        bytes.put_u8(self.disk_usage);
+        bytes.put_u8(self.disk_status.as_code());
         bytes
     }
 
@@ -40,26 +84,61 @@ impl BytesSerializable for NodeState {
     where
         Self: Sized,
     {
-        if bytes.len() < 34 {
+        if bytes.len() < 36 {
             return Err(SystemError::InvalidCommand);
         }
 
         let id = u64::from_le_bytes(bytes[0..8].try_into()?);
         let address_length = bytes[8] as usize;
+        if bytes.len() < 36 + address_length {
+            return Err(SystemError::InvalidCommand);
+        }
+
         let address = String::from_utf8(bytes[9..9 + address_length].to_vec()).unwrap();
         let term = u64::from_le_bytes(bytes[9 + address_length..17 + address_length].try_into()?);
         let commit_index =
             u64::from_le_bytes(bytes[17 + address_length..25 + address_length].try_into()?);
-        let last_applied =
+        let last_index =
             u64::from_le_bytes(bytes[25 + address_length..33 + address_length].try_into()?);
         let initial_sync_completed = bytes[33 + address_length] == 1;
+        let disk_usage = bytes[34 + address_length];
+        let disk_status = DiskStatus::from_code(bytes[35 + address_length])?;
         Ok(NodeState {
             id,
             address,
             term,
             commit_index,
-            last_applied,
+            last_index,
             initial_sync_completed,
+            disk_usage,
+            disk_status,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_serialize_and_deserialize_node_state() {
+        let node_state = NodeState {
+            id: 2,
+            address: "127.0.0.1:8202".to_string(),
+            term: 3,
+            commit_index: 10,
+            last_index: 9,
+            initial_sync_completed: true,
+            disk_usage: 97,
+            disk_status: DiskStatus::HardLimitExceeded,
+        };
+        let bytes = node_state.as_bytes();
+        let deserialized = NodeState::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized.id, 2);
+        assert_eq!(deserialized.address, node_state.address);
+        assert_eq!(deserialized.last_index, 9);
+        assert!(deserialized.initial_sync_completed);
+        assert_eq!(deserialized.disk_usage, 97);
+        assert_eq!(deserialized.disk_status, DiskStatus::HardLimitExceeded);
+    }
+}
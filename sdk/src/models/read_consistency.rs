@@ -0,0 +1,50 @@
+use crate::error::SystemError;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Linearizable reads are served only by the leader once it has confirmed its leadership with the quorum,
+/// relaxed reads are served from the local state of the node.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum ReadConsistency {
+    #[default]
+    Linearizable,
+    Relaxed,
+}
+
+impl Display for ReadConsistency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadConsistency::Linearizable => write!(f, "linearizable"),
+            ReadConsistency::Relaxed => write!(f, "relaxed"),
+        }
+    }
+}
+
+impl FromStr for ReadConsistency {
+    type Err = SystemError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "linearizable" => Ok(ReadConsistency::Linearizable),
+            "relaxed" => Ok(ReadConsistency::Relaxed),
+            _ => Err(SystemError::InvalidCommand),
+        }
+    }
+}
+
+impl ReadConsistency {
+    pub fn as_code(&self) -> u8 {
+        match self {
+            ReadConsistency::Linearizable => 1,
+            ReadConsistency::Relaxed => 2,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Result<Self, SystemError> {
+        match code {
+            1 => Ok(ReadConsistency::Linearizable),
+            2 => Ok(ReadConsistency::Relaxed),
+            _ => Err(SystemError::InvalidCommand),
+        }
+    }
+}